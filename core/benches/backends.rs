@@ -0,0 +1,27 @@
+//! [`Bench`] against an in-memory editor, the baseline for the backends' own runs (for nvim
+//! the ignored `bench_baseline` test of eel-nvim).
+//!
+//! `cargo bench -p eel --features tests`, `EEL_BENCH_ITERATIONS` overrides the iteration count.
+
+mod mem;
+
+use eel::bench::Bench;
+
+const DEFAULT_ITERATIONS: usize = 1000;
+
+fn main() {
+    let iterations = std::env::var("EEL_BENCH_ITERATIONS")
+        .ok()
+        .map(|i| i.parse().expect("Invalid EEL_BENCH_ITERATIONS"))
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    let report = Bench::new(mem::MemEditor::default())
+        .iterations(iterations)
+        .append_many()
+        .random_set_text()
+        .mark_churn()
+        .region_reads()
+        .report();
+
+    println!("memory backend\n\n{report}");
+}
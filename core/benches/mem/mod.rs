@@ -0,0 +1,326 @@
+//! Editor keeping its buffers in memory, with marks, for the benches to get a baseline without
+//! any backend overhead.
+
+use std::{
+    collections::HashMap,
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Weak},
+};
+
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, RawRwLock, RwLock};
+
+use eel::{
+    Editor, PosRange, Position, Result,
+    buffer::{BufferData, BufferHandle, ChangeHooks, ReadBuffer, WeakBufferHandle, WriteBuffer},
+    mark::{Gravity, MarkId, MarkReadBuffer, MarkWriteBuffer},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemMarkId(u64);
+
+impl MarkId for MemMarkId {}
+
+struct MemMark {
+    start: Position,
+    end: Option<Position>,
+    gravity: Gravity,
+}
+
+pub struct MemBuffer {
+    lines: Vec<String>,
+    marks: HashMap<u64, MemMark>,
+    next_mark: u64,
+    tick: u64,
+    changes: Arc<ChangeHooks>,
+}
+
+/// Where `position` ends up after `start..end` is replaced by text ending at `new_end`.
+fn shift(position: &Position, range: &PosRange, new_end: &Position, right: bool) -> Position {
+    let (start, end) = (range.start(), range.end());
+
+    if position < start || (position == start && !right) {
+        position.clone()
+    } else if position > end {
+        if position.row == end.row {
+            Position::new(new_end.row, new_end.col + position.col - end.col)
+        } else {
+            Position::new(position.row + new_end.row - end.row, position.col)
+        }
+    } else if right {
+        new_end.clone()
+    } else {
+        start.clone()
+    }
+}
+
+impl ReadBuffer for MemBuffer {
+    fn line_count(&self) -> Result<usize> {
+        Ok(self.lines.len())
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.lines.len(),
+        };
+
+        let end = end.min(self.lines.len());
+
+        Ok(self.lines[start.min(end)..end].iter().cloned())
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        Ok(self.tick)
+    }
+}
+
+impl WriteBuffer for MemBuffer {
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        let (start, end) = (range.start(), range.end());
+        self.validate_range(start, end)?;
+
+        let prefix = &self.lines[start.row][..start.col];
+        let suffix = &self.lines[end.row][end.col..];
+
+        let mut lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        lines[0].insert_str(0, prefix);
+        lines
+            .last_mut()
+            .expect("Split text has lines")
+            .push_str(suffix);
+
+        self.lines.splice(start.row..=end.row, lines);
+        self.tick += 1;
+
+        let new_end = start.offset(&Position::max_text_pos(text));
+        for mark in self.marks.values_mut() {
+            mark.start = shift(&mark.start, range, &new_end, mark.gravity.start_right());
+            mark.end = mark
+                .end
+                .as_ref()
+                .map(|end| shift(end, range, &new_end, mark.gravity.end_right()));
+        }
+
+        self.changes.changed();
+
+        Ok(())
+    }
+}
+
+impl MarkReadBuffer for MemBuffer {
+    type MarkId = MemMarkId;
+
+    fn get_mark_position(&self, id: MemMarkId) -> Result<Position> {
+        let mark = self.marks.get(&id.0).ok_or_else(|| missing_mark(id))?;
+
+        Ok(mark.start.clone())
+    }
+
+    fn get_mark_end(&self, id: MemMarkId) -> Result<Option<Position>> {
+        let mark = self.marks.get(&id.0).ok_or_else(|| missing_mark(id))?;
+
+        Ok(mark.end.clone())
+    }
+}
+
+impl MarkWriteBuffer for MemBuffer {
+    fn create_mark(&mut self, pos: &Position) -> Result<MemMarkId> {
+        self.create_mark_with(pos, None, Gravity::Right)
+    }
+
+    fn create_mark_with(
+        &mut self,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+    ) -> Result<MemMarkId> {
+        self.validate_pos(start)?;
+        if let Some(end) = end {
+            self.validate_pos(end)?;
+        }
+
+        let id = self.next_mark;
+        self.next_mark += 1;
+
+        self.marks.insert(
+            id,
+            MemMark {
+                start: start.clone(),
+                end: end.cloned(),
+                gravity,
+            },
+        );
+
+        Ok(MemMarkId(id))
+    }
+
+    fn destroy_mark(&mut self, id: MemMarkId) -> Result<()> {
+        self.marks.remove(&id.0).ok_or_else(|| missing_mark(id))?;
+
+        Ok(())
+    }
+
+    fn set_mark_position(&mut self, id: MemMarkId, pos: &Position) -> Result<()> {
+        self.validate_pos(pos)?;
+        self.marks
+            .get_mut(&id.0)
+            .ok_or_else(|| missing_mark(id))?
+            .start = pos.clone();
+
+        Ok(())
+    }
+
+    fn set_mark_gravity(&mut self, id: MemMarkId, gravity: Gravity) -> Result<()> {
+        self.marks
+            .get_mut(&id.0)
+            .ok_or_else(|| missing_mark(id))?
+            .gravity = gravity;
+
+        Ok(())
+    }
+}
+
+fn missing_mark(id: MemMarkId) -> eel::buffer::Error {
+    eel::buffer::Error::Custom(format!("No mark {id:?}").into())
+}
+
+#[derive(Clone)]
+pub struct MemBufferHandle {
+    buffer: Arc<RwLock<MemBuffer>>,
+    data: Arc<BufferData>,
+}
+
+impl PartialEq for MemBufferHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
+}
+
+impl Eq for MemBufferHandle {}
+
+#[derive(Clone)]
+pub struct WeakMemBufferHandle {
+    buffer: Weak<RwLock<MemBuffer>>,
+    data: Weak<BufferData>,
+}
+
+impl WeakBufferHandle for WeakMemBufferHandle {
+    type Handle = MemBufferHandle;
+
+    fn upgrade(&self) -> Option<MemBufferHandle> {
+        Some(MemBufferHandle {
+            buffer: self.buffer.upgrade()?,
+            data: self.data.upgrade()?,
+        })
+    }
+}
+
+impl BufferHandle for MemBufferHandle {
+    type ReadBuffer = MemBuffer;
+    type WriteBuffer = MemBuffer;
+    type ReadBufferLock = ArcRwLockReadGuard<RawRwLock, MemBuffer>;
+    type WriteBufferLock = ArcRwLockWriteGuard<RawRwLock, MemBuffer>;
+    type WeakHandle = WeakMemBufferHandle;
+
+    fn read(&self) -> Self::ReadBufferLock {
+        self.buffer.read_arc()
+    }
+
+    fn write(&self) -> Self::WriteBufferLock {
+        self.buffer.write_arc()
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        ArcRwLockWriteGuard::downgrade(lock)
+    }
+
+    fn data(&self) -> &BufferData {
+        &self.data
+    }
+
+    fn downgrade(&self) -> WeakMemBufferHandle {
+        WeakMemBufferHandle {
+            buffer: Arc::downgrade(&self.buffer),
+            data: Arc::downgrade(&self.data),
+        }
+    }
+
+    /// Buffers are never closed.
+    fn on_close(&self, _callback: impl FnOnce() + Send + 'static) {}
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.buffer.read().changes.on_change(callback);
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        Ok(self.buffer.read().tick)
+    }
+}
+
+#[derive(Default)]
+pub struct MemEditor {
+    buffers: RwLock<Vec<MemBufferHandle>>,
+    current: RwLock<Option<MemBufferHandle>>,
+}
+
+impl Editor for MemEditor {
+    type BufferHandle = MemBufferHandle;
+
+    fn current_buffer(&self) -> Result<MemBufferHandle> {
+        if let Some(buffer) = self.current.read().clone() {
+            return Ok(buffer);
+        }
+
+        let buffer = self.new_buffer()?;
+        *self.current.write() = Some(buffer.clone());
+
+        Ok(buffer)
+    }
+
+    fn new_buffer(&self) -> Result<MemBufferHandle> {
+        let buffer = MemBufferHandle {
+            buffer: Arc::new(RwLock::new(MemBuffer {
+                lines: vec![String::new()],
+                marks: HashMap::new(),
+                next_mark: 0,
+                tick: 0,
+                changes: Arc::default(),
+            })),
+            data: Arc::default(),
+        };
+        self.buffers.write().push(buffer.clone());
+
+        Ok(buffer)
+    }
+
+    fn set_current_buffer(&self, buffer: &mut MemBuffer) -> Result<()> {
+        // The buffer is locked, so it's found by its address
+        let handle = self
+            .buffers
+            .read()
+            .iter()
+            .find(|b| std::ptr::eq(b.buffer.data_ptr(), buffer))
+            .cloned();
+        *self.current.write() = handle;
+
+        Ok(())
+    }
+
+    fn buffers(&self) -> Result<Vec<MemBufferHandle>> {
+        Ok(self.buffers.read().clone())
+    }
+
+    /// Buffers have no names.
+    fn buffer_by_name(&self, _name: &str) -> Result<Option<MemBufferHandle>> {
+        Ok(None)
+    }
+}
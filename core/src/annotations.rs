@@ -0,0 +1,302 @@
+use std::{
+    ops::RangeBounds,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    Position, Result,
+    buffer::{WeakBufferHandle, clear_on_drop},
+    mark::{MarkBufferHandle, MarkReadBuffer, MarkWriteBuffer},
+    tracing::ResultExt,
+};
+
+/// Typed payloads anchored to marks, stored in the buffer's
+/// [`BufferData`](crate::buffer::BufferData) (one store per payload type).
+///
+/// Positions are read from the marks on every query, so annotations follow edits the same
+/// way marks do. Rendering them is up to the caller.
+///
+/// Marks are kept by ID rather than as [`Mark`](crate::mark::Mark)s, which hold the buffer
+/// and would keep the data storing them alive. The store destroys them once they're removed.
+pub struct Annotations<B: MarkBufferHandle, T> {
+    buffer: B::WeakHandle,
+    entries: Mutex<Vec<(B::MarkId, T)>>,
+}
+
+impl<B, T> Annotations<B, T>
+where
+    B: MarkBufferHandle,
+    T: Clone + Send + Sync + 'static,
+{
+    pub fn of(buffer: &B) -> Arc<Self> {
+        buffer.data().get_or_insert_with(|| Self {
+            buffer: buffer.downgrade(),
+            entries: Mutex::default(),
+        })
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Vec<(B::MarkId, T)>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn buffer(&self) -> Result<B> {
+        self.buffer
+            .upgrade()
+            .ok_or_else(|| crate::buffer::Error::Custom("Buffer was closed".into()).into())
+    }
+
+    fn destroy_marks(&self, ids: Vec<B::MarkId>) {
+        if ids.is_empty() {
+            return;
+        }
+
+        let Some(buffer) = self.buffer.upgrade() else {
+            return;
+        };
+
+        let Ok(mut lock) = buffer
+            .write_timeout()
+            .log_err_msg("Failed to destroy annotation marks")
+        else {
+            return;
+        };
+
+        for id in ids {
+            _ = lock
+                .destroy_mark(id)
+                .log_err_msg("Failed to destroy annotation mark");
+        }
+    }
+
+    /// Takes over the mark, it's destroyed once the annotation is removed.
+    pub fn insert(&self, id: B::MarkId, value: T) -> Option<T> {
+        let mut entries = self.entries();
+
+        match entries.iter_mut().find(|(m, _)| *m == id) {
+            Some((_, old)) => Some(std::mem::replace(old, value)),
+            None => {
+                entries.push((id, value));
+                None
+            }
+        }
+    }
+
+    pub fn annotate(&self, position: &Position, value: T) -> Result<B::MarkId> {
+        let id = self.buffer()?.write_timeout()?.create_mark(position)?;
+
+        self.insert(id, value);
+
+        Ok(id)
+    }
+
+    pub fn get(&self, id: B::MarkId) -> Option<T> {
+        self.entries()
+            .iter()
+            .find(|(mark, _)| *mark == id)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Mark of the first annotation with `value`.
+    pub fn find(&self, value: &T) -> Option<B::MarkId>
+    where
+        T: PartialEq,
+    {
+        self.entries()
+            .iter()
+            .find(|(_, v)| v == value)
+            .map(|(mark, _)| *mark)
+    }
+
+    /// Current position of an annotation, `None` if there's no annotation on the mark.
+    pub fn position(&self, id: B::MarkId) -> Result<Option<Position>> {
+        if self.get(id).is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(self.buffer()?.read_timeout()?.get_mark_position(id)?))
+    }
+
+    /// Takes the buffer's write lock to destroy the mark.
+    pub fn remove(&self, id: B::MarkId) -> Option<T> {
+        let value = {
+            let mut entries = self.entries();
+            let index = entries.iter().position(|(mark, _)| *mark == id)?;
+
+            entries.swap_remove(index).1
+        };
+
+        self.destroy_marks(vec![id]);
+
+        Some(value)
+    }
+
+    pub fn clear(&self) {
+        let ids = self.entries().drain(..).map(|(mark, _)| mark).collect();
+
+        self.destroy_marks(ids);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Annotations positioned inside `range`, ordered by position.
+    pub fn annotations_in<R: RangeBounds<Position>>(
+        &self,
+        range: R,
+    ) -> Result<Vec<(B::MarkId, Position, T)>> {
+        let Some(buffer) = self.buffer.upgrade() else {
+            return Ok(Vec::new());
+        };
+
+        let lock = buffer.read_timeout()?;
+
+        let mut found = Vec::new();
+
+        for (mark, value) in self.entries().iter() {
+            let position = lock.get_mark_position(*mark)?;
+
+            if range.contains(&position) {
+                found.push((*mark, position, value.clone()));
+            }
+        }
+
+        found.sort_by(|(_, a, _), (_, b, _)| a.cmp(b));
+
+        Ok(found)
+    }
+}
+
+impl<B: MarkBufferHandle, T> Drop for Annotations<B, T> {
+    fn drop(&mut self) {
+        let ids = self
+            .entries
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        // The marks go with the buffer, unless the store was taken out of the data of a buffer
+        // that's still open
+        let Some(buffer) = self.buffer.upgrade().filter(|_| !ids.is_empty()) else {
+            return;
+        };
+
+        clear_on_drop(buffer, "annotation", move |buffer| {
+            for id in ids {
+                _ = buffer
+                    .destroy_mark(id)
+                    .log_err_msg("Failed to destroy annotation mark");
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Severity {
+        Error,
+        Warning,
+    }
+
+    pub fn test_annotations<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(
+            &editor,
+            r#"First line
+Second line
+Third line"#,
+        );
+
+        let annotations = Annotations::<_, Severity>::of(&buffer);
+
+        let error = annotations
+            .annotate(&Position::new(1, 7), Severity::Error)
+            .expect("Failed to annotate");
+        let warning = annotations
+            .annotate(&Position::new(2, 0), Severity::Warning)
+            .expect("Failed to annotate");
+        annotations
+            .annotate(&Position::new(0, 0), Severity::Warning)
+            .expect("Failed to annotate");
+
+        assert_eq!(annotations.len(), 3);
+        assert_eq!(annotations.get(error), Some(Severity::Error));
+
+        // Shared through the buffer data
+        assert_eq!(Annotations::<_, Severity>::of(&buffer).len(), 3);
+
+        assert_eq!(
+            annotations
+                .annotations_in(Position::new(1, 0)..)
+                .expect("Failed to query annotations"),
+            [
+                (error, Position::new(1, 7), Severity::Error),
+                (warning, Position::new(2, 0), Severity::Warning),
+            ]
+        );
+
+        buffer
+            .write()
+            .set_text(
+                (Position::new(0, 10), Position::new(1, 6)),
+                " (no longer second)",
+            )
+            .expect("Failed to set text");
+
+        assert_eq!(
+            annotations
+                .annotations_in(Position::new(0, 1)..Position::new(1, 0))
+                .expect("Failed to query annotations"),
+            [(error, Position::new(0, 30), Severity::Error)]
+        );
+
+        assert_eq!(annotations.remove(error), Some(Severity::Error));
+        assert_eq!(annotations.get(error), None);
+        assert_eq!(
+            annotations
+                .annotations_in(..)
+                .expect("Failed to query annotations")
+                .len(),
+            2
+        );
+
+        annotations.clear();
+        assert!(annotations.is_empty());
+    }
+
+    #[macro_export]
+    macro_rules! eel_annotations_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::annotations::tests,
+                prefix: $prefix,
+                tests: [test_annotations],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_annotations_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Editor, Result,
+    buffer::{BufferHandle, WriteBuffer},
+    fuzz::Rng,
+    test_utils::new_buffer_with_content,
+};
+
+const BENCH_CONTENT: &str = "First line\nSecond line\nThird line";
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: &'static str,
+    pub iterations: usize,
+    pub elapsed: Duration,
+    /// Calls sent to the backend's main thread, `None` if the backend doesn't count them.
+    pub dispatches: Option<u64>,
+}
+
+impl BenchResult {
+    pub fn per_iteration(&self) -> Duration {
+        self.elapsed / self.iterations.max(1) as u32
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchReport {
+    pub fn get(&self, name: &str) -> Option<&BenchResult> {
+        self.results.iter().find(|r| r.name == name)
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} | {:>10} | {:>12} | {:>12} | {:>10}",
+            "bench", "iterations", "total", "per iter", "dispatches"
+        )?;
+        writeln!(
+            f,
+            "{:-<16}-+-{:->10}-+-{:->12}-+-{:->12}-+-{:->10}",
+            "", "", "", "", ""
+        )?;
+
+        for r in &self.results {
+            let dispatches = r.dispatches.map_or("-".to_string(), |d| d.to_string());
+
+            writeln!(
+                f,
+                "{:<16} | {:>10} | {:>12} | {:>12} | {:>10}",
+                r.name,
+                r.iterations,
+                format!("{:?}", r.elapsed),
+                format!("{:?}", r.per_iteration()),
+                dispatches
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+type DispatchCounter = Box<dyn Fn() -> u64>;
+
+/// Times common operations against an editor, to compare backends or get a baseline before
+/// performance work.
+///
+/// `cargo bench -p eel --features tests` prints a baseline from an in-memory editor.
+///
+/// Every bench runs on a fresh buffer, setup is not included in the measured time:
+///
+/// ```ignore
+/// let report = Bench::new(editor).iterations(1000).append_many().random_set_text().report();
+/// println!("{report}");
+/// ```
+pub struct Bench<E: Editor> {
+    editor: E,
+    iterations: usize,
+    seed: u64,
+    dispatch_counter: Option<DispatchCounter>,
+    report: BenchReport,
+}
+
+impl<E: Editor> Bench<E> {
+    pub fn new(editor: E) -> Self {
+        Self {
+            editor,
+            iterations: 100,
+            seed: 0x5eed,
+            dispatch_counter: None,
+            report: BenchReport::default(),
+        }
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Reports the difference of `counter` before and after every bench, e.g. the number of
+    /// calls the backend dispatched to its main thread.
+    pub fn dispatch_counter(mut self, counter: impl Fn() -> u64 + 'static) -> Self {
+        self.dispatch_counter = Some(Box::new(counter));
+        self
+    }
+
+    fn run<S, F>(&mut self, name: &'static str, setup: S, mut op: F)
+    where
+        S: FnOnce(&E::BufferHandle) -> Result<()>,
+        F: FnMut(&mut Rng, &E::BufferHandle) -> Result<()>,
+    {
+        let buffer = new_buffer_with_content(&self.editor, BENCH_CONTENT);
+        setup(&buffer).expect("Failed to set up bench");
+
+        let mut rng = Rng::new(self.seed);
+        let dispatches = self.dispatch_counter.as_ref().map(|c| c());
+        let start = Instant::now();
+
+        for _ in 0..self.iterations {
+            op(&mut rng, &buffer).expect("Bench operation failed");
+        }
+
+        let elapsed = start.elapsed();
+        let dispatches = self
+            .dispatch_counter
+            .as_ref()
+            .zip(dispatches)
+            .map(|(c, before)| c() - before);
+
+        self.report.results.push(BenchResult {
+            name,
+            iterations: self.iterations,
+            elapsed,
+            dispatches,
+        });
+    }
+
+    /// Appends a short line at the end of the buffer.
+    pub fn append_many(mut self) -> Self {
+        self.run(
+            "append_many",
+            |_| Ok(()),
+            |_, buffer| buffer.write().append("\nappended line"),
+        );
+        self
+    }
+
+    /// Replaces a random range with random text.
+    pub fn random_set_text(mut self) -> Self {
+        self.run(
+            "random_set_text",
+            |_| Ok(()),
+            |rng, buffer| {
+                let mut lock = buffer.write();
+
+                let (start, end) = rng.range(&*lock)?;
+                let text = rng.text(8);
+
+                lock.set_text((&start, &end), &text)
+            },
+        );
+        self
+    }
+
+    pub fn report(self) -> BenchReport {
+        self.report
+    }
+}
+
+#[cfg(feature = "mark")]
+impl<E> Bench<E>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    /// Creates a mark and moves it, the mark is destroyed in the background once dropped.
+    pub fn mark_churn(mut self) -> Self {
+        use crate::mark::Mark;
+
+        self.run(
+            "mark_churn",
+            |_| Ok(()),
+            |rng, buffer| {
+                let mut lock = buffer.write();
+
+                let position = rng.position(&*lock)?;
+                let mark = Mark::new(buffer, &position, &mut *lock)?;
+
+                let position = rng.position(&*lock)?;
+                mark.write(&mut *lock).set_position(&position)
+            },
+        );
+        self
+    }
+}
+
+#[cfg(feature = "region")]
+impl<E> Bench<E>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    /// Reads the content of a region spanning the whole buffer.
+    pub fn region_reads(mut self) -> Self {
+        use std::sync::OnceLock;
+
+        use crate::{Position, buffer::ReadBuffer, region::BufferRegion};
+
+        let region = OnceLock::new();
+
+        self.run(
+            "region_reads",
+            |buffer| {
+                let mut lock = buffer.write();
+                let end = lock.max_pos()?;
+
+                let _ = region.set(BufferRegion::new(
+                    buffer,
+                    (&Position::origin(), &end),
+                    &mut *lock,
+                )?);
+
+                Ok(())
+            },
+            |_, _| {
+                let region = region.get().expect("Region was set up");
+
+                region.read().get_content().map(|_| ())
+            },
+        );
+        self
+    }
+}
+
+pub mod tests {
+    use super::*;
+
+    pub fn test_bench_suite(editor: impl Editor) {
+        let report = Bench::new(editor)
+            .iterations(10)
+            .append_many()
+            .random_set_text()
+            .report();
+
+        assert_eq!(report.results.len(), 2);
+
+        for result in &report.results {
+            assert_eq!(result.iterations, 10);
+            assert!(result.dispatches.is_none());
+        }
+
+        assert!(report.get("append_many").is_some());
+        assert!(report.to_string().contains("random_set_text"));
+    }
+
+    #[cfg(feature = "region")]
+    pub fn test_bench_marks_regions<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        use std::{cell::Cell, rc::Rc};
+
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let report = Bench::new(editor)
+            .iterations(10)
+            .dispatch_counter(move || {
+                counter.set(counter.get() + 1);
+                counter.get()
+            })
+            .mark_churn()
+            .region_reads()
+            .report();
+
+        assert_eq!(count.get(), 4);
+
+        for name in ["mark_churn", "region_reads"] {
+            let result = report.get(name).expect("Bench missing from report");
+            assert_eq!(result.dispatches, Some(1));
+        }
+    }
+
+    #[macro_export]
+    #[cfg(feature = "region")]
+    macro_rules! eel_bench_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::bench::tests,
+                prefix: $prefix,
+                tests: [test_bench_suite],
+            );
+
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::bench::tests,
+                prefix: $prefix,
+                tests: [test_bench_marks_regions],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_bench_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "region"))]
+    macro_rules! eel_bench_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::bench::tests,
+                prefix: $prefix,
+                tests: [test_bench_suite],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_bench_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
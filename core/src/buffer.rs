@@ -1,9 +1,29 @@
-use std::ops::RangeBounds;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::RangeBounds,
+};
 
-use crate::{Position, Result};
+use crate::{PosRange, Position, Result};
 
 use itertools::Itertools;
 
+mod change;
+mod checked;
+mod close;
+mod data;
+mod patch;
+mod scope;
+mod validator;
+mod windows;
+pub use change::ChangeHooks;
+pub use checked::{Checked, CheckedResult, Diagnostic};
+pub use close::CloseHooks;
+pub use data::BufferData;
+pub use patch::AppliedHunk;
+pub use scope::{BufferScope, ScopedRead};
+pub use validator::{ValidationMode, Validator};
+pub use windows::LineWindows;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Row out of bounds: {row} (limit {limit})")]
@@ -12,6 +32,22 @@ pub enum Error {
     #[error("Col out of bounds: {col} (limit {limit})")]
     ColOutOfBounds { col: isize, limit: usize },
 
+    #[error("Col {col} is not a character boundary in row {row}")]
+    NotCharBoundary { row: usize, col: usize },
+
+    #[error("Buffer changed: tick {actual} (expected {expected})")]
+    Conflict { expected: u64, actual: u64 },
+
+    #[error("Read-only")]
+    ReadOnly,
+
+    #[error("Timed out waiting {0:?} for the buffer lock")]
+    LockTimeout(std::time::Duration),
+
+    /// Indices of the patch hunks whose context and removed lines weren't found.
+    #[error("Patch hunks {0:?} don't match the buffer")]
+    PatchRejected(Vec<usize>),
+
     #[error("Error: {0}")]
     Custom(Box<dyn std::error::Error + Sync + Send>),
 }
@@ -23,6 +59,9 @@ pub trait ReadBuffer: Send + Sync {
         range: R,
     ) -> Result<impl Iterator<Item = String> + Send>;
 
+    /// Counter increased on every change to the buffer, same as [`BufferHandle::changedtick`].
+    fn changedtick(&self) -> Result<u64>;
+
     fn max_row(&self) -> Result<usize> {
         Ok(self.line_count()? - 1)
     }
@@ -32,30 +71,48 @@ pub trait ReadBuffer: Send + Sync {
     }
 
     fn max_row_pos(&self, row: usize) -> Result<Position> {
-        let row_len = self.get_line(row)?.len();
-        Ok(Position::new(row, row_len))
+        Ok(Position::new(row, self.line_len(row)?))
     }
 
+    /// Length of the line in bytes.
+    fn line_len(&self, row: usize) -> Result<usize> {
+        Ok(self.get_line(row)?.len())
+    }
+
+    /// Character starting at the position, `None` at the end of the line.
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        self.validate_pos(position)?;
+
+        Ok(self
+            .get_line(position.row)?
+            .get(position.col..)
+            .and_then(|s| s.chars().next()))
+    }
+
+    /// Validates an insert position, see [`ValidationMode::Insert`].
     fn validate_pos(&self, position: &Position) -> Result<()> {
-        let max_row = self.max_row()?;
+        Validator::new(self).validate_pos(position)
+    }
 
-        if position.row > max_row {
-            Err(Error::RowOutOfBounds {
-                row: position.row as isize,
-                limit: max_row,
-            })?;
-        }
+    fn validate_pos_with(&self, position: &Position, mode: ValidationMode) -> Result<()> {
+        Validator::new(self).validate_pos_with(position, mode)
+    }
 
-        let max_col = self.max_row_pos(position.row)?.col;
+    /// Insert position right after the character at `position`, or `position` itself if it's
+    /// at the end of the line (e.g. an insert mode cursor).
+    fn pos_after_char(&self, position: &Position) -> Result<Position> {
+        Ok(match self.char_at(position)? {
+            Some(c) => Position::new(position.row, position.col + c.len_utf8()),
+            None => position.clone(),
+        })
+    }
 
-        if position.col > max_col {
-            Err(Error::ColOutOfBounds {
-                col: position.col as isize,
-                limit: max_col,
-            })?;
-        }
+    /// Validates both ends of a range, sharing the buffer metadata between them.
+    fn validate_range(&self, start: &Position, end: &Position) -> Result<()> {
+        let validator = Validator::new(self);
 
-        Ok(())
+        validator.validate_pos(start)?;
+        validator.validate_pos(end)
     }
 
     fn get_line(&self, row: usize) -> Result<String> {
@@ -86,57 +143,303 @@ pub trait ReadBuffer: Send + Sync {
     fn get_content(&self) -> Result<String> {
         Ok(self.get_all_lines()?.join("\n"))
     }
+
+    /// Text in a range given in any form convertible to [`PosRange`], e.g. a
+    /// `(start, end)` tuple in either order, see [`ReadBuffer::get_text_in`].
+    fn get_text(&self, range: impl Into<PosRange>) -> Result<String> {
+        self.get_text_in(&range.into())
+    }
+
+    /// Text in the range, same range semantics as [`WriteBuffer::set_text_in`].
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        let (start, end) = (range.start(), range.end());
+
+        self.validate_range(start, end)?;
+
+        let lines = self.get_lines(start.row..(end.row + 1))?.collect_vec();
+
+        if start.row == end.row {
+            return Ok(lines[0][start.col..end.col].to_string());
+        }
+
+        let (first, rest) = lines.split_first().expect("Range has at least two lines");
+        let (last, middle) = rest.split_last().expect("Range has at least two lines");
+
+        Ok(std::iter::once(&first[start.col..])
+            .chain(middle.iter().map(String::as_str))
+            .chain(std::iter::once(&last[..end.col]))
+            .join("\n"))
+    }
+
+    /// See [`textobject::word_at`](crate::textobject::word_at).
+    fn word_at(&self, position: &Position) -> Result<Option<(Position, Position)>> {
+        crate::textobject::word_at(self, position)
+    }
+
+    /// See [`textobject::paragraph_at`](crate::textobject::paragraph_at).
+    fn paragraph_at(&self, position: &Position) -> Result<(Position, Position)> {
+        crate::textobject::paragraph_at(self, position)
+    }
+
+    /// See [`textobject::matching_bracket`](crate::textobject::matching_bracket).
+    fn matching_bracket(&self, position: &Position) -> Result<Option<Position>> {
+        crate::textobject::matching_bracket(self, position)
+    }
+
+    /// Defaults to [`IndentSettings::global`](crate::indent::IndentSettings::global) for
+    /// backends without per-buffer settings.
+    fn indent_settings(&self) -> Result<crate::indent::IndentSettings> {
+        Ok(crate::indent::IndentSettings::global())
+    }
+
+    /// Wraps the buffer so invalid operations fail with a [`Diagnostic`], see [`Checked`].
+    fn checked(&self) -> Checked<&Self> {
+        Checked::new(self)
+    }
+
+    /// Consecutive chunks of `lines_per_chunk` lines, the last one can be shorter. Panics if
+    /// `lines_per_chunk` is zero.
+    fn chunks(&self, lines_per_chunk: usize) -> LineWindows<'_, Self> {
+        self.windows(lines_per_chunk, lines_per_chunk)
+    }
+
+    /// Windows of `size` lines starting every `stride` rows, up to the first one reaching the
+    /// end of the buffer, which can be shorter. Panics if `size` or `stride` is zero.
+    fn windows(&self, size: usize, stride: usize) -> LineWindows<'_, Self> {
+        LineWindows::new(self, size, stride)
+    }
+
+    /// Hash of the buffer content, lines are fetched in chunks so large buffers don't have
+    /// to be copied at once.
+    fn content_hash(&self) -> Result<u64> {
+        const CHUNK_SIZE: usize = 1024;
+
+        let mut hasher = DefaultHasher::new();
+        let line_count = self.line_count()?;
+
+        for start in (0..line_count).step_by(CHUNK_SIZE) {
+            let end = (start + CHUNK_SIZE).min(line_count);
+
+            for line in self.get_lines(start..end)? {
+                line.hash(&mut hasher);
+            }
+        }
+
+        Ok(hasher.finish())
+    }
 }
 
 pub trait WriteBuffer: ReadBuffer {
-    fn set_text(&mut self, start: &Position, end: &Position, text: &str) -> Result<()>;
+    /// Replaces the text in the range, the end is exclusive.
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()>;
+
+    /// Range in any form accepted by [`ReadBuffer::get_text`], see
+    /// [`WriteBuffer::set_text_in`].
+    fn set_text(&mut self, range: impl Into<PosRange>, text: &str) -> Result<()> {
+        self.set_text_in(&range.into(), text)
+    }
+
+    /// Like [`WriteBuffer::set_text_in`], but fails with [`Error::Conflict`] if the buffer
+    /// changed since [`ReadBuffer::changedtick`] returned `expected_tick`, e.g. while the edit
+    /// was computed off-thread.
+    ///
+    /// Backends where the user can edit the buffer without taking its lock should override
+    /// this, checking the tick and editing atomically.
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        let actual = self.changedtick()?;
+
+        if actual != expected_tick {
+            Err(Error::Conflict {
+                expected: expected_tick,
+                actual,
+            })?;
+        }
+
+        self.set_text_in(range, text)
+    }
+
+    /// Like [`ReadBuffer::checked`], also checking writes.
+    fn checked_mut(&mut self) -> Checked<&mut Self> {
+        Checked::new(self)
+    }
 
     fn set_content(&mut self, text: &str) -> Result<()> {
-        self.set_text(&Position::origin(), &self.max_pos()?, text)
+        self.set_text((&Position::origin(), &self.max_pos()?), text)
     }
 
     fn set_line(&mut self, row: usize, line: &str) -> Result<()> {
         let row_end = self.max_row_pos(row)?;
 
-        self.set_text(&Position::new(row, 0), &row_end, line)
+        self.set_text((&Position::new(row, 0), &row_end), line)
     }
 
+    /// Inserts `text` after the character at `position`, which is validated as a
+    /// [`ValidationMode::Cursor`] position.
     fn append_at_position(&mut self, position: &Position, text: &str) -> Result<()> {
-        let next_position = position.clone().next_col();
-
-        let position = if self.validate_pos(&next_position).is_ok() {
-            &next_position
-        } else {
-            position
-        };
+        self.validate_pos_with(position, ValidationMode::Cursor)?;
 
-        self.set_text(position, position, text)?;
+        let position = self.pos_after_char(position)?;
 
-        Ok(())
+        self.set_text((&position, &position), text)
     }
 
     fn prepend_at_position(&mut self, position: &Position, text: &str) -> Result<()> {
-        self.set_text(position, position, text)
+        self.set_text((position, position), text)
     }
 
     fn append(&mut self, text: &str) -> Result<()> {
-        let mut max_pos = self.max_pos()?;
-
-        if max_pos.col > 0 {
-            max_pos = max_pos.prev_col();
-        }
+        let max_pos = self.max_pos()?;
 
-        self.append_at_position(&max_pos, text)
+        self.set_text((&max_pos, &max_pos), text)
     }
 
     fn prepend(&mut self, text: &str) -> Result<()> {
         self.prepend_at_position(&Position::origin(), text)
     }
+
+    /// Removes the text in the range. Marks inside it collapse to its start, see
+    /// [`MarkWriteBuffer::delete_range_with`](crate::mark::MarkWriteBuffer::delete_range_with)
+    /// to invalidate them instead.
+    fn delete_range(&mut self, range: impl Into<PosRange>) -> Result<()> {
+        self.set_text(range, "")
+    }
+
+    /// Removes all text, see [`WriteBuffer::delete_range`] for what happens to marks.
+    fn clear(&mut self) -> Result<()> {
+        let max_pos = self.max_pos()?;
+
+        self.delete_range((Position::origin(), max_pos))
+    }
+
+    /// Keeps the first `lines` lines, removing the rest along with the line break before them.
+    /// See [`WriteBuffer::delete_range`] for what happens to marks.
+    fn truncate_lines(&mut self, lines: usize) -> Result<()> {
+        match truncated_range(self, lines)? {
+            Some(range) => self.delete_range(range),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the text between `start` and `end` with `f` applied to it, returning the end of
+    /// the new text.
+    ///
+    /// Unlike replacing the range with [`WriteBuffer::set_text`], marks at the boundaries stay
+    /// at the boundaries regardless of their gravity: the new text is inserted after the first
+    /// replaced character, then the old text around it is deleted.
+    fn transform_range(
+        &mut self,
+        start: &Position,
+        end: &Position,
+        f: impl FnOnce(&str) -> String,
+    ) -> Result<Position> {
+        let (start, end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        let old = self.get_text((start, end))?;
+        let new = f(&old);
+
+        if new == old {
+            return Ok(end.clone());
+        }
+
+        let Some(first) = old.chars().next() else {
+            self.set_text((start, start), &new)?;
+
+            return Ok(start.offset(&Position::max_text_pos(&new)));
+        };
+
+        let (first, rest) = old.split_at(first.len_utf8());
+
+        let split = start.offset(&Position::max_text_pos(first));
+        let new_end = split.offset(&Position::max_text_pos(&new));
+
+        self.set_text((&split, &split), &new)?;
+        self.set_text(
+            (&new_end, &new_end.offset(&Position::max_text_pos(rest))),
+            "",
+        )?;
+        self.set_text((start, &split), "")?;
+
+        Ok(start.offset(&Position::max_text_pos(&new)))
+    }
+
+    /// Applies the hunks of a unified diff, each one where its context and removed lines match
+    /// the buffer (searching near the stated position, in case of earlier edits).
+    ///
+    /// Either every hunk is applied or none is, if some don't match they're listed in
+    /// [`Error::PatchRejected`] and the buffer is left unchanged.
+    fn apply_patch(&mut self, patch: &str) -> Result<Vec<AppliedHunk>> {
+        patch::apply(self, patch)
+    }
+
+    /// See [`comment::toggle_comment`](crate::comment::toggle_comment).
+    fn toggle_comment(
+        &mut self,
+        start: &Position,
+        end: &Position,
+        spec: &crate::comment::CommentSpec,
+    ) -> Result<bool> {
+        crate::comment::toggle_comment(self, start, end, spec)
+    }
+
+    /// See [`indent::insert_indented`](crate::indent::insert_indented).
+    fn insert_indented(&mut self, position: &Position, text: &str) -> Result<Position> {
+        crate::indent::insert_indented(self, position, text)
+    }
+}
+
+/// Text [`WriteBuffer::truncate_lines`] removes, `None` if the buffer has at most `lines`
+/// lines.
+pub(crate) fn truncated_range<B: ReadBuffer + ?Sized>(
+    buffer: &B,
+    lines: usize,
+) -> Result<Option<PosRange>> {
+    if lines >= buffer.line_count()? {
+        return Ok(None);
+    }
+
+    let start = match lines.checked_sub(1) {
+        Some(row) => buffer.max_row_pos(row)?,
+        None => Position::origin(),
+    };
+
+    Ok(Some(PosRange::new(start, buffer.max_pos()?)))
+}
+
+/// Runs `cleanup` on the write locked buffer from another thread, for `Drop` impls removing
+/// what they added to a buffer the dropping thread may have locked itself.
+#[cfg(any(feature = "mark", feature = "decoration"))]
+pub(crate) fn clear_on_drop<B, F>(buffer: B, what: &'static str, cleanup: F)
+where
+    B: BufferHandle,
+    F: FnOnce(&mut B::WriteBuffer) + Send + 'static,
+{
+    use crate::tracing::ResultExt;
+
+    std::thread::spawn(move || {
+        let Ok(mut lock) = buffer
+            .write_timeout()
+            .log_err_msg(&format!("Failed to lock buffer for {what} cleanup"))
+        else {
+            return;
+        };
+
+        cleanup(&mut *lock);
+    });
 }
 
 pub trait ReadBufferLock: std::ops::Deref<Target = Self::ReadBuffer> + Sync + Send {
     type ReadBuffer: ReadBuffer;
 }
+
 pub trait WriteBufferLock:
     ReadBufferLock<ReadBuffer = Self::WriteBuffer> + std::ops::DerefMut<Target = Self::WriteBuffer>
 {
@@ -165,20 +468,72 @@ pub trait BufferHandle: Eq + Clone + Send + Sync + 'static {
     type WriteBuffer: WriteBuffer;
     type ReadBufferLock: ReadBufferLock<ReadBuffer = Self::ReadBuffer> + 'static;
     type WriteBufferLock: WriteBufferLock<WriteBuffer = Self::WriteBuffer> + 'static;
+    type WeakHandle: WeakBufferHandle<Handle = Self>;
 
     fn read(&self) -> Self::ReadBufferLock;
     fn write(&self) -> Self::WriteBufferLock;
+
+    /// Like [`BufferHandle::read`], but giving up after the
+    /// [lock timeout](crate::EditorConfig::lock_timeout). Backends without timed locks wait.
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        Ok(self.read())
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        Ok(self.write())
+    }
+
+    /// Turns a write lock into a read lock without releasing it, so no other writer can get
+    /// in between, e.g. when verifying the result of an edit.
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock;
+
+    /// User data attached to the buffer, shared by all handles to it.
+    fn data(&self) -> &BufferData;
+
+    /// Handle that doesn't keep the buffer alive.
+    fn downgrade(&self) -> Self::WeakHandle;
+
+    /// Called once the buffer is closed by the editor, or immediately if it already is.
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static);
+
+    /// Called after every change to the buffer until it returns `false`.
+    ///
+    /// May be called on the editor's main thread while the buffer is locked, so it must not
+    /// lock the buffer itself.
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static);
+
+    /// Counter increased on every change to the buffer.
+    fn changedtick(&self) -> Result<u64>;
+
+    /// Runs `f` with a [`BufferScope`], which reuses a single lock for all reads and writes
+    /// made through it.
+    fn scope<R>(&self, f: impl FnOnce(&mut BufferScope<'_, Self>) -> R) -> R {
+        f(&mut BufferScope::new(self))
+    }
+}
+
+pub trait WeakBufferHandle: Clone + Send + Sync + 'static {
+    type Handle: BufferHandle;
+
+    /// Returns `None` once the buffer was closed and all strong handles were dropped.
+    fn upgrade(&self) -> Option<Self::Handle>;
 }
 
 #[cfg(feature = "tests")]
 pub mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
     use super::*;
 
     use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
     use crate::{
-        assert_buffer_content, assert_buffer_error, editor::Editor,
-        test_utils::new_buffer_with_content,
+        assert_buffer_content, assert_buffer_error,
+        editor::Editor,
+        test_utils::{Fixture, new_buffer_with_content},
     };
 
     pub fn test_buffer_pos(editor: impl Editor) {
@@ -247,21 +602,21 @@ Third line!"#,
 
         buffer
             .write()
-            .set_text(&Position::new(0, 6), &Position::new(2, 5), ":)")
+            .set_text((Position::new(0, 6), Position::new(2, 5)), ":)")
             .expect("Failed to set text");
 
         assert_buffer_content!(buffer, r#"First :) line!"#);
 
         buffer
             .write()
-            .set_text(&Position::new(0, 6), &Position::new(0, 9), "")
+            .set_text((Position::new(0, 6), Position::new(0, 9)), "")
             .expect("Failed to set text");
 
         assert_buffer_content!(buffer, r#"First line!"#);
 
         buffer
             .write()
-            .set_text(&Position::new(0, 11), &Position::new(0, 11), " (wow)")
+            .set_text((Position::new(0, 11), Position::new(0, 11)), " (wow)")
             .expect("Failed to set text");
 
         assert_buffer_content!(buffer, r#"First line! (wow)"#);
@@ -276,7 +631,7 @@ Some line
 
         buffer
             .write()
-            .set_text(&Position::new(2, 0), &Position::new(2, 9), "")
+            .set_text((Position::new(2, 0), Position::new(2, 9)), "")
             .expect("Failed to set text");
 
         assert_buffer_content!(
@@ -288,7 +643,7 @@ Some line
 
         buffer
             .write()
-            .set_text(&Position::new(2, 0), &Position::new(2, 0), "This was empty")
+            .set_text((Position::new(2, 0), Position::new(2, 0)), "This was empty")
             .expect("Failed to set text");
 
         assert_buffer_content!(
@@ -301,7 +656,7 @@ This was empty
 
         buffer
             .write()
-            .set_text(&Position::new(0, 0), &Position::new(2, 0), "New line\n")
+            .set_text((Position::new(0, 0), Position::new(2, 0)), "New line\n")
             .expect("Failed to set text");
 
         assert_buffer_content!(
@@ -313,7 +668,7 @@ This was empty
 
         buffer
             .write()
-            .set_text(&Position::new(1, 0), &Position::new(1, 0), "Hey, ")
+            .set_text((Position::new(1, 0), Position::new(1, 0)), "Hey, ")
             .expect("Failed to set text");
 
         assert_buffer_content!(
@@ -324,6 +679,239 @@ Hey, This was empty
         );
     }
 
+    pub fn test_buffer_get_text(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+        let buffer = buffer.read();
+
+        let text = |start: (usize, usize), end: (usize, usize)| {
+            buffer
+                .get_text((Position::new(start.0, start.1), Position::new(end.0, end.1)))
+                .expect("Failed to get text")
+        };
+
+        assert_eq!(text((0, 6), (0, 10)), "line");
+        assert_eq!(text((1, 3), (1, 3)), "");
+        assert_eq!(text((0, 6), (1, 6)), "line\nSecond");
+        assert_eq!(text((2, 5), (0, 0)), "First line\nSecond line\nThird");
+        assert_eq!(text((0, 10), (2, 0)), "\nSecond line\n");
+
+        // Every range form gives the same text
+        let (start, end) = (Position::new(0, 6), Position::new(1, 6));
+        let range = PosRange::new(start.clone(), end.clone());
+        for text in [
+            buffer.get_text(start.clone()..end.clone()),
+            buffer.get_text((&end, &start)),
+            buffer.get_text(&range),
+            buffer.get_text(range.clone()),
+        ] {
+            assert_eq!(text.expect("Failed to get text"), "line\nSecond");
+        }
+
+        assert_buffer_error!(
+            buffer.get_text((Position::new(0, 0), Position::new(3, 0))),
+            crate::Error::Buffer(crate::buffer::Error::RowOutOfBounds { row: 3, limit: 2 })
+        );
+
+        assert_buffer_error!(
+            buffer.get_text((Position::new(0, 11), Position::new(1, 0))),
+            crate::Error::Buffer(crate::buffer::Error::ColOutOfBounds { col: 11, limit: 10 })
+        );
+    }
+
+    pub fn test_buffer_reversed_range(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let (start, end) = (Position::new(0, 6), Position::new(1, 6));
+        let range = PosRange::new(end.clone(), start.clone());
+
+        assert_eq!(range.start(), &start);
+        assert_eq!(range.end(), &end);
+        assert!(range.contains(&Position::new(1, 0)));
+        assert!(!range.contains(&end));
+
+        assert_eq!(
+            buffer
+                .read()
+                .get_text_in(&range)
+                .expect("Failed to get text"),
+            "line\nSecond"
+        );
+
+        buffer
+            .write()
+            .set_text((&end, &start), "text\nOther")
+            .expect("Failed to set text");
+        assert_buffer_content!(buffer, "First text\nOther line");
+    }
+
+    pub fn test_buffer_line_len(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\n\nzażółć");
+        let buffer = buffer.read();
+
+        let line_len = |row| buffer.line_len(row).expect("Failed to get line length");
+        let char_at = |row, col| {
+            buffer
+                .char_at(&Position::new(row, col))
+                .expect("Failed to get char")
+        };
+
+        assert_eq!(line_len(0), 10);
+        assert_eq!(line_len(1), 0);
+        assert_eq!(line_len(2), "zażółć".len());
+
+        assert_eq!(char_at(0, 0), Some('F'));
+        assert_eq!(char_at(0, 9), Some('e'));
+        assert_eq!(char_at(0, 10), None);
+        assert_eq!(char_at(1, 0), None);
+        assert_eq!(char_at(2, 2), Some('ż'));
+
+        assert_buffer_error!(
+            buffer.line_len(3),
+            crate::Error::Buffer(crate::buffer::Error::RowOutOfBounds { row: 3, limit: 2 })
+        );
+
+        assert_buffer_error!(
+            buffer.char_at(&Position::new(0, 11)),
+            crate::Error::Buffer(crate::buffer::Error::ColOutOfBounds { col: 11, limit: 10 })
+        );
+    }
+
+    pub fn test_buffer_validate_range(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        let buffer = buffer.read();
+
+        buffer
+            .validate_range(&Position::new(0, 10), &Position::new(1, 11))
+            .expect("Range should be valid");
+
+        assert_buffer_error!(
+            buffer.validate_range(&Position::new(0, 0), &Position::new(2, 0)),
+            crate::Error::Buffer(crate::buffer::Error::RowOutOfBounds { row: 2, limit: 1 })
+        );
+
+        assert_buffer_error!(
+            buffer.validate_range(&Position::new(1, 12), &Position::new(0, 0)),
+            crate::Error::Buffer(crate::buffer::Error::ColOutOfBounds { col: 12, limit: 11 })
+        );
+
+        buffer
+            .validate_pos_with(&Position::new(1, 10), ValidationMode::Cursor)
+            .expect("Position should be valid");
+
+        assert_buffer_error!(
+            buffer.validate_pos_with(&Position::new(1, 11), ValidationMode::Cursor),
+            crate::Error::Buffer(crate::buffer::Error::ColOutOfBounds { col: 11, limit: 10 })
+        );
+
+        let multibyte = new_buffer_with_content(&editor, "żółw\n中文");
+        let multibyte = multibyte.read();
+
+        multibyte
+            .validate_range(&Position::new(0, 2), &Position::new(1, 6))
+            .expect("Range should be valid");
+
+        assert_buffer_error!(
+            multibyte.validate_pos(&Position::new(0, 3)),
+            crate::Error::Buffer(crate::buffer::Error::NotCharBoundary { row: 0, col: 3 })
+        );
+
+        assert_buffer_error!(
+            multibyte.validate_pos_with(&Position::new(1, 4), ValidationMode::Cursor),
+            crate::Error::Buffer(crate::buffer::Error::NotCharBoundary { row: 1, col: 4 })
+        );
+
+        assert_buffer_error!(
+            multibyte.validate_pos_with(&Position::new(1, 6), ValidationMode::Cursor),
+            crate::Error::Buffer(crate::buffer::Error::ColOutOfBounds { col: 6, limit: 3 })
+        );
+
+        let validator = Validator::new(&*buffer);
+        assert_eq!(validator.line_count().expect("Failed to get line count"), 2);
+        assert_eq!(
+            validator.line_len(1).expect("Failed to get line length"),
+            11
+        );
+        assert_eq!(
+            validator.line_len(1).expect("Failed to get line length"),
+            11
+        );
+    }
+
+    pub fn test_buffer_checked(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond\nżółw\nLast");
+
+        let diagnostic = buffer
+            .read()
+            .checked()
+            .get_text((Position::new(0, 6), Position::new(0, 20)))
+            .expect_err("Range should be invalid");
+        assert_eq!(diagnostic.operation, "get_text");
+        assert_eq!(
+            diagnostic.nearest_valid,
+            Some(PosRange::new(Position::new(0, 6), Position::new(0, 10)))
+        );
+        assert_eq!(
+            diagnostic.excerpt,
+            [(0, "First line".to_string()), (1, "Second".to_string())]
+        );
+        assert!(matches!(
+            diagnostic.error,
+            crate::Error::Buffer(Error::ColOutOfBounds { col: 20, limit: 10 })
+        ));
+
+        let message = diagnostic.to_string();
+        assert!(message.starts_with("get_text failed at 0:6..0:20: "));
+        assert!(message.contains("Col out of bounds: 20 (limit 10)"));
+        assert!(message.contains("\nNearest valid range: 0:6..0:10"));
+        assert!(message.contains("\n> 0 | First line\n  1 | Second"));
+
+        let diagnostic = buffer
+            .read()
+            .checked()
+            .char_at(&Position::new(2, 1))
+            .expect_err("Position should be invalid");
+        assert_eq!(
+            diagnostic.nearest_valid,
+            Some(PosRange::new(Position::new(2, 0), Position::new(2, 0)))
+        );
+        assert_eq!(diagnostic.excerpt.len(), 3);
+
+        // Rows past the end are moved to the end of the buffer, nothing is written
+        let diagnostic = buffer
+            .write()
+            .checked_mut()
+            .set_text((Position::new(1, 0), Position::new(7, 0)), "")
+            .expect_err("Range should be invalid");
+        assert_eq!(
+            diagnostic.nearest_valid,
+            Some(PosRange::new(Position::new(1, 0), Position::new(3, 4)))
+        );
+        assert_eq!(
+            diagnostic.excerpt.iter().map(|(row, _)| *row).collect_vec(),
+            [0, 1, 2, 3]
+        );
+        assert_buffer_content!(buffer, "First line\nSecond\nżółw\nLast");
+
+        let mut lock = buffer.write();
+        let mut checked = lock.checked_mut();
+        checked
+            .set_text((Position::new(1, 0), Position::new(1, 6)), "2nd")
+            .expect("Failed to set text");
+        checked.set_line(3, "End").expect("Failed to set line");
+        assert_eq!(checked.get_line(1).expect("Failed to get line"), "2nd");
+        drop(lock);
+        assert_buffer_content!(buffer, "First line\n2nd\nżółw\nEnd");
+
+        // Converts into a regular error
+        let error: crate::Error = buffer
+            .read()
+            .checked()
+            .get_line(9)
+            .expect_err("Row should be invalid")
+            .into();
+        assert!(error.to_string().contains("get_line failed at 9:0..9:0"));
+    }
+
     pub fn test_buffer_append(editor: impl Editor) {
         let buffer = new_buffer_with_content(&editor, "");
 
@@ -342,6 +930,111 @@ Hey, This was empty
         assert_buffer_content!(buffer, "First line\nSecond line");
     }
 
+    pub fn test_buffer_delete(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+
+        buffer
+            .write()
+            .delete_range((Position::new(0, 5), Position::new(1, 6)))
+            .expect("Failed to delete range");
+        assert_buffer_content!(buffer, "First line\nThird line");
+
+        buffer
+            .write()
+            .truncate_lines(5)
+            .expect("Failed to truncate lines");
+        assert_buffer_content!(buffer, "First line\nThird line");
+
+        buffer
+            .write()
+            .truncate_lines(1)
+            .expect("Failed to truncate lines");
+        assert_buffer_content!(buffer, "First line");
+
+        buffer.write().append("\n").expect("Failed to append");
+        buffer
+            .write()
+            .truncate_lines(0)
+            .expect("Failed to truncate lines");
+        assert_buffer_content!(buffer, "");
+
+        buffer
+            .write()
+            .set_content("First line\nSecond line")
+            .expect("Failed to set content");
+        buffer.write().clear().expect("Failed to clear");
+        assert_buffer_content!(buffer, "");
+    }
+
+    pub fn test_buffer_windows(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First\nSecond\nThird\nFourth\nFifth");
+        let buffer_lock = buffer.read();
+
+        let windows = |iter: LineWindows<'_, _>| {
+            iter.map(|w| {
+                let (range, lines) = w.expect("Failed to get window");
+                (range.into_positions(), lines)
+            })
+            .collect::<Vec<_>>()
+        };
+        let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            windows(buffer_lock.chunks(2)),
+            [
+                (
+                    (Position::new(0, 0), Position::new(1, 6)),
+                    lines(&["First", "Second"])
+                ),
+                (
+                    (Position::new(2, 0), Position::new(3, 6)),
+                    lines(&["Third", "Fourth"])
+                ),
+                (
+                    (Position::new(4, 0), Position::new(4, 5)),
+                    lines(&["Fifth"])
+                ),
+            ]
+        );
+
+        assert_eq!(
+            windows(buffer_lock.windows(3, 1))
+                .into_iter()
+                .map(|(_, lines)| lines)
+                .collect::<Vec<_>>(),
+            [
+                lines(&["First", "Second", "Third"]),
+                lines(&["Second", "Third", "Fourth"]),
+                lines(&["Third", "Fourth", "Fifth"]),
+            ]
+        );
+
+        assert_eq!(
+            windows(buffer_lock.windows(1, 3)),
+            [
+                (
+                    (Position::new(0, 0), Position::new(0, 5)),
+                    lines(&["First"])
+                ),
+                (
+                    (Position::new(3, 0), Position::new(3, 6)),
+                    lines(&["Fourth"])
+                ),
+            ]
+        );
+
+        let (range, chunk) = buffer_lock
+            .chunks(10)
+            .next()
+            .expect("Missing chunk")
+            .expect("Failed to get chunk");
+        assert_eq!(chunk.len(), 5);
+        assert_eq!(
+            buffer_lock.get_text(range).expect("Failed to get text"),
+            chunk.join("\n")
+        );
+    }
+
     pub fn test_buffer_prepend(editor: impl Editor) {
         let buffer = new_buffer_with_content(&editor, "");
 
@@ -398,11 +1091,12 @@ Third line! :)"#
                 .append_at_position(&Position::new(3, 0), ":("),
             crate::Error::Buffer(Error::RowOutOfBounds { row: 3, limit: 2 })
         );
+        // Past the last character is a valid insert position, but there's no character there
         assert_buffer_error!(
             buffer
                 .write()
-                .append_at_position(&Position::new(1, 17), ":("),
-            crate::Error::Buffer(Error::ColOutOfBounds { col: 17, limit: 16 })
+                .append_at_position(&Position::new(1, 16), ":("),
+            crate::Error::Buffer(Error::ColOutOfBounds { col: 16, limit: 15 })
         );
 
         buffer
@@ -436,6 +1130,20 @@ Third line! :)"#
                 .prepend_at_position(&Position::new(4, 0), ":("),
             crate::Error::Buffer(Error::RowOutOfBounds { row: 4, limit: 3 })
         );
+
+        // Appending after the whole character, empty lines only have position 0
+        let buffer = new_buffer_with_content(&editor, "żółw\n");
+
+        buffer
+            .write()
+            .append_at_position(&Position::new(0, 0), "a")
+            .expect("Failed to append at position");
+        buffer
+            .write()
+            .append_at_position(&Position::new(1, 0), "b")
+            .expect("Failed to append at position");
+
+        assert_buffer_content!(buffer, "żaółw\nb");
     }
 
     pub fn test_buffer_append_many(editor: impl Editor) {
@@ -485,24 +1193,439 @@ Third line! :)"#
         assert!(values == nums, "Lists should be the same");
     }
 
+    pub fn test_buffer_data(editor: impl Editor) {
+        #[derive(Debug, PartialEq)]
+        struct Counter(usize);
+
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+        let other = editor.new_buffer().expect("Failed to create buffer");
+
+        assert!(buffer.data().get::<Counter>().is_none());
+
+        assert!(buffer.data().insert(Counter(1)).is_none());
+        assert_eq!(
+            buffer.clone().data().get::<Counter>().as_deref(),
+            Some(&Counter(1))
+        );
+        assert!(other.data().get::<Counter>().is_none());
+
+        let counter = buffer.data().get_or_insert_with(|| Counter(2));
+        assert_eq!(*counter, Counter(1));
+
+        buffer.data().insert(String::from("Hello"));
+        assert_eq!(buffer.data().len(), 2);
+
+        assert_eq!(
+            buffer.data().remove::<Counter>().as_deref(),
+            Some(&Counter(1))
+        );
+        assert!(!buffer.data().contains::<Counter>());
+
+        buffer.data().clear();
+        assert!(buffer.data().is_empty());
+    }
+
+    pub fn test_buffer_weak(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+        let weak = buffer.downgrade();
+
+        let upgraded = weak.upgrade().expect("Failed to upgrade weak handle");
+        assert!(upgraded == buffer);
+        assert_buffer_content!(upgraded, "First line");
+
+        let closed = Arc::new(AtomicBool::new(false));
+        buffer.on_close({
+            let closed = closed.clone();
+            move || closed.store(true, Ordering::SeqCst)
+        });
+
+        drop(upgraded);
+        assert!(weak.upgrade().is_some());
+        assert!(!closed.load(Ordering::SeqCst));
+    }
+
+    pub fn test_buffer_on_change(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        buffer.on_change({
+            let changes = changes.clone();
+            move || changes.fetch_add(1, Ordering::SeqCst) < 1
+        });
+
+        buffer.write().append("!").expect("Failed to append");
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        buffer.write().append("!").expect("Failed to append");
+        assert_eq!(changes.load(Ordering::SeqCst), 2);
+
+        // Unsubscribed by returning false
+        buffer.write().append("!").expect("Failed to append");
+        assert_eq!(changes.load(Ordering::SeqCst), 2);
+    }
+
+    pub fn test_buffer_downgrade_lock(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+        let written = AtomicBool::new(false);
+
+        let mut lock = buffer.write();
+        lock.append("\nSecond line").expect("Failed to append");
+
+        let lock = buffer.downgrade_lock(lock);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                buffer.write().append("!").expect("Failed to append");
+                written.store(true, Ordering::SeqCst);
+            });
+
+            // The writer waits until the downgraded lock is released
+            std::thread::sleep(std::time::Duration::from_millis(50));
+
+            assert!(!written.load(Ordering::SeqCst));
+            assert_eq!(
+                lock.get_content().expect("Failed to get content"),
+                "First line\nSecond line"
+            );
+
+            drop(lock);
+        });
+
+        assert!(written.load(Ordering::SeqCst));
+        assert_buffer_content!(buffer, "First line\nSecond line!");
+    }
+
+    pub fn test_buffer_scope(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+
+        let content = buffer.scope(|scope| {
+            assert!(!scope.is_locked());
+
+            let first = scope.read().get_line(0).expect("Failed to get line");
+
+            // Upgrading to a write lock, later reads reuse it and see the writes
+            scope
+                .write()
+                .append(&format!("\n{first}"))
+                .expect("Failed to append");
+            scope.write().append("!").expect("Failed to append");
+
+            assert_eq!(
+                scope.read().line_count().expect("Failed to get line count"),
+                2
+            );
+            assert!(scope.is_locked());
+
+            scope.read().get_content().expect("Failed to get content")
+        });
+
+        assert_eq!(content, "First line\nFirst line!");
+
+        // The lock is released once the scope ends, or earlier on request
+        buffer.write().append("?").expect("Failed to append");
+
+        buffer.scope(|scope| {
+            scope.read().line_count().expect("Failed to get line count");
+            scope.release();
+
+            buffer.write().append("?").expect("Failed to append");
+        });
+
+        assert_buffer_content!(buffer, "First line\nFirst line!??");
+    }
+
+    pub fn test_buffer_set_text_if_unchanged(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        let range = PosRange::new(Position::new(0, 0), Position::new(0, 5));
+
+        let tick = buffer
+            .read()
+            .changedtick()
+            .expect("Failed to get changedtick");
+        assert_eq!(
+            buffer.changedtick().expect("Failed to get changedtick"),
+            tick
+        );
+
+        buffer
+            .write()
+            .set_text_if_unchanged(tick, &range, "Last")
+            .expect("Failed to set text");
+        assert_buffer_content!(buffer, "Last line\nSecond line");
+
+        // The edit above moved the tick, so an edit computed before it is rejected
+        let actual = buffer
+            .read()
+            .changedtick()
+            .expect("Failed to get changedtick");
+        assert!(actual > tick);
+
+        let result = buffer.write().set_text_if_unchanged(tick, &range, "Other");
+        assert!(matches!(
+            result,
+            Err(crate::Error::Buffer(crate::buffer::Error::Conflict { expected, actual: a }))
+                if expected == tick && a == actual
+        ));
+        assert_buffer_content!(buffer, "Last line\nSecond line");
+    }
+
+    pub fn test_buffer_change_detection(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        let other = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let hash = buffer
+            .read()
+            .content_hash()
+            .expect("Failed to hash content");
+        let tick = buffer.changedtick().expect("Failed to get changedtick");
+
+        assert_eq!(
+            other.read().content_hash().expect("Failed to hash content"),
+            hash
+        );
+        assert_eq!(
+            buffer.changedtick().expect("Failed to get changedtick"),
+            tick
+        );
+
+        buffer.write().append("!").expect("Failed to append");
+
+        assert_ne!(
+            buffer
+                .read()
+                .content_hash()
+                .expect("Failed to hash content"),
+            hash
+        );
+        assert!(buffer.changedtick().expect("Failed to get changedtick") > tick);
+
+        // Line breaks are part of the hash, not just the characters
+        let joined = new_buffer_with_content(&editor, "First lineSecond line");
+        assert_ne!(
+            joined
+                .read()
+                .content_hash()
+                .expect("Failed to hash content"),
+            hash
+        );
+    }
+
+    pub fn test_buffer_transform_range(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+
+        let end = buffer
+            .write()
+            .transform_range(&Position::new(0, 6), &Position::new(1, 6), |text| {
+                assert_eq!(text, "line\nSecond");
+                text.to_uppercase().replace('\n', " ")
+            })
+            .expect("Failed to transform range");
+
+        assert_eq!(end, Position::new(0, 17));
+        assert_buffer_content!(buffer, "First LINE SECOND line\nThird line");
+
+        let end = buffer
+            .write()
+            .transform_range(&Position::new(1, 10), &Position::new(0, 22), |text| {
+                assert_eq!(text, "\nThird line");
+                "!\n\n".to_string()
+            })
+            .expect("Failed to transform range");
+
+        assert_eq!(end, Position::new(2, 0));
+        assert_buffer_content!(buffer, "First LINE SECOND line!\n\n");
+
+        let end = buffer
+            .write()
+            .transform_range(&Position::new(0, 0), &Position::new(0, 0), |text| {
+                assert_eq!(text, "");
+                "Ż".to_string()
+            })
+            .expect("Failed to transform range");
+
+        assert_eq!(end, Position::new(0, "Ż".len()));
+        assert_buffer_content!(buffer, "ŻFirst LINE SECOND line!\n\n");
+    }
+
+    const PATCH: &str = "\
+--- a/file
++++ b/file
+@@ -1,2 +1,2 @@
+-First line
++1st line
+ Second line
+@@ -4,2 +4,3 @@ Some context
+ Fourth line
+-Fifth line
++5th line
++Sixth line
+";
+
+    pub fn test_buffer_apply_patch(editor: impl Editor) {
+        let buffer = new_buffer_with_content(
+            &editor,
+            "First line\nSecond line\nThird line\nFourth line\nFifth line",
+        );
+
+        let results = buffer
+            .write()
+            .apply_patch(PATCH)
+            .expect("Failed to apply patch");
+
+        assert_eq!(
+            results,
+            [
+                AppliedHunk { row: 0, offset: 0 },
+                AppliedHunk { row: 3, offset: 0 },
+            ]
+        );
+        assert_buffer_content!(
+            buffer,
+            "1st line\nSecond line\nThird line\nFourth line\n5th line\nSixth line"
+        );
+
+        // Hunks are found near their stated position
+        let buffer = new_buffer_with_content(
+            &editor,
+            "Zeroth line\nFirst line\nSecond line\nThird line\nFourth line\nFifth line",
+        );
+
+        let results = buffer
+            .write()
+            .apply_patch(PATCH)
+            .expect("Failed to apply patch");
+
+        assert_eq!(
+            results,
+            [
+                AppliedHunk { row: 1, offset: 1 },
+                AppliedHunk { row: 4, offset: 1 },
+            ]
+        );
+
+        let buffer = new_buffer_with_content(&editor, "");
+
+        buffer
+            .write()
+            .apply_patch("@@ -0,0 +1,2 @@\n+a\n+b\n")
+            .expect("Failed to apply patch");
+        assert_buffer_content!(buffer, "a\nb");
+
+        buffer
+            .write()
+            .apply_patch("@@ -1,2 +0,0 @@\n-a\n-b\n")
+            .expect("Failed to apply patch");
+        assert_buffer_content!(buffer, "");
+    }
+
+    pub fn test_buffer_apply_patch_mismatch(editor: impl Editor) {
+        let buffer = new_buffer_with_content(
+            &editor,
+            "First line\nChanged line\nThird line\nFourth line\nFifth line",
+        );
+
+        let result = buffer.write().apply_patch(PATCH);
+
+        assert!(
+            matches!(
+                result,
+                Err(crate::Error::Buffer(Error::PatchRejected(ref hunks))) if hunks == &[0]
+            ),
+            "{result:?}"
+        );
+        // The matching hunk isn't applied either
+        assert_buffer_content!(
+            buffer,
+            "First line\nChanged line\nThird line\nFourth line\nFifth line"
+        );
+
+        for patch in [
+            "@@ -1,2 +1,2 @@\n-First line\n",
+            "@@ -x +1 @@\n+a\n",
+            "@@ -1 +1 @@\n?\n",
+        ] {
+            assert!(buffer.write().apply_patch(patch).is_err(), "{patch:?}");
+        }
+    }
+
+    pub fn test_buffer_fixtures(editor: impl Editor) {
+        for fixture in Fixture::all() {
+            let buffer = fixture.new_buffer(&editor);
+            let lines = fixture.content().split('\n').collect::<Vec<_>>();
+
+            let buffer = buffer.read();
+            assert_eq!(
+                buffer.line_count().expect("Failed to get line count"),
+                lines.len(),
+                "{fixture:?}"
+            );
+
+            for (row, line) in lines.iter().enumerate() {
+                assert_eq!(buffer.get_line(row).expect("Failed to get line"), *line);
+                assert_eq!(
+                    buffer.line_len(row).expect("Failed to get line length"),
+                    line.len()
+                );
+            }
+        }
+    }
+
+    /// Runs on [`Fixture::lorem`] with 3 lines.
+    pub fn test_buffer_fixture_apply(editor: impl Editor) {
+        let buffer = editor.current_buffer().expect("Failed to get buffer");
+        assert_buffer_content!(buffer, Fixture::lorem(3).content());
+
+        Fixture::crlf(2).apply(&buffer);
+        assert_buffer_content!(buffer, Fixture::crlf(2).content());
+    }
+
     #[macro_export]
     macro_rules! eel_buffer_tests {
-        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
-            $crate::eel_tests!(
-                test_tag: $test_tag,
-                editor_factory: $editor_factory,
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
                 editor_bounds: {},
                 module_path: $crate::buffer::tests,
-                prefix: $prefix,
                 tests: [
                     test_buffer_pos,
                     test_buffer_set_text,
+                    test_buffer_get_text,
+                    test_buffer_reversed_range,
+                    test_buffer_line_len,
+                    test_buffer_validate_range,
+                    test_buffer_checked,
                     test_buffer_append,
+                    test_buffer_delete,
+                    test_buffer_windows,
                     test_buffer_prepend,
                     test_buffer_pos_append,
                     test_buffer_append_many,
                     test_buffer_set_text_parallel,
+                    test_buffer_data,
+                    test_buffer_on_change,
+                    test_buffer_weak,
+                    test_buffer_downgrade_lock,
+                    test_buffer_scope,
+                    test_buffer_set_text_if_unchanged,
+                    test_buffer_change_detection,
+                    test_buffer_transform_range,
+                    test_buffer_apply_patch,
+                    test_buffer_apply_patch_mismatch,
+                    test_buffer_fixtures,
+                    test_buffer_fixture_apply: $crate::test_utils::Fixture::lorem(3),
                 ],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_buffer_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_buffer_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
             );
         };
 
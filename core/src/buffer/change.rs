@@ -0,0 +1,75 @@
+use std::sync::{Mutex, PoisonError};
+
+type ChangeCallback = Box<dyn FnMut() -> bool + Send>;
+
+/// Callbacks registered through [`super::BufferHandle::on_change`], for backends to run after
+/// every change to the buffer.
+pub struct ChangeHooks {
+    /// `None` once the buffer is closed
+    callbacks: Mutex<Option<Vec<ChangeCallback>>>,
+}
+
+impl std::fmt::Debug for ChangeHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let callbacks = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        f.debug_struct("ChangeHooks")
+            .field("closed", &callbacks.is_none())
+            .field("callbacks", &callbacks.as_ref().map_or(0, Vec::len))
+            .finish()
+    }
+}
+
+impl Default for ChangeHooks {
+    fn default() -> Self {
+        Self {
+            callbacks: Mutex::new(Some(Vec::new())),
+        }
+    }
+}
+
+impl ChangeHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Callbacks registered after the buffer was closed are dropped.
+    pub fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        if let Some(callbacks) = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            callbacks.push(Box::new(callback));
+        }
+    }
+
+    /// Runs all callbacks, dropping the ones returning `false`.
+    pub fn changed(&self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().edits_applied.inc();
+
+        if let Some(callbacks) = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            callbacks.retain_mut(|callback| callback());
+        }
+    }
+
+    pub fn close(&self) {
+        let callbacks = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        drop(callbacks);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::BTreeSet,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{PosRange, Position, Result};
+
+use super::{ReadBuffer, Validator, WriteBuffer};
+
+/// Rows shown around each end of the offending range in [`Diagnostic::excerpt`].
+const EXCERPT_CONTEXT: usize = 1;
+
+/// Failed operation of a [`Checked`] buffer, with the context needed to see what went wrong.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Name of the operation, e.g. `"set_text"`.
+    pub operation: &'static str,
+    /// Range the operation was given, empty for single positions.
+    pub range: PosRange,
+    /// `range` with both ends moved to the closest valid positions, `None` if the buffer
+    /// couldn't be read.
+    pub nearest_valid: Option<PosRange>,
+    /// Rows around the ends of `nearest_valid` and their text.
+    pub excerpt: Vec<(usize, String)>,
+    pub error: crate::Error,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn range(range: &PosRange) -> String {
+            let (start, end) = (range.start(), range.end());
+            format!("{}:{}..{}:{}", start.row, start.col, end.row, end.col)
+        }
+
+        write!(
+            f,
+            "{} failed at {}: {}",
+            self.operation,
+            range(&self.range),
+            self.error
+        )?;
+
+        if let Some(nearest) = &self.nearest_valid {
+            write!(f, "\nNearest valid range: {}", range(nearest))?;
+        }
+
+        let width = self
+            .excerpt
+            .last()
+            .map_or(0, |(row, _)| row.to_string().len());
+        for (row, line) in &self.excerpt {
+            let offending = (self.range.start().row..=self.range.end().row).contains(row);
+            let marker = if offending { '>' } else { ' ' };
+
+            write!(f, "\n{marker} {row:>width$} | {line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Diagnostics are boxed, they're large and only built on failures.
+pub type CheckedResult<T> = std::result::Result<T, Box<Diagnostic>>;
+
+/// Buffer whose operations fail with a [`Diagnostic`] instead of a bare
+/// [`Error`](crate::Error), see [`ReadBuffer::checked`] and [`WriteBuffer::checked_mut`].
+///
+/// Ranges are validated before they reach the backend, so invalid ones are reported the same
+/// way by every backend.
+pub struct Checked<T>(T);
+
+impl<T> Checked<T>
+where
+    T: Deref,
+    T::Target: ReadBuffer,
+{
+    pub(super) fn new(buffer: T) -> Self {
+        Self(buffer)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    fn diagnose(
+        &self,
+        operation: &'static str,
+        range: PosRange,
+        error: crate::Error,
+    ) -> Box<Diagnostic> {
+        let nearest_valid = nearest_valid(&*self.0, range.start())
+            .and_then(|start| Ok(PosRange::new(start, nearest_valid(&*self.0, range.end())?)))
+            .ok();
+
+        let excerpt = nearest_valid
+            .as_ref()
+            .map(|nearest| excerpt(&*self.0, nearest))
+            .unwrap_or_default();
+
+        Box::new(Diagnostic {
+            operation,
+            range,
+            nearest_valid,
+            excerpt,
+            error,
+        })
+    }
+
+    fn check<R>(
+        &self,
+        operation: &'static str,
+        range: PosRange,
+        f: impl FnOnce(&T::Target, &PosRange) -> Result<R>,
+    ) -> CheckedResult<R> {
+        let validator = Validator::new(&*self.0);
+
+        validator
+            .validate_pos(range.start())
+            .and_then(|_| validator.validate_pos(range.end()))
+            .and_then(|_| f(&*self.0, &range))
+            .map_err(|error| self.diagnose(operation, range, error))
+    }
+
+    pub fn validate_pos(&self, position: &Position) -> CheckedResult<()> {
+        self.validate_range(PosRange::new(position.clone(), position.clone()))
+    }
+
+    pub fn validate_range(&self, range: impl Into<PosRange>) -> CheckedResult<()> {
+        self.check("validate_range", range.into(), |_, _| Ok(()))
+    }
+
+    pub fn get_line(&self, row: usize) -> CheckedResult<String> {
+        let start = Position::new(row, 0);
+
+        self.check(
+            "get_line",
+            PosRange::new(start.clone(), start),
+            |buffer, _| buffer.get_line(row),
+        )
+    }
+
+    pub fn get_text(&self, range: impl Into<PosRange>) -> CheckedResult<String> {
+        self.check("get_text", range.into(), |buffer, range| {
+            buffer.get_text_in(range)
+        })
+    }
+
+    pub fn char_at(&self, position: &Position) -> CheckedResult<Option<char>> {
+        let range = PosRange::new(position.clone(), position.clone());
+
+        self.check("char_at", range, |buffer, range| {
+            buffer.char_at(range.start())
+        })
+    }
+}
+
+impl<T> Checked<T>
+where
+    T: DerefMut,
+    T::Target: WriteBuffer,
+{
+    fn check_mut<R>(
+        &mut self,
+        operation: &'static str,
+        range: PosRange,
+        f: impl FnOnce(&mut T::Target, &PosRange) -> Result<R>,
+    ) -> CheckedResult<R> {
+        self.check(operation, range.clone(), |_, _| Ok(()))?;
+
+        f(&mut *self.0, &range).map_err(|error| self.diagnose(operation, range, error))
+    }
+
+    pub fn set_text(&mut self, range: impl Into<PosRange>, text: &str) -> CheckedResult<()> {
+        self.check_mut("set_text", range.into(), |buffer, range| {
+            buffer.set_text_in(range, text)
+        })
+    }
+
+    pub fn set_line(&mut self, row: usize, line: &str) -> CheckedResult<()> {
+        let start = Position::new(row, 0);
+
+        self.check_mut(
+            "set_line",
+            PosRange::new(start.clone(), start),
+            |buffer, _| buffer.set_line(row, line),
+        )
+    }
+}
+
+/// Closest insert position, the end of the buffer for rows past it, moving back to the start
+/// of a character if `position` splits one.
+fn nearest_valid<B: ReadBuffer + ?Sized>(buffer: &B, position: &Position) -> Result<Position> {
+    if position.row > buffer.max_row()? {
+        return buffer.max_pos();
+    }
+
+    let line = buffer.get_line(position.row)?;
+    let col = (0..=position.col.min(line.len()))
+        .rev()
+        .find(|col| line.is_char_boundary(*col))
+        .unwrap_or(0);
+
+    Ok(Position::new(position.row, col))
+}
+
+/// Unreadable rows are left out, the excerpt is only there to help.
+fn excerpt<B: ReadBuffer + ?Sized>(buffer: &B, range: &PosRange) -> Vec<(usize, String)> {
+    let Ok(max_row) = buffer.max_row() else {
+        return Vec::new();
+    };
+
+    let around =
+        |row: usize| row.saturating_sub(EXCERPT_CONTEXT)..=(row + EXCERPT_CONTEXT).min(max_row);
+
+    around(range.start().row)
+        .chain(around(range.end().row))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|row| Some((row, buffer.get_line(row).ok()?)))
+        .collect()
+}
@@ -0,0 +1,64 @@
+use std::sync::{Mutex, PoisonError};
+
+type CloseCallback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct State {
+    closed: bool,
+    callbacks: Vec<CloseCallback>,
+}
+
+/// Callbacks registered through [`super::BufferHandle::on_close`], for backends to run once
+/// the buffer is gone.
+#[derive(Default)]
+pub struct CloseHooks {
+    state: Mutex<State>,
+}
+
+impl std::fmt::Debug for CloseHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        f.debug_struct("CloseHooks")
+            .field("closed", &state.closed)
+            .field("callbacks", &state.callbacks.len())
+            .finish()
+    }
+}
+
+impl CloseHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Callbacks registered after the buffer was closed are called immediately.
+    pub fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.closed {
+            drop(state);
+            callback();
+        } else {
+            state.callbacks.push(Box::new(callback));
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed
+    }
+
+    pub fn close(&self) {
+        let callbacks = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.closed = true;
+            std::mem::take(&mut state.callbacks)
+        };
+
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
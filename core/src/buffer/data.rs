@@ -0,0 +1,94 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+type DataMap = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// Typed per-buffer storage, holding at most one value of each type.
+///
+/// Backends clear it when the underlying buffer is wiped, so plugin state doesn't outlive
+/// the buffer it was computed for.
+#[derive(Default)]
+pub struct BufferData {
+    values: RwLock<DataMap>,
+}
+
+impl std::fmt::Debug for BufferData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferData")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl BufferData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, DataMap> {
+        self.values.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, DataMap> {
+        self.values.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn downcast<T: Any + Send + Sync>(value: Arc<dyn Any + Send + Sync>) -> Arc<T> {
+        value
+            .downcast()
+            .unwrap_or_else(|_| unreachable!("BufferData values are keyed by their TypeId"))
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.read()
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .map(Self::downcast)
+    }
+
+    /// Returns the previous value of the same type.
+    pub fn insert<T: Any + Send + Sync>(&self, value: T) -> Option<Arc<T>> {
+        self.write()
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .map(Self::downcast)
+    }
+
+    pub fn get_or_insert_with<T: Any + Send + Sync>(&self, init: impl FnOnce() -> T) -> Arc<T> {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        let value = self
+            .write()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(init()))
+            .clone();
+
+        Self::downcast(value)
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.write().remove(&TypeId::of::<T>()).map(Self::downcast)
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.read().contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn clear(&self) {
+        // Values are dropped outside of the lock, their destructors may access the data again
+        let values = std::mem::take(&mut *self.write());
+        drop(values);
+    }
+}
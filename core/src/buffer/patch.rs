@@ -0,0 +1,203 @@
+use crate::{Position, Result};
+
+use super::{Error, WriteBuffer};
+
+/// Where a hunk was applied, starting at `row`, `offset` rows away from where the hunk header
+/// put it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppliedHunk {
+    pub row: usize,
+    pub offset: isize,
+}
+
+#[derive(Debug)]
+struct Hunk {
+    /// Index of the first old line, for pure insertions the row to insert at.
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+fn malformed(message: impl std::fmt::Display) -> Error {
+    Error::Custom(format!("Malformed patch: {message}").into())
+}
+
+/// Parses `start[,len]`.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn parse_header(line: &str) -> Option<((usize, usize), (usize, usize))> {
+    let mut parts = line.strip_prefix("@@ ")?.split(' ');
+
+    let old = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let new = parse_range(parts.next()?.strip_prefix('+')?)?;
+
+    (parts.next()? == "@@").then_some((old, new))
+}
+
+/// Lines outside of hunks (file headers, `diff --git` lines etc.) are ignored.
+fn parse(patch: &str) -> std::result::Result<Vec<Hunk>, Error> {
+    let mut hunks = Vec::new();
+    let mut lines = patch.lines();
+
+    while let Some(line) = lines.next() {
+        if !line.starts_with("@@") {
+            continue;
+        }
+
+        let ((old_start, old_len), (_, new_len)) =
+            parse_header(line).ok_or_else(|| malformed(format!("invalid hunk header {line:?}")))?;
+
+        let mut hunk = Hunk {
+            old_start: if old_len == 0 {
+                old_start
+            } else {
+                old_start.saturating_sub(1)
+            },
+            old: Vec::new(),
+            new: Vec::new(),
+        };
+
+        // Counting lines, so blank context lines with their leading space stripped still parse
+        while hunk.old.len() < old_len || hunk.new.len() < new_len {
+            let line = lines
+                .next()
+                .ok_or_else(|| malformed("hunk is shorter than its header"))?;
+
+            if line.starts_with('\\') {
+                continue;
+            }
+
+            let (kind, text) = line.split_at(line.len().min(1));
+
+            match kind {
+                " " | "" => {
+                    hunk.old.push(text.to_string());
+                    hunk.new.push(text.to_string());
+                }
+                "-" => hunk.old.push(text.to_string()),
+                "+" => hunk.new.push(text.to_string()),
+                _ => return Err(malformed(format!("unexpected hunk line {line:?}"))),
+            }
+        }
+
+        if hunk.old.len() != old_len || hunk.new.len() != new_len {
+            return Err(malformed("hunk is longer than its header"));
+        }
+
+        hunks.push(hunk);
+    }
+
+    Ok(hunks)
+}
+
+/// Row closest to `expected`, not before `min_row`, at which `old` matches `lines`.
+fn find_hunk(lines: &[String], old: &[String], expected: usize, min_row: usize) -> Option<usize> {
+    let max_row = lines.len().checked_sub(old.len())?;
+
+    if old.is_empty() {
+        return (min_row..=max_row).contains(&expected).then_some(expected);
+    }
+
+    let matches = |row: usize| (min_row..=max_row).contains(&row) && lines[row..].starts_with(old);
+
+    (0..=lines.len()).find_map(|distance| {
+        [
+            expected.checked_add(distance),
+            expected.checked_sub(distance),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|row| matches(*row))
+    })
+}
+
+/// Replaces `len` rows starting at `row` with `new` rows, `line_count` being the number of
+/// rows in the buffer (0 if it's empty).
+fn replace_rows<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    row: usize,
+    len: usize,
+    new: &[String],
+    line_count: usize,
+) -> Result<()> {
+    let text = new.join("\n");
+
+    if row + len < line_count {
+        let text = if new.is_empty() { text } else { text + "\n" };
+
+        buffer.set_text((Position::new(row, 0), Position::new(row + len, 0)), &text)
+    } else if row > 0 {
+        // Rows up to the end of the buffer, taking the preceding line break with them
+        let text = if new.is_empty() {
+            text
+        } else {
+            "\n".to_string() + &text
+        };
+
+        buffer.set_text((&buffer.max_row_pos(row - 1)?, &buffer.max_pos()?), &text)
+    } else {
+        buffer.set_content(&text)
+    }
+}
+
+/// All hunks are located before the buffer is changed, so a mismatching one leaves it as it
+/// was, and a failed edit restores its content.
+pub(super) fn apply<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    patch: &str,
+) -> Result<Vec<AppliedHunk>> {
+    let hunks = parse(patch)?;
+
+    let mut lines: Vec<String> = buffer.get_all_lines()?.collect();
+
+    // A diff of an empty file has no lines, while an empty buffer has one
+    if lines == [""] {
+        lines.clear();
+    }
+
+    let original = lines.join("\n");
+
+    let mut shift = 0isize;
+    let mut min_row = 0;
+    let mut rejected = Vec::new();
+    let mut edits = Vec::new();
+
+    for (i, hunk) in hunks.into_iter().enumerate() {
+        let expected = hunk.old_start.saturating_add_signed(shift);
+
+        let Some(row) = find_hunk(&lines, &hunk.old, expected, min_row) else {
+            rejected.push(i);
+            continue;
+        };
+
+        min_row = row + hunk.new.len();
+        shift += hunk.new.len() as isize - hunk.old.len() as isize;
+
+        let applied = AppliedHunk {
+            row,
+            offset: row as isize - expected as isize,
+        };
+        edits.push((applied, hunk.old.len(), lines.len(), hunk.new.clone()));
+        lines.splice(row..row + hunk.old.len(), hunk.new);
+    }
+
+    if !rejected.is_empty() {
+        Err(Error::PatchRejected(rejected))?;
+    }
+
+    for (applied, len, line_count, new) in &edits {
+        if let Err(e) = replace_rows(buffer, applied.row, *len, new, *line_count) {
+            // The edit's error is the one worth reporting
+            let _ = buffer.set_content(&original);
+
+            return Err(e);
+        }
+    }
+
+    Ok(edits.into_iter().map(|(applied, ..)| applied).collect())
+}
@@ -0,0 +1,122 @@
+use std::ops::RangeBounds;
+
+use itertools::Either;
+
+use crate::{PosRange, Position, Result, indent::IndentSettings};
+
+use super::{BufferHandle, ReadBuffer};
+
+enum ScopeLock<B: BufferHandle> {
+    Unlocked,
+    Read(B::ReadBufferLock),
+    Write(B::WriteBufferLock),
+}
+
+/// Lock held across the calls of a [`BufferHandle::scope`], so code within the scope can read
+/// and write repeatedly without re-locking (and deadlocking on a lock it already holds).
+///
+/// The lock is taken on first use and released when the scope ends.
+pub struct BufferScope<'a, B: BufferHandle> {
+    handle: &'a B,
+    lock: ScopeLock<B>,
+}
+
+impl<'a, B: BufferHandle> BufferScope<'a, B> {
+    pub(super) fn new(handle: &'a B) -> Self {
+        Self {
+            handle,
+            lock: ScopeLock::Unlocked,
+        }
+    }
+
+    pub fn handle(&self) -> &B {
+        self.handle
+    }
+
+    /// Reuses the held lock, read or write, taking a read lock if none is held.
+    pub fn read(&mut self) -> ScopedRead<'_, B> {
+        if let ScopeLock::Unlocked = self.lock {
+            self.lock = ScopeLock::Read(self.handle.read());
+        }
+
+        match &self.lock {
+            ScopeLock::Read(lock) => ScopedRead::Read(&**lock),
+            ScopeLock::Write(lock) => ScopedRead::Write(&**lock),
+            ScopeLock::Unlocked => unreachable!("Lock was just taken"),
+        }
+    }
+
+    /// Reuses a held write lock. A held read lock is released before taking the write lock,
+    /// so anything read through it may be outdated once this returns.
+    pub fn write(&mut self) -> &mut B::WriteBuffer {
+        if !matches!(self.lock, ScopeLock::Write(_)) {
+            self.release();
+            self.lock = ScopeLock::Write(self.handle.write());
+        }
+
+        match &mut self.lock {
+            ScopeLock::Write(lock) => lock,
+            _ => unreachable!("Write lock was just taken"),
+        }
+    }
+
+    /// Releases the held lock early, e.g. before waiting for another thread using the buffer.
+    pub fn release(&mut self) {
+        self.lock = ScopeLock::Unlocked;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        !matches!(self.lock, ScopeLock::Unlocked)
+    }
+}
+
+/// Read access through whichever lock a [`BufferScope`] holds.
+pub enum ScopedRead<'a, B: BufferHandle> {
+    Read(&'a B::ReadBuffer),
+    Write(&'a B::WriteBuffer),
+}
+
+macro_rules! delegate {
+    ($self:ident, $buffer:ident => $call:expr) => {
+        match $self {
+            ScopedRead::Read($buffer) => $call,
+            ScopedRead::Write($buffer) => $call,
+        }
+    };
+}
+
+impl<B: BufferHandle> ReadBuffer for ScopedRead<'_, B> {
+    fn line_count(&self) -> Result<usize> {
+        delegate!(self, buffer => buffer.line_count())
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        Ok(match self {
+            ScopedRead::Read(buffer) => Either::Left(buffer.get_lines(range)?),
+            ScopedRead::Write(buffer) => Either::Right(buffer.get_lines(range)?),
+        })
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        delegate!(self, buffer => buffer.changedtick())
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        delegate!(self, buffer => buffer.indent_settings())
+    }
+
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        delegate!(self, buffer => buffer.get_text_in(range))
+    }
+
+    fn line_len(&self, row: usize) -> Result<usize> {
+        delegate!(self, buffer => buffer.line_len(row))
+    }
+
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        delegate!(self, buffer => buffer.char_at(position))
+    }
+}
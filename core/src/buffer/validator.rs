@@ -0,0 +1,152 @@
+use std::cell::{Cell, RefCell};
+
+use crate::{Position, Result};
+
+use super::{Error, ReadBuffer};
+
+/// Which column bounds a position is validated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Positions of characters, the column can't be past the last character (0 on empty
+    /// lines), like a normal mode cursor.
+    Cursor,
+    /// Positions between characters, the column may be right after the last character, like
+    /// an insert mode cursor or the ends of ranges.
+    #[default]
+    Insert,
+}
+
+/// Validates positions against a buffer, fetching `line_count` and each row's length or line at
+/// most once.
+///
+/// Only valid while the buffer can't change, i.e. within a single lock scope.
+pub struct Validator<'a, B: ReadBuffer + ?Sized> {
+    buffer: &'a B,
+    line_count: Cell<Option<usize>>,
+    lines: RefCell<Vec<Line>>,
+}
+
+struct Line {
+    row: usize,
+    len: usize,
+    /// Only fetched once a column has to be checked against the characters.
+    text: Option<String>,
+}
+
+impl<'a, B: ReadBuffer + ?Sized> Validator<'a, B> {
+    pub fn new(buffer: &'a B) -> Self {
+        Self {
+            buffer,
+            line_count: Cell::new(None),
+            lines: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn line_count(&self) -> Result<usize> {
+        if let Some(count) = self.line_count.get() {
+            return Ok(count);
+        }
+
+        let count = self.buffer.line_count()?;
+        self.line_count.set(Some(count));
+
+        Ok(count)
+    }
+
+    pub fn line_len(&self, row: usize) -> Result<usize> {
+        if let Some(line) = self.lines.borrow().iter().find(|line| line.row == row) {
+            return Ok(line.len);
+        }
+
+        let len = self.buffer.line_len(row)?;
+        self.lines.borrow_mut().push(Line {
+            row,
+            len,
+            text: None,
+        });
+
+        Ok(len)
+    }
+
+    fn with_line<R>(&self, row: usize, f: impl FnOnce(&str) -> R) -> Result<R> {
+        if let Some(text) = self
+            .lines
+            .borrow()
+            .iter()
+            .find(|line| line.row == row)
+            .and_then(|line| line.text.as_deref())
+        {
+            return Ok(f(text));
+        }
+
+        let text = self.buffer.get_line(row)?;
+        let result = f(&text);
+
+        let mut lines = self.lines.borrow_mut();
+        lines.retain(|line| line.row != row);
+        lines.push(Line {
+            row,
+            len: text.len(),
+            text: Some(text),
+        });
+
+        Ok(result)
+    }
+
+    pub fn validate_pos(&self, position: &Position) -> Result<()> {
+        self.validate_pos_with(position, ValidationMode::Insert)
+    }
+
+    pub fn validate_pos_with(&self, position: &Position, mode: ValidationMode) -> Result<()> {
+        let max_row = self.line_count()? - 1;
+
+        if position.row > max_row {
+            Err(Error::RowOutOfBounds {
+                row: position.row as isize,
+                limit: max_row,
+            })?;
+        }
+
+        // The line is needed for the boundary check anyway, its length comes with it
+        let len = if position.col > 0 {
+            self.with_line(position.row, str::len)?
+        } else {
+            self.line_len(position.row)?
+        };
+
+        let out_of_bounds = match mode {
+            ValidationMode::Cursor => position.col >= len.max(1),
+            ValidationMode::Insert => position.col > len,
+        };
+
+        if out_of_bounds {
+            let limit = match mode {
+                // Start of the last character
+                ValidationMode::Cursor => self.last_char_col(position.row)?,
+                ValidationMode::Insert => len,
+            };
+
+            Err(Error::ColOutOfBounds {
+                col: position.col as isize,
+                limit,
+            })?;
+        }
+
+        // Both ends of the line are always boundaries
+        if position.col > 0
+            && position.col < len
+            && !self.with_line(position.row, |line| line.is_char_boundary(position.col))?
+        {
+            Err(Error::NotCharBoundary {
+                row: position.row,
+                col: position.col,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn last_char_col(&self, row: usize) -> Result<usize> {
+        self.with_line(row, |line| line.char_indices().last().map_or(0, |(i, _)| i))
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{PosRange, Position, Result};
+
+use super::ReadBuffer;
+
+/// Groups of consecutive lines and the range they span, see [`ReadBuffer::windows`] and
+/// [`ReadBuffer::chunks`].
+///
+/// Lines are only read as the iterator advances. It stops after the first error.
+pub struct LineWindows<'a, B: ?Sized> {
+    buffer: &'a B,
+    size: usize,
+    stride: usize,
+    row: usize,
+    line_count: Option<usize>,
+    done: bool,
+}
+
+impl<'a, B: ReadBuffer + ?Sized> LineWindows<'a, B> {
+    pub(super) fn new(buffer: &'a B, size: usize, stride: usize) -> Self {
+        assert!(size > 0, "Window size must be non-zero");
+        assert!(stride > 0, "Window stride must be non-zero");
+
+        Self {
+            buffer,
+            size,
+            stride,
+            row: 0,
+            line_count: None,
+            done: false,
+        }
+    }
+
+    fn line_count(&mut self) -> Result<usize> {
+        if let Some(line_count) = self.line_count {
+            return Ok(line_count);
+        }
+
+        let line_count = self.buffer.line_count()?;
+        self.line_count = Some(line_count);
+
+        Ok(line_count)
+    }
+
+    fn window(&mut self) -> Result<Option<(PosRange, Vec<String>)>> {
+        let line_count = self.line_count()?;
+        if self.row >= line_count {
+            return Ok(None);
+        }
+
+        let end = (self.row + self.size).min(line_count);
+        let range = PosRange::new(
+            Position::new(self.row, 0),
+            self.buffer.max_row_pos(end - 1)?,
+        );
+        let lines = self.buffer.get_lines(self.row..end)?.collect();
+
+        // The last window is the one reaching the end, later ones would only overlap it
+        if end == line_count {
+            self.done = true;
+        }
+        self.row += self.stride;
+
+        Ok(Some((range, lines)))
+    }
+}
+
+impl<B: ReadBuffer + ?Sized> Iterator for LineWindows<'_, B> {
+    type Item = Result<(PosRange, Vec<String>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let window = self.window();
+        if !matches!(window, Ok(Some(_))) {
+            self.done = true;
+        }
+
+        window.transpose()
+    }
+}
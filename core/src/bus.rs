@@ -0,0 +1,229 @@
+//! Pub/sub topics for plugins to coordinate without sharing globals, e.g. a formatter
+//! announcing reformatted buffers, see [`crate::Editor::publish`].
+//!
+//! Values are sent as JSON, so plugins linking their own copy of eel, or not written in Rust
+//! at all, can exchange them. Subscribers only receive the values that deserialize as the
+//! type they subscribed with.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex, MutexGuard, OnceLock, PoisonError,
+        mpsc::{Receiver, Sender, channel},
+    },
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::Result;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Bus payload error: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+/// What a subscriber did with a payload published to its topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Received,
+    /// The payload isn't of the subscriber's type.
+    Skipped,
+    /// The subscription was dropped, the subscriber is removed.
+    Unsubscribed,
+}
+
+pub type Subscriber = Box<dyn Fn(&str) -> Delivery + Send>;
+
+pub fn encode<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value).map_err(Error::from)?)
+}
+
+/// Subscriber sending the payloads deserializing as `T` to `sender`.
+pub fn subscriber<T: DeserializeOwned + Send + 'static>(sender: Sender<T>) -> Subscriber {
+    Box::new(move |payload| {
+        let Ok(value) = serde_json::from_str::<T>(payload) else {
+            return Delivery::Skipped;
+        };
+
+        match sender.send(value) {
+            Ok(()) => Delivery::Received,
+            Err(_) => Delivery::Unsubscribed,
+        }
+    })
+}
+
+/// Topics of the subscribers in this process, for backends without a bus of their own.
+#[derive(Default)]
+pub struct Bus {
+    subscribers: Mutex<HashMap<String, Vec<Subscriber>>>,
+}
+
+impl std::fmt::Debug for Bus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bus")
+            .field("topics", &self.subscribers().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bus used by [`crate::Editor::publish`] unless the backend has its own.
+    pub fn global() -> &'static Bus {
+        static BUS: OnceLock<Bus> = OnceLock::new();
+
+        BUS.get_or_init(Bus::new)
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, HashMap<String, Vec<Subscriber>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Receives every value of type `T` published to `topic` from now on, until dropped.
+    pub fn subscribe<T: DeserializeOwned + Send + 'static>(&self, topic: &str) -> Receiver<T> {
+        let (sender, receiver) = channel::<T>();
+        self.subscribe_with(topic, subscriber(sender));
+
+        receiver
+    }
+
+    pub fn subscribe_with(&self, topic: &str, subscriber: Subscriber) {
+        self.subscribers()
+            .entry(topic.to_string())
+            .or_default()
+            .push(subscriber);
+    }
+
+    /// Sends `value` to the subscribers of `topic`, returns how many received it.
+    pub fn publish<T: Serialize>(&self, topic: &str, value: &T) -> Result<usize> {
+        Ok(self.publish_payload(topic, &encode(value)?))
+    }
+
+    /// Sends a JSON `payload` to the subscribers of `topic`, returns how many received it.
+    /// Dropped subscriptions are removed.
+    pub fn publish_payload(&self, topic: &str, payload: &str) -> usize {
+        let mut subscribers = self.subscribers();
+
+        let Some(topic_subscribers) = subscribers.get_mut(topic) else {
+            return 0;
+        };
+
+        let mut received = 0;
+        topic_subscribers.retain(|subscriber| match subscriber(payload) {
+            Delivery::Received => {
+                received += 1;
+                true
+            }
+            Delivery::Skipped => true,
+            Delivery::Unsubscribed => false,
+        });
+
+        if topic_subscribers.is_empty() {
+            subscribers.remove(topic);
+        }
+
+        received
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::Editor;
+
+    /// Topics are shared by everything in the process, or the whole editor.
+    fn unique_topic() -> String {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        format!(
+            "eel_test_bus_{}_{}",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    struct Reformatted {
+        buffer: usize,
+    }
+
+    pub fn test_bus<E: Editor>(editor: E) {
+        let topic = &unique_topic();
+
+        assert_eq!(
+            editor.publish(topic, &Reformatted { buffer: 0 }).ok(),
+            Some(0)
+        );
+
+        let first = editor
+            .subscribe::<Reformatted>(topic)
+            .expect("Failed to subscribe");
+        let second = editor
+            .subscribe::<Reformatted>(topic)
+            .expect("Failed to subscribe");
+        let other_type = editor
+            .subscribe::<String>(topic)
+            .expect("Failed to subscribe");
+
+        assert_eq!(
+            editor.publish(topic, &Reformatted { buffer: 1 }).ok(),
+            Some(2)
+        );
+        assert_eq!(first.try_recv().ok(), Some(Reformatted { buffer: 1 }));
+        assert_eq!(second.try_recv().ok(), Some(Reformatted { buffer: 1 }));
+        assert!(other_type.try_recv().is_err());
+
+        drop(second);
+        assert_eq!(
+            editor.publish(topic, &Reformatted { buffer: 2 }).ok(),
+            Some(1)
+        );
+        assert_eq!(first.try_recv().ok(), Some(Reformatted { buffer: 2 }));
+
+        assert_eq!(editor.publish(topic, &"text").ok(), Some(1));
+        assert_eq!(other_type.try_recv().ok().as_deref(), Some("text"));
+    }
+
+    /// Publishing from other threads reaches the subscribers too.
+    pub fn test_bus_threads<E: Editor>(editor: E) {
+        let topic = &unique_topic();
+        let editor = std::sync::Arc::new(editor);
+
+        let receiver = editor
+            .subscribe::<Reformatted>(topic)
+            .expect("Failed to subscribe");
+
+        let publisher = std::thread::spawn({
+            let (editor, topic) = (editor.clone(), topic.clone());
+            move || editor.publish(&topic, &Reformatted { buffer: 3 }).ok()
+        });
+
+        assert_eq!(publisher.join().ok().flatten(), Some(1));
+        assert_eq!(receiver.try_recv().ok(), Some(Reformatted { buffer: 3 }));
+    }
+
+    #[macro_export]
+    macro_rules! eel_bus_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::bus::tests,
+                prefix: $prefix,
+                tests: [test_bus, test_bus_threads],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_bus_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,501 @@
+//! Experimental collaborative editing on top of any [`BufferHandle`].
+//!
+//! Implements the two-party Jupiter OT protocol over single character operations, so
+//! transformations never have to split an operation. A server relaying between several
+//! clients keeps one [`CollabBuffer`] per client.
+//!
+//! Remote operations are applied through regular `set_text` calls, so marks and the cursor are
+//! moved by the backend the same way as for local edits.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex, PoisonError,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+pub type SiteId = u64;
+
+/// Offsets are in characters from the start of the buffer, line breaks included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Insert {
+        offset: usize,
+        ch: char,
+        site: SiteId,
+    },
+    Delete {
+        offset: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub ops: Vec<Op>,
+    /// Messages sent by the author before this one.
+    pub sent: u64,
+    /// Messages the author received before sending this one.
+    pub received: u64,
+}
+
+/// Returns `a` transformed to apply after `b` and `b` transformed to apply after `a`.
+fn transform(a: &Op, b: &Op) -> (Option<Op>, Option<Op>) {
+    use Op::*;
+
+    match (a, b) {
+        (
+            Insert {
+                offset: a_off,
+                site: a_site,
+                ..
+            },
+            Insert {
+                offset: b_off,
+                site: b_site,
+                ..
+            },
+        ) => {
+            if (a_off, a_site) < (b_off, b_site) {
+                (Some(a.clone()), Some(shifted(b, 1)))
+            } else {
+                (Some(shifted(a, 1)), Some(b.clone()))
+            }
+        }
+        (Insert { offset: a_off, .. }, Delete { offset: b_off }) => {
+            if a_off <= b_off {
+                (Some(a.clone()), Some(shifted(b, 1)))
+            } else {
+                (Some(shifted(a, -1)), Some(b.clone()))
+            }
+        }
+        (Delete { offset: a_off }, Insert { offset: b_off, .. }) => {
+            if b_off <= a_off {
+                (Some(shifted(a, 1)), Some(b.clone()))
+            } else {
+                (Some(a.clone()), Some(shifted(b, -1)))
+            }
+        }
+        (Delete { offset: a_off }, Delete { offset: b_off }) => match a_off.cmp(b_off) {
+            std::cmp::Ordering::Less => (Some(a.clone()), Some(shifted(b, -1))),
+            std::cmp::Ordering::Greater => (Some(shifted(a, -1)), Some(b.clone())),
+            // Both sides deleted the same character
+            std::cmp::Ordering::Equal => (None, None),
+        },
+    }
+}
+
+fn shifted(op: &Op, by: isize) -> Op {
+    let mut op = op.clone();
+
+    match &mut op {
+        Op::Insert { offset, .. } | Op::Delete { offset } => {
+            *offset = offset.saturating_add_signed(by);
+        }
+    }
+
+    op
+}
+
+fn transform_seq(a: Vec<Op>, b: Vec<Op>) -> (Vec<Op>, Vec<Op>) {
+    let mut a = a;
+    let mut b_result = Vec::with_capacity(b.len());
+
+    for b_op in b {
+        let mut b_op = Some(b_op);
+        let mut a_next = Vec::with_capacity(a.len());
+
+        for a_op in a {
+            match b_op.take() {
+                Some(current) => {
+                    let (a_op, b_transformed) = transform(&a_op, &current);
+                    a_next.extend(a_op);
+                    b_op = b_transformed;
+                }
+                None => a_next.push(a_op),
+            }
+        }
+
+        b_result.extend(b_op);
+        a = a_next;
+    }
+
+    (a, b_result)
+}
+
+fn char_offset<B: ReadBuffer + ?Sized>(buffer: &B, position: &Position) -> Result<usize> {
+    let mut offset = 0;
+
+    for row in 0..position.row {
+        offset += buffer.get_line(row)?.chars().count() + 1;
+    }
+
+    let line = buffer.get_line(position.row)?;
+
+    Ok(offset + line[..position.col].chars().count())
+}
+
+/// Reads the lines up to `offset` one by one, the end of the buffer if it's past it.
+fn position_at<B: ReadBuffer + ?Sized>(buffer: &B, offset: usize) -> Result<Position> {
+    let mut remaining = offset;
+
+    for row in 0..buffer.line_count()? {
+        let line = buffer.get_line(row)?;
+        let chars = line.chars().count();
+
+        if remaining <= chars {
+            let col = line
+                .char_indices()
+                .nth(remaining)
+                .map_or(line.len(), |(i, _)| i);
+
+            return Ok(Position::new(row, col));
+        }
+
+        remaining -= chars + 1;
+    }
+
+    buffer.max_pos()
+}
+
+/// Applies runs of deletes at the same offset and of inserts at consecutive offsets from one
+/// site with a single `set_text` each, like the ops [`CollabBuffer::set_text`] sends.
+fn apply_ops(buffer: &mut impl WriteBuffer, ops: &[Op]) -> Result<()> {
+    let mut rest = ops;
+
+    while let Some(first) = rest.first() {
+        let count = match first {
+            Op::Insert { offset, site, .. } => {
+                let text: String = rest
+                    .iter()
+                    .enumerate()
+                    .map_while(|(i, op)| match op {
+                        Op::Insert {
+                            offset: next,
+                            ch,
+                            site: next_site,
+                        } if *next == offset + i && next_site == site => Some(*ch),
+                        _ => None,
+                    })
+                    .collect();
+
+                let position = position_at(&*buffer, *offset)?;
+                buffer.set_text((&position, &position), &text)?;
+
+                text.chars().count()
+            }
+            Op::Delete { offset } => {
+                let count = rest
+                    .iter()
+                    .take_while(|op| matches!(op, Op::Delete { offset: next } if next == offset))
+                    .count();
+
+                let start = position_at(&*buffer, *offset)?;
+                let end = position_at(&*buffer, offset + count)?;
+                buffer.set_text((&start, &end), "")?;
+
+                count
+            }
+        };
+
+        rest = &rest[count..];
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct JupiterState {
+    sent: u64,
+    received: u64,
+    /// Local messages not yet acknowledged by the other side
+    outgoing: VecDeque<(u64, Vec<Op>)>,
+}
+
+/// One side of a two-party collaborative session.
+///
+/// Only edits made through [`CollabBuffer::set_text`] are shared, edits made through other
+/// handles to the same buffer will make the sides diverge.
+#[derive(Debug)]
+pub struct CollabBuffer<B: BufferHandle> {
+    buffer: B,
+    site: SiteId,
+    state: Mutex<JupiterState>,
+    local_tx: Sender<Message>,
+    local_rx: Mutex<Option<Receiver<Message>>>,
+}
+
+impl<B: BufferHandle> CollabBuffer<B> {
+    /// Both sides have to start from the same content and use different `site`s.
+    pub fn new(buffer: B, site: SiteId) -> Self {
+        let (local_tx, local_rx) = mpsc::channel();
+
+        Self {
+            buffer,
+            site,
+            state: Mutex::default(),
+            local_tx,
+            local_rx: Mutex::new(Some(local_rx)),
+        }
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    pub fn site(&self) -> SiteId {
+        self.site
+    }
+
+    /// Messages to deliver to the other side, in order. Can only be taken once.
+    pub fn local_ops_stream(&self) -> Option<Receiver<Message>> {
+        self.local_rx
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    pub fn set_text(&self, range: impl Into<PosRange>, text: &str) -> Result<()> {
+        let mut buffer = self.buffer.write_timeout()?;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let range = range.into();
+        let (start, end) = (range.start(), range.end());
+        buffer.validate_range(start, end)?;
+
+        let offset = char_offset(&*buffer, start)?;
+        let removed = char_offset(&*buffer, end)? - offset;
+
+        buffer.set_text_in(&range, text)?;
+
+        let ops: Vec<Op> = std::iter::repeat_n(Op::Delete { offset }, removed)
+            .chain(text.chars().enumerate().map(|(i, ch)| Op::Insert {
+                offset: offset + i,
+                ch,
+                site: self.site,
+            }))
+            .collect();
+
+        let message = Message {
+            ops: ops.clone(),
+            sent: state.sent,
+            received: state.received,
+        };
+
+        let sent = state.sent;
+        state.outgoing.push_back((sent, ops));
+        state.sent += 1;
+
+        // Nobody listening is fine, the session might be offline
+        _ = self.local_tx.send(message);
+
+        Ok(())
+    }
+
+    pub fn apply_remote_ops(&self, message: Message) -> Result<()> {
+        let mut buffer = self.buffer.write_timeout()?;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        assert_eq!(
+            message.sent, state.received,
+            "Remote messages have to be applied in order"
+        );
+
+        // The other side has seen everything we sent before `message.received`
+        while state
+            .outgoing
+            .front()
+            .is_some_and(|(sent, _)| *sent < message.received)
+        {
+            state.outgoing.pop_front();
+        }
+
+        let mut ops = message.ops;
+
+        for (_, outgoing) in state.outgoing.iter_mut() {
+            let (outgoing_transformed, ops_transformed) =
+                transform_seq(std::mem::take(outgoing), ops);
+
+            *outgoing = outgoing_transformed;
+            ops = ops_transformed;
+        }
+
+        apply_ops(&mut *buffer, &ops)?;
+
+        state.received += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{Editor, assert_buffer_content, test_utils::new_buffer_with_content};
+
+    fn exchange<B: BufferHandle>(a: &CollabBuffer<B>, b: &CollabBuffer<B>) {
+        let a_rx = a.local_ops_stream().expect("Stream already taken");
+        let b_rx = b.local_ops_stream().expect("Stream already taken");
+
+        for message in a_rx.try_iter().collect::<Vec<_>>() {
+            b.apply_remote_ops(message).expect("Failed to apply ops");
+        }
+
+        for message in b_rx.try_iter().collect::<Vec<_>>() {
+            a.apply_remote_ops(message).expect("Failed to apply ops");
+        }
+    }
+
+    pub fn test_collab_concurrent_edits(editor: impl Editor) {
+        let content = "First line\nSecond line";
+
+        let a = CollabBuffer::new(new_buffer_with_content(&editor, content), 1);
+        let b = CollabBuffer::new(new_buffer_with_content(&editor, content), 2);
+
+        a.set_text((Position::new(0, 0), Position::new(0, 5)), "1st")
+            .expect("Failed to set text");
+        a.set_text((Position::new(1, 11), Position::new(1, 11)), "!")
+            .expect("Failed to set text");
+
+        b.set_text((Position::new(0, 6), Position::new(1, 0)), "")
+            .expect("Failed to set text");
+        b.set_text((Position::new(0, 0), Position::new(0, 0)), ">")
+            .expect("Failed to set text");
+
+        exchange(&a, &b);
+
+        // Concurrent inserts at the same offset are ordered by site
+        assert_buffer_content!(a.buffer(), "1st> Second line!");
+        assert_buffer_content!(b.buffer(), "1st> Second line!");
+    }
+
+    pub fn test_collab_same_position(editor: impl Editor) {
+        let a = CollabBuffer::new(new_buffer_with_content(&editor, "ab"), 1);
+        let b = CollabBuffer::new(new_buffer_with_content(&editor, "ab"), 2);
+
+        a.set_text((Position::new(0, 1), Position::new(0, 1)), "x")
+            .expect("Failed to set text");
+        b.set_text((Position::new(0, 1), Position::new(0, 1)), "y")
+            .expect("Failed to set text");
+
+        // Both delete the same character
+        a.set_text((Position::new(0, 0), Position::new(0, 1)), "")
+            .expect("Failed to set text");
+        b.set_text((Position::new(0, 0), Position::new(0, 1)), "")
+            .expect("Failed to set text");
+
+        exchange(&a, &b);
+
+        assert_buffer_content!(a.buffer(), "xyb");
+        assert_buffer_content!(b.buffer(), "xyb");
+    }
+
+    pub fn test_collab_multiline(editor: impl Editor) {
+        let content = "zażółć\ngęślą\njaźń";
+
+        let a = CollabBuffer::new(new_buffer_with_content(&editor, content), 1);
+        let b = CollabBuffer::new(new_buffer_with_content(&editor, content), 2);
+
+        a.set_text((Position::new(2, 0), Position::new(2, 0)), "ł\n")
+            .expect("Failed to set text");
+
+        b.set_text(
+            (Position::new(0, "zażó".len()), Position::new(1, "gę".len())),
+            "ę\nż",
+        )
+        .expect("Failed to set text");
+
+        exchange(&a, &b);
+
+        assert_buffer_content!(a.buffer(), "zażóę\nżślą\nł\njaźń");
+        assert_buffer_content!(b.buffer(), "zażóę\nżślą\nł\njaźń");
+    }
+
+    #[cfg(feature = "mark")]
+    pub fn test_collab_marks<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        use crate::mark::Mark;
+
+        let a = CollabBuffer::new(new_buffer_with_content(&editor, "Hello world"), 1);
+        let b = CollabBuffer::new(new_buffer_with_content(&editor, "Hello world"), 2);
+
+        let mark = Mark::lock_new(a.buffer(), &Position::new(0, 6)).expect("Failed to create mark");
+
+        b.set_text((Position::new(0, 0), Position::new(0, 0)), "Well, ")
+            .expect("Failed to set text");
+
+        exchange(&a, &b);
+
+        assert_buffer_content!(a.buffer(), "Well, Hello world");
+        assert_eq!(
+            mark.lock_read()
+                .get_position()
+                .expect("Failed to get position"),
+            Position::new(0, 12)
+        );
+    }
+
+    #[macro_export]
+    #[cfg(feature = "mark")]
+    macro_rules! eel_collab_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::collab::tests,
+                prefix: $prefix,
+                tests: [
+                    test_collab_concurrent_edits,
+                    test_collab_same_position,
+                    test_collab_multiline,
+                ],
+            );
+
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::collab::tests,
+                prefix: $prefix,
+                tests: [test_collab_marks],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_collab_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_collab_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::collab::tests,
+                prefix: $prefix,
+                tests: [
+                    test_collab_concurrent_edits,
+                    test_collab_same_position,
+                    test_collab_multiline,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_collab_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
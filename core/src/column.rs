@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use crate::{Position, Result, buffer::ReadBuffer};
+
+/// Unit a column is counted in. [`Position::col`] is in bytes, like nvim's columns, while
+/// tests and users usually think in characters, and anything aligned on screen in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    Byte,
+    Char,
+    /// Terminal cells, wide characters take two and combining marks none. Tabs count as one,
+    /// their width depends on the editor's `tabstop`.
+    Cell,
+}
+
+/// Cells `c` takes up, approximating `wcwidth` with the East Asian wide and fullwidth
+/// characters and emoji of Unicode 15.
+pub fn char_cells(c: char) -> usize {
+    match c as u32 {
+        0x09 => 1,
+        0x00..=0x1f | 0x7f..=0x9f => 0,
+        0x0300..=0x036f
+        | 0x0483..=0x0489
+        | 0x0591..=0x05bd
+        | 0x05bf
+        | 0x05c1..=0x05c2
+        | 0x05c4..=0x05c5
+        | 0x05c7
+        | 0x0610..=0x061a
+        | 0x064b..=0x065f
+        | 0x0670
+        | 0x06d6..=0x06dc
+        | 0x06df..=0x06e4
+        | 0x06e7..=0x06e8
+        | 0x06ea..=0x06ed
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200b..=0x200f
+        | 0x2028..=0x202e
+        | 0x2060..=0x2064
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f
+        | 0xfeff
+        | 0xe0000..=0xe007f
+        | 0xe0100..=0xe01ef => 0,
+        0x1100..=0x115f
+        | 0x231a..=0x231b
+        | 0x2329..=0x232a
+        | 0x23e9..=0x23ec
+        | 0x23f0
+        | 0x23f3
+        | 0x25fd..=0x25fe
+        | 0x2614..=0x2615
+        | 0x2648..=0x2653
+        | 0x267f
+        | 0x2693
+        | 0x26a1
+        | 0x26aa..=0x26ab
+        | 0x26bd..=0x26be
+        | 0x26c4..=0x26c5
+        | 0x26ce
+        | 0x26d4
+        | 0x26ea
+        | 0x26f2..=0x26f3
+        | 0x26f5
+        | 0x26fa
+        | 0x26fd
+        | 0x2705
+        | 0x270a..=0x270b
+        | 0x2728
+        | 0x274c
+        | 0x274e
+        | 0x2753..=0x2755
+        | 0x2757
+        | 0x2795..=0x2797
+        | 0x27b0
+        | 0x27bf
+        | 0x2b1b..=0x2b1c
+        | 0x2b50
+        | 0x2b55
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xa960..=0xa97f
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe10..=0xfe19
+        | 0xfe30..=0xfe6f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x16fe0..=0x16fe4
+        | 0x17000..=0x18cff
+        | 0x1aff0..=0x1b2ff
+        | 0x1f004
+        | 0x1f0cf
+        | 0x1f18e
+        | 0x1f191..=0x1f19a
+        | 0x1f200..=0x1f202
+        | 0x1f210..=0x1f23b
+        | 0x1f240..=0x1f248
+        | 0x1f250..=0x1f251
+        | 0x1f260..=0x1f265
+        | 0x1f300..=0x1f320
+        | 0x1f32d..=0x1f335
+        | 0x1f337..=0x1f37c
+        | 0x1f37e..=0x1f393
+        | 0x1f3a0..=0x1f3ca
+        | 0x1f3cf..=0x1f3d3
+        | 0x1f3e0..=0x1f3f0
+        | 0x1f3f4
+        | 0x1f3f8..=0x1f43e
+        | 0x1f440
+        | 0x1f442..=0x1f4fc
+        | 0x1f4ff..=0x1f53d
+        | 0x1f54b..=0x1f54e
+        | 0x1f550..=0x1f567
+        | 0x1f57a
+        | 0x1f595..=0x1f596
+        | 0x1f5a4
+        | 0x1f5fb..=0x1f64f
+        | 0x1f680..=0x1f6c5
+        | 0x1f6cc
+        | 0x1f6d0..=0x1f6d2
+        | 0x1f6d5..=0x1f6d7
+        | 0x1f6dc..=0x1f6df
+        | 0x1f6eb..=0x1f6ec
+        | 0x1f6f4..=0x1f6fc
+        | 0x1f7e0..=0x1f7eb
+        | 0x1f7f0
+        | 0x1f90c..=0x1f93a
+        | 0x1f93c..=0x1f945
+        | 0x1f947..=0x1f9ff
+        | 0x1fa70..=0x1faff
+        | 0x20000..=0x2fffd
+        | 0x30000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Offsets of every character of a line in each [`Column`] unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnTable {
+    /// Byte and cell offset of each char, followed by the end of the line.
+    offsets: Vec<(usize, usize)>,
+}
+
+impl ColumnTable {
+    pub fn new(line: &str) -> Self {
+        let mut cells = 0;
+
+        let mut offsets = line
+            .char_indices()
+            .map(|(byte, c)| {
+                let offset = (byte, cells);
+                cells += char_cells(c);
+                offset
+            })
+            .collect::<Vec<_>>();
+        offsets.push((line.len(), cells));
+
+        Self { offsets }
+    }
+
+    pub fn len(&self, unit: Column) -> usize {
+        self.offset(self.offsets.len() - 1, unit)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.len() == 1
+    }
+
+    fn offset(&self, index: usize, unit: Column) -> usize {
+        let (byte, cell) = self.offsets[index];
+
+        match unit {
+            Column::Byte => byte,
+            Column::Char => index,
+            Column::Cell => cell,
+        }
+    }
+
+    /// Index of the char at `col`, a column inside a char (a continuation byte, the second
+    /// cell of a wide char) belongs to it.
+    fn index(&self, col: usize, unit: Column) -> usize {
+        match unit {
+            Column::Char => col.min(self.offsets.len() - 1),
+            Column::Byte => self.offsets.partition_point(|&(byte, _)| byte <= col) - 1,
+            // Zero width chars share the cell of the next one, which is the one at that cell
+            Column::Cell => self.offsets.partition_point(|&(_, cell)| cell <= col) - 1,
+        }
+    }
+
+    /// Columns past the end of the line convert to its end.
+    pub fn convert(&self, col: usize, from: Column, to: Column) -> usize {
+        self.offset(self.index(col, from), to)
+    }
+}
+
+/// Converts columns of a buffer's lines, computing the table of each line on first use.
+///
+/// The tables aren't invalidated, the converter is meant to live as long as a buffer lock.
+#[derive(Debug)]
+pub struct ColumnConverter<'a, B> {
+    buffer: &'a B,
+    tables: HashMap<usize, ColumnTable>,
+}
+
+impl<'a, B: ReadBuffer> ColumnConverter<'a, B> {
+    pub fn new(buffer: &'a B) -> Self {
+        Self {
+            buffer,
+            tables: HashMap::new(),
+        }
+    }
+
+    pub fn table(&mut self, row: usize) -> Result<&ColumnTable> {
+        if !self.tables.contains_key(&row) {
+            let line = self.buffer.get_line(row)?;
+            self.tables.insert(row, ColumnTable::new(&line));
+        }
+
+        Ok(&self.tables[&row])
+    }
+
+    /// Column of `position` in `unit`.
+    pub fn col(&mut self, position: &Position, unit: Column) -> Result<usize> {
+        Ok(self
+            .table(position.row)?
+            .convert(position.col, Column::Byte, unit))
+    }
+
+    /// Position of `col` counted in `unit` on `row`.
+    pub fn position(&mut self, row: usize, col: usize, unit: Column) -> Result<Position> {
+        let col = self.table(row)?.convert(col, unit, Column::Byte);
+
+        Ok(Position::new(row, col))
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{buffer::BufferHandle, editor::Editor};
+
+    /// Runs on [`Fixture::unicode`](crate::test_utils::Fixture::unicode).
+    pub fn test_column_converter(editor: impl Editor) {
+        let buffer = editor.current_buffer().expect("Failed to get buffer");
+        let buffer = buffer.read();
+        let mut converter = ColumnConverter::new(&*buffer);
+
+        let position = converter
+            .position(0, 4, Column::Char)
+            .expect("Failed to convert");
+        assert_eq!(position, Position::new(0, "zażó".len()));
+        assert_eq!(
+            converter
+                .col(&position, Column::Cell)
+                .expect("Failed to convert"),
+            4
+        );
+
+        let position = converter
+            .position(2, 6, Column::Cell)
+            .expect("Failed to convert");
+        assert_eq!(position, Position::new(2, "日本語".len()));
+        assert_eq!(
+            converter
+                .col(&position, Column::Char)
+                .expect("Failed to convert"),
+            3
+        );
+
+        assert!(converter.position(10, 0, Column::Char).is_err());
+    }
+
+    #[macro_export]
+    macro_rules! eel_column_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::column::tests,
+                prefix: $prefix,
+                tests: [test_column_converter: $crate::test_utils::Fixture::unicode()],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_column_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn column_table() {
+        // 'ż' takes 2 bytes, '日' 3 bytes and 2 cells, the combining accent 2 bytes and no cells
+        let table = ColumnTable::new("ża日e\u{301}!");
+
+        assert_eq!(table.len(Column::Byte), 10);
+        assert_eq!(table.len(Column::Char), 6);
+        assert_eq!(table.len(Column::Cell), 6);
+
+        let columns = [
+            (0, 0, 0),
+            (2, 1, 1),
+            (3, 2, 2),
+            (6, 3, 4),
+            (7, 4, 5),
+            (9, 5, 5),
+            (10, 6, 6),
+        ];
+        for (byte, char, cell) in columns {
+            assert_eq!(table.convert(byte, Column::Byte, Column::Char), char);
+            assert_eq!(table.convert(char, Column::Char, Column::Byte), byte);
+            assert_eq!(table.convert(byte, Column::Byte, Column::Cell), cell);
+        }
+
+        // The combining accent shares its cell with '!'
+        assert_eq!(table.convert(5, Column::Cell, Column::Byte), 9);
+
+        // Inside a char
+        assert_eq!(table.convert(1, Column::Byte, Column::Char), 0);
+        assert_eq!(table.convert(5, Column::Byte, Column::Cell), 2);
+        assert_eq!(table.convert(3, Column::Cell, Column::Byte), 3);
+
+        // Past the end
+        assert_eq!(table.convert(20, Column::Char, Column::Byte), 10);
+        assert_eq!(table.convert(20, Column::Byte, Column::Cell), 6);
+
+        let empty = ColumnTable::new("");
+        assert!(empty.is_empty());
+        assert_eq!(empty.convert(3, Column::Cell, Column::Char), 0);
+    }
+
+    #[test]
+    fn tab_cells() {
+        assert_eq!(char_cells('\t'), 1);
+        assert_eq!(char_cells('\n'), 0);
+        assert_eq!(ColumnTable::new("\tx").len(Column::Cell), 2);
+    }
+
+    #[test]
+    fn emoji_cells() {
+        // Transport and map symbols, supplemental symbols and misc symbols
+        for c in ['🚀', '🛟', '🤖', '🥰', '⌚', '⚡', '✅', '🀄'] {
+            assert_eq!(char_cells(c), 2, "{c:?}");
+        }
+
+        // Text presentation by default
+        for c in ['☺', '✈', '♥'] {
+            assert_eq!(char_cells(c), 1, "{c:?}");
+        }
+
+        // The presentation selector doesn't take a cell of its own
+        assert_eq!(char_cells('\u{fe0f}'), 0);
+        assert_eq!(ColumnTable::new("a🚀b").len(Column::Cell), 4);
+    }
+}
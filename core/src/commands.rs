@@ -0,0 +1,579 @@
+//! Commands declared as a typed argument struct and a handler, turned into the backend's own
+//! commands (e.g. nvim user commands) along with their completion.
+//!
+//! ```ignore
+//! #[derive(EelCommandArgs)]
+//! struct SortArgs {
+//!     #[eel(range)]
+//!     rows: Option<Range<usize>>,
+//!     #[eel(bang)]
+//!     reverse: bool,
+//!     #[eel(flag)]
+//!     ignore_case: bool,
+//!     #[eel(complete = sort_keys)]
+//!     key: Option<String>,
+//! }
+//!
+//! let command = Command::new("EelSort", |editor: &E, args: SortArgs| sort(editor, args));
+//! ```
+//!
+//! Plain fields are positional, parsed with [`FromStr`]: `T` is required, `Option<T>` optional
+//! and `Vec<T>` takes the remaining arguments. `#[eel(flag)]` fields are set by `--name` (with
+//! `-` for `_`) anywhere in the arguments, up to a `--`. `#[eel(range)]` gets the rows of an
+//! explicitly given range, either as `Option<Range<usize>>` or as a required `Range<usize>`.
+//! `#[eel(complete = f)]` completes a positional with `f: fn(&str) -> Vec<String>`, getting the
+//! typed prefix.
+
+use std::{collections::VecDeque, fmt::Display, ops::Range, str::FromStr, sync::Arc, thread};
+
+use tracing::error;
+
+use crate::{Editor, Result};
+
+pub use eel_macros::EelCommandArgs;
+
+const END_OF_FLAGS: &str = "--";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Missing argument: {0}")]
+    MissingArgument(&'static str),
+
+    #[error("Too many arguments: {0}")]
+    TooManyArguments(String),
+
+    #[error("Unknown flag: {0}")]
+    UnknownFlag(String),
+
+    #[error("Invalid {name} {value:?}: {message}")]
+    InvalidArgument {
+        name: &'static str,
+        value: String,
+        message: String,
+    },
+
+    #[error("Range required")]
+    MissingRange,
+
+    #[error("No range allowed")]
+    UnexpectedRange,
+
+    #[error("No ! allowed")]
+    UnexpectedBang,
+
+    #[error("{source}, usage: {usage}")]
+    Usage { usage: String, source: Box<Error> },
+
+    #[error("Failed to spawn command thread: {0}")]
+    Spawn(#[from] std::io::Error),
+}
+
+/// A command as invoked by the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invocation {
+    pub args: Vec<String>,
+    /// Rows of the given range, end-exclusive. `None` if there's no explicit range.
+    pub range: Option<Range<usize>>,
+    pub bang: bool,
+}
+
+/// What a command accepts, for backends declaring it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Spec {
+    pub args: bool,
+    pub range: bool,
+    pub bang: bool,
+    /// Arguments in vim's help notation, e.g. `[--force] {name} [files...]`.
+    pub usage: &'static str,
+}
+
+pub type Completer = fn(&str) -> Vec<String>;
+
+/// Implemented with `#[derive(EelCommandArgs)]`, see the [module docs](self).
+pub trait CommandArgs: Sized {
+    fn spec() -> Spec;
+    fn parse(invocation: Invocation) -> std::result::Result<Self, Error>;
+
+    /// Candidates for the argument starting with `lead`, `preceding` being the arguments
+    /// before it.
+    fn complete(preceding: &[String], lead: &str) -> Vec<String>;
+}
+
+/// Commands without arguments.
+impl CommandArgs for () {
+    fn spec() -> Spec {
+        Spec::default()
+    }
+
+    fn parse(invocation: Invocation) -> std::result::Result<Self, Error> {
+        Parser::new(invocation).finish()
+    }
+
+    fn complete(_preceding: &[String], _lead: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Takes the arguments apart for the code generated by [`EelCommandArgs`], positionals are
+/// taken in declaration order.
+#[derive(Debug)]
+pub struct Parser {
+    args: VecDeque<String>,
+    flags: Vec<String>,
+    range: Option<Range<usize>>,
+    bang: bool,
+}
+
+impl Parser {
+    pub fn new(invocation: Invocation) -> Self {
+        let mut args = VecDeque::new();
+        let mut flags = Vec::new();
+
+        let mut iter = invocation.args.into_iter();
+        for arg in iter.by_ref() {
+            if arg == END_OF_FLAGS {
+                break;
+            }
+
+            if arg.starts_with("--") {
+                flags.push(arg);
+            } else {
+                args.push_back(arg);
+            }
+        }
+        args.extend(iter);
+
+        Self {
+            args,
+            flags,
+            range: invocation.range,
+            bang: invocation.bang,
+        }
+    }
+
+    pub fn range(&mut self) -> Option<Range<usize>> {
+        self.range.take()
+    }
+
+    pub fn required_range(&mut self) -> std::result::Result<Range<usize>, Error> {
+        self.range().ok_or(Error::MissingRange)
+    }
+
+    pub fn bang(&mut self) -> bool {
+        std::mem::take(&mut self.bang)
+    }
+
+    /// `flag` includes the leading `--`.
+    pub fn flag(&mut self, flag: &str) -> bool {
+        let count = self.flags.len();
+        self.flags.retain(|f| f != flag);
+
+        self.flags.len() != count
+    }
+
+    pub fn required<T>(&mut self, name: &'static str) -> std::result::Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.optional(name)?.ok_or(Error::MissingArgument(name))
+    }
+
+    pub fn optional<T>(&mut self, name: &'static str) -> std::result::Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.args
+            .pop_front()
+            .map(|value| parse_arg(name, value))
+            .transpose()
+    }
+
+    pub fn rest<T>(&mut self, name: &'static str) -> std::result::Result<Vec<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.args
+            .drain(..)
+            .map(|value| parse_arg(name, value))
+            .collect()
+    }
+
+    /// Fails on anything not taken.
+    pub fn finish(mut self) -> std::result::Result<(), Error> {
+        if let Some(flag) = self.flags.pop() {
+            return Err(Error::UnknownFlag(flag));
+        }
+
+        if let Some(arg) = self.args.pop_front() {
+            return Err(Error::TooManyArguments(arg));
+        }
+
+        if self.range.is_some() {
+            return Err(Error::UnexpectedRange);
+        }
+
+        if self.bang {
+            return Err(Error::UnexpectedBang);
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_arg<T>(name: &'static str, value: String) -> std::result::Result<T, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|e: T::Err| Error::InvalidArgument {
+        name,
+        message: e.to_string(),
+        value,
+    })
+}
+
+/// Completion for the code generated by [`EelCommandArgs`]: flags if `lead` starts with `-`,
+/// the positional at the position of `lead` otherwise (the last one if `variadic`).
+pub fn complete(
+    preceding: &[String],
+    lead: &str,
+    flags: &[&str],
+    positionals: &[Option<Completer>],
+    variadic: bool,
+) -> Vec<String> {
+    let end_of_flags = preceding.iter().position(|arg| arg == END_OF_FLAGS);
+
+    if end_of_flags.is_none() && lead.starts_with('-') {
+        return flags
+            .iter()
+            .filter(|flag| flag.starts_with(lead) && !preceding.iter().any(|arg| arg == *flag))
+            .map(|flag| flag.to_string())
+            .collect();
+    }
+
+    let index = preceding
+        .iter()
+        .enumerate()
+        .filter(|(i, arg)| end_of_flags.is_some_and(|end| *i > end) || !arg.starts_with("--"))
+        .count();
+
+    let completer = match positionals.get(index) {
+        Some(completer) => completer,
+        None if variadic => positionals.last().unwrap_or(&None),
+        None => &None,
+    };
+
+    completer
+        .map(|completer| completer(lead))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(lead))
+        .collect()
+}
+
+type Runner<E> = dyn Fn(Arc<E>, Invocation) -> Result<thread::JoinHandle<Result<()>>> + Send + Sync;
+
+/// A named command with its handler, see the [module docs](self).
+pub struct Command<E> {
+    name: String,
+    description: Option<String>,
+    spec: Spec,
+    runner: Arc<Runner<E>>,
+    completer: fn(&[String], &str) -> Vec<String>,
+}
+
+impl<E: Editor> Command<E> {
+    /// `handler` runs on a separate thread, reaching the editor through its dispatching.
+    pub fn new<A, F>(name: impl Into<String>, handler: F) -> Self
+    where
+        A: CommandArgs + Send + 'static,
+        F: Fn(&E, A) -> Result<()> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let spec = A::spec();
+        let usage = usage(&name, &spec);
+
+        let handler = Arc::new(handler);
+        let command_name = name.clone();
+
+        let runner = move |editor: Arc<E>, invocation: Invocation| {
+            let args = A::parse(invocation).map_err(|e| Error::Usage {
+                usage: usage.clone(),
+                source: Box::new(e),
+            })?;
+
+            let handler = handler.clone();
+            let name = command_name.clone();
+
+            let handle = thread::Builder::new()
+                .name("eel-command".into())
+                .spawn(move || {
+                    let result = handler(&editor, args);
+
+                    if let Err(e) = &result {
+                        error!("{name} failed: {e}");
+                    }
+
+                    result
+                })
+                .map_err(Error::from)?;
+
+            Ok(handle)
+        };
+
+        Self {
+            name,
+            description: None,
+            spec,
+            runner: Arc::new(runner),
+            completer: A::complete,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// E.g. `[range]EelSort[!] [--ignore-case] [key]`.
+    pub fn usage(&self) -> String {
+        usage(&self.name, &self.spec)
+    }
+
+    /// Parses the arguments and starts the handler, failing right away on invalid arguments.
+    /// Handler errors are also logged.
+    pub fn run(
+        &self,
+        editor: Arc<E>,
+        invocation: Invocation,
+    ) -> Result<thread::JoinHandle<Result<()>>> {
+        (self.runner)(editor, invocation)
+    }
+
+    pub fn complete(&self, preceding: &[String], lead: &str) -> Vec<String> {
+        (self.completer)(preceding, lead)
+    }
+}
+
+impl<E> std::fmt::Debug for Command<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("spec", &self.spec)
+            .finish()
+    }
+}
+
+fn usage(name: &str, spec: &Spec) -> String {
+    let range = if spec.range { "[range]" } else { "" };
+    let bang = if spec.bang { "[!]" } else { "" };
+
+    format!("{range}{name}{bang} {}", spec.usage)
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        assert_buffer_content,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    fn complete_modes(_lead: &str) -> Vec<String> {
+        vec!["fast".into(), "full".into(), "slow".into()]
+    }
+
+    #[derive(Debug, PartialEq, EelCommandArgs)]
+    struct TestArgs {
+        #[eel(range)]
+        rows: Option<Range<usize>>,
+        #[eel(bang)]
+        force: bool,
+        #[eel(flag)]
+        dry_run: bool,
+        count: usize,
+        #[eel(complete = complete_modes)]
+        mode: Option<String>,
+        files: Vec<String>,
+    }
+
+    #[derive(Debug, EelCommandArgs)]
+    struct SetArgs {
+        words: Vec<String>,
+    }
+
+    fn invocation(args: &[&str]) -> Invocation {
+        Invocation {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn test_command_parse(_editor: impl Editor) {
+        let spec = TestArgs::spec();
+        assert!(spec.args && spec.range && spec.bang);
+        assert_eq!(spec.usage, "[--dry-run] {count} [mode] [files...]");
+
+        let args = TestArgs::parse(Invocation {
+            range: Some(2..5),
+            bang: true,
+            ..invocation(&["3", "--dry-run", "fast", "a", "--", "--b"])
+        })
+        .expect("Failed to parse");
+
+        assert_eq!(
+            args,
+            TestArgs {
+                rows: Some(2..5),
+                force: true,
+                dry_run: true,
+                count: 3,
+                mode: Some("fast".into()),
+                files: vec!["a".into(), "--b".into()],
+            }
+        );
+
+        let args = TestArgs::parse(invocation(&["1"])).expect("Failed to parse");
+        assert_eq!(
+            args,
+            TestArgs {
+                rows: None,
+                force: false,
+                dry_run: false,
+                count: 1,
+                mode: None,
+                files: vec![],
+            }
+        );
+
+        assert!(matches!(
+            TestArgs::parse(invocation(&[])),
+            Err(Error::MissingArgument("count"))
+        ));
+        assert!(matches!(
+            TestArgs::parse(invocation(&["x"])),
+            Err(Error::InvalidArgument { name: "count", .. })
+        ));
+        assert!(matches!(
+            TestArgs::parse(invocation(&["1", "--force"])),
+            Err(Error::UnknownFlag(flag)) if flag == "--force"
+        ));
+
+        assert!(matches!(
+            <()>::parse(invocation(&["x"])),
+            Err(Error::TooManyArguments(arg)) if arg == "x"
+        ));
+        assert!(matches!(
+            <()>::parse(Invocation {
+                bang: true,
+                ..Default::default()
+            }),
+            Err(Error::UnexpectedBang)
+        ));
+    }
+
+    pub fn test_command_complete(_editor: impl Editor) {
+        let complete = |preceding: &[&str], lead| {
+            let preceding: Vec<String> = preceding.iter().map(|arg| arg.to_string()).collect();
+            TestArgs::complete(&preceding, lead)
+        };
+
+        assert_eq!(complete(&[], "--"), ["--dry-run"]);
+        assert_eq!(complete(&["--dry-run"], "--"), Vec::<String>::new());
+        assert_eq!(complete(&[], ""), Vec::<String>::new());
+        assert_eq!(complete(&["1"], ""), ["fast", "full", "slow"]);
+        assert_eq!(complete(&["--dry-run", "1"], "f"), ["fast", "full"]);
+        assert_eq!(complete(&["1", "fast"], ""), Vec::<String>::new());
+        assert_eq!(complete(&["1", "--", "--x"], ""), Vec::<String>::new());
+    }
+
+    pub fn test_command_run<E: Editor>(editor: E) {
+        let editor = Arc::new(editor);
+        let buffer = new_buffer_with_content(&*editor, "");
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        let command = Command::new("EelTestSet", |editor: &E, args: SetArgs| {
+            if args.words.is_empty() {
+                Err(crate::buffer::Error::Custom("Nothing to set".into()))?;
+            }
+
+            editor
+                .current_buffer()?
+                .write()
+                .set_content(&args.words.join(" "))
+        })
+        .with_description("Sets the current buffer");
+
+        assert_eq!(command.usage(), "EelTestSet [words...]");
+
+        command
+            .run(editor.clone(), invocation(&["Hello", "world"]))
+            .expect("Failed to run command")
+            .join()
+            .expect("Command panicked")
+            .expect("Command failed");
+
+        assert_buffer_content!(buffer, "Hello world");
+
+        let error = command
+            .run(
+                editor.clone(),
+                Invocation {
+                    range: Some(0..1),
+                    ..invocation(&["x"])
+                },
+            )
+            .expect_err("Range accepted");
+        assert!(error.to_string().contains("usage: EelTestSet"), "{error}");
+
+        assert!(
+            command
+                .run(editor, invocation(&[]))
+                .expect("Failed to run command")
+                .join()
+                .expect("Command panicked")
+                .is_err()
+        );
+        assert_buffer_content!(buffer, "Hello world");
+    }
+
+    #[macro_export]
+    macro_rules! eel_commands_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::commands::tests,
+                prefix: $prefix,
+                tests: [test_command_parse, test_command_complete, test_command_run],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_commands_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{Position, Result, buffer::WriteBuffer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentMode {
+    /// Every non-blank row of the range is commented on its own.
+    Line,
+    /// The comment markers wrap the range.
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentSpec {
+    pub start: String,
+    pub end: String,
+    pub mode: CommentMode,
+}
+
+impl CommentSpec {
+    pub fn line(start: impl Into<String>) -> Self {
+        Self {
+            start: start.into(),
+            end: String::new(),
+            mode: CommentMode::Line,
+        }
+    }
+
+    pub fn block(start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+            mode: CommentMode::Block,
+        }
+    }
+
+    /// Parses a vim `commentstring`, e.g. `// %s` or `/* %s */`. `None` if it has no `%s`.
+    pub fn from_commentstring(commentstring: &str, mode: CommentMode) -> Option<Self> {
+        let (start, end) = commentstring.split_once("%s")?;
+
+        Some(Self {
+            start: start.to_string(),
+            end: end.to_string(),
+            mode,
+        })
+    }
+}
+
+/// Strips `marker` from the start of `text`, also without its padding (e.g. `//` for `// `).
+fn strip_start<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    text.strip_prefix(marker)
+        .or_else(|| text.strip_prefix(marker.trim_end()))
+}
+
+fn strip_end<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    text.strip_suffix(marker)
+        .or_else(|| text.strip_suffix(marker.trim_start()))
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Byte offset after the first `chars` characters of the indent.
+fn indent_offset(line: &str, chars: usize) -> usize {
+    line.char_indices()
+        .nth(chars)
+        .map_or(line.len(), |(i, _)| i)
+}
+
+fn uncomment_line(line: &str, spec: &CommentSpec) -> Option<String> {
+    let (indent, text) = line.split_at(indent(line));
+
+    let text = strip_start(text, &spec.start)?;
+    let text = if spec.end.is_empty() {
+        text
+    } else {
+        strip_end(text, &spec.end)?
+    };
+
+    Some(format!("{indent}{text}"))
+}
+
+fn toggle_lines<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    start_row: usize,
+    end_row: usize,
+    spec: &CommentSpec,
+) -> Result<bool> {
+    let lines: Vec<String> = buffer.get_lines(start_row..end_row + 1)?.collect();
+    let code = || lines.iter().filter(|l| !l.trim().is_empty());
+
+    let uncommented: Option<Vec<String>> = code().map(|l| uncomment_line(l, spec)).collect();
+
+    // Uncommenting only if all code lines are commented
+    let comment = uncommented.is_none();
+
+    let mut uncommented = uncommented.into_iter().flatten();
+    // In characters, the indent of other lines may be made of wider whitespace
+    let min_indent = code()
+        .map(|l| l[..indent(l)].chars().count())
+        .min()
+        .unwrap_or(0);
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let new = if comment {
+            let (indent, text) = line.split_at(indent_offset(line, min_indent));
+            format!("{indent}{}{text}{}", spec.start, spec.end)
+        } else {
+            uncommented.next().expect("Every code line was uncommented")
+        };
+
+        buffer.set_line(start_row + i, &new)?;
+    }
+
+    Ok(comment)
+}
+
+fn toggle_block<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    start: &Position,
+    end: &Position,
+    spec: &CommentSpec,
+) -> Result<bool> {
+    let text = buffer.get_text((start, end))?;
+
+    let stripped = strip_start(&text, &spec.start)
+        .and_then(|rest| Some((rest.len(), strip_end(rest, &spec.end)?.len())));
+
+    let Some((rest_len, inner_len)) = stripped else {
+        buffer.set_text((end, end), &spec.end)?;
+        buffer.set_text((start, start), &spec.start)?;
+
+        return Ok(true);
+    };
+
+    let start_len = text.len() - rest_len;
+    let inner_end = start.offset(&Position::max_text_pos(&text[..start_len + inner_len]));
+    let inner_start = start.offset(&Position::max_text_pos(&text[..start_len]));
+
+    // Removing the end marker first, so the start positions stay valid
+    buffer.set_text((&inner_end, end), "")?;
+    buffer.set_text((start, &inner_start), "")?;
+
+    Ok(false)
+}
+
+/// Comments the range out or, if it's already commented, uncomments it.
+///
+/// In line mode the rows are commented unless all of their non-blank lines are, the markers
+/// are placed at the smallest indentation. Returns whether the range is commented afterwards.
+pub fn toggle_comment<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    start: &Position,
+    end: &Position,
+    spec: &CommentSpec,
+) -> Result<bool> {
+    buffer.validate_range(start, end)?;
+
+    let (start, end) = if start <= end {
+        (start, end)
+    } else {
+        (end, start)
+    };
+
+    match spec.mode {
+        CommentMode::Line => toggle_lines(buffer, start.row, end.row, spec),
+        CommentMode::Block => toggle_block(buffer, start, end, spec),
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor, assert_buffer_content, buffer::BufferHandle, test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_comment_lines(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "fn f() {\n    a();\n\n  b();\n}");
+        let spec = CommentSpec::from_commentstring("// %s", CommentMode::Line)
+            .expect("Failed to parse commentstring");
+
+        let (start, end) = (Position::new(1, 0), Position::new(3, 0));
+
+        let commented = buffer
+            .write()
+            .toggle_comment(&start, &end, &spec)
+            .expect("Failed to toggle comment");
+
+        assert!(commented);
+        assert_buffer_content!(buffer, "fn f() {\n  //   a();\n\n  // b();\n}");
+
+        let commented = buffer
+            .write()
+            .toggle_comment(&end, &start, &spec)
+            .expect("Failed to toggle comment");
+
+        assert!(!commented);
+        assert_buffer_content!(buffer, "fn f() {\n    a();\n\n  b();\n}");
+
+        // Partially commented ranges get commented, markers without padding are recognized
+        buffer
+            .write()
+            .set_line(1, "    //a();")
+            .expect("Failed to set line");
+        buffer
+            .write()
+            .toggle_comment(&Position::new(0, 0), &Position::new(1, 0), &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "// fn f() {\n//     //a();\n\n  b();\n}");
+
+        buffer
+            .write()
+            .toggle_comment(&Position::new(1, 0), &Position::new(1, 0), &spec)
+            .expect("Failed to toggle comment");
+        buffer
+            .write()
+            .toggle_comment(&Position::new(1, 0), &Position::new(1, 0), &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "// fn f() {\n    a();\n\n  b();\n}");
+
+        let spec = CommentSpec::from_commentstring("/* %s */", CommentMode::Line)
+            .expect("Failed to parse commentstring");
+        buffer
+            .write()
+            .toggle_comment(&Position::new(3, 0), &Position::new(4, 0), &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "// fn f() {\n    a();\n\n/*   b(); */\n/* } */");
+
+        let buffer = new_buffer_with_content(&editor, "\u{3000}a();\n  b();");
+        let spec = CommentSpec::line("// ");
+        let (start, end) = (Position::new(0, 0), Position::new(1, 0));
+
+        buffer
+            .write()
+            .toggle_comment(&start, &end, &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "\u{3000}// a();\n //  b();");
+
+        buffer
+            .write()
+            .toggle_comment(&start, &end, &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "\u{3000}a();\n  b();");
+    }
+
+    pub fn test_comment_block(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "let a = f(1, 2);\nlet b = 3;");
+        let spec = CommentSpec::block("/* ", " */");
+
+        let (start, end) = (Position::new(0, 13), Position::new(0, 14));
+
+        let commented = buffer
+            .write()
+            .toggle_comment(&start, &end, &spec)
+            .expect("Failed to toggle comment");
+
+        assert!(commented);
+        assert_buffer_content!(buffer, "let a = f(1, /* 2 */);\nlet b = 3;");
+
+        let commented = buffer
+            .write()
+            .toggle_comment(&start, &Position::new(0, 20), &spec)
+            .expect("Failed to toggle comment");
+
+        assert!(!commented);
+        assert_buffer_content!(buffer, "let a = f(1, 2);\nlet b = 3;");
+
+        let (start, end) = (Position::new(0, 8), Position::new(1, 9));
+
+        buffer
+            .write()
+            .toggle_comment(&start, &end, &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "let a = /* f(1, 2);\nlet b = 3 */;");
+
+        buffer
+            .write()
+            .toggle_comment(&start, &Position::new(1, 12), &spec)
+            .expect("Failed to toggle comment");
+        assert_buffer_content!(buffer, "let a = f(1, 2);\nlet b = 3;");
+
+        assert!(CommentSpec::from_commentstring("#", CommentMode::Line).is_none());
+    }
+
+    #[macro_export]
+    macro_rules! eel_comment_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::comment::tests,
+                prefix: $prefix,
+                tests: [test_comment_lines, test_comment_block],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_comment_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -3,6 +3,9 @@ use crate::buffer::{BufferHandle, ReadBuffer, WriteBuffer};
 #[cfg(feature = "mark")]
 use crate::mark::MarkId;
 
+#[cfg(feature = "region")]
+use crate::{Position, Result};
+
 #[cfg(feature = "cursor")]
 mod cursor {
     use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
@@ -59,8 +62,44 @@ mod mark {
     }
 }
 
+#[cfg(feature = "region")]
+mod region {
+    use crate::{
+        CompleteBufferHandle, Position, Result, mark::MarkBufferHandle, region::BufferRegion,
+    };
+
+    pub trait RegionRequirement {
+        type RRegion: CompleteBufferHandle;
+
+        fn create_region(&self, start: &Position, end: &Position) -> Result<Self::RRegion>;
+    }
+    impl<T> RegionRequirement for T
+    where
+        T: MarkBufferHandle,
+        T::MReadBuffer: super::cursor::CursorReadRequirement,
+        T::MWriteBuffer: super::cursor::CursorWriteRequirement,
+    {
+        type RRegion = BufferRegion<T>;
+
+        fn create_region(&self, start: &Position, end: &Position) -> Result<Self::RRegion> {
+            BufferRegion::lock_new(self, (start, end))
+        }
+    }
+}
+
+#[cfg(not(feature = "region"))]
+mod region {
+    pub trait RegionRequirement {
+        type RRegion;
+    }
+    impl<T> RegionRequirement for T {
+        type RRegion = ();
+    }
+}
+
 pub trait CompleteBufferHandle:
     BufferHandle<ReadBuffer = Self::CompleteReadBuffer, WriteBuffer = Self::CompleteWriteBuffer>
+    + region::RegionRequirement<RRegion = Self::Region>
 {
     type CompleteReadBuffer: ReadBuffer
         + mark::MarkReadRequirement<RMarkId = Self::MarkId>
@@ -75,6 +114,18 @@ pub trait CompleteBufferHandle:
 
     #[cfg(not(feature = "mark"))]
     type MarkId;
+
+    #[cfg(feature = "region")]
+    type Region: CompleteBufferHandle;
+
+    #[cfg(not(feature = "region"))]
+    type Region;
+
+    /// Region over `start..end` of this buffer, see [`crate::region::BufferRegion`].
+    #[cfg(feature = "region")]
+    fn region(&self, start: &Position, end: &Position) -> Result<Self::Region> {
+        region::RegionRequirement::create_region(self, start, end)
+    }
 }
 
 impl<B> CompleteBufferHandle for B
@@ -84,11 +135,14 @@ where
     B::WriteBuffer: WriteBuffer
         + mark::MarkWriteRequirement<RMarkId = <B::ReadBuffer as mark::MarkReadRequirement>::RMarkId>
         + cursor::CursorWriteRequirement,
+    B: region::RegionRequirement,
 {
     type CompleteReadBuffer = B::ReadBuffer;
     type CompleteWriteBuffer = B::WriteBuffer;
 
     type MarkId = <B::ReadBuffer as mark::MarkReadRequirement>::RMarkId;
+
+    type Region = <B as region::RegionRequirement>::RRegion;
 }
 
 #[cfg(feature = "tests")]
@@ -150,3 +204,165 @@ mod static_tests {
         }
     }
 }
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{Editor, Position, assert_buffer_content, test_utils::new_buffer_with_content};
+
+    /// Uses every enabled capability on the first row, which needs at least 5 columns. The
+    /// content is restored afterwards, the cursor is left at `(0, 5)`.
+    fn exercise<B: CompleteBufferHandle>(buffer: &B) {
+        let before = buffer
+            .read()
+            .get_text((Position::new(0, 0), Position::new(0, 4)))
+            .expect("Failed to get text");
+
+        #[cfg(feature = "cursor")]
+        {
+            use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+            buffer
+                .write()
+                .set_cursor(&Position::new(0, 2))
+                .expect("Failed to set cursor");
+            assert_eq!(
+                buffer.read().get_cursor().expect("Failed to get cursor"),
+                Position::new(0, 2)
+            );
+        }
+
+        #[cfg(feature = "mark")]
+        {
+            use crate::mark::{MarkReadBuffer, MarkWriteBuffer};
+
+            let mark = buffer
+                .write()
+                .create_mark(&Position::new(0, 3))
+                .expect("Failed to create mark");
+
+            buffer
+                .write()
+                .set_text((Position::new(0, 0), Position::new(0, 0)), "ab")
+                .expect("Failed to set text");
+
+            let moved = buffer
+                .read()
+                .get_mark_position(mark)
+                .expect("Failed to get mark position");
+            assert_eq!(moved, Position::new(0, 5));
+
+            #[cfg(feature = "cursor")]
+            {
+                use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+                buffer
+                    .write()
+                    .set_cursor(&moved)
+                    .expect("Failed to set cursor");
+                assert_eq!(
+                    buffer.read().get_cursor().expect("Failed to get cursor"),
+                    moved
+                );
+            }
+
+            buffer
+                .write()
+                .set_text((Position::new(0, 0), Position::new(0, 2)), "")
+                .expect("Failed to set text");
+
+            assert_eq!(
+                buffer
+                    .read()
+                    .get_mark_position(mark)
+                    .expect("Failed to get mark position"),
+                Position::new(0, 3)
+            );
+
+            buffer
+                .write()
+                .destroy_mark(mark)
+                .expect("Failed to destroy mark");
+        }
+
+        // Edits may or may not move the cursor depending on the backend
+        #[cfg(feature = "cursor")]
+        {
+            use crate::cursor::CursorWriteBuffer;
+
+            buffer
+                .write()
+                .set_cursor(&Position::new(0, 5))
+                .expect("Failed to set cursor");
+        }
+
+        assert_eq!(
+            buffer
+                .read()
+                .get_text((Position::new(0, 0), Position::new(0, 4)))
+                .expect("Failed to get text"),
+            before
+        );
+    }
+
+    pub fn test_complete_capabilities<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: CompleteBufferHandle,
+    {
+        let content = "First line\nSecond line\nThird line";
+        let buffer = new_buffer_with_content(&editor, content);
+
+        exercise(&buffer);
+
+        #[cfg(feature = "region")]
+        {
+            let region = buffer
+                .region(&Position::new(1, 0), &Position::new(2, 5))
+                .expect("Failed to create region");
+
+            exercise(&region);
+
+            let nested = region
+                .region(&Position::new(0, 2), &Position::new(1, 3))
+                .expect("Failed to create region");
+
+            exercise(&nested);
+
+            #[cfg(feature = "cursor")]
+            {
+                use crate::cursor::CursorReadBuffer;
+
+                assert_eq!(
+                    buffer.read().get_cursor().expect("Failed to get cursor"),
+                    Position::new(1, 7)
+                );
+                assert_eq!(
+                    region.read().get_cursor().expect("Failed to get cursor"),
+                    Position::new(0, 7)
+                );
+            }
+        }
+
+        assert_buffer_content!(buffer, content);
+    }
+
+    #[macro_export]
+    macro_rules! eel_complete_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::CompleteBufferHandle },
+                module_path: $crate::complete_buffer::tests,
+                prefix: $prefix,
+                tests: [test_complete_capabilities],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_complete_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use crate::{
+    Editor, Position, Result, assert_buffer_content,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+    test_utils::new_buffer_with_content,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Buffer,
+    Cursor,
+    Mark,
+    Region,
+    UnicodeColumns,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Buffer => "buffer",
+            Capability::Cursor => "cursor",
+            Capability::Mark => "mark",
+            Capability::Region => "region",
+            Capability::UnicodeColumns => "unicode columns",
+        };
+
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TestOutcome {
+    pub name: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CapabilityReport {
+    pub capability: Capability,
+    pub tests: Vec<TestOutcome>,
+}
+
+impl CapabilityReport {
+    pub fn passed(&self) -> usize {
+        self.tests.iter().filter(|t| t.passed).count()
+    }
+
+    pub fn supported(&self) -> bool {
+        !self.tests.is_empty() && self.passed() == self.tests.len()
+    }
+}
+
+/// Result of running the shared test suite against an [`Editor`] implementation.
+///
+/// Capabilities which are disabled at compile time are not present in the report.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ConformanceReport {
+    pub capabilities: Vec<CapabilityReport>,
+}
+
+impl ConformanceReport {
+    pub fn get(&self, capability: Capability) -> Option<&CapabilityReport> {
+        self.capabilities
+            .iter()
+            .find(|c| c.capability == capability)
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.get(capability)
+            .is_some_and(CapabilityReport::supported)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (Capability, &TestOutcome)> {
+        self.capabilities.iter().flat_map(|c| {
+            c.tests
+                .iter()
+                .filter(|t| !t.passed)
+                .map(|t| (c.capability, t))
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Conformance report is always serializable")
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} | {:<9} | {:>6}",
+            "capability", "supported", "passed"
+        )?;
+        writeln!(f, "{:-<16}-+-{:-<9}-+-{:->6}", "", "", "")?;
+
+        for c in &self.capabilities {
+            let passed = format!("{}/{}", c.passed(), c.tests.len());
+            let supported = if c.supported() { "yes" } else { "no" };
+
+            writeln!(f, "{:<16} | {:<9} | {:>6}", c.capability, supported, passed)?;
+        }
+
+        for (capability, test) in self.failures() {
+            writeln!(
+                f,
+                "FAILED {capability}::{}: {}",
+                test.name,
+                test.message.as_deref().unwrap_or("<no message>")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shares a single editor between all the tests of a conformance run.
+///
+/// Editors are usually bound to the thread they were created on (e.g. nvim), so the
+/// runner can't create a fresh one for each test.
+pub struct SharedEditor<E: Editor>(Arc<E>);
+
+impl<E: Editor> Clone for SharedEditor<E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<E: Editor> Editor for SharedEditor<E> {
+    type BufferHandle = E::BufferHandle;
+
+    fn current_buffer(&self) -> Result<Self::BufferHandle> {
+        self.0.current_buffer()
+    }
+
+    fn new_buffer(&self) -> Result<Self::BufferHandle> {
+        self.0.new_buffer()
+    }
+
+    fn set_current_buffer(
+        &self,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+    ) -> Result<()> {
+        self.0.set_current_buffer(buffer)
+    }
+
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>> {
+        self.0.buffers()
+    }
+
+    fn buffer_name(&self, buffer: &Self::BufferHandle) -> Result<Option<String>> {
+        self.0.buffer_name(buffer)
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>> {
+        self.0.buffer_by_name(name)
+    }
+
+    fn buffer_by_path(&self, path: &std::path::Path) -> Result<Option<Self::BufferHandle>> {
+        self.0.buffer_by_path(path)
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<crate::OptionValue>> {
+        self.0.get_option(name)
+    }
+
+    fn set_option(&self, name: &str, value: crate::OptionValue) -> Result<()> {
+        self.0.set_option(name, value)
+    }
+
+    fn on_idle(
+        &self,
+        delay: std::time::Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.0.on_idle(delay, callback)
+    }
+
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        self.0.tasks()
+    }
+
+    #[cfg(feature = "ui")]
+    fn prompt(&self, spec: &crate::ui::PromptSpec) -> Result<crate::ui::UserResponse> {
+        self.0.prompt(spec)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.0.jump_back()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.0.jump_forward()
+    }
+
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.0.execute_normal(keys)
+    }
+
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        self.0.detect_filetype(path, content)
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        self.0.capabilities()
+    }
+}
+
+type ConformanceTest<E> = (&'static str, fn(E));
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".into()
+    }
+}
+
+fn run_tests<E: Editor>(
+    editor: impl Fn() -> E,
+    tests: Vec<ConformanceTest<E>>,
+) -> Vec<TestOutcome> {
+    tests
+        .into_iter()
+        .map(|(name, test)| {
+            let editor = editor();
+            let result = panic::catch_unwind(AssertUnwindSafe(move || test(editor)));
+
+            TestOutcome {
+                name: name.to_string(),
+                passed: result.is_ok(),
+                message: result.err().map(panic_message),
+            }
+        })
+        .collect()
+}
+
+fn buffer_tests<E: Editor>() -> Vec<ConformanceTest<E>> {
+    crate::eel_buffer_tests!(@list)
+}
+
+#[cfg(feature = "cursor")]
+fn cursor_tests<E>() -> Vec<ConformanceTest<E>>
+where
+    E: Editor,
+    <E::BufferHandle as BufferHandle>::ReadBuffer: crate::cursor::CursorReadBuffer,
+    <E::BufferHandle as BufferHandle>::WriteBuffer: crate::cursor::CursorWriteBuffer,
+{
+    crate::eel_cursor_tests!(@list)
+}
+
+#[cfg(feature = "mark")]
+fn mark_tests<E>() -> Vec<ConformanceTest<E>>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    crate::eel_mark_tests!(@list)
+}
+
+/// Columns are byte offsets, multibyte characters have to be handled accordingly.
+fn test_unicode_columns<E: Editor>(editor: E) {
+    let buffer = new_buffer_with_content(&editor, "zażółć\ngęślą jaźń");
+
+    assert_eq!(
+        buffer
+            .read()
+            .max_row_pos(0)
+            .expect("Failed to get max row pos"),
+        Position::new(0, "zażółć".len())
+    );
+
+    buffer
+        .write()
+        .set_text(
+            (
+                &Position::new(0, "zażółć".len()),
+                &Position::new(0, "zażółć".len()),
+            ),
+            " 🦀",
+        )
+        .expect("Failed to set text");
+
+    assert_buffer_content!(buffer, "zażółć 🦀\ngęślą jaźń");
+
+    buffer
+        .write()
+        .set_text(
+            (
+                &Position::new(1, "gęślą".len()),
+                &Position::new(1, "gęślą ".len()),
+            ),
+            "_",
+        )
+        .expect("Failed to set text");
+
+    assert_buffer_content!(buffer, "zażółć 🦀\ngęślą_jaźń");
+}
+
+/// Runs the shared test suites against an editor, one capability at a time.
+///
+/// Each capability is opt-in, so backends only have to satisfy the trait bounds of
+/// the capabilities they claim to support:
+///
+/// ```ignore
+/// let report = Conformance::new(editor).buffer().cursor().mark().region().report();
+/// println!("{report}");
+/// ```
+pub struct Conformance<E: Editor> {
+    editor: SharedEditor<E>,
+    report: ConformanceReport,
+}
+
+impl<E: Editor> Conformance<E> {
+    pub fn new(editor: E) -> Self {
+        Self {
+            editor: SharedEditor(Arc::new(editor)),
+            report: ConformanceReport::default(),
+        }
+    }
+
+    fn record<T: Editor>(
+        &mut self,
+        capability: Capability,
+        editor: impl Fn() -> T,
+        tests: Vec<ConformanceTest<T>>,
+    ) {
+        let outcomes = run_tests(editor, tests);
+
+        match self
+            .report
+            .capabilities
+            .iter_mut()
+            .find(|c| c.capability == capability)
+        {
+            Some(c) => c.tests.extend(outcomes),
+            None => self.report.capabilities.push(CapabilityReport {
+                capability,
+                tests: outcomes,
+            }),
+        }
+    }
+
+    fn shared(&self) -> impl Fn() -> SharedEditor<E> + use<E> {
+        let editor = self.editor.clone();
+        move || editor.clone()
+    }
+
+    pub fn buffer(mut self) -> Self {
+        let mut tests = buffer_tests();
+        tests.extend(crate::eel_workspace_tests!(@list));
+        tests.extend(crate::eel_fuzz_tests!(@list));
+
+        self.record(Capability::Buffer, self.shared(), tests);
+        self
+    }
+
+    pub fn unicode_columns(mut self) -> Self {
+        self.record(
+            Capability::UnicodeColumns,
+            self.shared(),
+            vec![("test_unicode_columns", test_unicode_columns as fn(_))],
+        );
+        self
+    }
+
+    pub fn report(self) -> ConformanceReport {
+        self.report
+    }
+}
+
+#[cfg(feature = "cursor")]
+impl<E> Conformance<E>
+where
+    E: Editor,
+    <E::BufferHandle as BufferHandle>::ReadBuffer: crate::cursor::CursorReadBuffer,
+    <E::BufferHandle as BufferHandle>::WriteBuffer: crate::cursor::CursorWriteBuffer,
+{
+    pub fn cursor(mut self) -> Self {
+        self.record(Capability::Cursor, self.shared(), cursor_tests());
+        self
+    }
+}
+
+#[cfg(feature = "mark")]
+impl<E> Conformance<E>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    pub fn mark(mut self) -> Self {
+        self.record(Capability::Mark, self.shared(), mark_tests());
+        self
+    }
+}
+
+#[cfg(feature = "region")]
+mod region {
+    use super::*;
+
+    use crate::{
+        mark::MarkBufferHandle,
+        region::{
+            BufferRegion,
+            editor_factory::{RegionEditor, region_editor_factory},
+        },
+        test_utils::EditorFactory,
+    };
+
+    impl<E> Conformance<E>
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+        BufferRegion<E::BufferHandle>: MarkBufferHandle,
+    {
+        fn region_editors(
+            &self,
+        ) -> [(
+            &'static str,
+            impl Fn() -> RegionEditor<SharedEditor<E>> + use<E>,
+        ); 2] {
+            [(false, "region_"), (true, "region_empty_")].map(|(empty, prefix)| {
+                let factory = region_editor_factory(self.shared(), empty);
+                (prefix, move || factory.create_editor())
+            })
+        }
+
+        fn record_prefixed<T: Editor>(
+            &mut self,
+            prefix: &'static str,
+            editor: impl Fn() -> T,
+            tests: Vec<ConformanceTest<T>>,
+        ) {
+            let start = self
+                .report
+                .get(Capability::Region)
+                .map_or(0, |c| c.tests.len());
+
+            self.record(Capability::Region, editor, tests);
+
+            if let Some(c) = self
+                .report
+                .capabilities
+                .iter_mut()
+                .find(|c| c.capability == Capability::Region)
+            {
+                for test in &mut c.tests[start..] {
+                    test.name = format!("{prefix}{}", test.name);
+                }
+            }
+        }
+
+        /// Region specific tests, followed by the buffer and mark suites run inside regions.
+        pub fn region(mut self) -> Self {
+            self.record(
+                Capability::Region,
+                self.shared(),
+                crate::eel_region_tests!(@list),
+            );
+
+            self.record(
+                Capability::Region,
+                self.shared(),
+                crate::eel_fuzz_tests!(@region_list),
+            );
+
+            for (prefix, editor) in self.region_editors() {
+                self.record_prefixed(prefix, &editor, buffer_tests());
+                self.record_prefixed(prefix, &editor, mark_tests());
+            }
+
+            self
+        }
+    }
+
+    #[cfg(feature = "cursor")]
+    impl<E> Conformance<E>
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+        BufferRegion<E::BufferHandle>: MarkBufferHandle,
+        <BufferRegion<E::BufferHandle> as BufferHandle>::ReadBuffer:
+            crate::cursor::CursorReadBuffer,
+        <BufferRegion<E::BufferHandle> as BufferHandle>::WriteBuffer:
+            crate::cursor::CursorWriteBuffer,
+    {
+        /// The cursor suite run inside regions, reported under [`Capability::Region`].
+        pub fn region_cursor(mut self) -> Self {
+            for (prefix, editor) in self.region_editors() {
+                self.record_prefixed(prefix, &editor, cursor_tests());
+            }
+
+            self
+        }
+    }
+}
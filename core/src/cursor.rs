@@ -1,3 +1,5 @@
+use std::sync::{Mutex, PoisonError};
+
 use crate::{
     Position, Result,
     buffer::{BufferHandle, ReadBuffer, WriteBuffer},
@@ -10,8 +12,15 @@ pub trait CursorReadBuffer: ReadBuffer {
 pub trait CursorWriteBuffer: CursorReadBuffer + WriteBuffer {
     fn set_cursor(&mut self, position: &Position) -> Result<()>;
 
+    /// Records the cursor position in the editor's jump list, see [`crate::Editor::jump_back`].
+    fn push_jump(&mut self) -> Result<()>;
+
+    /// Inserts after the character under the cursor, or at the cursor if it's at the end of
+    /// the line.
     fn append_at_cursor(&mut self, text: &str) -> Result<()> {
-        self.append_at_position(&self.get_cursor()?, text)
+        let position = self.pos_after_char(&self.get_cursor()?)?;
+
+        self.set_text((&position, &position), text)
     }
 
     fn prepend_at_cursor(&mut self, text: &str) -> Result<()> {
@@ -23,23 +32,95 @@ pub trait CursorWriteBuffer: CursorReadBuffer + WriteBuffer {
             return Ok(());
         }
 
-        let position = self.get_cursor()?;
+        let position = self.pos_after_char(&self.get_cursor()?)?;
         let max_text_pos = Position::max_text_pos(text);
 
-        let next_position = position.clone().next_col();
-
-        let position = if self.validate_pos(&next_position).is_ok() {
-            next_position
-        } else {
-            position
-        };
-
         self.prepend_at_position(&position, text)?;
 
         self.set_cursor(&position.offset(&max_text_pos).prev_col())
     }
 }
 
+#[derive(Debug)]
+struct JumpListState<T> {
+    jumps: Vec<T>,
+    index: usize,
+}
+
+/// Jump list for backends without a native one, behaving like a browser history.
+#[derive(Debug)]
+pub struct JumpList<T> {
+    state: Mutex<JumpListState<T>>,
+}
+
+impl<T> Default for JumpList<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(JumpListState {
+                jumps: Vec::new(),
+                index: 0,
+            }),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> JumpList<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, JumpListState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Drops the entries we jumped back from.
+    pub fn push(&self, jump: T) {
+        let mut state = self.state();
+
+        let index = state.index;
+        state.jumps.truncate(index);
+        state.jumps.retain(|j| *j != jump);
+        state.jumps.push(jump);
+        state.index = state.jumps.len();
+    }
+
+    /// `current` is recorded when jumping back from the newest entry, so
+    /// [`JumpList::forward`] can return to it.
+    pub fn back(&self, current: T) -> Option<T> {
+        let mut state = self.state();
+
+        if state.index == 0 {
+            return None;
+        }
+
+        if state.index == state.jumps.len() {
+            state.jumps.retain(|j| *j != current);
+            state.jumps.push(current);
+            state.index = state.jumps.len() - 1;
+
+            if state.index == 0 {
+                return None;
+            }
+        }
+
+        state.index -= 1;
+
+        Some(state.jumps[state.index].clone())
+    }
+
+    pub fn forward(&self) -> Option<T> {
+        let mut state = self.state();
+
+        if state.index + 1 >= state.jumps.len() {
+            return None;
+        }
+
+        state.index += 1;
+
+        Some(state.jumps[state.index].clone())
+    }
+}
+
 pub trait CursorBufferHandle:
     BufferHandle<ReadBuffer = Self::CReadBuffer, WriteBuffer = Self::CWriteBuffer>
 {
@@ -219,24 +300,117 @@ Third line!"#
         assert_buffer_state!(buffer, r#"tes|t"#);
     }
 
+    pub fn test_cursor_jump_list<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: CursorBufferHandle,
+    {
+        let buffer = new_buffer_with_state(
+            &editor,
+            r#"First |line
+Second line
+Third line"#,
+        );
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        buffer.write().push_jump().expect("Failed to push jump");
+        buffer
+            .write()
+            .set_cursor(&Position::new(2, 3))
+            .expect("Failed to set cursor");
+
+        assert!(editor.jump_back().expect("Failed to jump back"));
+        assert_cursor_pos!(buffer, Position::new(0, 6));
+
+        assert!(editor.jump_forward().expect("Failed to jump forward"));
+        assert_cursor_pos!(buffer, Position::new(2, 3));
+
+        assert!(!editor.jump_forward().expect("Failed to jump forward"));
+        assert_cursor_pos!(buffer, Position::new(2, 3));
+    }
+
+    pub fn test_editor_cursor_position<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: CursorBufferHandle,
+    {
+        let buffer = new_buffer_with_state(
+            &editor,
+            r#"First line
+Second |line"#,
+        );
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        assert_eq!(
+            editor.cursor_position().expect("Failed to get cursor"),
+            Position::new(1, 7)
+        );
+
+        editor
+            .set_cursor_position(&Position::new(0, 2))
+            .expect("Failed to set cursor");
+        assert_cursor_pos!(buffer, Position::new(0, 2));
+
+        assert!(editor.set_cursor_position(&Position::new(5, 0)).is_err());
+        assert_cursor_pos!(buffer, Position::new(0, 2));
+    }
+
+    pub fn test_cursor_delete_inner_word<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: CursorBufferHandle,
+    {
+        let buffer = new_buffer_with_state(&editor, "let fo|o_1 = bar;");
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        crate::textobject::delete_inner_word(&editor).expect("Failed to delete word");
+        assert_buffer_state!(buffer, "let | = bar;");
+
+        crate::textobject::delete_inner_word(&editor).expect("Failed to delete word");
+        // Blanks are a word too
+        assert_buffer_state!(buffer, "let|= bar;");
+    }
+
     #[macro_export]
     macro_rules! eel_cursor_tests {
-        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
-            $crate::eel_tests!(
-                test_tag: $test_tag,
-                editor_factory: $editor_factory,
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
                 editor_bounds: {
                     <E::BufferHandle as $crate::buffer::BufferHandle>::ReadBuffer: $crate::cursor::CursorReadBuffer,
                     <E::BufferHandle as $crate::buffer::BufferHandle>::WriteBuffer: $crate::cursor::CursorWriteBuffer,
                 },
                 module_path: $crate::cursor::tests,
-                prefix: $prefix,
                 tests: [
                     test_cursor,
                     test_cursor_append,
                     test_cursor_type_text,
-                    test_cursor_type_text_empty
+                    test_cursor_type_text_empty,
+                    test_cursor_jump_list,
+                    test_editor_cursor_position,
+                    test_cursor_delete_inner_word,
                 ],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_cursor_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_cursor_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
             );
         };
 
@@ -0,0 +1,551 @@
+use std::{
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+        mpsc::{Receiver, RecvTimeoutError, Sender, channel},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{BufferHandle, WriteBuffer},
+    tracing::ResultExt,
+};
+
+pub const DEFAULT_QUIET_PERIOD: Duration = Duration::from_millis(50);
+pub const DEFAULT_MAX_PENDING: usize = 64;
+
+#[derive(Debug, Clone)]
+struct PendingEdit {
+    start: Position,
+    end: Position,
+    text: String,
+}
+
+impl PendingEdit {
+    /// Appends `next` if it inserts right after this insertion, the way typing does.
+    fn merge(&mut self, next: &PendingEdit) -> bool {
+        let insert_end = self.start.offset(&Position::max_text_pos(&self.text));
+
+        if self.start != self.end || next.start != next.end || next.start != insert_end {
+            return false;
+        }
+
+        self.text.push_str(&next.text);
+
+        true
+    }
+}
+
+struct Shared<B> {
+    buffer: B,
+    pending: Mutex<Vec<PendingEdit>>,
+    flushing: Mutex<()>,
+}
+
+impl<B: BufferHandle> Shared<B> {
+    fn pending(&self) -> MutexGuard<'_, Vec<PendingEdit>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn flush(&self) -> Result<()> {
+        // Keeps concurrent flushes from reordering edits
+        let _flushing = self.flushing.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let edits = std::mem::take(&mut *self.pending());
+
+        if edits.is_empty() {
+            return Ok(());
+        }
+
+        let mut lock = self.buffer.write_timeout()?;
+
+        for edit in edits {
+            lock.set_text((&edit.start, &edit.end), &edit.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Queues `set_text` calls and applies them in order once the writer is quiet for a while or
+/// too many edits are pending, merging consecutive insertions.
+///
+/// Queued edits aren't visible in the buffer until flushed. Positions are interpreted the same
+/// way as if the edits were applied immediately. Pending edits are flushed on drop.
+pub struct DebouncedWriter<B: BufferHandle> {
+    shared: Arc<Shared<B>>,
+    max_pending: usize,
+    notify: Sender<()>,
+}
+
+impl<B: BufferHandle> DebouncedWriter<B> {
+    pub fn new(buffer: B) -> Self {
+        Self::with_limits(buffer, DEFAULT_QUIET_PERIOD, DEFAULT_MAX_PENDING)
+    }
+
+    pub fn with_limits(buffer: B, quiet_period: Duration, max_pending: usize) -> Self {
+        let shared = Arc::new(Shared {
+            buffer,
+            pending: Mutex::default(),
+            flushing: Mutex::default(),
+        });
+
+        let (notify, activity) = channel();
+
+        let worker = shared.clone();
+        std::thread::spawn(move || Self::run(worker, activity, quiet_period));
+
+        Self {
+            shared,
+            max_pending,
+            notify,
+        }
+    }
+
+    fn run(shared: Arc<Shared<B>>, activity: Receiver<()>, quiet_period: Duration) {
+        while activity.recv().is_ok() {
+            loop {
+                match activity.recv_timeout(quiet_period) {
+                    Ok(()) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            _ = shared
+                .flush()
+                .log_err_msg("Failed to flush debounced edits");
+        }
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.shared.buffer
+    }
+
+    pub fn set_text(&self, range: impl Into<PosRange>, text: &str) -> Result<()> {
+        let (start, end) = range.into().into_positions();
+        let edit = PendingEdit {
+            start,
+            end,
+            text: text.to_string(),
+        };
+
+        let pending = {
+            let mut pending = self.shared.pending();
+
+            if !pending.last_mut().is_some_and(|last| last.merge(&edit)) {
+                pending.push(edit);
+            }
+
+            pending.len()
+        };
+
+        if pending >= self.max_pending {
+            return self.flush();
+        }
+
+        _ = self.notify.send(());
+
+        Ok(())
+    }
+
+    pub fn insert(&self, position: &Position, text: &str) -> Result<()> {
+        self.set_text((position, position), text)
+    }
+
+    /// Number of queued edits, after merging.
+    pub fn pending(&self) -> usize {
+        self.shared.pending().len()
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.shared.flush()
+    }
+}
+
+impl<B: BufferHandle> Drop for DebouncedWriter<B> {
+    fn drop(&mut self) {
+        _ = self
+            .shared
+            .flush()
+            .log_err_msg("Failed to flush debounced edits");
+    }
+}
+
+/// Yields the last event of every burst, once `events` was quiet for `quiet_period`.
+///
+/// A pending event is still delivered when `events` disconnects. Stops once either end is
+/// dropped.
+pub fn debounced<T: Send + 'static>(events: Receiver<T>, quiet_period: Duration) -> Receiver<T> {
+    let (tx, rx) = channel();
+
+    std::thread::spawn(move || {
+        while let Ok(mut last) = events.recv() {
+            let disconnected = loop {
+                match events.recv_timeout(quiet_period) {
+                    Ok(event) => last = event,
+                    Err(RecvTimeoutError::Timeout) => break false,
+                    Err(RecvTimeoutError::Disconnected) => break true,
+                }
+            };
+
+            if tx.send(last).is_err() || disconnected {
+                return;
+            }
+        }
+    });
+
+    rx
+}
+
+type IdleCallback = Box<dyn FnMut() -> bool + Send>;
+
+struct IdleEntry {
+    delay: Duration,
+    /// Whether it already ran since the last activity
+    fired: bool,
+    callback: IdleCallback,
+}
+
+struct IdleState {
+    last_activity: Instant,
+    /// Bumped on activity, so callbacks running meanwhile can be reset
+    generation: u64,
+    /// Set when the editor reports idleness, firing everything right away
+    settled: bool,
+    closed: bool,
+    entries: Vec<IdleEntry>,
+}
+
+struct IdleShared {
+    state: Mutex<IdleState>,
+    wake: Condvar,
+}
+
+impl IdleShared {
+    fn state(&self) -> MutexGuard<'_, IdleState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Runs callbacks once no activity was reported for their delay, for backends to implement
+/// [`crate::Editor::on_idle`] on top of their input or change events.
+///
+/// Every callback runs at most once per idle period, on the timer's own thread, and is dropped
+/// once it returns `false`.
+pub struct IdleTimer {
+    shared: Arc<IdleShared>,
+}
+
+impl std::fmt::Debug for IdleTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.shared.state();
+
+        f.debug_struct("IdleTimer")
+            .field("callbacks", &state.entries.len())
+            .finish()
+    }
+}
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        let shared = Arc::new(IdleShared {
+            state: Mutex::new(IdleState {
+                last_activity: Instant::now(),
+                generation: 0,
+                settled: false,
+                closed: false,
+                entries: Vec::new(),
+            }),
+            wake: Condvar::new(),
+        });
+
+        let worker = shared.clone();
+        std::thread::spawn(move || Self::run(&worker));
+
+        Self { shared }
+    }
+}
+
+impl IdleTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn run(shared: &IdleShared) {
+        let mut state = shared.state();
+
+        loop {
+            if state.closed {
+                return;
+            }
+
+            let now = Instant::now();
+            let (last_activity, settled) = (state.last_activity, state.settled);
+            let is_due =
+                |entry: &IdleEntry| !entry.fired && (settled || last_activity + entry.delay <= now);
+
+            if state.entries.iter().any(is_due) {
+                // Run without the lock, so callbacks can register more of them
+                let mut entries = std::mem::take(&mut state.entries);
+                let generation = state.generation;
+                drop(state);
+
+                entries.retain_mut(|entry| {
+                    if !is_due(entry) {
+                        return true;
+                    }
+
+                    entry.fired = true;
+                    (entry.callback)()
+                });
+
+                state = shared.state();
+                if state.generation != generation {
+                    for entry in &mut entries {
+                        entry.fired = false;
+                    }
+                }
+                entries.append(&mut state.entries);
+                state.entries = entries;
+
+                continue;
+            }
+
+            let next = state
+                .entries
+                .iter()
+                .filter(|entry| !entry.fired)
+                .map(|entry| last_activity + entry.delay)
+                .min();
+
+            state = match next {
+                Some(next) => {
+                    shared
+                        .wake
+                        .wait_timeout(state, next.saturating_duration_since(now))
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => shared
+                    .wake
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut IdleState)) {
+        f(&mut self.shared.state());
+
+        self.shared.wake.notify_all();
+    }
+
+    /// Runs `callback` every time there was no activity for `delay`, until it returns `false`.
+    pub fn on_idle(&self, delay: Duration, callback: impl FnMut() -> bool + Send + 'static) {
+        self.update(|state| {
+            state.entries.push(IdleEntry {
+                delay,
+                fired: false,
+                callback: Box::new(callback),
+            })
+        });
+    }
+
+    /// Restarts the idle period.
+    pub fn activity(&self) {
+        self.update(|state| {
+            state.last_activity = Instant::now();
+            state.generation += 1;
+            state.settled = false;
+
+            for entry in &mut state.entries {
+                entry.fired = false;
+            }
+        });
+    }
+
+    /// Ends the idle period early, running the callbacks that didn't run yet.
+    pub fn settle(&self) {
+        self.update(|state| state.settled = true);
+    }
+}
+
+impl Drop for IdleTimer {
+    fn drop(&mut self) {
+        self.update(|state| state.closed = true);
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    use crate::{
+        Editor, assert_buffer_content, buffer::ReadBuffer, test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_debounce_merge(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "fn main() {}");
+        let writer = DebouncedWriter::with_limits(buffer.clone(), Duration::from_secs(60), 64);
+
+        for (i, c) in "todo!()".chars().enumerate() {
+            writer
+                .insert(&Position::new(0, 11 + i), &c.to_string())
+                .expect("Failed to queue edit");
+        }
+
+        assert_eq!(writer.pending(), 1);
+        assert_buffer_content!(buffer, "fn main() {}");
+
+        writer
+            .set_text((Position::new(0, 3), Position::new(0, 7)), "run")
+            .expect("Failed to queue edit");
+
+        assert_eq!(writer.pending(), 2);
+
+        writer.flush().expect("Failed to flush");
+
+        assert_eq!(writer.pending(), 0);
+        assert_buffer_content!(buffer, "fn run() {todo!()}");
+    }
+
+    pub fn test_debounce_limits(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "");
+        let writer = DebouncedWriter::with_limits(buffer.clone(), Duration::from_secs(60), 2);
+
+        writer
+            .insert(&Position::new(0, 0), "a")
+            .expect("Failed to queue edit");
+        writer
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "b")
+            .expect("Failed to queue edit");
+
+        // Second edit doesn't merge, reaching the limit
+        assert_eq!(writer.pending(), 0);
+        assert_buffer_content!(buffer, "ba");
+
+        let writer = DebouncedWriter::with_limits(buffer.clone(), Duration::from_millis(10), 64);
+
+        writer
+            .insert(&Position::new(0, 2), "!")
+            .expect("Failed to queue edit");
+
+        // Flushed once quiet
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while buffer.read().get_content().expect("Failed to get content") != "ba!" {
+            assert!(Instant::now() < deadline, "Edits were not flushed");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        // And on drop
+        writer
+            .insert(&Position::new(0, 3), "?")
+            .expect("Failed to queue edit");
+        drop(writer);
+
+        assert_buffer_content!(buffer, "ba!?");
+    }
+
+    #[macro_export]
+    macro_rules! eel_debounce_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::debounce::tests,
+                prefix: $prefix,
+                tests: [
+                    test_debounce_merge,
+                    test_debounce_limits,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_debounce_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn debounced_events() {
+        let (tx, rx) = channel();
+        let events = debounced(rx, Duration::from_millis(50));
+
+        for i in 0..5 {
+            tx.send(i).expect("Failed to send event");
+        }
+
+        assert_eq!(
+            events
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No debounced event"),
+            4
+        );
+        assert!(events.try_recv().is_err());
+
+        tx.send(5).expect("Failed to send event");
+        drop(tx);
+
+        assert_eq!(
+            events
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No debounced event"),
+            5
+        );
+        assert!(events.recv().is_err());
+    }
+
+    #[test]
+    fn idle_timer() {
+        let timer = IdleTimer::new();
+        let (tx, rx) = channel();
+
+        let start = Instant::now();
+        let fast = tx.clone();
+        timer.on_idle(Duration::from_millis(20), move || fast.send("fast").is_ok());
+        let mut runs = 0;
+        timer.on_idle(Duration::from_millis(60), move || {
+            runs += 1;
+            _ = tx.send("slow");
+            runs < 2
+        });
+
+        let recv = || {
+            rx.recv_timeout(Duration::from_secs(5))
+                .expect("Idle callback didn't run")
+        };
+
+        assert_eq!(recv(), "fast");
+        assert_eq!(recv(), "slow");
+        assert!(start.elapsed() >= Duration::from_millis(60));
+
+        // Once per idle period
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(rx.try_recv().is_err());
+
+        timer.activity();
+        timer.settle();
+
+        let mut fired = [recv(), recv()];
+        fired.sort();
+        assert_eq!(fired, ["fast", "slow"]);
+
+        // The slow one unregistered itself
+        timer.activity();
+        assert_eq!(recv(), "fast");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,461 @@
+use tracing::debug;
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer, clear_on_drop},
+    tracing::ResultExt,
+};
+
+pub trait DecorationId: std::fmt::Debug + Clone + Copy + Eq + Sync + Send {}
+
+/// Side of the row the virtual lines are shown on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Placement {
+    Above,
+    #[default]
+    Below,
+}
+
+/// Line shown between buffer lines without being part of the content, made of chunks with
+/// an optional highlight group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VirtualLine {
+    pub chunks: Vec<(String, Option<String>)>,
+}
+
+impl VirtualLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.chunks.push((text.into(), None));
+        self
+    }
+
+    pub fn highlighted(mut self, text: impl Into<String>, highlight: impl Into<String>) -> Self {
+        self.chunks.push((text.into(), Some(highlight.into())));
+        self
+    }
+
+    /// Text of all chunks.
+    pub fn content(&self) -> String {
+        self.chunks.iter().map(|(text, _)| text.as_str()).collect()
+    }
+}
+
+impl From<&str> for VirtualLine {
+    fn from(value: &str) -> Self {
+        Self::new().text(value)
+    }
+}
+
+impl From<String> for VirtualLine {
+    fn from(value: String) -> Self {
+        Self::new().text(value)
+    }
+}
+
+pub trait DecorationReadBuffer: ReadBuffer {
+    type DecorationId: DecorationId;
+
+    /// Row the virtual lines are attached to, it follows edits like a mark.
+    fn virtual_lines_row(&self, id: Self::DecorationId) -> Result<usize>;
+
+    fn virtual_text_position(&self, id: Self::DecorationId) -> Result<Position>;
+
+    /// Text covered by the highlight, the whole row for line highlights.
+    fn highlighted_range(&self, id: Self::DecorationId) -> Result<PosRange>;
+}
+
+pub trait DecorationWriteBuffer: DecorationReadBuffer + WriteBuffer {
+    fn add_virtual_lines(
+        &mut self,
+        row: usize,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<Self::DecorationId>;
+
+    /// Replaces the lines, keeping them at their current row.
+    fn set_virtual_lines(
+        &mut self,
+        id: Self::DecorationId,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<()>;
+
+    fn remove_virtual_lines(&mut self, id: Self::DecorationId) -> Result<()>;
+
+    /// Text shown inline before the character at `position`, pushing the rest of the line
+    /// right. It follows edits like a mark.
+    fn add_virtual_text(
+        &mut self,
+        position: &Position,
+        text: VirtualLine,
+    ) -> Result<Self::DecorationId>;
+
+    fn remove_virtual_text(&mut self, id: Self::DecorationId) -> Result<()>;
+
+    /// Highlights the text in `range` with `group`. Rows the range continues past are
+    /// highlighted to the edge of the window.
+    ///
+    /// The range follows edits without growing on insertions at its ends.
+    fn highlight_range(
+        &mut self,
+        range: impl Into<PosRange>,
+        group: &str,
+    ) -> Result<Self::DecorationId>;
+
+    /// Highlights the whole width of `row`, following it when lines are inserted above.
+    fn highlight_line(&mut self, row: usize, group: &str) -> Result<Self::DecorationId>;
+
+    fn remove_highlight(&mut self, id: Self::DecorationId) -> Result<()>;
+}
+
+pub trait DecorationBufferHandle:
+    BufferHandle<ReadBuffer = Self::DReadBuffer, WriteBuffer = Self::DWriteBuffer>
+{
+    type DecorationId: DecorationId;
+    type DReadBuffer: DecorationReadBuffer<DecorationId = Self::DecorationId>;
+    type DWriteBuffer: DecorationWriteBuffer<DecorationId = Self::DecorationId>;
+}
+
+impl<B, I> DecorationBufferHandle for B
+where
+    B: BufferHandle,
+    I: DecorationId,
+    B::ReadBuffer: DecorationReadBuffer<DecorationId = I>,
+    B::WriteBuffer: DecorationWriteBuffer<DecorationId = I>,
+{
+    type DecorationId = I;
+    type DReadBuffer = B::ReadBuffer;
+    type DWriteBuffer = B::WriteBuffer;
+}
+
+/// Block of virtual lines, removed when dropped.
+#[derive(Debug)]
+pub struct VirtualLines<B: DecorationBufferHandle> {
+    id: B::DecorationId,
+    placement: Placement,
+    buffer: B,
+    removed: bool,
+}
+
+impl<B: DecorationBufferHandle> VirtualLines<B> {
+    pub fn lock_new(
+        buffer: &B,
+        row: usize,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<Self> {
+        let id = buffer
+            .write_timeout()?
+            .add_virtual_lines(row, lines, placement)?;
+
+        Ok(Self {
+            id,
+            placement,
+            buffer: buffer.clone(),
+            removed: false,
+        })
+    }
+
+    pub fn id(&self) -> B::DecorationId {
+        self.id
+    }
+
+    pub fn placement(&self) -> Placement {
+        self.placement
+    }
+
+    pub fn row(&self) -> Result<usize> {
+        self.buffer.read_timeout()?.virtual_lines_row(self.id)
+    }
+
+    pub fn update(&self, lines: Vec<VirtualLine>) -> Result<()> {
+        self.buffer
+            .write_timeout()?
+            .set_virtual_lines(self.id, lines, self.placement)
+    }
+
+    /// Removes the lines right away, unlike dropping.
+    pub fn remove(mut self) -> Result<()> {
+        self.removed = true;
+
+        self.buffer.write_timeout()?.remove_virtual_lines(self.id)
+    }
+}
+
+impl<B: DecorationBufferHandle> Drop for VirtualLines<B> {
+    fn drop(&mut self) {
+        if self.removed {
+            return;
+        }
+
+        debug!("Removing virtual lines ({:?})", self.id);
+
+        let id = self.id;
+        clear_on_drop(self.buffer.clone(), "virtual lines", move |buffer| {
+            _ = buffer
+                .remove_virtual_lines(id)
+                .log_err_msg("Failed to remove virtual lines");
+        });
+    }
+}
+
+/// Highlighted range or line, removed when dropped.
+#[derive(Debug)]
+pub struct Highlight<B: DecorationBufferHandle> {
+    id: B::DecorationId,
+    buffer: B,
+    removed: bool,
+}
+
+impl<B: DecorationBufferHandle> Highlight<B> {
+    /// See [`DecorationWriteBuffer::highlight_range`].
+    pub fn lock_range(buffer: &B, range: impl Into<PosRange>, group: &str) -> Result<Self> {
+        let id = buffer.write_timeout()?.highlight_range(range, group)?;
+
+        Ok(Self::from_id(buffer, id))
+    }
+
+    /// See [`DecorationWriteBuffer::highlight_line`].
+    pub fn lock_line(buffer: &B, row: usize, group: &str) -> Result<Self> {
+        let id = buffer.write_timeout()?.highlight_line(row, group)?;
+
+        Ok(Self::from_id(buffer, id))
+    }
+
+    fn from_id(buffer: &B, id: B::DecorationId) -> Self {
+        Self {
+            id,
+            buffer: buffer.clone(),
+            removed: false,
+        }
+    }
+
+    pub fn id(&self) -> B::DecorationId {
+        self.id
+    }
+
+    pub fn range(&self) -> Result<PosRange> {
+        self.buffer.read_timeout()?.highlighted_range(self.id)
+    }
+
+    /// Removes the highlight right away, unlike dropping.
+    pub fn remove(mut self) -> Result<()> {
+        self.removed = true;
+
+        self.buffer.write_timeout()?.remove_highlight(self.id)
+    }
+}
+
+impl<B: DecorationBufferHandle> Drop for Highlight<B> {
+    fn drop(&mut self) {
+        if self.removed {
+            return;
+        }
+
+        debug!("Removing highlight ({:?})", self.id);
+
+        let id = self.id;
+        clear_on_drop(self.buffer.clone(), "highlight", move |buffer| {
+            _ = buffer
+                .remove_highlight(id)
+                .log_err_msg("Failed to remove highlight");
+        });
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{Editor, test_utils::new_buffer_with_content};
+
+    use super::*;
+
+    pub fn test_virtual_lines<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: DecorationBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let lines = VirtualLines::lock_new(
+            &buffer,
+            1,
+            vec![
+                "- removed".into(),
+                VirtualLine::new().highlighted("+ added", "DiffAdd"),
+            ],
+            Placement::Above,
+        )
+        .expect("Failed to add virtual lines");
+
+        assert_eq!(lines.row().expect("Failed to get row"), 1);
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "Zeroth line\n")
+            .expect("Failed to set text");
+
+        assert_eq!(lines.row().expect("Failed to get row"), 2);
+
+        lines
+            .update(vec!["- removed again".into()])
+            .expect("Failed to update virtual lines");
+
+        assert_eq!(lines.row().expect("Failed to get row"), 2);
+
+        let id = lines.id();
+        lines.remove().expect("Failed to remove virtual lines");
+
+        assert!(buffer.read().virtual_lines_row(id).is_err());
+    }
+
+    pub fn test_virtual_lines_drop<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: DecorationBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let lines = VirtualLines::lock_new(&buffer, 0, vec!["Preview".into()], Placement::Below)
+            .expect("Failed to add virtual lines");
+        let id = lines.id();
+
+        drop(lines);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while buffer.read().virtual_lines_row(id).is_ok() {
+            assert!(Instant::now() < deadline, "Virtual lines weren't removed");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    pub fn test_virtual_text<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: DecorationBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line");
+
+        let id = buffer
+            .write()
+            .add_virtual_text(
+                &Position::new(0, 6),
+                VirtualLine::new().highlighted("virtual ", "Comment"),
+            )
+            .expect("Failed to add virtual text");
+
+        assert_eq!(
+            buffer
+                .read()
+                .virtual_text_position(id)
+                .expect("Failed to get position"),
+            Position::new(0, 6)
+        );
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "The ")
+            .expect("Failed to set text");
+
+        assert_eq!(
+            buffer
+                .read()
+                .virtual_text_position(id)
+                .expect("Failed to get position"),
+            Position::new(0, 10)
+        );
+        assert_eq!(
+            buffer.read().get_content().expect("Failed to get content"),
+            "The First line"
+        );
+
+        buffer
+            .write()
+            .remove_virtual_text(id)
+            .expect("Failed to remove virtual text");
+
+        assert!(buffer.read().virtual_text_position(id).is_err());
+    }
+
+    pub fn test_highlight_range<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: DecorationBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+
+        let highlight = Highlight::lock_range(
+            &buffer,
+            (Position::new(0, 6), Position::new(1, 6)),
+            "Visual",
+        )
+        .expect("Failed to highlight range");
+
+        assert_eq!(
+            highlight.range().expect("Failed to get range"),
+            PosRange::new(Position::new(0, 6), Position::new(1, 6))
+        );
+
+        buffer
+            .write()
+            .set_text((Position::new(1, 6), Position::new(1, 6)), "long ")
+            .expect("Failed to set text");
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "Zeroth line\n")
+            .expect("Failed to set text");
+
+        assert_eq!(
+            highlight.range().expect("Failed to get range"),
+            PosRange::new(Position::new(1, 6), Position::new(2, 6))
+        );
+
+        let line =
+            Highlight::lock_line(&buffer, 3, "DiffChange").expect("Failed to highlight line");
+
+        assert_eq!(
+            line.range().expect("Failed to get range"),
+            PosRange::new(Position::new(3, 0), Position::new(3, 10))
+        );
+
+        let (id, line_id) = (highlight.id(), line.id());
+        highlight.remove().expect("Failed to remove highlight");
+        drop(line);
+
+        assert!(buffer.read().highlighted_range(id).is_err());
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while buffer.read().highlighted_range(line_id).is_ok() {
+            assert!(Instant::now() < deadline, "Line highlight wasn't removed");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[macro_export]
+    macro_rules! eel_decoration_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::decoration::DecorationBufferHandle },
+                module_path: $crate::decoration::tests,
+                prefix: $prefix,
+                tests: [
+                    test_virtual_lines,
+                    test_virtual_lines_drop,
+                    test_virtual_text,
+                    test_highlight_range,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_decoration_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use tracing::{error, trace};
+
+use crate::Result;
+
+/// Function sent to the main thread.
+pub type Task = Box<dyn FnOnce() + Send>;
+
+/// How often [`MainThreadDispatcher::block_on`] runs the queue on the main thread.
+const BLOCK_ON_POLL: Duration = Duration::from_millis(1);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Dispatch function send error")]
+    FuncSend,
+
+    #[error("Result receive error: {0}")]
+    ResultRecv(#[from] mpsc::RecvError),
+
+    #[error("Dispatch timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchStats {
+    /// Functions sent to the main thread, calls inlined on the main thread are not counted.
+    pub sent: u64,
+    /// Calls made from the main thread, run inline.
+    pub inlined: u64,
+    /// Times the main thread woke up and ran the queued functions.
+    pub batches: u64,
+    /// Calls given up on by [`MainThreadDispatcher::dispatch_timeout`] and
+    /// [`MainThreadDispatcher::block_on`].
+    pub timed_out: u64,
+}
+
+/// Runs functions on an editor's main thread, for APIs which may only be called from there.
+pub trait MainThreadDispatcher: Send + Sync {
+    fn on_main_thread(&self) -> bool;
+
+    /// Runs `func` on the main thread and waits for the result.
+    ///
+    /// Calls made from the main thread, including nested ones from dispatched functions, run
+    /// inline. Dispatched functions must not wait for other threads dispatching, as those can't
+    /// run until the main thread is free.
+    ///
+    /// Gives up after the [operation timeout](crate::EditorConfig::op_timeout) if one is set.
+    fn dispatch<F, R>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static;
+
+    /// Schedules `func` on the main thread without waiting, the result is delivered through
+    /// the returned receiver.
+    ///
+    /// Unlike [`MainThreadDispatcher::dispatch`], calls from the main thread are not inlined.
+    fn dispatch_async<F, R>(&self, func: F) -> Result<mpsc::Receiver<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static;
+
+    /// Like [`MainThreadDispatcher::dispatch`], but fails if the result doesn't arrive in time.
+    /// The function still runs once the main thread gets to it.
+    fn dispatch_timeout<F, R>(&self, func: F, timeout: Duration) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static;
+
+    /// Runs `func` on a helper thread and waits for it. On the main thread the functions
+    /// dispatched meanwhile are run while waiting, so synchronous main thread callbacks can
+    /// call APIs which dispatch without deadlocking.
+    ///
+    /// Fails if `func` doesn't finish in time, it still runs to completion.
+    fn block_on<F, R>(&self, func: F, timeout: Duration) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static;
+
+    fn stats(&self) -> DispatchStats;
+}
+
+/// The backend specific part of a [`Dispatcher`], getting the main thread to run
+/// [`TaskQueue::run_pending`].
+pub trait DispatchTransport: Send + Sync + 'static {
+    fn on_main_thread(&self) -> bool;
+
+    /// Makes the main thread call [`TaskQueue::run_pending`] soon. Called once per batch, not
+    /// for every dispatched function.
+    fn wake(&self) -> Result<()>;
+
+    /// Called before running a dispatch inline on the main thread, to refuse contexts where
+    /// the main thread can't run it.
+    fn check_inline(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Functions waiting for the main thread.
+#[derive(Debug)]
+pub struct TaskQueue {
+    tx: mpsc::Sender<Task>,
+    rx: Mutex<mpsc::Receiver<Task>>,
+    /// Set while a wake is outstanding, further functions join its batch.
+    scheduled: AtomicBool,
+    batches: AtomicU64,
+}
+
+impl TaskQueue {
+    fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            tx,
+            rx: Mutex::new(rx),
+            scheduled: AtomicBool::new(false),
+            batches: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs all queued functions, to be called on the main thread by the transport.
+    pub fn run_pending(&self) {
+        // Cleared first, functions queued from now on need another wake
+        self.scheduled.store(false, Ordering::SeqCst);
+
+        let mut count = 0;
+
+        // Not holding the lock while running, so functions can queue more
+        while let Some(task) = self.next_task() {
+            task();
+            count += 1;
+        }
+
+        trace!(count, "Ran dispatched functions");
+
+        if count > 0 {
+            self.batches.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn next_task(&self) -> Option<Task> {
+        let rx = self.rx.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match rx.try_recv() {
+            Ok(task) => Some(task),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("Func channel disconnected");
+                None
+            }
+        }
+    }
+}
+
+/// [`MainThreadDispatcher`] on top of a [`DispatchTransport`].
+#[derive(Debug)]
+pub struct Dispatcher<T> {
+    transport: T,
+    queue: Arc<TaskQueue>,
+    sent: AtomicU64,
+    inlined: AtomicU64,
+    timed_out: AtomicU64,
+}
+
+impl<T: DispatchTransport> Dispatcher<T> {
+    /// `transport` gets the queue its main thread callback runs.
+    pub fn new(transport: impl FnOnce(Arc<TaskQueue>) -> Result<T>) -> Result<Self> {
+        let queue = Arc::new(TaskQueue::new());
+
+        Ok(Self {
+            transport: transport(queue.clone())?,
+            queue,
+            sent: AtomicU64::new(0),
+            inlined: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+        })
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    fn send_func<F, R>(&self, func: F) -> Result<mpsc::Receiver<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::sync_channel::<R>(1);
+
+        #[cfg(feature = "metrics")]
+        let sent = Instant::now();
+
+        let task: Task = Box::new(move || {
+            trace!("Calling dispatched function");
+
+            #[cfg(feature = "metrics")]
+            crate::metrics::Metrics::global()
+                .dispatch_latency
+                .observe(sent.elapsed());
+
+            if result_tx.send(func()).is_err() {
+                trace!("Dispatch result receiver dropped");
+            }
+        });
+
+        trace!("Sending function to dispatch");
+
+        self.queue.tx.send(task).map_err(|_| Error::FuncSend)?;
+        self.sent.fetch_add(1, Ordering::Relaxed);
+
+        if !self.queue.scheduled.swap(true, Ordering::SeqCst) {
+            trace!("Waking main thread");
+
+            if let Err(e) = self.transport.wake() {
+                self.queue.scheduled.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        }
+
+        Ok(result_rx)
+    }
+
+    fn inline<F, R>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        trace!("Dispatch called from main thread");
+
+        self.transport.check_inline()?;
+        self.inlined.fetch_add(1, Ordering::Relaxed);
+
+        Ok(func())
+    }
+}
+
+impl<T: DispatchTransport> MainThreadDispatcher for Dispatcher<T> {
+    fn on_main_thread(&self) -> bool {
+        self.transport.on_main_thread()
+    }
+
+    fn dispatch<F, R>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        if self.on_main_thread() {
+            return self.inline(func);
+        }
+
+        if let Some(timeout) = crate::EditorConfig::current().op_timeout {
+            return self.dispatch_timeout(func, timeout);
+        }
+
+        let result_rx = self.send_func(func)?;
+
+        trace!("Awaiting result");
+
+        Ok(result_rx.recv().map_err(Error::from)?)
+    }
+
+    fn dispatch_async<F, R>(&self, func: F) -> Result<mpsc::Receiver<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.send_func(func)
+    }
+
+    fn dispatch_timeout<F, R>(&self, func: F, timeout: Duration) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        if self.on_main_thread() {
+            return self.inline(func);
+        }
+
+        let result_rx = self.send_func(func)?;
+
+        match result_rx.recv_timeout(timeout) {
+            Ok(result) => Ok(result),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.timed_out.fetch_add(1, Ordering::Relaxed);
+                Err(Error::Timeout(timeout))?
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::ResultRecv(mpsc::RecvError))?,
+        }
+    }
+
+    fn block_on<F, R>(&self, func: F, timeout: Duration) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let on_main_thread = self.on_main_thread();
+        if on_main_thread {
+            self.transport.check_inline()?;
+        }
+
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        std::thread::spawn(move || _ = result_tx.send(func()));
+
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if on_main_thread {
+                self.queue.run_pending();
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                self.timed_out.fetch_add(1, Ordering::Relaxed);
+                Err(Error::Timeout(timeout))?;
+            }
+
+            let wait = if on_main_thread {
+                BLOCK_ON_POLL.min(deadline - now)
+            } else {
+                deadline - now
+            };
+
+            match result_rx.recv_timeout(wait) {
+                Ok(result) => return Ok(result),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    Err(Error::ResultRecv(mpsc::RecvError))?
+                }
+            }
+        }
+    }
+
+    fn stats(&self) -> DispatchStats {
+        DispatchStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            inlined: self.inlined.load(Ordering::Relaxed),
+            batches: self.queue.batches.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+
+    /// Dispatched calls run on the main thread, nested ones inline.
+    pub fn test_nested_dispatch<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        assert!(!dispatcher.on_main_thread());
+
+        let outer = dispatcher.clone();
+        let result = dispatcher
+            .dispatch(move || {
+                let inner = outer.clone();
+
+                outer.dispatch(move || {
+                    let tid = std::thread::current().id();
+
+                    let check = inner.clone();
+
+                    inner.dispatch(move || {
+                        (check.on_main_thread(), std::thread::current().id() == tid)
+                    })
+                })
+            })
+            .expect("Failed to dispatch")
+            .expect("Failed to dispatch nested")
+            .expect("Failed to dispatch nested");
+
+        assert_eq!(result, (true, true));
+    }
+
+    pub fn test_dispatch_stats<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        let before = dispatcher.stats();
+
+        let nested = dispatcher.clone();
+        dispatcher
+            .dispatch(move || nested.dispatch(|| ()))
+            .expect("Failed to dispatch")
+            .expect("Failed to dispatch nested");
+        dispatcher.dispatch(|| ()).expect("Failed to dispatch");
+
+        let stats = dispatcher.stats();
+
+        // Nested call ran inline
+        assert_eq!(stats.sent - before.sent, 2);
+        assert_eq!(stats.inlined - before.inlined, 1);
+        assert!(stats.batches > before.batches);
+    }
+
+    pub fn test_concurrent_dispatch<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        const THREADS: usize = 8;
+        const CALLS: usize = 100;
+
+        let before = dispatcher.stats();
+
+        let sums: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let dispatcher = dispatcher.clone();
+
+                    scope.spawn(move || {
+                        (0..CALLS)
+                            .map(|i| {
+                                let nested = dispatcher.clone();
+
+                                dispatcher
+                                    .dispatch(move || nested.dispatch(move || t * CALLS + i))
+                                    .expect("Failed to dispatch")
+                                    .expect("Failed to dispatch nested")
+                            })
+                            .sum()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Dispatch thread panicked"))
+                .collect()
+        });
+
+        for (t, sum) in sums.into_iter().enumerate() {
+            assert_eq!(sum, (0..CALLS).map(|i| t * CALLS + i).sum::<usize>());
+        }
+
+        let stats = dispatcher.stats();
+        assert_eq!(stats.sent - before.sent, (THREADS * CALLS) as u64);
+        assert!(stats.batches - before.batches <= stats.sent - before.sent);
+    }
+
+    pub fn test_dispatch_async<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        let result_rx = dispatcher
+            .dispatch_async(|| 42)
+            .expect("Failed to dispatch");
+        assert_eq!(result_rx.recv().expect("Failed to receive result"), 42);
+
+        // From the main thread the function runs only after the current one returns
+        let nested = dispatcher.clone();
+        let (ran_inline, result_rx) = dispatcher
+            .dispatch(move || {
+                let ran = Arc::new(AtomicBool::new(false));
+                let flag = ran.clone();
+
+                let result_rx = nested
+                    .dispatch_async(move || flag.store(true, Ordering::SeqCst))
+                    .expect("Failed to dispatch");
+
+                (ran.load(Ordering::SeqCst), result_rx)
+            })
+            .expect("Failed to dispatch");
+
+        assert!(!ran_inline);
+        result_rx
+            .recv_timeout(Duration::from_millis(500))
+            .expect("Async dispatch wasn't run");
+    }
+
+    pub fn test_dispatch_timeout<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        let before = dispatcher.stats();
+
+        assert_eq!(
+            dispatcher
+                .dispatch_timeout(|| 42, Duration::from_secs(1))
+                .expect("Failed to dispatch"),
+            42
+        );
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let result = dispatcher.dispatch_timeout(
+            move || {
+                std::thread::sleep(Duration::from_millis(100));
+                let _ = done_tx.send(());
+            },
+            Duration::from_millis(10),
+        );
+
+        assert!(matches!(
+            result,
+            Err(crate::Error::Dispatch(Error::Timeout(_)))
+        ));
+        assert_eq!(dispatcher.stats().timed_out - before.timed_out, 1);
+
+        // Still ran
+        done_rx
+            .recv_timeout(Duration::from_secs(1))
+            .expect("Timed out function wasn't run");
+    }
+
+    pub fn test_block_on<D: MainThreadDispatcher + 'static>(dispatcher: Arc<D>) {
+        assert_eq!(
+            dispatcher
+                .block_on(|| 42, Duration::from_secs(1))
+                .expect("Failed to block on"),
+            42
+        );
+
+        // Waiting on the main thread for a thread which dispatches
+        let outer = dispatcher.clone();
+        let result = dispatcher
+            .dispatch(move || {
+                let inner = outer.clone();
+
+                outer.block_on(
+                    move || {
+                        let first = inner.dispatch(|| 1).expect("Failed to dispatch");
+                        let second = inner.dispatch(|| 2).expect("Failed to dispatch");
+
+                        first + second
+                    },
+                    Duration::from_secs(5),
+                )
+            })
+            .expect("Failed to dispatch");
+
+        assert_eq!(result.expect("Failed to block on"), 3);
+
+        let result = dispatcher.block_on(
+            || std::thread::sleep(Duration::from_millis(100)),
+            Duration::from_millis(10),
+        );
+
+        assert!(matches!(
+            result,
+            Err(crate::Error::Dispatch(Error::Timeout(_)))
+        ));
+    }
+}
@@ -1,4 +1,126 @@
-use crate::{Result, buffer::BufferHandle};
+use std::{
+    cell::Cell,
+    path::Path,
+    sync::{
+        Mutex, PoisonError, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{OptionValue, Result, buffer::BufferHandle};
+
+/// Features supported by an [`Editor`] backend, for generic code that wants to check them at
+/// runtime instead of through trait bounds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub marks: bool,
+    pub cursor: bool,
+    pub regions: bool,
+    pub decorations: bool,
+    pub undo: bool,
+    pub windows: bool,
+    /// [`Editor::execute_normal`] runs native normal mode commands.
+    pub normal_mode: bool,
+    /// [`Editor::get_option`] and [`Editor::set_option`] take vim option names, e.g. `tabstop`.
+    pub vim_options: bool,
+}
+
+/// Limits of how long editor operations may take, `None` waits forever.
+///
+/// Dispatching to the main thread fails after `op_timeout` with
+/// [`crate::dispatch::Error::Timeout`], though the operation still runs to completion, and
+/// [`BufferHandle::read_timeout`] and [`BufferHandle::write_timeout`] fail after
+/// `lock_timeout` with [`crate::buffer::Error::LockTimeout`]. The library's own helpers take
+/// buffer locks through the timed variants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    pub op_timeout: Option<Duration>,
+    pub lock_timeout: Option<Duration>,
+}
+
+/// Overrides [`EditorConfig::op_timeout`] in [`EditorConfig::with_env`].
+pub const OP_TIMEOUT_ENV: &str = "EEL_OP_TIMEOUT_MS";
+/// Overrides [`EditorConfig::lock_timeout`] in [`EditorConfig::with_env`].
+pub const LOCK_TIMEOUT_ENV: &str = "EEL_LOCK_TIMEOUT_MS";
+
+static GLOBAL_CONFIG: RwLock<EditorConfig> = RwLock::new(EditorConfig {
+    op_timeout: None,
+    lock_timeout: None,
+});
+
+thread_local! {
+    static SCOPED_CONFIG: Cell<Option<EditorConfig>> = const { Cell::new(None) };
+}
+
+impl EditorConfig {
+    pub fn global() -> Self {
+        *GLOBAL_CONFIG.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn set_global(config: Self) {
+        *GLOBAL_CONFIG
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = config;
+    }
+
+    /// Overrides the timeouts set in the [`OP_TIMEOUT_ENV`] and [`LOCK_TIMEOUT_ENV`] environment
+    /// variables, in milliseconds, `0` meaning none.
+    pub fn with_env(self) -> Result<Self> {
+        self.with_vars(|name| std::env::var(name).ok())
+    }
+
+    fn with_vars(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let timeouts = [
+            (OP_TIMEOUT_ENV, &mut self.op_timeout),
+            (LOCK_TIMEOUT_ENV, &mut self.lock_timeout),
+        ];
+
+        for (name, timeout) in timeouts {
+            let Some(value) = var(name) else {
+                continue;
+            };
+
+            let millis: u64 = value.trim().parse().map_err(|e| {
+                crate::buffer::Error::Custom(format!("Invalid {name} {value:?}: {e}").into())
+            })?;
+
+            *timeout = (millis > 0).then(|| Duration::from_millis(millis));
+        }
+
+        Ok(self)
+    }
+
+    /// Config of the current thread, the global one unless overridden by [`EditorConfig::scope`].
+    pub fn current() -> Self {
+        SCOPED_CONFIG.get().unwrap_or_else(Self::global)
+    }
+
+    /// Runs `f` with this config on the current thread, operations it moves to other threads
+    /// use the global one.
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<EditorConfig>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED_CONFIG.set(self.0);
+            }
+        }
+
+        let _restore = Restore(SCOPED_CONFIG.replace(Some(self)));
+
+        f()
+    }
+}
+
+/// Runs `f` with both timeouts set to `timeout`, see [`EditorConfig::scope`].
+pub fn with_timeout<R>(timeout: Duration, f: impl FnOnce() -> R) -> R {
+    EditorConfig {
+        op_timeout: Some(timeout),
+        lock_timeout: Some(timeout),
+    }
+    .scope(f)
+}
 
 pub trait Editor: Sized + Sync + Send + 'static {
     type BufferHandle: BufferHandle;
@@ -9,4 +131,391 @@ pub trait Editor: Sized + Sync + Send + 'static {
         &self,
         buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
     ) -> Result<()>;
+
+    /// All buffers currently loaded in the editor.
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>>;
+
+    fn buffer_name(&self, _buffer: &Self::BufferHandle) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>>;
+
+    /// Backends are expected to normalize paths, so relative ones resolve against the
+    /// editor's working directory.
+    fn buffer_by_path(&self, path: &Path) -> Result<Option<Self::BufferHandle>> {
+        self.buffer_by_name(&path.to_string_lossy())
+    }
+
+    /// Saves all loaded buffers along with their cursor, named marks and named regions
+    /// (depending on enabled features).
+    #[cfg(feature = "session")]
+    fn save_session(&self, path: &Path) -> Result<()>
+    where
+        Self::BufferHandle: crate::session::SessionBufferHandle,
+    {
+        crate::session::capture(self)?.save(path)
+    }
+
+    #[cfg(feature = "session")]
+    fn restore_session(&self, path: &Path) -> Result<Vec<Self::BufferHandle>>
+    where
+        Self::BufferHandle: crate::session::SessionBufferHandle,
+    {
+        crate::session::restore(self, &crate::session::Session::load(path)?)
+    }
+
+    /// Runs `f` on all buffers using at most `concurrency` threads, results are in
+    /// [`Editor::buffers`] order.
+    ///
+    /// Blocks until all buffers are processed, so on backends dispatching to a main thread it
+    /// must not be called from that thread.
+    fn for_each_buffer<T, F>(&self, concurrency: usize, f: F) -> Result<Vec<Result<T>>>
+    where
+        T: Send,
+        F: Fn(Self::BufferHandle) -> Result<T> + Sync,
+    {
+        let buffers = self.buffers()?;
+        let results: Vec<Mutex<Option<Result<T>>>> =
+            buffers.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, buffers.len().max(1)) {
+                scope.spawn(|| {
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+
+                        let Some(buffer) = buffers.get(i) else {
+                            break;
+                        };
+
+                        let result = f(buffer.clone());
+                        *results[i].lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+                    }
+                });
+            }
+        });
+
+        Ok(results
+            .into_iter()
+            .map(|r| {
+                r.into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .expect("All buffers were processed")
+            })
+            .collect())
+    }
+
+    /// Applies edits to several buffers as one transaction: all buffers are locked (in
+    /// [`Editor::buffers`] order), all edits validated, and the applied ones rolled back if
+    /// any edit fails.
+    fn apply_workspace_edit(
+        &self,
+        edits: Vec<(Self::BufferHandle, Vec<crate::workspace::TextEdit>)>,
+    ) -> Result<()> {
+        crate::workspace::apply(edits)
+    }
+
+    /// Global option, `None` if the backend doesn't support options.
+    fn get_option(&self, _name: &str) -> Result<Option<OptionValue>> {
+        Ok(None)
+    }
+
+    fn set_option(&self, _name: &str, _value: OptionValue) -> Result<()> {
+        Err(crate::Error::Unsupported("Options"))
+    }
+
+    /// Runs `callback` every time the user was idle for `delay`, until it returns `false`.
+    ///
+    /// Backends may end the idle period early when the editor reports idleness itself, see
+    /// [`crate::debounce::IdleTimer`] for one built on activity events.
+    fn on_idle(
+        &self,
+        _delay: Duration,
+        _callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        Err(crate::Error::Unsupported("Idle callbacks"))
+    }
+
+    /// Supervisor of the plugin's background jobs, [`crate::tasks::Tasks::global`] unless the
+    /// backend keeps its own.
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        crate::tasks::Tasks::global()
+    }
+
+    /// Cursor of the current window.
+    #[cfg(feature = "cursor")]
+    fn cursor_position(&self) -> Result<crate::Position>
+    where
+        Self::BufferHandle: crate::cursor::CursorBufferHandle,
+    {
+        use crate::cursor::CursorReadBuffer;
+
+        self.current_buffer()?.read_timeout()?.get_cursor()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn set_cursor_position(&self, position: &crate::Position) -> Result<()>
+    where
+        Self::BufferHandle: crate::cursor::CursorBufferHandle,
+    {
+        use crate::cursor::CursorWriteBuffer;
+
+        self.current_buffer()?.write_timeout()?.set_cursor(position)
+    }
+
+    /// Moves to the previous entry of the jump list, which may be in another buffer.
+    ///
+    /// Returns `false` if there was nothing to jump to.
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Asks the user a question, blocking until it's answered or dismissed.
+    ///
+    /// On backends dispatching to a main thread it must not be called from that thread.
+    #[cfg(feature = "ui")]
+    fn prompt(&self, _spec: &crate::ui::PromptSpec) -> Result<crate::ui::UserResponse> {
+        Err(crate::Error::Unsupported("Prompts"))
+    }
+
+    /// Runs normal mode commands in the current window, without user mappings like `:normal!`.
+    ///
+    /// Only backends reporting [`Capabilities::normal_mode`] support it, generic code should
+    /// fall back to the core implementations on [`crate::Error::Unsupported`], e.g.
+    /// [`crate::textobject::word_at`].
+    fn execute_normal(&self, _keys: &str) -> Result<()> {
+        Err(crate::Error::Unsupported("Normal mode commands"))
+    }
+
+    /// Filetype of a file named `path` containing `content` (either may be unknown), `None` if
+    /// it's not recognized, e.g. for scratch buffers.
+    ///
+    /// Backends without their own detection use [`crate::filetype::detect`].
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        Ok(crate::filetype::detect(path, content))
+    }
+
+    /// Sends `value` to the subscribers of `topic` it deserializes for, returns how many
+    /// received it. See [`crate::bus`].
+    #[cfg(feature = "bus")]
+    fn publish<T: serde::Serialize>(&self, topic: &str, value: &T) -> Result<usize> {
+        crate::bus::Bus::global().publish(topic, value)
+    }
+
+    /// Values of type `T` published to `topic` from now on, until the receiver is dropped.
+    #[cfg(feature = "bus")]
+    fn subscribe<T: serde::de::DeserializeOwned + Send + 'static>(
+        &self,
+        topic: &str,
+    ) -> Result<std::sync::mpsc::Receiver<T>> {
+        Ok(crate::bus::Bus::global().subscribe(topic))
+    }
+
+    /// Backends not overriding this report no optional features.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        buffer::{BufferHandle, ReadBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_editor_for_each_buffer(editor: impl Editor) {
+        let created = ["a", "b\nb", "c\nc\nc", "fail"]
+            .map(|content| new_buffer_with_content(&editor, content));
+
+        let buffers = editor.buffers().expect("Failed to list buffers");
+
+        let results = editor
+            .for_each_buffer(2, |buffer| {
+                let content = buffer.read().get_content()?;
+
+                if content == "fail" {
+                    Err(crate::buffer::Error::Custom("Scan failed".into()))?;
+                }
+
+                buffer.read().line_count()
+            })
+            .expect("Failed to scan buffers");
+
+        assert_eq!(results.len(), buffers.len());
+
+        for (i, expected) in [Some(1), Some(2), Some(3), None].into_iter().enumerate() {
+            let index = buffers
+                .iter()
+                .position(|b| *b == created[i])
+                .expect("Created buffer not listed");
+
+            assert_eq!(results[index].as_ref().ok().copied(), expected);
+        }
+    }
+
+    pub fn test_editor_options(editor: impl Editor) {
+        // Option names are the backend's own
+        if !editor.capabilities().vim_options {
+            return;
+        }
+
+        editor
+            .set_option("tabstop", OptionValue::Int(3))
+            .expect("Failed to set option");
+        editor
+            .set_option("wrapscan", false.into())
+            .expect("Failed to set option");
+
+        assert_eq!(
+            editor.get_option("tabstop").expect("Failed to get option"),
+            Some(OptionValue::Int(3))
+        );
+        assert_eq!(
+            editor.get_option("wrapscan").expect("Failed to get option"),
+            Some(OptionValue::Bool(false))
+        );
+
+        editor
+            .set_option("tabstop", 8.into())
+            .expect("Failed to set option");
+        editor
+            .set_option("wrapscan", true.into())
+            .expect("Failed to set option");
+    }
+
+    pub fn test_editor_config(editor: impl Editor) {
+        let limited = EditorConfig {
+            op_timeout: Some(Duration::from_secs(5)),
+            lock_timeout: None,
+        };
+
+        let global = EditorConfig::current();
+        limited.scope(|| {
+            assert_eq!(EditorConfig::current(), limited);
+
+            with_timeout(Duration::from_millis(20), || {
+                assert_eq!(
+                    EditorConfig::current().lock_timeout,
+                    Some(Duration::from_millis(20))
+                );
+            });
+
+            assert_eq!(EditorConfig::current(), limited);
+        });
+        assert_eq!(EditorConfig::current(), global);
+
+        let vars = |op: &'static str, lock: Option<&'static str>| {
+            move |name: &str| match name {
+                OP_TIMEOUT_ENV => Some(op.to_string()),
+                LOCK_TIMEOUT_ENV => lock.map(str::to_string),
+                _ => None,
+            }
+        };
+
+        // Unset variables keep the previous layer's values
+        let layered = limited
+            .with_vars(vars("250", None))
+            .expect("Failed to read variables");
+        assert_eq!(layered.op_timeout, Some(Duration::from_millis(250)));
+        assert_eq!(layered.lock_timeout, None);
+
+        let layered = layered
+            .with_vars(vars("0", Some(" 40 ")))
+            .expect("Failed to read variables");
+        assert_eq!(layered.op_timeout, None);
+        assert_eq!(layered.lock_timeout, Some(Duration::from_millis(40)));
+
+        assert!(limited.with_vars(vars("soon", None)).is_err());
+
+        let buffer = new_buffer_with_content(&editor, "locked");
+
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let holder = buffer.clone();
+        let thread = std::thread::spawn(move || {
+            let _lock = holder.write();
+            locked_tx.send(()).expect("Failed to send");
+            _ = release_rx.recv();
+        });
+        locked_rx.recv().expect("Lock wasn't taken");
+
+        with_timeout(Duration::from_millis(20), || {
+            let error = buffer
+                .read_timeout()
+                .err()
+                .expect("Read lock didn't time out");
+            assert!(matches!(
+                error,
+                crate::Error::Buffer(crate::buffer::Error::LockTimeout(_))
+            ));
+            assert!(buffer.write_timeout().is_err());
+        });
+
+        drop(release_tx);
+        thread.join().expect("Lock thread panicked");
+
+        with_timeout(Duration::from_millis(20), || {
+            let content = buffer
+                .read_timeout()
+                .expect("Failed to lock")
+                .get_content()
+                .expect("Failed to get content");
+            assert_eq!(content, "locked");
+        });
+    }
+
+    pub fn test_editor_detect_filetype(editor: impl Editor) {
+        let detect = |path, content| {
+            editor
+                .detect_filetype(path, content)
+                .expect("Failed to detect filetype")
+        };
+
+        assert_eq!(detect(Some("src/main.rs"), None).as_deref(), Some("rust"));
+        assert_eq!(detect(Some("/tmp/Makefile"), None).as_deref(), Some("make"));
+        assert_eq!(
+            detect(Some("config.yml"), Some("key: value")).as_deref(),
+            Some("yaml")
+        );
+        assert_eq!(
+            detect(None, Some("#!/usr/bin/env python3\nprint('eel')")).as_deref(),
+            Some("python")
+        );
+        assert_eq!(detect(Some("notes.unknownext"), Some("plain words")), None);
+        assert_eq!(detect(None, None), None);
+    }
+
+    #[macro_export]
+    macro_rules! eel_editor_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::editor::tests,
+                prefix: $prefix,
+                tests: [
+                    test_editor_for_each_buffer,
+                    test_editor_options,
+                    test_editor_config,
+                    test_editor_detect_filetype,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_editor_tests!($test_tag, $editor_factory, "");
+        };
+    }
 }
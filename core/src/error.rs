@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+pub use eel_macros::EelPlatformError;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Buffer error: {0}")]
@@ -7,6 +9,41 @@ pub enum Error {
 
     #[error("Platform error: {0}")]
     Platform(Arc<dyn PlatformError>),
+
+    #[error("Dispatch error: {0}")]
+    Dispatch(#[from] crate::dispatch::Error),
+
+    #[error("Command error: {0}")]
+    Command(#[from] crate::commands::Error),
+
+    #[error("Task error: {0}")]
+    Task(#[from] crate::tasks::Error),
+
+    #[error("{0} not supported")]
+    Unsupported(&'static str),
+
+    #[error("{0}")]
+    Diagnostic(#[from] Box<crate::buffer::Diagnostic>),
+
+    #[cfg(feature = "suggestion")]
+    #[error("Suggestion error: {0}")]
+    Suggestion(#[from] crate::suggestion::Error),
+
+    #[cfg(feature = "session")]
+    #[error("Session error: {0}")]
+    Session(#[from] crate::session::Error),
+
+    #[cfg(feature = "metrics")]
+    #[error("Metrics error: {0}")]
+    Metrics(#[from] crate::metrics::Error),
+
+    #[cfg(feature = "ops_recorder")]
+    #[error("Recording error: {0}")]
+    Recording(#[from] crate::ops_recorder::Error),
+
+    #[cfg(feature = "bus")]
+    #[error("Bus error: {0}")]
+    Bus(#[from] crate::bus::Error),
 }
 
 pub type Result<R> = std::result::Result<R, Error>;
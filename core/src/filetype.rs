@@ -0,0 +1,133 @@
+//! Filetype detection for backends without their own, see [`crate::Editor::detect_filetype`].
+//!
+//! Names follow nvim's filetypes, so plugins get the same ones whatever the backend.
+
+use std::path::Path;
+
+const FILENAMES: &[(&str, &str)] = &[
+    ("Makefile", "make"),
+    ("makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Cargo.lock", "toml"),
+    (".bashrc", "bash"),
+    (".zshrc", "zsh"),
+    (".gitignore", "gitignore"),
+    (".gitconfig", "gitconfig"),
+];
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("py", "python"),
+    ("pyi", "python"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "javascriptreact"),
+    ("ts", "typescript"),
+    ("tsx", "typescriptreact"),
+    ("lua", "lua"),
+    ("vim", "vim"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("hh", "cpp"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("scala", "scala"),
+    ("cs", "cs"),
+    ("swift", "swift"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("pl", "perl"),
+    ("hs", "haskell"),
+    ("ml", "ocaml"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("zig", "zig"),
+    ("nix", "nix"),
+    ("dart", "dart"),
+    ("sh", "sh"),
+    ("bash", "bash"),
+    ("zsh", "zsh"),
+    ("fish", "fish"),
+    ("md", "markdown"),
+    ("markdown", "markdown"),
+    ("rst", "rst"),
+    ("tex", "tex"),
+    ("json", "json"),
+    ("jsonc", "jsonc"),
+    ("toml", "toml"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("xml", "xml"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("sql", "sql"),
+    ("diff", "diff"),
+    ("patch", "diff"),
+    ("txt", "text"),
+];
+
+/// Interpreters of `#!` lines.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("bash", "bash"),
+    ("sh", "sh"),
+    ("zsh", "zsh"),
+    ("fish", "fish"),
+    ("node", "javascript"),
+    ("lua", "lua"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+];
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, filetype)| *filetype)
+}
+
+fn from_path(path: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+
+    let name = path.file_name()?.to_str()?;
+    if let Some(filetype) = lookup(FILENAMES, name) {
+        return Some(filetype);
+    }
+
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    lookup(EXTENSIONS, &extension)
+}
+
+/// From the `#!` line, e.g. `#!/usr/bin/env python3`.
+fn from_content(content: &str) -> Option<&'static str> {
+    let shebang = content.lines().next()?.strip_prefix("#!")?;
+
+    let mut words = shebang.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    // Versioned interpreters, like python3 or lua5.1
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    lookup(INTERPRETERS, program)
+}
+
+/// The path decides if it's known, the content is only looked at otherwise.
+pub fn detect(path: Option<&str>, content: Option<&str>) -> Option<String> {
+    path.and_then(from_path)
+        .or_else(|| content.and_then(from_content))
+        .map(str::to_string)
+}
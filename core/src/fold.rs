@@ -0,0 +1,321 @@
+use crate::{
+    Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+/// Rows are inclusive on both ends in all fold methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start_row: usize,
+    pub end_row: usize,
+    /// Hidden, by itself or a closed fold containing it.
+    pub closed: bool,
+}
+
+impl Fold {
+    pub fn new(start_row: usize, end_row: usize, closed: bool) -> Self {
+        Self {
+            start_row,
+            end_row,
+            closed,
+        }
+    }
+}
+
+pub trait FoldReadBuffer: ReadBuffer {
+    fn is_folded(&self, row: usize) -> Result<bool>;
+
+    /// Folds overlapping the rows, by start row, outer folds before the ones nested in them.
+    ///
+    /// By default only the closed folds are found, from the runs of folded rows, so adjacent
+    /// closed folds are reported as one and open or nested ones are missing.
+    fn folds_in(&self, start_row: usize, end_row: usize) -> Result<Vec<Fold>> {
+        let line_count = self.line_count()?;
+        let mut folds: Vec<Fold> = Vec::new();
+
+        for row in start_row..=end_row.min(line_count.saturating_sub(1)) {
+            if !self.is_folded(row)? {
+                continue;
+            }
+
+            match folds.last_mut() {
+                Some(fold) if fold.end_row + 1 == row => fold.end_row = row,
+                _ => folds.push(Fold::new(row, row, true)),
+            }
+        }
+
+        // Closed folds overlapping the rows may reach past them
+        if let Some(first) = folds.first_mut().filter(|fold| fold.start_row == start_row) {
+            while first.start_row > 0 && self.is_folded(first.start_row - 1)? {
+                first.start_row -= 1;
+            }
+        }
+        if let Some(last) = folds.last_mut().filter(|fold| fold.end_row == end_row) {
+            while last.end_row + 1 < line_count && self.is_folded(last.end_row + 1)? {
+                last.end_row += 1;
+            }
+        }
+
+        Ok(folds)
+    }
+}
+
+pub trait FoldWriteBuffer: FoldReadBuffer + WriteBuffer {
+    /// Creates a closed fold, replacing any folds overlapping the rows.
+    ///
+    /// Backends with window-local folds fail if no window shows the buffer.
+    fn fold(&mut self, start_row: usize, end_row: usize) -> Result<()>;
+
+    /// Removes all folds overlapping the rows.
+    fn unfold(&mut self, start_row: usize, end_row: usize) -> Result<()>;
+
+    /// Opens the closed fold hiding `row`, or closes the innermost fold containing it. Returns
+    /// whether the row is folded afterwards, `None` if no fold contains it.
+    ///
+    /// Backends that can't open a fold without removing it don't toggle by default.
+    fn toggle_fold(&mut self, row: usize) -> Result<Option<bool>> {
+        let _ = row;
+        Ok(None)
+    }
+}
+
+pub trait FoldBufferHandle:
+    BufferHandle<ReadBuffer = Self::FReadBuffer, WriteBuffer = Self::FWriteBuffer>
+{
+    type FReadBuffer: FoldReadBuffer;
+    type FWriteBuffer: FoldWriteBuffer;
+}
+
+impl<B> FoldBufferHandle for B
+where
+    B: BufferHandle,
+    B::ReadBuffer: FoldReadBuffer,
+    B::WriteBuffer: FoldWriteBuffer,
+{
+    type FReadBuffer = B::ReadBuffer;
+    type FWriteBuffer = B::WriteBuffer;
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use crate::{Editor, test_utils::new_buffer_with_content};
+
+    use super::*;
+
+    fn new_current_buffer<E: Editor>(editor: &E) -> E::BufferHandle {
+        let buffer = new_buffer_with_content(
+            editor,
+            r#"First line
+Second line
+Third line
+Fourth line"#,
+        );
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        buffer
+    }
+
+    pub fn test_fold<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: FoldBufferHandle,
+    {
+        let buffer = new_current_buffer(&editor);
+
+        buffer.write().fold(1, 2).expect("Failed to fold");
+
+        let folded = |row| buffer.read().is_folded(row).expect("Failed to check fold");
+
+        assert!(!folded(0));
+        assert!(folded(1));
+        assert!(folded(2));
+        assert!(!folded(3));
+
+        assert_eq!(
+            buffer.read().folds_in(0, 3).expect("Failed to list folds"),
+            [Fold::new(1, 2, true)]
+        );
+        assert_eq!(
+            buffer.read().folds_in(2, 2).expect("Failed to list folds"),
+            [Fold::new(1, 2, true)]
+        );
+        assert!(
+            buffer
+                .read()
+                .folds_in(3, 3)
+                .expect("Failed to list folds")
+                .is_empty()
+        );
+
+        buffer.write().unfold(2, 3).expect("Failed to unfold");
+
+        assert!(!folded(1));
+        assert!(!folded(2));
+        assert!(
+            buffer
+                .read()
+                .folds_in(0, 3)
+                .expect("Failed to list folds")
+                .is_empty()
+        );
+    }
+
+    pub fn test_fold_toggle<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: FoldBufferHandle,
+    {
+        let buffer = new_current_buffer(&editor);
+
+        buffer.write().fold(0, 1).expect("Failed to fold");
+        buffer.write().fold(2, 3).expect("Failed to fold");
+
+        let toggle = |row| buffer.write().toggle_fold(row).expect("Failed to toggle");
+        let folds = || buffer.read().folds_in(0, 3).expect("Failed to list folds");
+
+        assert_eq!(toggle(1), Some(false));
+        assert_eq!(folds(), [Fold::new(0, 1, false), Fold::new(2, 3, true)]);
+        assert!(!buffer.read().is_folded(0).expect("Failed to check fold"));
+
+        assert_eq!(toggle(0), Some(true));
+        assert_eq!(toggle(3), Some(false));
+        assert_eq!(folds(), [Fold::new(0, 1, true), Fold::new(2, 3, false)]);
+
+        buffer.write().unfold(0, 3).expect("Failed to unfold");
+        assert_eq!(toggle(2), None);
+    }
+
+    pub fn test_fold_list_keeps_state<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: FoldBufferHandle,
+    {
+        let buffer = new_current_buffer(&editor);
+
+        buffer.write().fold(0, 1).expect("Failed to fold");
+        buffer.write().fold(2, 3).expect("Failed to fold");
+        buffer.write().toggle_fold(3).expect("Failed to toggle");
+
+        let folded = |row| buffer.read().is_folded(row).expect("Failed to check fold");
+
+        // Listing folds in some rows leaves the others alone
+        let folds = buffer.read().folds_in(2, 3).expect("Failed to list folds");
+        assert!(folds.iter().all(|fold| fold.start_row >= 2));
+
+        assert!(folded(0));
+        assert!(folded(1));
+        assert_eq!(folded(2), folds.iter().any(|fold| fold.closed));
+
+        assert_eq!(
+            buffer.read().folds_in(0, 1).expect("Failed to list folds"),
+            [Fold::new(0, 1, true)]
+        );
+    }
+
+    #[cfg(feature = "region")]
+    pub fn test_fold_region<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+        <E::BufferHandle as BufferHandle>::ReadBuffer: FoldReadBuffer,
+        <E::BufferHandle as BufferHandle>::WriteBuffer: FoldWriteBuffer,
+    {
+        use crate::{Position, region::BufferRegion};
+
+        let buffer = new_current_buffer(&editor);
+
+        let region = BufferRegion::lock_new(&buffer, (Position::new(1, 0), Position::new(2, 5)))
+            .expect("Failed to create region");
+
+        let folded = |row| buffer.read().is_folded(row).expect("Failed to check fold");
+
+        region.fold().expect("Failed to fold region");
+
+        assert!(region.is_folded().expect("Failed to check fold"));
+        assert!(folded(1));
+        assert!(folded(2));
+        assert!(!folded(3));
+
+        region.unfold().expect("Failed to unfold region");
+        region
+            .write()
+            .append(" line\nGenerated")
+            .expect("Failed to append");
+
+        assert!(!region.is_folded().expect("Failed to check fold"));
+
+        // Folding again follows the region
+        region.fold().expect("Failed to fold region");
+
+        assert!(folded(3));
+        assert!(!folded(4));
+
+        // Rows of folds are relative to the region, clamped to it
+        buffer.write().fold(0, 4).expect("Failed to fold");
+        assert_eq!(
+            region.read().folds_in(0, 2).expect("Failed to list folds"),
+            [Fold::new(0, 2, true)]
+        );
+
+        assert_eq!(
+            region.write().toggle_fold(1).expect("Failed to toggle"),
+            Some(false)
+        );
+        assert!(!folded(0));
+
+        buffer.write().unfold(0, 4).expect("Failed to unfold");
+
+        assert!((0..5).all(|row| !folded(row)));
+    }
+
+    #[macro_export]
+    macro_rules! eel_fold_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    <E::BufferHandle as $crate::buffer::BufferHandle>::ReadBuffer: $crate::fold::FoldReadBuffer,
+                    <E::BufferHandle as $crate::buffer::BufferHandle>::WriteBuffer: $crate::fold::FoldWriteBuffer,
+                },
+                module_path: $crate::fold::tests,
+                prefix: $prefix,
+                tests: [test_fold, test_fold_toggle, test_fold_list_keeps_state],
+            );
+
+            $crate::eel_fold_region_tests!($test_tag, $editor_factory, $prefix);
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_fold_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(feature = "region")]
+    macro_rules! eel_fold_region_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    E::BufferHandle: $crate::mark::MarkBufferHandle,
+                    <E::BufferHandle as $crate::buffer::BufferHandle>::ReadBuffer: $crate::fold::FoldReadBuffer,
+                    <E::BufferHandle as $crate::buffer::BufferHandle>::WriteBuffer: $crate::fold::FoldWriteBuffer,
+                },
+                module_path: $crate::fold::tests,
+                prefix: $prefix,
+                tests: [test_fold_region],
+            );
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "region"))]
+    macro_rules! eel_fold_region_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {};
+    }
+}
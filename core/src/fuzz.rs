@@ -0,0 +1,398 @@
+use std::{
+    ops::DerefMut,
+    sync::atomic::{AtomicI64, AtomicUsize, Ordering},
+};
+
+use crate::{
+    Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub seed: u64,
+    pub threads: usize,
+    pub ops_per_thread: usize,
+    pub max_text_len: usize,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x5eed,
+            threads: 4,
+            ops_per_thread: 50,
+            max_text_len: 8,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FuzzStats {
+    pub set_text: usize,
+    pub mark_moves: usize,
+    pub region_reads: usize,
+}
+
+/// Small xorshift generator, we only need reproducible sequences, not quality randomness.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    pub fn below(&mut self, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+
+        (self.next_u64() % limit as u64) as usize
+    }
+
+    pub fn text(&mut self, max_len: usize) -> String {
+        const ALPHABET: &[u8] = b"abcxyz \n";
+
+        let len = self.below(max_len + 1);
+        (0..len)
+            .map(|_| ALPHABET[self.below(ALPHABET.len())] as char)
+            .collect()
+    }
+
+    pub fn position(&mut self, buffer: &impl ReadBuffer) -> Result<Position> {
+        let row = self.below(buffer.line_count()?);
+        let col = self.below(buffer.max_row_pos(row)?.col + 1);
+
+        Ok(Position::new(row, col))
+    }
+
+    pub fn range(&mut self, buffer: &impl ReadBuffer) -> Result<(Position, Position)> {
+        let a = self.position(buffer)?;
+        let b = self.position(buffer)?;
+
+        Ok(if a <= b { (a, b) } else { (b, a) })
+    }
+}
+
+/// Byte length of the text between `start` and `end`, counting line breaks as one byte.
+fn text_len(buffer: &impl ReadBuffer, start: &Position, end: &Position) -> Result<usize> {
+    if start.row == end.row {
+        return Ok(end.col - start.col);
+    }
+
+    let middle: usize = buffer
+        .get_lines((start.row + 1)..end.row)?
+        .map(|l| l.len() + 1)
+        .sum();
+
+    Ok(buffer.max_row_pos(start.row)?.col - start.col + 1 + middle + end.col)
+}
+
+fn content_len(buffer: &impl ReadBuffer) -> Result<usize> {
+    text_len(buffer, &Position::origin(), &buffer.max_pos()?)
+}
+
+fn random_set_text<W: WriteBuffer>(
+    rng: &mut Rng,
+    buffer: &mut W,
+    config: &FuzzConfig,
+) -> Result<i64> {
+    let (start, end) = rng.range(buffer)?;
+    let text = rng.text(config.max_text_len);
+
+    let removed = text_len(buffer, &start, &end)?;
+    let before = content_len(buffer)?;
+
+    buffer.set_text((&start, &end), &text)?;
+
+    let delta = text.len() as i64 - removed as i64;
+
+    assert_eq!(
+        content_len(buffer)? as i64,
+        before as i64 + delta,
+        "Content length mismatch after set_text({start:?}, {end:?}, {text:?})"
+    );
+
+    Ok(delta)
+}
+
+fn run_threads<B, F>(buffer: &B, config: &FuzzConfig, op: F) -> FuzzStats
+where
+    B: BufferHandle,
+    F: Fn(&mut Rng, &B, &FuzzCounters) -> Result<()> + Sync,
+{
+    let initial_len = content_len(&*buffer.read()).expect("Failed to get content length");
+    let counters = FuzzCounters::default();
+
+    std::thread::scope(|s| {
+        for i in 0..config.threads {
+            let mut rng = Rng::new(config.seed.wrapping_add(i as u64));
+            let counters = &counters;
+            let op = &op;
+
+            s.spawn(move || {
+                for _ in 0..config.ops_per_thread {
+                    op(&mut rng, buffer, counters).expect("Fuzz operation failed");
+                }
+            });
+        }
+    });
+
+    let final_len = content_len(&*buffer.read()).expect("Failed to get content length");
+
+    assert_eq!(
+        final_len as i64,
+        initial_len as i64 + counters.delta.load(Ordering::SeqCst),
+        "Final content length doesn't match applied edits"
+    );
+
+    counters.stats()
+}
+
+#[derive(Default)]
+struct FuzzCounters {
+    delta: AtomicI64,
+    set_text: AtomicUsize,
+    mark_moves: AtomicUsize,
+    region_reads: AtomicUsize,
+}
+
+impl FuzzCounters {
+    fn stats(&self) -> FuzzStats {
+        FuzzStats {
+            set_text: self.set_text.load(Ordering::SeqCst),
+            mark_moves: self.mark_moves.load(Ordering::SeqCst),
+            region_reads: self.region_reads.load(Ordering::SeqCst),
+        }
+    }
+
+    fn record_set_text(&self, delta: i64) {
+        self.delta.fetch_add(delta, Ordering::SeqCst);
+        self.set_text.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Applies random `set_text` calls from multiple threads, verifying that content length
+/// always matches the applied edits.
+pub fn fuzz_set_text<B: BufferHandle>(buffer: &B, config: &FuzzConfig) -> FuzzStats {
+    run_threads(buffer, config, |rng, buffer, counters| {
+        let delta = random_set_text(rng, buffer.write().deref_mut(), config)?;
+        counters.record_set_text(delta);
+
+        Ok(())
+    })
+}
+
+#[cfg(feature = "region")]
+mod region {
+    use super::*;
+
+    use crate::{
+        mark::{Mark, MarkBufferHandle},
+        region::BufferRegion,
+    };
+
+    const MARK_COUNT: usize = 8;
+    const REGION_COUNT: usize = 4;
+
+    fn check_marks<B: MarkBufferHandle>(
+        buffer: &impl crate::mark::MarkReadBuffer<MarkId = B::MarkId>,
+        marks: &[Mark<B>],
+    ) -> Result<()> {
+        for mark in marks {
+            let position = mark.read(buffer).get_position()?;
+
+            buffer.validate_pos(&position)?;
+        }
+
+        Ok(())
+    }
+
+    /// Interleaves random `set_text` calls, mark moves and region reads from multiple threads.
+    ///
+    /// After every operation all marks have to point at valid positions and every region
+    /// has to stay consistent with its own bounds.
+    pub fn fuzz_marks_regions<B: MarkBufferHandle>(buffer: &B, config: &FuzzConfig) -> FuzzStats {
+        let mut rng = Rng::new(config.seed);
+
+        let (marks, regions) = {
+            let mut lock = buffer.write();
+
+            let marks = (0..MARK_COUNT)
+                .map(|_| {
+                    let position = rng.position(&*lock)?;
+                    Mark::new(buffer, &position, &mut *lock)
+                })
+                .collect::<Result<Vec<_>>>()
+                .expect("Failed to create marks");
+
+            let regions = (0..REGION_COUNT)
+                .map(|_| {
+                    let (start, end) = rng.range(&*lock)?;
+                    BufferRegion::new(buffer, (&start, &end), &mut *lock)
+                })
+                .collect::<Result<Vec<_>>>()
+                .expect("Failed to create regions");
+
+            (marks, regions)
+        };
+
+        run_threads(buffer, config, |rng, buffer, counters| {
+            match rng.below(3) {
+                0 => {
+                    let mut lock = buffer.write();
+
+                    let delta = random_set_text(rng, &mut *lock, config)?;
+                    counters.record_set_text(delta);
+
+                    check_marks(&*lock, &marks)?;
+                }
+                1 => {
+                    let mut lock = buffer.write();
+
+                    let mark = &marks[rng.below(marks.len())];
+                    let position = rng.position(&*lock)?;
+
+                    mark.write(&mut *lock).set_position(&position)?;
+
+                    assert_eq!(mark.read(&*lock).get_position()?, position);
+                    counters.mark_moves.fetch_add(1, Ordering::SeqCst);
+                }
+                _ => {
+                    let region = regions[rng.below(regions.len())].read();
+
+                    let content = region.get_content()?;
+
+                    assert_eq!(
+                        Position::max_text_pos(&content),
+                        region.max_pos()?,
+                        "Region content doesn't match region bounds"
+                    );
+                    counters.region_reads.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "region")]
+pub use region::fuzz_marks_regions;
+
+pub mod tests {
+    use super::*;
+
+    use crate::{Editor, test_utils::new_buffer_with_content};
+
+    const FUZZ_CONTENT: &str = "First line\nSecond line\nThird line";
+
+    pub fn test_fuzz_set_text(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, FUZZ_CONTENT);
+
+        let stats = fuzz_set_text(&buffer, &FuzzConfig::default());
+
+        assert_eq!(stats.set_text, 200);
+    }
+
+    #[cfg(feature = "region")]
+    pub fn test_fuzz_marks_regions<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, FUZZ_CONTENT);
+
+        let stats = fuzz_marks_regions(&buffer, &FuzzConfig::default());
+
+        assert_eq!(stats.set_text + stats.mark_moves + stats.region_reads, 200);
+    }
+
+    #[macro_export]
+    #[cfg(feature = "region")]
+    macro_rules! eel_fuzz_tests {
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
+                editor_bounds: {},
+                module_path: $crate::fuzz::tests,
+                tests: [test_fuzz_set_text],
+            }
+        };
+
+        (@region_tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::fuzz::tests,
+                tests: [test_fuzz_marks_regions],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_fuzz_tests!(@tests list: {},)
+        };
+
+        (@region_list) => {
+            $crate::eel_fuzz_tests!(@region_tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_fuzz_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
+            );
+
+            $crate::eel_fuzz_tests!(@region_tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_fuzz_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "region"))]
+    macro_rules! eel_fuzz_tests {
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
+                editor_bounds: {},
+                module_path: $crate::fuzz::tests,
+                tests: [test_fuzz_set_text],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_fuzz_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_fuzz_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_fuzz_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::{PoisonError, RwLock};
+
+use itertools::Itertools;
+
+use crate::{
+    Position, Result,
+    buffer::{ReadBuffer, WriteBuffer},
+};
+
+/// How a buffer is indented, see [`ReadBuffer::indent_settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentSettings {
+    /// Columns of one indentation level.
+    pub shift_width: usize,
+    /// Columns a tab takes up.
+    pub tab_width: usize,
+    /// Indent with spaces only, otherwise with as many tabs as fit.
+    pub expand_tab: bool,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            shift_width: 4,
+            tab_width: 4,
+            expand_tab: true,
+        }
+    }
+}
+
+static GLOBAL_SETTINGS: RwLock<Option<IndentSettings>> = RwLock::new(None);
+
+impl IndentSettings {
+    /// Settings of buffers whose backend has none of its own.
+    pub fn global() -> Self {
+        GLOBAL_SETTINGS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .unwrap_or_default()
+    }
+
+    pub fn set_global(settings: Self) {
+        *GLOBAL_SETTINGS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(settings);
+    }
+
+    /// Leading whitespace of `width` columns.
+    pub fn indent(&self, width: usize) -> String {
+        if self.expand_tab || self.tab_width == 0 {
+            return " ".repeat(width);
+        }
+
+        "\t".repeat(width / self.tab_width) + &" ".repeat(width % self.tab_width)
+    }
+
+    /// One indentation level.
+    pub fn unit(&self) -> String {
+        self.indent(self.shift_width)
+    }
+
+    /// Columns taken up by the leading whitespace of `line`.
+    pub fn width(&self, line: &str) -> usize {
+        line.chars()
+            .map_while(|c| match c {
+                ' ' => Some(1),
+                '\t' => Some(self.tab_width.max(1)),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+fn split_indent(line: &str) -> (&str, &str) {
+    line.split_at(line.len() - line.trim_start_matches([' ', '\t']).len())
+}
+
+/// `line` with `width` more columns of indentation, blank lines are emptied.
+fn indent_by(line: &str, width: usize, settings: &IndentSettings) -> String {
+    if line.trim().is_empty() {
+        return String::new();
+    }
+
+    let (indent, rest) = split_indent(line);
+    settings.indent(width + settings.width(indent)) + rest
+}
+
+/// Indentation of the line at `row`, in columns.
+pub fn line_indent<B: ReadBuffer + ?Sized>(buffer: &B, row: usize) -> Result<usize> {
+    Ok(buffer.indent_settings()?.width(&buffer.get_line(row)?))
+}
+
+/// Inserts `text` at `position` like pasting a snippet: the first line goes at the position,
+/// the following ones keep their indentation relative to the line it's on, converted to the
+/// buffer's [`IndentSettings`]. Returns the position after the inserted text.
+pub fn insert_indented<B: WriteBuffer + ?Sized>(
+    buffer: &mut B,
+    position: &Position,
+    text: &str,
+) -> Result<Position> {
+    let settings = buffer.indent_settings()?;
+    let base = line_indent(buffer, position.row)?;
+
+    let text = match text.split_once('\n') {
+        Some((first, rest)) => std::iter::once(first.to_string())
+            .chain(
+                rest.split('\n')
+                    .map(|line| indent_by(line, base, &settings)),
+            )
+            .join("\n"),
+        None => text.to_string(),
+    };
+
+    buffer.set_text((position, position), &text)?;
+
+    Ok(match text.rsplit_once('\n') {
+        Some((before, last)) => {
+            Position::new(position.row + before.matches('\n').count() + 1, last.len())
+        }
+        None => Position::new(position.row, position.col + text.len()),
+    })
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor, assert_buffer_content, buffer::BufferHandle, test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_insert_indented(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "fn f() {\n    let a = \n}");
+        let settings = buffer
+            .read()
+            .indent_settings()
+            .expect("Failed to get indent settings");
+
+        let base = settings.indent(4);
+        let nested = settings.indent(4 + settings.shift_width);
+
+        let snippet = format!("match b {{\n{}_ => {{}}\n\n}};", settings.unit());
+        let end = buffer
+            .write()
+            .insert_indented(&Position::new(1, 12), &snippet)
+            .expect("Failed to insert");
+
+        assert_buffer_content!(
+            buffer,
+            format!("fn f() {{\n    let a = match b {{\n{nested}_ => {{}}\n\n{base}}};\n}}")
+        );
+        assert_eq!(end, Position::new(4, base.len() + 2));
+
+        // Single lines are inserted as they are
+        let end = buffer
+            .write()
+            .insert_indented(&Position::new(0, 0), "  // f")
+            .expect("Failed to insert");
+
+        assert_eq!(end, Position::new(0, 6));
+        assert_eq!(
+            buffer.read().get_line(0).expect("Failed to get line"),
+            "  // ffn f() {"
+        );
+        assert_eq!(
+            line_indent(&*buffer.read(), 0).expect("Failed to get indent"),
+            2
+        );
+    }
+
+    #[macro_export]
+    macro_rules! eel_indent_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::indent::tests,
+                prefix: $prefix,
+                tests: [test_insert_indented],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_indent_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn indent_settings() {
+        let spaces = IndentSettings::default();
+        assert_eq!(spaces.unit(), "    ");
+        assert_eq!(spaces.width("  \tx"), 6);
+
+        let tabs = IndentSettings {
+            shift_width: 2,
+            tab_width: 8,
+            expand_tab: false,
+        };
+        assert_eq!(tabs.unit(), "  ");
+        assert_eq!(tabs.indent(10), "\t  ");
+        assert_eq!(tabs.width("\t  x"), 10);
+        assert_eq!(tabs.width(""), 0);
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    collections::VecDeque,
+    ops::RangeBounds,
+    sync::{Arc, Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{
+        BufferData, BufferHandle, ReadBuffer, ReadBufferLock, WeakBufferHandle, WriteBuffer,
+        WriteBufferLock,
+    },
+    indent::IndentSettings,
+};
+
+pub const DEFAULT_JOURNAL_CAPACITY: usize = 1024;
+
+/// Single `set_text` call as seen by the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: Position,
+    pub old_end: Position,
+    pub new_end: Position,
+    pub old_text: String,
+    pub new_text: String,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug)]
+struct JournalState {
+    version: u64,
+    edits: VecDeque<(u64, TextEdit)>,
+}
+
+/// Edits applied to a buffer through [`JournaledBufferHandle`], each bumping the version.
+///
+/// Only the last `capacity` edits are kept.
+#[derive(Debug)]
+pub struct Journal {
+    state: Mutex<JournalState>,
+    capacity: usize,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_JOURNAL_CAPACITY)
+    }
+}
+
+impl Journal {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(JournalState {
+                version: 0,
+                edits: VecDeque::new(),
+            }),
+            capacity,
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, JournalState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn version(&self) -> u64 {
+        self.state().version
+    }
+
+    /// Edits applied after `version`, oldest first.
+    ///
+    /// Returns `None` if some of them were already dropped from the journal.
+    pub fn changes_since(&self, version: u64) -> Option<Vec<TextEdit>> {
+        let state = self.state();
+
+        let oldest = state.edits.front().map_or(state.version + 1, |(v, _)| *v);
+
+        if version + 1 < oldest {
+            return None;
+        }
+
+        Some(
+            state
+                .edits
+                .iter()
+                .filter(|(v, _)| *v > version)
+                .map(|(_, edit)| edit.clone())
+                .collect(),
+        )
+    }
+
+    fn record(&self, edit: TextEdit) -> u64 {
+        let mut state = self.state();
+
+        state.version += 1;
+        let version = state.version;
+
+        state.edits.push_back((version, edit));
+
+        while state.edits.len() > self.capacity {
+            state.edits.pop_front();
+        }
+
+        version
+    }
+}
+
+/// Handle recording every `set_text` into the buffer's [`Journal`].
+///
+/// The journal is kept in [`BufferHandle::data`], so all journaled handles to the same buffer
+/// share it. Edits made through other handles are not recorded.
+#[derive(Debug, Clone)]
+pub struct JournaledBufferHandle<B: BufferHandle> {
+    inner: B,
+    journal: Arc<Journal>,
+}
+
+impl<B: BufferHandle> PartialEq for JournaledBufferHandle<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<B: BufferHandle> Eq for JournaledBufferHandle<B> {}
+
+impl<B: BufferHandle> JournaledBufferHandle<B> {
+    pub fn new(inner: B) -> Self {
+        let journal = inner.data().get_or_insert_with(Journal::default);
+
+        Self { inner, journal }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn version(&self) -> u64 {
+        self.journal.version()
+    }
+
+    pub fn changes_since(&self, version: u64) -> Option<Vec<TextEdit>> {
+        self.journal.changes_since(version)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeakJournaledBufferHandle<B: BufferHandle> {
+    inner: B::WeakHandle,
+}
+
+impl<B: BufferHandle> WeakBufferHandle for WeakJournaledBufferHandle<B> {
+    type Handle = JournaledBufferHandle<B>;
+
+    fn upgrade(&self) -> Option<Self::Handle> {
+        self.inner.upgrade().map(JournaledBufferHandle::new)
+    }
+}
+
+pub struct JournaledBuffer<L> {
+    buffer_lock: L,
+    journal: Arc<Journal>,
+}
+
+impl<L: ReadBufferLock> ReadBuffer for JournaledBuffer<L> {
+    fn line_count(&self) -> Result<usize> {
+        self.buffer_lock.line_count()
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        self.buffer_lock.get_lines(range)
+    }
+
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        self.buffer_lock.get_text_in(range)
+    }
+
+    fn line_len(&self, row: usize) -> Result<usize> {
+        self.buffer_lock.line_len(row)
+    }
+
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        self.buffer_lock.char_at(position)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.buffer_lock.changedtick()
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        self.buffer_lock.indent_settings()
+    }
+}
+
+impl<L: WriteBufferLock> JournaledBuffer<L> {
+    fn record_edit(
+        &mut self,
+        range: &PosRange,
+        text: &str,
+        edit: impl FnOnce(&mut L::WriteBuffer) -> Result<()>,
+    ) -> Result<()> {
+        let old_text = self.get_text_in(range)?;
+
+        edit(&mut self.buffer_lock)?;
+
+        self.journal.record(TextEdit {
+            start: range.start().clone(),
+            old_end: range.end().clone(),
+            new_end: range.start().offset(&Position::max_text_pos(text)),
+            old_text,
+            new_text: text.to_string(),
+            timestamp: SystemTime::now(),
+        });
+
+        Ok(())
+    }
+}
+
+impl<L: WriteBufferLock> WriteBuffer for JournaledBuffer<L> {
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        self.record_edit(range, text, |buffer| buffer.set_text_in(range, text))
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        self.record_edit(range, text, |buffer| {
+            buffer.set_text_if_unchanged(expected_tick, range, text)
+        })
+    }
+}
+
+impl<B: BufferHandle> BufferHandle for JournaledBufferHandle<B> {
+    type ReadBuffer = JournaledBuffer<B::ReadBufferLock>;
+    type WriteBuffer = JournaledBuffer<B::WriteBufferLock>;
+    type ReadBufferLock = Box<Self::ReadBuffer>;
+    type WriteBufferLock = Box<Self::WriteBuffer>;
+    type WeakHandle = WeakJournaledBufferHandle<B>;
+
+    fn read(&self) -> Self::ReadBufferLock {
+        Box::new(JournaledBuffer {
+            buffer_lock: self.inner.read(),
+            journal: self.journal.clone(),
+        })
+    }
+
+    fn write(&self) -> Self::WriteBufferLock {
+        Box::new(JournaledBuffer {
+            buffer_lock: self.inner.write(),
+            journal: self.journal.clone(),
+        })
+    }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        Ok(Box::new(JournaledBuffer {
+            buffer_lock: self.inner.read_timeout()?,
+            journal: self.journal.clone(),
+        }))
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        Ok(Box::new(JournaledBuffer {
+            buffer_lock: self.inner.write_timeout()?,
+            journal: self.journal.clone(),
+        }))
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        let JournaledBuffer {
+            buffer_lock,
+            journal,
+        } = *lock;
+
+        Box::new(JournaledBuffer {
+            buffer_lock: self.inner.downgrade_lock(buffer_lock),
+            journal,
+        })
+    }
+
+    fn data(&self) -> &BufferData {
+        self.inner.data()
+    }
+
+    fn downgrade(&self) -> Self::WeakHandle {
+        WeakJournaledBufferHandle {
+            inner: self.inner.downgrade(),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.inner.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.inner.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.inner.changedtick()
+    }
+}
+
+#[cfg(feature = "cursor")]
+mod cursor {
+    use super::*;
+
+    use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+    impl<L> CursorReadBuffer for JournaledBuffer<L>
+    where
+        L: ReadBufferLock,
+        L::ReadBuffer: CursorReadBuffer,
+    {
+        fn get_cursor(&self) -> Result<Position> {
+            self.buffer_lock.get_cursor()
+        }
+    }
+
+    impl<L> CursorWriteBuffer for JournaledBuffer<L>
+    where
+        L: WriteBufferLock,
+        L::WriteBuffer: CursorWriteBuffer,
+    {
+        fn set_cursor(&mut self, position: &Position) -> Result<()> {
+            self.buffer_lock.set_cursor(position)
+        }
+
+        fn push_jump(&mut self) -> Result<()> {
+            self.buffer_lock.push_jump()
+        }
+    }
+}
+
+#[cfg(feature = "mark")]
+mod mark {
+    use super::*;
+
+    use crate::mark::{Gravity, MarkReadBuffer, MarkWriteBuffer};
+
+    impl<L> MarkReadBuffer for JournaledBuffer<L>
+    where
+        L: ReadBufferLock,
+        L::ReadBuffer: MarkReadBuffer,
+    {
+        type MarkId = <L::ReadBuffer as MarkReadBuffer>::MarkId;
+
+        fn get_mark_position(&self, id: Self::MarkId) -> Result<Position> {
+            self.buffer_lock.get_mark_position(id)
+        }
+
+        fn get_mark_end(&self, id: Self::MarkId) -> Result<Option<Position>> {
+            self.buffer_lock.get_mark_end(id)
+        }
+    }
+
+    impl<L> MarkWriteBuffer for JournaledBuffer<L>
+    where
+        L: WriteBufferLock,
+        L::WriteBuffer: MarkWriteBuffer,
+    {
+        fn create_mark(&mut self, pos: &Position) -> Result<Self::MarkId> {
+            self.buffer_lock.create_mark(pos)
+        }
+
+        fn destroy_mark(&mut self, id: Self::MarkId) -> Result<()> {
+            self.buffer_lock.destroy_mark(id)
+        }
+
+        fn create_mark_with(
+            &mut self,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<Self::MarkId> {
+            self.buffer_lock.create_mark_with(start, end, gravity)
+        }
+
+        fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()> {
+            self.buffer_lock.set_mark_position(id, pos)
+        }
+
+        fn set_mark_gravity(&mut self, id: Self::MarkId, gravity: Gravity) -> Result<()> {
+            self.buffer_lock.set_mark_gravity(id, gravity)
+        }
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{Editor, assert_buffer_content, test_utils::new_buffer_with_content};
+
+    pub fn test_journal_changes(editor: impl Editor) {
+        let buffer =
+            JournaledBufferHandle::new(new_buffer_with_content(&editor, "First line\nSecond line"));
+
+        assert_eq!(buffer.version(), 0);
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 6), Position::new(1, 6)), "row")
+            .expect("Failed to set text");
+
+        buffer.write().append("!").expect("Failed to append");
+
+        assert_buffer_content!(buffer, "First row line!");
+        assert_eq!(buffer.version(), 2);
+
+        let changes = buffer.changes_since(0).expect("Changes were dropped");
+        assert_eq!(changes.len(), 2);
+
+        assert_eq!(changes[0].start, Position::new(0, 6));
+        assert_eq!(changes[0].old_end, Position::new(1, 6));
+        assert_eq!(changes[0].new_end, Position::new(0, 9));
+        assert_eq!(changes[0].old_text, "line\nSecond");
+        assert_eq!(changes[0].new_text, "row");
+
+        assert_eq!(changes[1].old_text, "");
+        assert_eq!(changes[1].new_text, "!");
+
+        let changes = buffer.changes_since(1).expect("Changes were dropped");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].new_text, "!");
+
+        assert!(
+            buffer
+                .changes_since(2)
+                .expect("Changes were dropped")
+                .is_empty()
+        );
+
+        // Other journaled handles share the journal
+        let other = JournaledBufferHandle::new(buffer.inner().clone());
+        assert_eq!(other.version(), 2);
+    }
+
+    pub fn test_journal_capacity(editor: impl Editor) {
+        let inner = editor.new_buffer().expect("Failed to create buffer");
+        inner.data().insert(Journal::with_capacity(2));
+
+        let buffer = JournaledBufferHandle::new(inner);
+
+        for _ in 0..3 {
+            buffer.write().append("a").expect("Failed to append");
+        }
+
+        assert_eq!(buffer.version(), 3);
+        assert!(buffer.changes_since(0).is_none());
+        assert_eq!(
+            buffer.changes_since(1).expect("Changes were dropped").len(),
+            2
+        );
+    }
+
+    #[macro_export]
+    macro_rules! eel_journal_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::journal::tests,
+                prefix: $prefix,
+                tests: [
+                    test_journal_changes,
+                    test_journal_capacity,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_journal_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -1,17 +1,36 @@
+// Lets the code generated by eel-macros refer to ::eel inside this crate
+extern crate self as eel;
+
 pub mod error;
 pub use error::{Error, Result};
 
 pub mod tracing;
 
-mod editor;
+pub mod editor;
+mod option;
 mod position;
 
-pub use editor::Editor;
-pub use position::Position;
+pub use editor::{Capabilities, Editor, EditorConfig};
+pub use option::OptionValue;
+pub use position::{PosRange, Position};
 
 pub mod buffer;
+#[cfg(feature = "bus")]
+pub mod bus;
+pub mod column;
+pub mod commands;
+pub mod comment;
+pub mod debounce;
+pub mod dispatch;
+pub mod filetype;
+pub mod indent;
+pub mod journal;
+pub mod search;
+pub mod tasks;
+pub mod textobject;
+pub mod workspace;
 
-mod complete_buffer;
+pub mod complete_buffer;
 pub use complete_buffer::CompleteBufferHandle;
 
 #[cfg(feature = "cursor")]
@@ -20,12 +39,60 @@ pub mod cursor;
 #[cfg(feature = "mark")]
 pub mod mark;
 
+#[cfg(feature = "mark")]
+pub mod annotations;
+
+#[cfg(feature = "mark")]
+pub mod line_tracker;
+
 #[cfg(feature = "region")]
 pub mod region;
 
+#[cfg(feature = "fold")]
+pub mod fold;
+
+#[cfg(feature = "undo")]
+pub mod undo;
+
+#[cfg(feature = "decoration")]
+pub mod decoration;
+
+#[cfg(feature = "suggestion")]
+pub mod suggestion;
+
+#[cfg(feature = "substitute")]
+pub mod substitute;
+
+#[cfg(feature = "collab")]
+pub mod collab;
+
+#[cfg(feature = "session")]
+pub mod session;
+
+#[cfg(feature = "ui")]
+pub mod ui;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "ops_recorder")]
+pub mod ops_recorder;
+
 #[cfg(feature = "tests")]
 pub mod test_utils;
 
+#[cfg(feature = "tests")]
+pub mod fuzz;
+
+#[cfg(feature = "tests")]
+pub mod bench;
+
+#[cfg(feature = "conformance")]
+pub mod conformance;
+
 #[cfg(feature = "tests")]
 mod tests {
     #[macro_export]
@@ -40,19 +107,132 @@ mod tests {
         ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
     }
 
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_annotations_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_line_tracker_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
     #[macro_export]
     #[cfg(not(feature = "region"))]
     macro_rules! eel_region_tests {
         ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
     }
 
+    #[macro_export]
+    #[cfg(not(feature = "fold"))]
+    macro_rules! eel_fold_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "undo"))]
+    macro_rules! eel_undo_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "decoration"))]
+    macro_rules! eel_decoration_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "suggestion"))]
+    macro_rules! eel_suggestion_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "substitute"))]
+    macro_rules! eel_substitute_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "collab"))]
+    macro_rules! eel_collab_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "session"))]
+    macro_rules! eel_session_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "bus"))]
+    macro_rules! eel_bus_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "ui"))]
+    macro_rules! eel_ui_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "server"))]
+    macro_rules! eel_server_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "metrics"))]
+    macro_rules! eel_metrics_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "ops_recorder"))]
+    macro_rules! eel_ops_recorder_tests {
+        ($test_tag:path, $editor_factory:expr $(, $_:tt)?) => {};
+    }
+
     #[macro_export]
     macro_rules! eel_full_tests {
         ($test_tag:path, $editor_factory:expr) => {
             $crate::eel_buffer_tests!($test_tag, $editor_factory);
+            $crate::eel_editor_tests!($test_tag, $editor_factory);
+            $crate::eel_bus_tests!($test_tag, $editor_factory);
+            $crate::eel_complete_tests!($test_tag, $editor_factory);
+            $crate::eel_column_tests!($test_tag, $editor_factory);
             $crate::eel_cursor_tests!($test_tag, $editor_factory);
             $crate::eel_mark_tests!($test_tag, $editor_factory);
+            $crate::eel_annotations_tests!($test_tag, $editor_factory);
+            $crate::eel_line_tracker_tests!($test_tag, $editor_factory);
             $crate::eel_region_tests!($test_tag, $editor_factory);
+            $crate::eel_fold_tests!($test_tag, $editor_factory);
+            $crate::eel_undo_tests!($test_tag, $editor_factory);
+            $crate::eel_decoration_tests!($test_tag, $editor_factory);
+            $crate::eel_suggestion_tests!($test_tag, $editor_factory);
+            $crate::eel_substitute_tests!($test_tag, $editor_factory);
+            $crate::eel_journal_tests!($test_tag, $editor_factory);
+            $crate::eel_debounce_tests!($test_tag, $editor_factory);
+            $crate::eel_search_tests!($test_tag, $editor_factory);
+            $crate::eel_textobject_tests!($test_tag, $editor_factory);
+            $crate::eel_comment_tests!($test_tag, $editor_factory);
+            $crate::eel_indent_tests!($test_tag, $editor_factory);
+            $crate::eel_commands_tests!($test_tag, $editor_factory);
+            $crate::eel_tasks_tests!($test_tag, $editor_factory);
+            $crate::eel_workspace_tests!($test_tag, $editor_factory);
+            $crate::eel_ui_tests!($test_tag, $editor_factory);
+            $crate::eel_collab_tests!($test_tag, $editor_factory);
+            $crate::eel_session_tests!($test_tag, $editor_factory);
+            $crate::eel_server_tests!($test_tag, $editor_factory);
+            $crate::eel_metrics_tests!($test_tag, $editor_factory);
+            $crate::eel_ops_recorder_tests!($test_tag, $editor_factory);
+            $crate::eel_fuzz_tests!($test_tag, $editor_factory);
+            $crate::eel_bench_tests!($test_tag, $editor_factory);
+            $crate::eel_faulty_tests!($test_tag, $editor_factory);
         };
     }
 }
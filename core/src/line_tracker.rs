@@ -0,0 +1,198 @@
+use std::{
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    Position, Result,
+    annotations::Annotations,
+    buffer::WeakBufferHandle,
+    mark::{Gravity, MarkBufferHandle, MarkWriteBuffer},
+};
+
+/// Stable ID of a line tracked by a [`LineTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineId(u64);
+
+/// IDs for lines that survive edits, stored in the buffer's
+/// [`BufferData`](crate::buffer::BufferData), e.g. to re-anchor test results reported
+/// against line numbers that have since moved.
+///
+/// Every line is tracked by an [`Annotations`] mark at its start. Lines joined into the
+/// previous one report its row, the IDs of deleted lines move to the line following the
+/// deletion.
+pub struct LineTracker<B: MarkBufferHandle> {
+    buffer: B::WeakHandle,
+    next_id: AtomicU64,
+    lines: Arc<Annotations<B, LineId>>,
+}
+
+impl<B: MarkBufferHandle> LineTracker<B> {
+    pub fn of(buffer: &B) -> Arc<Self> {
+        // Buffer data can't be accessed while it initializes an entry
+        let lines = Annotations::of(buffer);
+
+        buffer.data().get_or_insert_with(|| Self {
+            buffer: buffer.downgrade(),
+            next_id: AtomicU64::new(0),
+            lines,
+        })
+    }
+
+    fn buffer(&self) -> Result<B> {
+        self.buffer
+            .upgrade()
+            .ok_or_else(|| crate::buffer::Error::Custom("Buffer was closed".into()).into())
+    }
+
+    /// New ID for the line at `row`, every call gives a different one.
+    pub fn track(&self, row: usize) -> Result<LineId> {
+        Ok(self.track_lines(row..(row + 1))?[0])
+    }
+
+    /// IDs for the lines in `rows` in order, created under a single lock.
+    pub fn track_lines(&self, rows: Range<usize>) -> Result<Vec<LineId>> {
+        let buffer = self.buffer()?;
+        let mut lock = buffer.write_timeout()?;
+
+        let mut ids = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            // Text typed at the start of the line pushes the mark along with the line
+            let mark = lock.create_mark_with(&Position::new(row, 0), None, Gravity::Right)?;
+
+            let id = LineId(self.next_id.fetch_add(1, Ordering::Relaxed));
+            self.lines.insert(mark, id);
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+
+    /// Row the line is on now, `None` if it isn't tracked.
+    pub fn current_row(&self, id: LineId) -> Result<Option<usize>> {
+        let Some(mark) = self.lines.find(&id) else {
+            return Ok(None);
+        };
+
+        Ok(self.lines.position(mark)?.map(|position| position.row))
+    }
+
+    /// Current rows of all tracked lines, ordered by ID.
+    pub fn current_rows(&self) -> Result<Vec<(LineId, usize)>> {
+        let mut rows = self
+            .lines
+            .annotations_in(..)?
+            .into_iter()
+            .map(|(_, position, id)| (id, position.row))
+            .collect::<Vec<_>>();
+
+        rows.sort();
+
+        Ok(rows)
+    }
+
+    /// Returns whether the line was tracked.
+    pub fn untrack(&self, id: LineId) -> bool {
+        self.lines
+            .find(&id)
+            .and_then(|mark| self.lines.remove(mark))
+            .is_some()
+    }
+
+    pub fn clear(&self) {
+        self.lines.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_line_tracker<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "a\nb\nc\nd\ne");
+
+        let tracker = LineTracker::of(&buffer);
+        let ids = tracker.track_lines(0..5).expect("Failed to track lines");
+        let row = |id| tracker.current_row(id).expect("Failed to get row");
+
+        // Shared through the buffer data
+        assert_eq!(LineTracker::of(&buffer).len(), 5);
+        assert_ne!(tracker.track(1).expect("Failed to track line"), ids[1]);
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "new\nx")
+            .expect("Failed to set text");
+        assert_eq!(
+            ids.iter().map(|id| row(*id)).collect::<Vec<_>>(),
+            [Some(1), Some(2), Some(3), Some(4), Some(5)]
+        );
+
+        // Deleting "b\n", then joining "d" into "c"
+        buffer
+            .write()
+            .set_text((Position::new(2, 0), Position::new(3, 0)), "")
+            .expect("Failed to set text");
+        buffer
+            .write()
+            .set_text((Position::new(2, 1), Position::new(3, 0)), "")
+            .expect("Failed to set text");
+
+        assert_eq!(row(ids[0]), Some(1));
+        assert_eq!(row(ids[1]), Some(2));
+        assert_eq!(row(ids[2]), Some(2));
+        assert_eq!(row(ids[3]), Some(2));
+        assert_eq!(row(ids[4]), Some(3));
+
+        assert!(tracker.untrack(ids[4]));
+        assert!(!tracker.untrack(ids[4]));
+        assert_eq!(row(ids[4]), None);
+
+        let rows = tracker.current_rows().expect("Failed to get rows");
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], (ids[0], 1));
+
+        tracker.clear();
+        assert!(tracker.is_empty());
+    }
+
+    #[macro_export]
+    macro_rules! eel_line_tracker_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::line_tracker::tests,
+                prefix: $prefix,
+                tests: [test_line_tracker],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_line_tracker_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
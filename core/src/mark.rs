@@ -1,33 +1,151 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::{
+        Arc, Weak,
+        mpsc::{self, Receiver},
+    },
+};
 
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::{
-    Position, Result,
+    PosRange, Position, Result,
     buffer::{BufferHandle, ReadBuffer, ReadBufferLock, WriteBuffer, WriteBufferLock},
     tracing::ResultExt,
 };
 
-pub trait MarkId: std::fmt::Debug + Clone + Copy + Eq + Sync + Send {}
+pub trait MarkId: std::fmt::Debug + Clone + Copy + Eq + Sync + Send + 'static {}
 
-#[derive(Debug, PartialEq, Eq)]
+/// Where a mark moves when text is inserted at its position.
+///
+/// For marks with an extent `Left` and `Right` apply to both ends, `Both` makes the extent grow
+/// on insertions at either end and `None` keeps it from growing. Marks without an extent only
+/// have a start, so `Both` behaves like `Left` and `None` like `Right`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Gravity {
     Left,
     Right,
+    Both,
+    None,
+}
+
+impl Gravity {
+    pub fn start_right(self) -> bool {
+        matches!(self, Gravity::Right | Gravity::None)
+    }
+
+    pub fn end_right(self) -> bool {
+        matches!(self, Gravity::Right | Gravity::Both)
+    }
+}
+
+/// What happens to marks inside text removed by [`MarkWriteBuffer::delete_range_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkPolicy {
+    /// Marks move to the start of the removed text, like with any other edit.
+    #[default]
+    Collapse,
+    /// Marks are destroyed and their ids mustn't be used anymore, so this is meant for ids
+    /// rather than [`Mark`]s, which destroy theirs when dropped.
+    Invalidate,
 }
 
 pub trait MarkReadBuffer: ReadBuffer {
     type MarkId: MarkId;
 
     fn get_mark_position(&self, id: Self::MarkId) -> Result<Position>;
+
+    /// End of a mark created with an extent, see [`MarkWriteBuffer::create_mark_with`].
+    fn get_mark_end(&self, _id: Self::MarkId) -> Result<Option<Position>> {
+        Ok(None)
+    }
 }
 
 pub trait MarkWriteBuffer: MarkReadBuffer + WriteBuffer {
     fn create_mark(&mut self, pos: &Position) -> Result<Self::MarkId>;
     fn destroy_mark(&mut self, id: Self::MarkId) -> Result<()>;
 
+    /// Mark tracking `start..end` if `end` is given, backends without extent support only
+    /// accept `None`.
+    ///
+    /// The extent is kept when the mark's position or gravity changes.
+    fn create_mark_with(
+        &mut self,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+    ) -> Result<Self::MarkId> {
+        if end.is_some() {
+            Err(crate::Error::Unsupported("Mark extents"))?;
+        }
+
+        let id = self.create_mark(start)?;
+        self.set_mark_gravity(id, gravity)?;
+
+        Ok(id)
+    }
+
     fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()>;
     fn set_mark_gravity(&mut self, id: Self::MarkId, gravity: Gravity) -> Result<()>;
+
+    /// [`WriteBuffer::delete_range`] applying `policy` to the `marks` inside the range,
+    /// including its ends, which are returned.
+    ///
+    /// Marks can't be listed, so only the given ones are affected. Marks with an extent are
+    /// only inside if all of it is.
+    fn delete_range_with(
+        &mut self,
+        range: impl Into<PosRange>,
+        policy: MarkPolicy,
+        marks: &[Self::MarkId],
+    ) -> Result<Vec<Self::MarkId>> {
+        let range = range.into();
+
+        let mut inside = Vec::new();
+        for &id in marks {
+            let start = self.get_mark_position(id)?;
+            let end = self.get_mark_end(id)?.unwrap_or_else(|| start.clone());
+
+            if range.start() <= &start && &end <= range.end() {
+                inside.push(id);
+            }
+        }
+
+        self.delete_range(range)?;
+
+        if policy == MarkPolicy::Invalidate {
+            for &id in &inside {
+                self.destroy_mark(id)?;
+            }
+        }
+
+        Ok(inside)
+    }
+
+    /// [`WriteBuffer::clear`], see [`MarkWriteBuffer::delete_range_with`].
+    fn clear_with(
+        &mut self,
+        policy: MarkPolicy,
+        marks: &[Self::MarkId],
+    ) -> Result<Vec<Self::MarkId>> {
+        let max_pos = self.max_pos()?;
+
+        self.delete_range_with((Position::origin(), max_pos), policy, marks)
+    }
+
+    /// [`WriteBuffer::truncate_lines`], see [`MarkWriteBuffer::delete_range_with`].
+    fn truncate_lines_with(
+        &mut self,
+        lines: usize,
+        policy: MarkPolicy,
+        marks: &[Self::MarkId],
+    ) -> Result<Vec<Self::MarkId>> {
+        match crate::buffer::truncated_range(self, lines)? {
+            Some(range) => self.delete_range_with(range, policy, marks),
+            None => Ok(Vec::new()),
+        }
+    }
 }
 
 pub trait MarkBufferHandle:
@@ -69,6 +187,10 @@ where
     pub fn get_position(&self) -> Result<Position> {
         self.buffer_lock.get_mark_position(self.id)
     }
+
+    pub fn get_end(&self) -> Result<Option<Position>> {
+        self.buffer_lock.get_mark_end(self.id)
+    }
 }
 
 impl<'a, L> MarkAccess<'a, L>
@@ -84,10 +206,11 @@ where
         self.buffer_lock.set_mark_gravity(self.id, gravity)
     }
 
+    /// Inserts after the character at the mark, or at the mark if it's at the end of the line.
     pub fn append_at(&mut self, text: &str) -> Result<()> {
-        let position = self.get_position()?;
+        let position = self.buffer_lock.pos_after_char(&self.get_position()?)?;
 
-        self.buffer_lock.append_at_position(&position, text)
+        self.buffer_lock.set_text((&position, &position), text)
     }
 }
 
@@ -125,19 +248,67 @@ impl<B: MarkBufferHandle> Mark<B> {
         //       The same applies to below methods.
         let id = buffer_lock.create_mark(position)?;
 
-        Ok(Self {
+        Ok(Self::from_id(buffer, id))
+    }
+
+    /// See [`MarkWriteBuffer::create_mark_with`].
+    pub fn new_with<Buf, L>(
+        buffer: &B,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+        mut buffer_lock: L,
+    ) -> Result<Self>
+    where
+        Buf: MarkWriteBuffer<MarkId = B::MarkId>,
+        L: WriteBufferLock<WriteBuffer = Buf>,
+    {
+        let id = buffer_lock.create_mark_with(start, end, gravity)?;
+
+        Ok(Self::from_id(buffer, id))
+    }
+
+    fn from_id(buffer: &B, id: B::MarkId) -> Self {
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().marks_alive.inc();
+
+        Self {
             inner: Arc::new(InnerMark {
                 id,
                 buffer: buffer.clone(),
             }),
-        })
+        }
+    }
+
+    pub fn id(&self) -> B::MarkId {
+        self.inner.id
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.inner.buffer
+    }
+
+    pub fn downgrade(&self) -> WeakMark<B> {
+        WeakMark {
+            inner: Arc::downgrade(&self.inner),
+        }
     }
 
     pub fn lock_new(buffer: &B, position: &Position) -> Result<Self> {
-        let lock = buffer.write();
+        let lock = buffer.write_timeout()?;
         Self::new(buffer, position, lock)
     }
 
+    pub fn lock_new_with(
+        buffer: &B,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+    ) -> Result<Self> {
+        let lock = buffer.write_timeout()?;
+        Self::new_with(buffer, start, end, gravity, lock)
+    }
+
     pub fn read<'a, Buf, L>(&self, buffer_lock: L) -> MarkAccess<'a, L>
     where
         Buf: MarkReadBuffer<MarkId = B::MarkId>,
@@ -174,6 +345,56 @@ impl<B: MarkBufferHandle> Mark<B> {
         }
     }
 
+    /// Yields the mark's position now and after every buffer change that moved it.
+    ///
+    /// Stops once the receiver or all [`Mark`]s are dropped, or the buffer is closed. Dropped
+    /// receivers and marks are only noticed on the next change.
+    pub fn watch(&self) -> Receiver<Position> {
+        let (position_tx, position_rx) = mpsc::channel();
+        let (change_tx, change_rx) = mpsc::channel();
+
+        self.inner
+            .buffer
+            .on_change(move || change_tx.send(()).is_ok());
+
+        let mark = self.downgrade();
+
+        std::thread::spawn(move || {
+            let mut last = None;
+
+            loop {
+                let Some(position) = mark.upgrade().map(|m| m.lock_read().get_position()) else {
+                    return;
+                };
+
+                let position = match position {
+                    Ok(position) => position,
+                    Err(e) => {
+                        warn!("Failed to get watched mark position: {e}");
+                        return;
+                    }
+                };
+
+                if last.as_ref() != Some(&position) {
+                    if position_tx.send(position.clone()).is_err() {
+                        return;
+                    }
+
+                    last = Some(position);
+                }
+
+                if change_rx.recv().is_err() {
+                    return;
+                }
+
+                // Bursts of changes only need a single update
+                while change_rx.try_recv().is_ok() {}
+            }
+        });
+
+        position_rx
+    }
+
     pub fn lock_write(
         &self,
     ) -> MarkAccess<'static, impl WriteBufferLock<WriteBuffer = B::WriteBuffer> + 'static> {
@@ -187,10 +408,33 @@ impl<B: MarkBufferHandle> Mark<B> {
     }
 }
 
+/// Doesn't keep the mark alive, it gets destroyed once all [`Mark`]s are dropped.
+#[derive(Debug)]
+pub struct WeakMark<B: MarkBufferHandle> {
+    inner: Weak<InnerMark<B>>,
+}
+
+impl<B: MarkBufferHandle> Clone for WeakMark<B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B: MarkBufferHandle> WeakMark<B> {
+    pub fn upgrade(&self) -> Option<Mark<B>> {
+        self.inner.upgrade().map(|inner| Mark { inner })
+    }
+}
+
 impl<B: MarkBufferHandle> Drop for InnerMark<B> {
     fn drop(&mut self) {
         debug!("Destroying mark ({:?})", self.id);
 
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().marks_alive.dec();
+
         let buffer = self.buffer.clone();
         let id = self.id;
         std::thread::spawn(move || {
@@ -251,8 +495,7 @@ pub mod tests {
 
         buffer_lock
             .set_text(
-                &Position::new(0, 6),
-                &Position::new(0, 6),
+                (Position::new(0, 6), Position::new(0, 6)),
                 "(actually) line\nSecond ",
             )
             .expect("Failed to set text");
@@ -284,7 +527,7 @@ pub mod tests {
         );
 
         buffer_lock
-            .set_text(&Position::new(0, 1), &Position::new(0, 9), "ir")
+            .set_text((Position::new(0, 1), Position::new(0, 9)), "ir")
             .expect("Failed to set text");
 
         assert_eq!(
@@ -295,7 +538,7 @@ pub mod tests {
         );
 
         buffer_lock
-            .set_text(&Position::new(0, 3), &Position::new(0, 3), "...")
+            .set_text((Position::new(0, 3), Position::new(0, 3)), "...")
             .expect("Failed to set text");
 
         assert_eq!(
@@ -329,7 +572,7 @@ pub mod tests {
         );
 
         buffer_lock
-            .set_text(&Position::new(0, 1), &Position::new(0, 9), "ir")
+            .set_text((Position::new(0, 1), Position::new(0, 9)), "ir")
             .expect("Failed to set text");
 
         assert_eq!(
@@ -340,7 +583,7 @@ pub mod tests {
         );
 
         buffer_lock
-            .set_text(&Position::new(0, 1), &Position::new(0, 3), "...")
+            .set_text((Position::new(0, 1), Position::new(0, 3)), "...")
             .expect("Failed to set text");
 
         assert_eq!(
@@ -351,21 +594,330 @@ pub mod tests {
         );
     }
 
+    /// Marks at the start, inside, at the end and outside of the deleted second line, and one
+    /// with an extent crossing it.
+    fn delete_marks<B: MarkWriteBuffer>(buffer_lock: &mut B) -> [B::MarkId; 5] {
+        let mut create = |start: Position, end: Option<Position>| {
+            buffer_lock
+                .create_mark_with(&start, end.as_ref(), Gravity::Right)
+                .expect("Failed to create mark")
+        };
+
+        [
+            create(Position::new(0, 10), None),
+            create(Position::new(1, 3), None),
+            create(Position::new(1, 11), None),
+            create(Position::new(2, 0), None),
+            create(Position::new(1, 0), Some(Position::new(2, 5))),
+        ]
+    }
+
+    pub fn test_mark_delete_collapse<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+        let mut buffer_lock = buffer.write();
+
+        let marks = delete_marks(&mut *buffer_lock);
+
+        let inside = buffer_lock
+            .delete_range_with(
+                (Position::new(0, 10), Position::new(1, 11)),
+                MarkPolicy::Collapse,
+                &marks,
+            )
+            .expect("Failed to delete range");
+        assert_eq!(inside, marks[..3]);
+        assert_eq!(
+            buffer_lock.get_content().expect("Failed to get content"),
+            "First line\nThird line"
+        );
+
+        let positions = marks.map(|id| {
+            buffer_lock
+                .get_mark_position(id)
+                .expect("Failed to get position")
+        });
+        assert_eq!(
+            positions,
+            [
+                Position::new(0, 10),
+                Position::new(0, 10),
+                Position::new(0, 10),
+                Position::new(1, 0),
+                Position::new(0, 10),
+            ]
+        );
+
+        let inside = buffer_lock
+            .clear_with(MarkPolicy::Collapse, &marks)
+            .expect("Failed to clear");
+        assert_eq!(inside, marks);
+        assert_eq!(
+            buffer_lock.get_content().expect("Failed to get content"),
+            ""
+        );
+
+        for id in marks {
+            buffer_lock
+                .destroy_mark(id)
+                .expect("Failed to destroy mark");
+        }
+    }
+
+    pub fn test_mark_delete_invalidate<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line\nThird line");
+        let mut buffer_lock = buffer.write();
+
+        let marks = delete_marks(&mut *buffer_lock);
+
+        let invalidated = buffer_lock
+            .delete_range_with(
+                (Position::new(0, 10), Position::new(1, 11)),
+                MarkPolicy::Invalidate,
+                &marks,
+            )
+            .expect("Failed to delete range");
+        assert_eq!(invalidated, marks[..3]);
+
+        assert_eq!(
+            buffer_lock
+                .get_mark_position(marks[3])
+                .expect("Failed to get position"),
+            Position::new(1, 0)
+        );
+        assert_eq!(
+            buffer_lock
+                .get_mark_end(marks[4])
+                .expect("Failed to get end"),
+            Some(Position::new(1, 5))
+        );
+
+        let invalidated = buffer_lock
+            .truncate_lines_with(1, MarkPolicy::Invalidate, &marks[3..])
+            .expect("Failed to truncate lines");
+        assert_eq!(invalidated, marks[3..]);
+        assert_eq!(
+            buffer_lock.get_content().expect("Failed to get content"),
+            "First line"
+        );
+
+        let invalidated = buffer_lock
+            .truncate_lines_with(1, MarkPolicy::Invalidate, &marks[3..])
+            .expect("Failed to truncate lines");
+        assert!(invalidated.is_empty());
+    }
+
+    pub fn test_mark_extent<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line");
+        let mut buffer_lock = buffer.write();
+
+        let point = Mark::new(&buffer, &Position::new(0, 2), &mut *buffer_lock)
+            .expect("Failed to create mark");
+        assert_eq!(
+            point
+                .read(&*buffer_lock)
+                .get_end()
+                .expect("Failed to get end"),
+            None
+        );
+
+        let mark = Mark::new_with(
+            &buffer,
+            &Position::new(0, 0),
+            Some(&Position::new(0, 5)),
+            Gravity::Both,
+            &mut *buffer_lock,
+        )
+        .expect("Failed to create mark");
+
+        let extent = |buffer_lock: &<E::BufferHandle as BufferHandle>::WriteBufferLock| {
+            let access = mark.read(&**buffer_lock);
+
+            (
+                access.get_position().expect("Failed to get position"),
+                access.get_end().expect("Failed to get end"),
+            )
+        };
+
+        assert_eq!(
+            extent(&buffer_lock),
+            (Position::new(0, 0), Some(Position::new(0, 5)))
+        );
+
+        // Both ends grow
+        buffer_lock
+            .prepend_at_position(&Position::new(0, 0), "A")
+            .expect("Failed to prepend");
+        buffer_lock
+            .prepend_at_position(&Position::new(0, 6), "B")
+            .expect("Failed to prepend");
+
+        assert_eq!(
+            extent(&buffer_lock),
+            (Position::new(0, 0), Some(Position::new(0, 7)))
+        );
+
+        mark.write(&mut *buffer_lock)
+            .set_gravity(Gravity::None)
+            .expect("Failed to set gravity");
+
+        // Neither end grows
+        buffer_lock
+            .prepend_at_position(&Position::new(0, 0), "C")
+            .expect("Failed to prepend");
+        buffer_lock
+            .prepend_at_position(&Position::new(0, 8), "D")
+            .expect("Failed to prepend");
+
+        assert_eq!(
+            extent(&buffer_lock),
+            (Position::new(0, 1), Some(Position::new(0, 8)))
+        );
+
+        mark.write(&mut *buffer_lock)
+            .set_position(&Position::new(0, 2))
+            .expect("Failed to set position");
+
+        assert_eq!(
+            extent(&buffer_lock),
+            (Position::new(0, 2), Some(Position::new(0, 8)))
+        );
+    }
+
+    pub fn test_mark_transform_range<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line");
+        let mut buffer_lock = buffer.write();
+
+        let marks = [
+            (0, 6, Gravity::Left),
+            (0, 6, Gravity::Right),
+            (0, 10, Gravity::Left),
+        ]
+        .map(|(row, col, gravity)| {
+            Mark::new_with(
+                &buffer,
+                &Position::new(row, col),
+                None,
+                gravity,
+                &mut *buffer_lock,
+            )
+            .expect("Failed to create mark")
+        });
+
+        let end = buffer_lock
+            .transform_range(&Position::new(0, 6), &Position::new(0, 10), |text| {
+                format!("{}!!", text.to_uppercase())
+            })
+            .expect("Failed to transform range");
+
+        assert_eq!(end, Position::new(0, 12));
+        assert_eq!(
+            buffer_lock.get_content().expect("Failed to get content"),
+            "First LINE!!"
+        );
+
+        let positions = marks.map(|mark| {
+            mark.read(&*buffer_lock)
+                .get_position()
+                .expect("Failed to get position")
+        });
+
+        assert_eq!(
+            positions,
+            [
+                Position::new(0, 6),
+                Position::new(0, 6),
+                Position::new(0, 12)
+            ]
+        );
+    }
+
+    pub fn test_mark_watch<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let timeout = std::time::Duration::from_millis(500);
+
+        let buffer = new_buffer_with_content(&editor, "First line");
+        let mark = Mark::lock_new(&buffer, &Position::new(0, 6)).expect("Failed to create mark");
+
+        let positions = mark.watch();
+        assert_eq!(
+            positions
+                .recv_timeout(timeout)
+                .expect("No initial position"),
+            Position::new(0, 6)
+        );
+
+        buffer
+            .write()
+            .prepend_at_position(&Position::new(0, 0), "My ")
+            .expect("Failed to prepend");
+
+        assert_eq!(
+            positions.recv_timeout(timeout).expect("No position update"),
+            Position::new(0, 9)
+        );
+
+        // Changes after the mark don't move it
+        buffer.write().append("!").expect("Failed to append");
+        buffer
+            .write()
+            .prepend_at_position(&Position::new(0, 0), "\n")
+            .expect("Failed to prepend");
+
+        assert_eq!(
+            positions.recv_timeout(timeout).expect("No position update"),
+            Position::new(1, 9)
+        );
+    }
+
     #[macro_export]
     macro_rules! eel_mark_tests {
-        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
-            $crate::eel_tests!(
-                test_tag: $test_tag,
-                editor_factory: $editor_factory,
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
                 editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
                 module_path: $crate::mark::tests,
-                prefix: $prefix,
                 tests: [
                     test_mark_basic,
                     test_mark_set_text,
                     test_mark_gravity_right,
                     test_mark_gravity_left,
+                    test_mark_extent,
+                    test_mark_delete_collapse,
+                    test_mark_delete_invalidate,
+                    test_mark_transform_range,
+                    test_mark_watch,
                 ],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_mark_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_mark_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
             );
         };
 
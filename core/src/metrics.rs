@@ -0,0 +1,426 @@
+//! Counters and histograms of what eel is doing, for monitoring editors embedded in automation.
+//!
+//! Everything is recorded into [`Metrics::global`], which can be read with
+//! [`Metrics::snapshot`], rendered in the Prometheus text format with [`Metrics::render`] or
+//! served over HTTP with [`serve`].
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        OnceLock,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use crate::Result;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to start metrics server: {0}")]
+    Serve(#[from] std::io::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Upper bounds of the [`Histogram`] buckets.
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_micros(100),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+/// Latencies counted in the [`LATENCY_BUCKETS`].
+#[derive(Debug, Default)]
+pub struct Histogram {
+    /// Not cumulative, the last one counts the latencies above all bounds
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Every bucket bound with the number of latencies up to it, like Prometheus buckets.
+    pub buckets: Vec<(Duration, u64)>,
+    pub count: u64,
+    pub sum: Duration,
+}
+
+impl Histogram {
+    pub fn observe(&self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS.partition_point(|&bound| bound < latency);
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Observes the time until the returned guard is dropped.
+    pub fn start_timer(&self) -> Timer<'_> {
+        Timer {
+            histogram: self,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut count = 0;
+
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&bound, bucket)| {
+                count += bucket.load(Ordering::Relaxed);
+                (bound, count)
+            })
+            .collect();
+
+        count += self.buckets[LATENCY_BUCKETS.len()].load(Ordering::Relaxed);
+
+        HistogramSnapshot {
+            buckets,
+            count,
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Timer<'a> {
+    histogram: &'a Histogram,
+    started: Instant,
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        self.histogram.observe(self.started.elapsed());
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Changes the backends notified the [change hooks](crate::buffer::ChangeHooks) of.
+    pub edits_applied: Counter,
+    /// Time dispatched functions waited for the main thread.
+    pub dispatch_latency: Histogram,
+    /// Time spent waiting for buffer locks.
+    pub lock_wait: Histogram,
+    pub marks_alive: Gauge,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub edits_applied: u64,
+    pub dispatch_latency: HistogramSnapshot,
+    pub lock_wait: HistogramSnapshot,
+    pub marks_alive: i64,
+}
+
+fn render_histogram(out: &mut String, name: &str, help: &str, histogram: &HistogramSnapshot) {
+    _ = writeln!(out, "# HELP {name} {help}");
+    _ = writeln!(out, "# TYPE {name} histogram");
+
+    for (bound, count) in &histogram.buckets {
+        _ = writeln!(
+            out,
+            "{name}_bucket{{le=\"{}\"}} {count}",
+            bound.as_secs_f64()
+        );
+    }
+
+    _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count);
+    _ = writeln!(out, "{name}_sum {}", histogram.sum.as_secs_f64());
+    _ = writeln!(out, "{name}_count {}", histogram.count);
+}
+
+impl Metrics {
+    pub fn global() -> &'static Metrics {
+        static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+        METRICS.get_or_init(Metrics::default)
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            edits_applied: self.edits_applied.get(),
+            dispatch_latency: self.dispatch_latency.snapshot(),
+            lock_wait: self.lock_wait.snapshot(),
+            marks_alive: self.marks_alive.get(),
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        _ = writeln!(
+            out,
+            "# HELP eel_edits_applied_total Buffer changes applied."
+        );
+        _ = writeln!(out, "# TYPE eel_edits_applied_total counter");
+        _ = writeln!(out, "eel_edits_applied_total {}", snapshot.edits_applied);
+
+        render_histogram(
+            &mut out,
+            "eel_dispatch_latency_seconds",
+            "Time dispatched functions waited for the main thread.",
+            &snapshot.dispatch_latency,
+        );
+        render_histogram(
+            &mut out,
+            "eel_lock_wait_seconds",
+            "Time spent waiting for buffer locks.",
+            &snapshot.lock_wait,
+        );
+
+        _ = writeln!(out, "# HELP eel_marks_alive Marks currently alive.");
+        _ = writeln!(out, "# TYPE eel_marks_alive gauge");
+        _ = writeln!(out, "eel_marks_alive {}", snapshot.marks_alive);
+
+        out
+    }
+}
+
+/// Connections are served one at a time, so slow or endless requests are cut off.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+fn respond(stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_HEAD));
+
+    // Whatever the path, every request gets the metrics
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let body = Metrics::global().render();
+    let mut stream = stream;
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Serves [`Metrics::render`] over HTTP on `listener`, from a background thread running as
+/// long as the process.
+pub fn serve(listener: TcpListener) -> Result<()> {
+    debug!("Serving metrics on {:?}", listener.local_addr());
+
+    std::thread::Builder::new()
+        .name("eel-metrics".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                if let Err(e) = stream.and_then(respond) {
+                    warn!("Failed to serve metrics: {e}");
+                }
+            }
+        })
+        .map_err(Error::from)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_metrics_recorded(editor: impl Editor) {
+        let metrics = Metrics::global();
+        let before = metrics.snapshot();
+
+        let buffer = new_buffer_with_content(&editor, "metrics");
+        buffer
+            .write()
+            .set_content("metrics\nrecorded")
+            .expect("Failed to set content");
+
+        // Other tests record concurrently, only growth is certain
+        let after = metrics.snapshot();
+        assert!(after.edits_applied > before.edits_applied);
+        assert!(after.lock_wait.count > before.lock_wait.count);
+    }
+
+    #[cfg(feature = "mark")]
+    pub fn test_metrics_marks_alive<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        use crate::mark::Mark;
+
+        let buffer = new_buffer_with_content(&editor, "marks");
+        let mark =
+            Mark::lock_new(&buffer, &crate::Position::new(0, 0)).expect("Failed to create mark");
+
+        assert!(Metrics::global().marks_alive.get() > 0);
+        drop(mark);
+    }
+
+    #[macro_export]
+    macro_rules! eel_metrics_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::metrics::tests,
+                prefix: $prefix,
+                tests: [test_metrics_recorded],
+            );
+            $crate::eel_metrics_mark_tests!($test_tag, $editor_factory, $prefix);
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_metrics_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[doc(hidden)]
+    #[macro_export]
+    #[cfg(feature = "mark")]
+    macro_rules! eel_metrics_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::metrics::tests,
+                prefix: $prefix,
+                tests: [test_metrics_marks_alive],
+            );
+        };
+    }
+
+    #[doc(hidden)]
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_metrics_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {};
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let histogram = Histogram::default();
+
+        histogram.observe(Duration::from_micros(50));
+        histogram.observe(Duration::from_millis(1));
+        histogram.observe(Duration::from_secs(10));
+        drop(histogram.start_timer());
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 4);
+        assert_eq!(snapshot.buckets[0].1, 2);
+        assert_eq!(snapshot.buckets[2], (Duration::from_millis(1), 3));
+        assert_eq!(snapshot.buckets.last(), Some(&(Duration::from_secs(5), 3)));
+        assert!(snapshot.sum >= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn serve_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("Failed to get address");
+        serve(listener).expect("Failed to serve");
+
+        let mut stream = TcpStream::connect(addr).expect("Failed to connect");
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").expect("Failed to send");
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .expect("Failed to read response");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("\r\n\r\n# HELP eel_edits_applied_total "));
+        assert!(response.contains("\n# TYPE eel_edits_applied_total counter\n"));
+        assert!(response.contains("eel_lock_wait_seconds_bucket{le=\"0.001\"} "));
+        assert!(response.contains("\neel_marks_alive "));
+    }
+
+    #[test]
+    fn stalled_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("Failed to get address");
+        serve(listener).expect("Failed to serve");
+
+        // Never finishes its request, it's cut off instead of blocking the others
+        let mut stalled = TcpStream::connect(addr).expect("Failed to connect");
+        write!(stalled, "GET /metrics HTTP/1.1\r\n").expect("Failed to send");
+
+        let started = Instant::now();
+        let mut stream = TcpStream::connect(addr).expect("Failed to connect");
+        write!(stream, "GET / HTTP/1.1\r\n\r\n").expect("Failed to send");
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .expect("Failed to read response");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(started.elapsed() < REQUEST_TIMEOUT * 2);
+    }
+}
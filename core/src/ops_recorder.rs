@@ -0,0 +1,1749 @@
+//! Recording of buffer operations made through an editor, so a reported state can be reproduced
+//! by replaying them on another editor.
+//!
+//! [`RecordingEditor`] wraps the editor the problem happens in, [`replay`] applies the
+//! [`Recording`] to e.g. a test editor and [`History`] steps through it op by op.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    ops::RangeBounds,
+    path::Path,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    Capabilities, Editor, PosRange, Position, Result,
+    buffer::{
+        BufferData, BufferHandle, ReadBuffer, ReadBufferLock, WeakBufferHandle, WriteBuffer,
+        WriteBufferLock,
+    },
+    indent::IndentSettings,
+    journal::{JournaledBufferHandle, TextEdit},
+    tracing::ResultExt as _,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Recording IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Recording format error: {0}")]
+    Format(#[from] serde_json::Error),
+
+    #[error("Recording uses buffer {0} before opening it")]
+    UnknownBuffer(usize),
+
+    #[error("Recording uses mark {mark} of buffer {buffer} before creating it")]
+    UnknownMark { buffer: usize, mark: usize },
+
+    #[error("Journal of buffer {0} dropped the replayed edit")]
+    MissingEdit(usize),
+}
+
+/// Operation made through a [`RecordingEditor`]. Buffers and marks are numbered in the order
+/// they're first used.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    /// First use of a buffer, with its content at that point.
+    Open { buffer: usize, content: String },
+    SetText {
+        buffer: usize,
+        start: Position,
+        end: Position,
+        text: String,
+    },
+    #[cfg(feature = "cursor")]
+    SetCursor { buffer: usize, position: Position },
+    #[cfg(feature = "cursor")]
+    PushJump { buffer: usize },
+    /// Marks created before the recording started are recorded when first used, with
+    /// [`Gravity::Right`](crate::mark::Gravity::Right) as their gravity isn't known.
+    #[cfg(feature = "mark")]
+    CreateMark {
+        buffer: usize,
+        mark: usize,
+        start: Position,
+        end: Option<Position>,
+        gravity: crate::mark::Gravity,
+    },
+    #[cfg(feature = "mark")]
+    DestroyMark { buffer: usize, mark: usize },
+    #[cfg(feature = "mark")]
+    SetMarkPosition {
+        buffer: usize,
+        mark: usize,
+        position: Position,
+    },
+    #[cfg(feature = "mark")]
+    SetMarkGravity {
+        buffer: usize,
+        mark: usize,
+        gravity: crate::mark::Gravity,
+    },
+}
+
+/// Ops in the order they were made, saved as one JSON object per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub ops: Vec<Op>,
+}
+
+impl Recording {
+    /// Also reads files a [`RecordingEditor::to_file`] was still writing, as every op is on its
+    /// own line. A last line cut off mid-write is ignored.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(Error::from)?;
+
+        let mut lines = content.lines().collect::<Vec<_>>();
+        let partial = if content.ends_with('\n') {
+            None
+        } else {
+            lines.pop()
+        };
+
+        let mut ops = lines
+            .into_iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line).map_err(Error::from)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(op) = partial.and_then(|line| serde_json::from_str(line).ok()) {
+            ops.push(op);
+        }
+
+        Ok(Self { ops })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path).map_err(Error::from)?;
+
+        for op in &self.ops {
+            write_op(&mut file, op)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_op(file: &mut File, op: &Op) -> std::result::Result<(), Error> {
+    let mut line = serde_json::to_vec(op)?;
+    line.push(b'\n');
+
+    Ok(file.write_all(&line)?)
+}
+
+/// Recording numbers of a buffer by recorder, kept in its [`BufferData`] so all handles to it
+/// share them.
+#[derive(Default)]
+struct RecordedBuffer {
+    ids: Mutex<std::collections::HashMap<u64, usize>>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    ops: Vec<Op>,
+    file: Option<File>,
+    buffers: usize,
+    #[cfg(feature = "mark")]
+    marks: mark::RecordedMarks,
+}
+
+struct Recorder {
+    /// Tells the buffers of different recorders apart.
+    id: u64,
+    state: Mutex<RecorderState>,
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("ops", &self.state().ops.len())
+            .finish()
+    }
+}
+
+impl Recorder {
+    fn new(file: Option<File>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            state: Mutex::new(RecorderState {
+                file,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, RecorderState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, op: Op) {
+        let mut state = self.state();
+
+        if let Some(file) = &mut state.file {
+            _ = write_op(file, &op).log_err_msg("Failed to write recorded op");
+        }
+
+        state.ops.push(op);
+    }
+
+    /// Number of the buffer, recording its content if it's used for the first time.
+    fn buffer_id(&self, handle: &impl BufferHandle, buffer: &impl ReadBuffer) -> Result<usize> {
+        let recorded = handle.data().get_or_insert_with(RecordedBuffer::default);
+
+        // Held until the open is recorded, so other handles don't number the buffer again or
+        // record ops on it before it
+        let mut ids = recorded.ids.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(id) = ids.get(&self.id) {
+            return Ok(*id);
+        }
+
+        let content = buffer.get_content()?;
+
+        let id = {
+            let mut state = self.state();
+            state.buffers += 1;
+            state.buffers - 1
+        };
+
+        ids.insert(self.id, id);
+        self.record(Op::Open {
+            buffer: id,
+            content,
+        });
+
+        Ok(id)
+    }
+}
+
+/// Wraps any [`Editor`], recording the operations made on its buffers.
+///
+/// Only operations made through this editor's handles are recorded.
+#[derive(Debug)]
+pub struct RecordingEditor<E: Editor> {
+    inner: E,
+    recorder: Arc<Recorder>,
+}
+
+impl<E: Editor> RecordingEditor<E> {
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            recorder: Arc::new(Recorder::new(None)),
+        }
+    }
+
+    /// Also writes every op to `path` as it's made, so the file is usable even if the process
+    /// dies afterwards.
+    pub fn to_file(inner: E, path: &Path) -> Result<Self> {
+        let file = File::create(path).map_err(Error::from)?;
+
+        Ok(Self {
+            inner,
+            recorder: Arc::new(Recorder::new(Some(file))),
+        })
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Ops recorded so far.
+    pub fn recording(&self) -> Recording {
+        Recording {
+            ops: self.recorder.state().ops.clone(),
+        }
+    }
+
+    /// Ops recorded so far replayed on new buffers of `target`, positioned after the last one.
+    ///
+    /// The replayed buffers stay in `target` once the history is dropped, so it should be an
+    /// editor kept for that rather than the one being recorded.
+    pub fn history<'t, T>(&self, target: &'t T) -> Result<History<'t, T>>
+    where
+        T: Editor,
+        T::BufferHandle: ReplayBufferHandle,
+    {
+        let mut history = History::new(target, self.recording());
+        history.seek(history.len())?;
+
+        Ok(history)
+    }
+
+    fn wrap(&self, inner: E::BufferHandle) -> RecordingBufferHandle<E::BufferHandle> {
+        RecordingBufferHandle {
+            inner,
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<E: Editor> Editor for RecordingEditor<E> {
+    type BufferHandle = RecordingBufferHandle<E::BufferHandle>;
+
+    fn current_buffer(&self) -> Result<Self::BufferHandle> {
+        Ok(self.wrap(self.inner.current_buffer()?))
+    }
+
+    fn new_buffer(&self) -> Result<Self::BufferHandle> {
+        Ok(self.wrap(self.inner.new_buffer()?))
+    }
+
+    fn set_current_buffer(
+        &self,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+    ) -> Result<()> {
+        self.inner.set_current_buffer(&mut buffer.buffer_lock)
+    }
+
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>> {
+        Ok(self
+            .inner
+            .buffers()?
+            .into_iter()
+            .map(|b| self.wrap(b))
+            .collect())
+    }
+
+    fn buffer_name(&self, buffer: &Self::BufferHandle) -> Result<Option<String>> {
+        self.inner.buffer_name(&buffer.inner)
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>> {
+        Ok(self.inner.buffer_by_name(name)?.map(|b| self.wrap(b)))
+    }
+
+    fn buffer_by_path(&self, path: &Path) -> Result<Option<Self::BufferHandle>> {
+        Ok(self.inner.buffer_by_path(path)?.map(|b| self.wrap(b)))
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<crate::OptionValue>> {
+        self.inner.get_option(name)
+    }
+
+    fn set_option(&self, name: &str, value: crate::OptionValue) -> Result<()> {
+        self.inner.set_option(name, value)
+    }
+
+    fn on_idle(
+        &self,
+        delay: std::time::Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.inner.on_idle(delay, callback)
+    }
+
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        self.inner.tasks()
+    }
+
+    #[cfg(feature = "ui")]
+    fn prompt(&self, spec: &crate::ui::PromptSpec) -> Result<crate::ui::UserResponse> {
+        self.inner.prompt(spec)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.inner.jump_back()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.inner.jump_forward()
+    }
+
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.inner.execute_normal(keys)
+    }
+
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        self.inner.detect_filetype(path, content)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingBufferHandle<B: BufferHandle> {
+    inner: B,
+    recorder: Arc<Recorder>,
+}
+
+impl<B: BufferHandle> PartialEq for RecordingBufferHandle<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<B: BufferHandle> Eq for RecordingBufferHandle<B> {}
+
+impl<B: BufferHandle> RecordingBufferHandle<B> {
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    fn wrap<L>(&self, buffer_lock: L) -> Box<RecordingBuffer<B, L>> {
+        Box::new(RecordingBuffer {
+            buffer_lock,
+            handle: self.inner.clone(),
+            recorder: self.recorder.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingWeakBufferHandle<B: BufferHandle> {
+    inner: B::WeakHandle,
+    recorder: Arc<Recorder>,
+}
+
+impl<B: BufferHandle> WeakBufferHandle for RecordingWeakBufferHandle<B> {
+    type Handle = RecordingBufferHandle<B>;
+
+    fn upgrade(&self) -> Option<Self::Handle> {
+        Some(RecordingBufferHandle {
+            inner: self.inner.upgrade()?,
+            recorder: self.recorder.clone(),
+        })
+    }
+}
+
+pub struct RecordingBuffer<B, L> {
+    buffer_lock: L,
+    handle: B,
+    recorder: Arc<Recorder>,
+}
+
+impl<B: BufferHandle, L: ReadBufferLock> RecordingBuffer<B, L> {
+    fn buffer_id(&self) -> Result<usize> {
+        self.recorder.buffer_id(&self.handle, &*self.buffer_lock)
+    }
+}
+
+impl<B: BufferHandle, L: ReadBufferLock> ReadBuffer for RecordingBuffer<B, L> {
+    fn line_count(&self) -> Result<usize> {
+        self.buffer_lock.line_count()
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        self.buffer_lock.get_lines(range)
+    }
+
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        self.buffer_lock.get_text_in(range)
+    }
+
+    fn line_len(&self, row: usize) -> Result<usize> {
+        self.buffer_lock.line_len(row)
+    }
+
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        self.buffer_lock.char_at(position)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.buffer_lock.changedtick()
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        self.buffer_lock.indent_settings()
+    }
+}
+
+impl<B: BufferHandle, L: WriteBufferLock> RecordingBuffer<B, L> {
+    fn record_set_text(&self, buffer: usize, range: &PosRange, text: &str) {
+        self.recorder.record(Op::SetText {
+            buffer,
+            start: range.start().clone(),
+            end: range.end().clone(),
+            text: text.to_string(),
+        });
+    }
+}
+
+impl<B: BufferHandle, L: WriteBufferLock> WriteBuffer for RecordingBuffer<B, L> {
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        let buffer = self.buffer_id()?;
+
+        self.buffer_lock.set_text_in(range, text)?;
+        self.record_set_text(buffer, range, text);
+
+        Ok(())
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        let buffer = self.buffer_id()?;
+
+        self.buffer_lock
+            .set_text_if_unchanged(expected_tick, range, text)?;
+        self.record_set_text(buffer, range, text);
+
+        Ok(())
+    }
+}
+
+impl<B: BufferHandle> BufferHandle for RecordingBufferHandle<B> {
+    type ReadBuffer = RecordingBuffer<B, B::ReadBufferLock>;
+    type WriteBuffer = RecordingBuffer<B, B::WriteBufferLock>;
+    type ReadBufferLock = Box<Self::ReadBuffer>;
+    type WriteBufferLock = Box<Self::WriteBuffer>;
+    type WeakHandle = RecordingWeakBufferHandle<B>;
+
+    fn read(&self) -> Self::ReadBufferLock {
+        self.wrap(self.inner.read())
+    }
+
+    fn write(&self) -> Self::WriteBufferLock {
+        self.wrap(self.inner.write())
+    }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        Ok(self.wrap(self.inner.read_timeout()?))
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        Ok(self.wrap(self.inner.write_timeout()?))
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        self.wrap(self.inner.downgrade_lock(lock.buffer_lock))
+    }
+
+    fn data(&self) -> &BufferData {
+        self.inner.data()
+    }
+
+    fn downgrade(&self) -> Self::WeakHandle {
+        RecordingWeakBufferHandle {
+            inner: self.inner.downgrade(),
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.inner.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.inner.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.inner.changedtick()
+    }
+}
+
+#[cfg(feature = "cursor")]
+mod cursor {
+    use super::*;
+
+    use crate::cursor::{CursorBufferHandle, CursorReadBuffer, CursorWriteBuffer};
+
+    impl<B, L> CursorReadBuffer for RecordingBuffer<B, L>
+    where
+        B: BufferHandle,
+        L: ReadBufferLock,
+        L::ReadBuffer: CursorReadBuffer,
+    {
+        fn get_cursor(&self) -> Result<Position> {
+            self.buffer_lock.get_cursor()
+        }
+    }
+
+    impl<B, L> CursorWriteBuffer for RecordingBuffer<B, L>
+    where
+        B: BufferHandle,
+        L: WriteBufferLock,
+        L::WriteBuffer: CursorWriteBuffer,
+    {
+        fn set_cursor(&mut self, position: &Position) -> Result<()> {
+            let buffer = self.buffer_id()?;
+
+            self.buffer_lock.set_cursor(position)?;
+            self.recorder.record(Op::SetCursor {
+                buffer,
+                position: position.clone(),
+            });
+
+            Ok(())
+        }
+
+        fn push_jump(&mut self) -> Result<()> {
+            let buffer = self.buffer_id()?;
+
+            self.buffer_lock.push_jump()?;
+            self.recorder.record(Op::PushJump { buffer });
+
+            Ok(())
+        }
+    }
+
+    pub trait CursorReplay {
+        fn replay_set_cursor(&self, position: &Position) -> Result<()>;
+        fn replay_push_jump(&self) -> Result<()>;
+        fn replay_cursor(&self) -> Result<Position>;
+    }
+
+    impl<B: CursorBufferHandle> CursorReplay for B {
+        fn replay_set_cursor(&self, position: &Position) -> Result<()> {
+            self.write_timeout()?.set_cursor(position)
+        }
+
+        fn replay_push_jump(&self) -> Result<()> {
+            self.write_timeout()?.push_jump()
+        }
+
+        fn replay_cursor(&self) -> Result<Position> {
+            self.read_timeout()?.get_cursor()
+        }
+    }
+}
+
+#[cfg(not(feature = "cursor"))]
+mod cursor {
+    pub trait CursorReplay {}
+
+    impl<T> CursorReplay for T {}
+}
+
+#[cfg(feature = "mark")]
+mod mark {
+    use std::any::Any;
+
+    use super::*;
+
+    use crate::mark::{Gravity, MarkBufferHandle, MarkId, MarkReadBuffer, MarkWriteBuffer};
+
+    /// Recording numbers of the backends' marks, `MarkId`s are only comparable within their
+    /// own type.
+    #[derive(Default)]
+    pub(super) struct RecordedMarks {
+        next: usize,
+        marks: Vec<(usize, Box<dyn Any + Send>, usize)>,
+    }
+
+    impl RecordedMarks {
+        fn get<I: MarkId>(&self, buffer: usize, id: I) -> Option<usize> {
+            self.marks.iter().find_map(|(b, i, mark)| {
+                (*b == buffer && i.downcast_ref::<I>() == Some(&id)).then_some(*mark)
+            })
+        }
+
+        fn insert<I: MarkId>(&mut self, buffer: usize, id: I) -> usize {
+            let mark = self.next;
+            self.next += 1;
+            self.marks.push((buffer, Box::new(id), mark));
+
+            mark
+        }
+
+        fn remove<I: MarkId>(&mut self, buffer: usize, id: I) {
+            self.marks
+                .retain(|(b, i, _)| !(*b == buffer && i.downcast_ref::<I>() == Some(&id)));
+        }
+    }
+
+    impl<B, L> RecordingBuffer<B, L>
+    where
+        B: BufferHandle,
+        L: ReadBufferLock,
+        L::ReadBuffer: MarkReadBuffer,
+    {
+        /// Number of the mark, recording it at its current position if it was created
+        /// before the recording started.
+        fn mark_id(
+            &self,
+            buffer: usize,
+            id: <L::ReadBuffer as MarkReadBuffer>::MarkId,
+        ) -> Result<usize> {
+            if let Some(mark) = self.recorder.state().marks.get(buffer, id) {
+                return Ok(mark);
+            }
+
+            let start = self.buffer_lock.get_mark_position(id)?;
+            let end = self.buffer_lock.get_mark_end(id)?;
+
+            Ok(self.record_new_mark(buffer, id, start, end, Gravity::Right))
+        }
+
+        fn record_new_mark(
+            &self,
+            buffer: usize,
+            id: <L::ReadBuffer as MarkReadBuffer>::MarkId,
+            start: Position,
+            end: Option<Position>,
+            gravity: Gravity,
+        ) -> usize {
+            let mark = self.recorder.state().marks.insert(buffer, id);
+
+            self.recorder.record(Op::CreateMark {
+                buffer,
+                mark,
+                start,
+                end,
+                gravity,
+            });
+
+            mark
+        }
+    }
+
+    impl<B, L> MarkReadBuffer for RecordingBuffer<B, L>
+    where
+        B: BufferHandle,
+        L: ReadBufferLock,
+        L::ReadBuffer: MarkReadBuffer,
+    {
+        type MarkId = <L::ReadBuffer as MarkReadBuffer>::MarkId;
+
+        fn get_mark_position(&self, id: Self::MarkId) -> Result<Position> {
+            self.buffer_lock.get_mark_position(id)
+        }
+
+        fn get_mark_end(&self, id: Self::MarkId) -> Result<Option<Position>> {
+            self.buffer_lock.get_mark_end(id)
+        }
+    }
+
+    impl<B, L> MarkWriteBuffer for RecordingBuffer<B, L>
+    where
+        B: BufferHandle,
+        L: WriteBufferLock,
+        L::WriteBuffer: MarkWriteBuffer,
+    {
+        fn create_mark(&mut self, pos: &Position) -> Result<Self::MarkId> {
+            let buffer = self.buffer_id()?;
+
+            let id = self.buffer_lock.create_mark(pos)?;
+            self.record_new_mark(buffer, id, pos.clone(), None, Gravity::Right);
+
+            Ok(id)
+        }
+
+        fn destroy_mark(&mut self, id: Self::MarkId) -> Result<()> {
+            let buffer = self.buffer_id()?;
+            let mark = self.mark_id(buffer, id)?;
+
+            self.buffer_lock.destroy_mark(id)?;
+            self.recorder.state().marks.remove(buffer, id);
+            self.recorder.record(Op::DestroyMark { buffer, mark });
+
+            Ok(())
+        }
+
+        fn create_mark_with(
+            &mut self,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<Self::MarkId> {
+            let buffer = self.buffer_id()?;
+
+            let id = self.buffer_lock.create_mark_with(start, end, gravity)?;
+            self.record_new_mark(buffer, id, start.clone(), end.cloned(), gravity);
+
+            Ok(id)
+        }
+
+        fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()> {
+            let buffer = self.buffer_id()?;
+            let mark = self.mark_id(buffer, id)?;
+
+            self.buffer_lock.set_mark_position(id, pos)?;
+            self.recorder.record(Op::SetMarkPosition {
+                buffer,
+                mark,
+                position: pos.clone(),
+            });
+
+            Ok(())
+        }
+
+        fn set_mark_gravity(&mut self, id: Self::MarkId, gravity: Gravity) -> Result<()> {
+            let buffer = self.buffer_id()?;
+            let mark = self.mark_id(buffer, id)?;
+
+            self.buffer_lock.set_mark_gravity(id, gravity)?;
+            self.recorder.record(Op::SetMarkGravity {
+                buffer,
+                mark,
+                gravity,
+            });
+
+            Ok(())
+        }
+    }
+
+    pub trait MarkReplay {
+        type ReplayMark: MarkId;
+
+        fn replay_create_mark(
+            &self,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<Self::ReplayMark>;
+        fn replay_destroy_mark(&self, mark: Self::ReplayMark) -> Result<()>;
+        fn replay_set_mark_position(&self, mark: Self::ReplayMark, pos: &Position) -> Result<()>;
+        fn replay_set_mark_gravity(&self, mark: Self::ReplayMark, gravity: Gravity) -> Result<()>;
+        fn replay_mark_position(&self, mark: Self::ReplayMark) -> Result<Position>;
+        fn replay_mark_end(&self, mark: Self::ReplayMark) -> Result<Option<Position>>;
+    }
+
+    impl<B: MarkBufferHandle> MarkReplay for B {
+        type ReplayMark = B::MarkId;
+
+        fn replay_create_mark(
+            &self,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<Self::ReplayMark> {
+            self.write_timeout()?.create_mark_with(start, end, gravity)
+        }
+
+        fn replay_destroy_mark(&self, mark: Self::ReplayMark) -> Result<()> {
+            self.write_timeout()?.destroy_mark(mark)
+        }
+
+        fn replay_set_mark_position(&self, mark: Self::ReplayMark, pos: &Position) -> Result<()> {
+            self.write_timeout()?.set_mark_position(mark, pos)
+        }
+
+        fn replay_set_mark_gravity(&self, mark: Self::ReplayMark, gravity: Gravity) -> Result<()> {
+            self.write_timeout()?.set_mark_gravity(mark, gravity)
+        }
+
+        fn replay_mark_position(&self, mark: Self::ReplayMark) -> Result<Position> {
+            self.read_timeout()?.get_mark_position(mark)
+        }
+
+        fn replay_mark_end(&self, mark: Self::ReplayMark) -> Result<Option<Position>> {
+            self.read_timeout()?.get_mark_end(mark)
+        }
+    }
+}
+
+#[cfg(not(feature = "mark"))]
+mod mark {
+    pub trait MarkReplay {}
+
+    impl<T> MarkReplay for T {}
+}
+
+pub use cursor::CursorReplay;
+pub use mark::MarkReplay;
+
+/// Buffers a [`Recording`] can be replayed on, covering whatever features are enabled.
+pub trait ReplayBufferHandle: BufferHandle + CursorReplay + MarkReplay {}
+
+impl<B> ReplayBufferHandle for B where B: BufferHandle + CursorReplay + MarkReplay {}
+
+/// Applies ops to buffers of an editor one at a time, see [`replay`] and [`History`].
+pub struct Replayer<'a, E>
+where
+    E: Editor,
+    E::BufferHandle: ReplayBufferHandle,
+{
+    editor: &'a E,
+    /// Buffers opened so far and their content when opened.
+    buffers: BTreeMap<usize, (E::BufferHandle, String)>,
+    /// Buffers kept by [`Replayer::reset`] to be reused when opened again.
+    spare: BTreeMap<usize, (E::BufferHandle, String)>,
+    #[cfg(feature = "mark")]
+    marks: std::collections::HashMap<(usize, usize), <E::BufferHandle as MarkReplay>::ReplayMark>,
+}
+
+impl<'a, E> Replayer<'a, E>
+where
+    E: Editor,
+    E::BufferHandle: ReplayBufferHandle,
+{
+    pub fn new(editor: &'a E) -> Self {
+        Self {
+            editor,
+            buffers: BTreeMap::new(),
+            spare: BTreeMap::new(),
+            #[cfg(feature = "mark")]
+            marks: Default::default(),
+        }
+    }
+
+    /// Buffers opened so far, in the order they were opened in the recording.
+    pub fn buffers(&self) -> Vec<E::BufferHandle> {
+        self.buffers.values().map(|(b, _)| b.clone()).collect()
+    }
+
+    /// Buffer the recording numbered `buffer`.
+    pub fn buffer(&self, buffer: usize) -> Option<&E::BufferHandle> {
+        self.buffers.get(&buffer).map(|(b, _)| b)
+    }
+
+    fn get(&self, buffer: usize) -> Result<&E::BufferHandle> {
+        Ok(self.buffer(buffer).ok_or(Error::UnknownBuffer(buffer))?)
+    }
+
+    /// Mark the recording numbered `mark` in `buffer`.
+    #[cfg(feature = "mark")]
+    pub fn mark(
+        &self,
+        buffer: usize,
+        mark: usize,
+    ) -> Option<<E::BufferHandle as MarkReplay>::ReplayMark> {
+        self.marks.get(&(buffer, mark)).copied()
+    }
+
+    #[cfg(feature = "mark")]
+    fn get_mark(
+        &self,
+        buffer: usize,
+        mark: usize,
+    ) -> Result<<E::BufferHandle as MarkReplay>::ReplayMark> {
+        Ok(self
+            .mark(buffer, mark)
+            .ok_or(Error::UnknownMark { buffer, mark })?)
+    }
+
+    pub fn apply(&mut self, op: &Op) -> Result<()> {
+        match op {
+            Op::Open { buffer, content } => {
+                let handle = match self.spare.remove(buffer) {
+                    Some((handle, _)) => handle,
+                    None => self.editor.new_buffer()?,
+                };
+                handle.write_timeout()?.set_content(content)?;
+
+                self.buffers.insert(*buffer, (handle, content.clone()));
+            }
+            Op::SetText {
+                buffer,
+                start,
+                end,
+                text,
+            } => self
+                .get(*buffer)?
+                .write_timeout()?
+                .set_text((start, end), text)?,
+            #[cfg(feature = "cursor")]
+            Op::SetCursor { buffer, position } => self.get(*buffer)?.replay_set_cursor(position)?,
+            #[cfg(feature = "cursor")]
+            Op::PushJump { buffer } => self.get(*buffer)?.replay_push_jump()?,
+            #[cfg(feature = "mark")]
+            Op::CreateMark {
+                buffer,
+                mark,
+                start,
+                end,
+                gravity,
+            } => {
+                let id = self
+                    .get(*buffer)?
+                    .replay_create_mark(start, end.as_ref(), *gravity)?;
+
+                self.marks.insert((*buffer, *mark), id);
+            }
+            #[cfg(feature = "mark")]
+            Op::DestroyMark { buffer, mark } => {
+                let id = self.get_mark(*buffer, *mark)?;
+
+                self.get(*buffer)?.replay_destroy_mark(id)?;
+                self.marks.remove(&(*buffer, *mark));
+            }
+            #[cfg(feature = "mark")]
+            Op::SetMarkPosition {
+                buffer,
+                mark,
+                position,
+            } => self
+                .get(*buffer)?
+                .replay_set_mark_position(self.get_mark(*buffer, *mark)?, position)?,
+            #[cfg(feature = "mark")]
+            Op::SetMarkGravity {
+                buffer,
+                mark,
+                gravity,
+            } => self
+                .get(*buffer)?
+                .replay_set_mark_gravity(self.get_mark(*buffer, *mark)?, *gravity)?,
+        }
+
+        Ok(())
+    }
+
+    /// Goes back to before the first op: marks are destroyed and buffers get their content
+    /// from when they were opened back. The buffers are reused once opened again.
+    pub fn reset(&mut self) -> Result<()> {
+        #[cfg(feature = "mark")]
+        for ((buffer, _), id) in std::mem::take(&mut self.marks) {
+            self.get(buffer)?.replay_destroy_mark(id)?;
+        }
+
+        for (buffer, (handle, content)) in std::mem::take(&mut self.buffers) {
+            handle.write_timeout()?.set_content(&content)?;
+            #[cfg(feature = "cursor")]
+            handle.replay_set_cursor(&Position::origin())?;
+
+            self.spare.insert(buffer, (handle, content));
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies `recording` to new buffers of `editor`, returning them in the order they were
+/// opened in the recording.
+pub fn replay<E>(editor: &E, recording: &Recording) -> Result<Vec<E::BufferHandle>>
+where
+    E: Editor,
+    E::BufferHandle: ReplayBufferHandle,
+{
+    let mut replayer = Replayer::new(editor);
+
+    for op in &recording.ops {
+        replayer.apply(op)?;
+    }
+
+    Ok(replayer.buffers())
+}
+
+/// How to revert an op applied by a [`History`], captured right before applying it.
+enum Undo {
+    Open(usize),
+    /// Marks are moved back too, as ones in replaced text don't return by themselves.
+    SetText {
+        buffer: usize,
+        edit: TextEdit,
+        #[cfg(feature = "mark")]
+        marks: Vec<(usize, Position)>,
+    },
+    #[cfg(feature = "cursor")]
+    SetCursor {
+        buffer: usize,
+        position: Position,
+    },
+    /// Jumps can't be taken back out of the jumplist.
+    #[cfg(feature = "cursor")]
+    PushJump,
+    #[cfg(feature = "mark")]
+    CreateMark {
+        buffer: usize,
+        mark: usize,
+    },
+    #[cfg(feature = "mark")]
+    DestroyMark {
+        buffer: usize,
+        mark: usize,
+        start: Position,
+        end: Option<Position>,
+        gravity: crate::mark::Gravity,
+    },
+    #[cfg(feature = "mark")]
+    SetMarkPosition {
+        buffer: usize,
+        mark: usize,
+        position: Position,
+    },
+    #[cfg(feature = "mark")]
+    SetMarkGravity {
+        buffer: usize,
+        mark: usize,
+        gravity: crate::mark::Gravity,
+    },
+}
+
+/// Steps through a [`Recording`] replayed on an editor, to find the op that broke something.
+///
+/// Text edits go through the buffers' [`Journal`](crate::journal::Journal) and stepping back
+/// applies their inverse, other ops remember what they changed. Jumps pushed to the jumplist
+/// stay there.
+pub struct History<'a, E>
+where
+    E: Editor,
+    E::BufferHandle: ReplayBufferHandle,
+{
+    replayer: Replayer<'a, E>,
+    recording: Recording,
+    /// One entry per applied op.
+    undo: Vec<Undo>,
+    #[cfg(feature = "mark")]
+    gravities: std::collections::HashMap<(usize, usize), crate::mark::Gravity>,
+}
+
+impl<'a, E> History<'a, E>
+where
+    E: Editor,
+    E::BufferHandle: ReplayBufferHandle,
+{
+    /// Starts before the first op.
+    pub fn new(editor: &'a E, recording: Recording) -> Self {
+        Self {
+            replayer: Replayer::new(editor),
+            recording,
+            undo: Vec::new(),
+            #[cfg(feature = "mark")]
+            gravities: Default::default(),
+        }
+    }
+
+    /// Number of ops applied.
+    pub fn position(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.recording.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recording.ops.is_empty()
+    }
+
+    pub fn replayer(&self) -> &Replayer<'a, E> {
+        &self.replayer
+    }
+
+    /// Buffers opened by the applied ops.
+    pub fn buffers(&self) -> Vec<E::BufferHandle> {
+        self.replayer.buffers()
+    }
+
+    /// Applies the next op and returns it, `None` at the end of the recording.
+    pub fn step_forward(&mut self) -> Result<Option<&Op>> {
+        let position = self.position();
+        let Some(op) = self.recording.ops.get(position).cloned() else {
+            return Ok(None);
+        };
+
+        let undo = self.apply(&op)?;
+        self.undo.push(undo);
+
+        Ok(self.recording.ops.get(position))
+    }
+
+    /// Reverts the last applied op and returns it, `None` at the start of the recording.
+    pub fn step_back(&mut self) -> Result<Option<&Op>> {
+        let Some(undo) = self.undo.pop() else {
+            return Ok(None);
+        };
+
+        if let Err(err) = self.revert(&undo) {
+            self.undo.push(undo);
+            return Err(err);
+        }
+
+        Ok(self.recording.ops.get(self.position()))
+    }
+
+    /// Moves to right after the first `position` ops, or the end of the recording.
+    pub fn seek(&mut self, position: usize) -> Result<()> {
+        let position = position.min(self.len());
+
+        while self.position() > position {
+            self.step_back()?;
+        }
+
+        while self.position() < position {
+            self.step_forward()?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mark")]
+    fn mark_positions(&self, buffer: usize) -> Result<Vec<(usize, Position)>> {
+        let handle = self.replayer.get(buffer)?;
+
+        self.replayer
+            .marks
+            .iter()
+            .filter(|((b, _), _)| *b == buffer)
+            .map(|((_, mark), id)| Ok((*mark, handle.replay_mark_position(*id)?)))
+            .collect()
+    }
+
+    fn apply(&mut self, op: &Op) -> Result<Undo> {
+        let undo = match op {
+            Op::Open { buffer, .. } => Undo::Open(*buffer),
+            Op::SetText {
+                buffer,
+                start,
+                end,
+                text,
+            } => {
+                #[cfg(feature = "mark")]
+                let marks = self.mark_positions(*buffer)?;
+
+                let handle = JournaledBufferHandle::new(self.replayer.get(*buffer)?.clone());
+                let mut lock = handle.write_timeout()?;
+                let version = handle.version();
+                lock.set_text((start, end), text)?;
+
+                let edit = handle
+                    .changes_since(version)
+                    .and_then(|edits| edits.into_iter().next())
+                    .ok_or(Error::MissingEdit(*buffer))?;
+
+                return Ok(Undo::SetText {
+                    buffer: *buffer,
+                    edit,
+                    #[cfg(feature = "mark")]
+                    marks,
+                });
+            }
+            #[cfg(feature = "cursor")]
+            Op::SetCursor { buffer, .. } => Undo::SetCursor {
+                buffer: *buffer,
+                position: self.replayer.get(*buffer)?.replay_cursor()?,
+            },
+            #[cfg(feature = "cursor")]
+            Op::PushJump { .. } => Undo::PushJump,
+            #[cfg(feature = "mark")]
+            Op::CreateMark {
+                buffer,
+                mark,
+                gravity,
+                ..
+            } => {
+                self.gravities.insert((*buffer, *mark), *gravity);
+
+                Undo::CreateMark {
+                    buffer: *buffer,
+                    mark: *mark,
+                }
+            }
+            #[cfg(feature = "mark")]
+            Op::DestroyMark { buffer, mark } => {
+                let handle = self.replayer.get(*buffer)?;
+                let id = self.replayer.get_mark(*buffer, *mark)?;
+
+                Undo::DestroyMark {
+                    buffer: *buffer,
+                    mark: *mark,
+                    start: handle.replay_mark_position(id)?,
+                    end: handle.replay_mark_end(id)?,
+                    gravity: self
+                        .gravities
+                        .get(&(*buffer, *mark))
+                        .copied()
+                        .unwrap_or(crate::mark::Gravity::Right),
+                }
+            }
+            #[cfg(feature = "mark")]
+            Op::SetMarkPosition { buffer, mark, .. } => Undo::SetMarkPosition {
+                buffer: *buffer,
+                mark: *mark,
+                position: self
+                    .replayer
+                    .get(*buffer)?
+                    .replay_mark_position(self.replayer.get_mark(*buffer, *mark)?)?,
+            },
+            #[cfg(feature = "mark")]
+            Op::SetMarkGravity {
+                buffer,
+                mark,
+                gravity,
+            } => {
+                let previous = self
+                    .gravities
+                    .insert((*buffer, *mark), *gravity)
+                    .unwrap_or(crate::mark::Gravity::Right);
+
+                Undo::SetMarkGravity {
+                    buffer: *buffer,
+                    mark: *mark,
+                    gravity: previous,
+                }
+            }
+        };
+
+        self.replayer.apply(op)?;
+
+        Ok(undo)
+    }
+
+    fn revert(&mut self, undo: &Undo) -> Result<()> {
+        match undo {
+            // Whatever the ops after it did is already reverted, the buffer has its content
+            // from when it was opened
+            Undo::Open(buffer) => {
+                if let Some(opened) = self.replayer.buffers.remove(buffer) {
+                    self.replayer.spare.insert(*buffer, opened);
+                }
+            }
+            Undo::SetText {
+                buffer,
+                edit,
+                #[cfg(feature = "mark")]
+                marks,
+            } => {
+                let handle = self.replayer.get(*buffer)?;
+                handle
+                    .write_timeout()?
+                    .set_text((&edit.start, &edit.new_end), &edit.old_text)?;
+
+                #[cfg(feature = "mark")]
+                for (mark, position) in marks {
+                    handle.replay_set_mark_position(
+                        self.replayer.get_mark(*buffer, *mark)?,
+                        position,
+                    )?;
+                }
+            }
+            #[cfg(feature = "cursor")]
+            Undo::SetCursor { buffer, position } => {
+                self.replayer.get(*buffer)?.replay_set_cursor(position)?
+            }
+            #[cfg(feature = "cursor")]
+            Undo::PushJump => {}
+            #[cfg(feature = "mark")]
+            Undo::CreateMark { buffer, mark } => {
+                let id = self.replayer.get_mark(*buffer, *mark)?;
+
+                self.replayer.get(*buffer)?.replay_destroy_mark(id)?;
+                self.replayer.marks.remove(&(*buffer, *mark));
+                self.gravities.remove(&(*buffer, *mark));
+            }
+            #[cfg(feature = "mark")]
+            Undo::DestroyMark {
+                buffer,
+                mark,
+                start,
+                end,
+                gravity,
+            } => {
+                let id = self.replayer.get(*buffer)?.replay_create_mark(
+                    start,
+                    end.as_ref(),
+                    *gravity,
+                )?;
+
+                self.replayer.marks.insert((*buffer, *mark), id);
+                self.gravities.insert((*buffer, *mark), *gravity);
+            }
+            #[cfg(feature = "mark")]
+            Undo::SetMarkPosition {
+                buffer,
+                mark,
+                position,
+            } => self
+                .replayer
+                .get(*buffer)?
+                .replay_set_mark_position(self.replayer.get_mark(*buffer, *mark)?, position)?,
+            #[cfg(feature = "mark")]
+            Undo::SetMarkGravity {
+                buffer,
+                mark,
+                gravity,
+            } => {
+                self.replayer
+                    .get(*buffer)?
+                    .replay_set_mark_gravity(self.replayer.get_mark(*buffer, *mark)?, *gravity)?;
+                self.gravities.insert((*buffer, *mark), *gravity);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{assert_buffer_content, test_utils::new_buffer_with_content};
+
+    fn recording_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("eel-ops-{}-{name}.jsonl", std::process::id()))
+    }
+
+    /// Replays what `editor` recorded on its inner editor and checks the buffer ends up the same.
+    fn assert_replays<E>(
+        editor: &RecordingEditor<E>,
+        buffer: &RecordingBufferHandle<E::BufferHandle>,
+    ) where
+        E: Editor,
+        E::BufferHandle: ReplayBufferHandle,
+    {
+        let replayed = replay(editor.inner(), &editor.recording()).expect("Failed to replay");
+        assert_eq!(replayed.len(), 1);
+
+        let content = buffer.read().get_content().expect("Failed to get content");
+        assert_buffer_content!(replayed[0], content);
+    }
+
+    pub fn test_ops_recorder<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: ReplayBufferHandle,
+    {
+        let path = recording_path("text");
+        let editor = RecordingEditor::to_file(editor, &path).expect("Failed to create recording");
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 5)), "1st")
+            .expect("Failed to set text");
+        buffer
+            .write()
+            .set_line(1, "Last line")
+            .expect("Failed to set line");
+        assert_buffer_content!(buffer, "1st line\nLast line");
+
+        let recording = editor.recording();
+        assert_eq!(
+            recording.ops[0],
+            Op::Open {
+                buffer: 0,
+                content: String::new(),
+            }
+        );
+        assert_eq!(
+            recording.ops[2],
+            Op::SetText {
+                buffer: 0,
+                start: Position::new(0, 0),
+                end: Position::new(0, 5),
+                text: "1st".to_string(),
+            }
+        );
+        assert_eq!(recording.ops.len(), 4);
+
+        let loaded = Recording::load(&path).expect("Failed to load recording");
+        std::fs::remove_file(&path).expect("Failed to remove recording");
+        assert_eq!(loaded, recording);
+
+        assert_replays(&editor, &buffer);
+    }
+
+    pub fn test_ops_recorder_existing_buffer<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: ReplayBufferHandle,
+    {
+        let existing = new_buffer_with_content(&editor, "Already there");
+        let editor = RecordingEditor::new(editor);
+
+        let buffer = editor
+            .buffers()
+            .expect("Failed to get buffers")
+            .into_iter()
+            .find(|b| b.inner() == &existing)
+            .expect("Buffer missing");
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 7)), "Still")
+            .expect("Failed to set text");
+
+        assert_eq!(
+            editor.recording().ops[0],
+            Op::Open {
+                buffer: 0,
+                content: "Already there".to_string(),
+            }
+        );
+        assert_replays(&editor, &buffer);
+
+        let path = recording_path("broken");
+        let open = "{\"op\":\"open\",\"buffer\":0,\"content\":\"\"}\n";
+        std::fs::write(&path, format!("{open}{{\"op\":\"set_text\",\"buffer\":3,"))
+            .expect("Failed to write");
+        assert_eq!(
+            Recording::load(&path)
+                .map(|recording| recording.ops.len())
+                .ok(),
+            Some(1)
+        );
+        std::fs::write(
+            &path,
+            format!("{{\"op\":\"set_text\",\"buffer\":3,\n{open}"),
+        )
+        .expect("Failed to write");
+        assert!(Recording::load(&path).is_err());
+        std::fs::remove_file(&path).expect("Failed to remove recording");
+
+        let unknown = Recording {
+            ops: vec![Op::SetText {
+                buffer: 3,
+                start: Position::new(0, 0),
+                end: Position::new(0, 0),
+                text: "x".to_string(),
+            }],
+        };
+        assert!(replay(editor.inner(), &unknown).is_err());
+    }
+
+    pub fn test_ops_recorder_history<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: ReplayBufferHandle,
+    {
+        let editor = RecordingEditor::new(editor);
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        buffer
+            .write()
+            .set_line(0, "1st line")
+            .expect("Failed to set line");
+        buffer
+            .write()
+            .set_line(1, "2nd line")
+            .expect("Failed to set line");
+
+        let mut history = editor.history(editor.inner()).expect("Failed to replay");
+        assert_eq!(history.position(), 4);
+        assert_buffer_content!(history.buffers()[0], "1st line\n2nd line");
+        assert_eq!(history.step_forward().expect("Failed to step"), None);
+
+        let undone = history.step_back().expect("Failed to step").cloned();
+        assert!(matches!(undone, Some(Op::SetText { .. })));
+        assert_buffer_content!(history.buffers()[0], "1st line\nSecond line");
+
+        history.seek(1).expect("Failed to seek");
+        let replayed = history.buffers();
+        assert_buffer_content!(replayed[0], "");
+
+        history.step_forward().expect("Failed to step");
+        assert_buffer_content!(replayed[0], "First line\nSecond line");
+
+        history.seek(0).expect("Failed to seek");
+        assert!(history.buffers().is_empty());
+        assert_eq!(history.step_back().expect("Failed to step"), None);
+
+        // The buffer is reused once opened again
+        history.seek(history.len()).expect("Failed to seek");
+        assert!(history.buffers()[0] == replayed[0]);
+        assert_buffer_content!(replayed[0], "1st line\n2nd line");
+    }
+
+    #[cfg(feature = "cursor")]
+    pub fn test_ops_recorder_cursor<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::cursor::CursorBufferHandle + ReplayBufferHandle,
+    {
+        use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+        let editor = RecordingEditor::new(editor);
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        buffer
+            .write()
+            .set_cursor(&Position::new(1, 3))
+            .expect("Failed to set cursor");
+
+        assert_eq!(
+            editor.recording().ops.last(),
+            Some(&Op::SetCursor {
+                buffer: 0,
+                position: Position::new(1, 3),
+            })
+        );
+
+        let replayed = replay(editor.inner(), &editor.recording()).expect("Failed to replay");
+        assert_eq!(
+            replayed[0]
+                .read()
+                .get_cursor()
+                .expect("Failed to get cursor"),
+            Position::new(1, 3)
+        );
+
+        buffer
+            .write()
+            .set_cursor(&Position::new(0, 2))
+            .expect("Failed to set cursor");
+
+        let mut history = editor.history(editor.inner()).expect("Failed to replay");
+        let cursor = |history: &History<'_, E>| {
+            history.buffers()[0]
+                .read()
+                .get_cursor()
+                .expect("Failed to get cursor")
+        };
+        assert_eq!(cursor(&history), Position::new(0, 2));
+
+        history.step_back().expect("Failed to step");
+        assert_eq!(cursor(&history), Position::new(1, 3));
+
+        history.step_back().expect("Failed to step");
+        assert_eq!(cursor(&history), Position::origin());
+    }
+
+    #[cfg(feature = "mark")]
+    pub fn test_ops_recorder_marks<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle + ReplayBufferHandle,
+    {
+        use crate::mark::{Gravity, MarkWriteBuffer};
+
+        let editor = RecordingEditor::new(editor);
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        let mut lock = buffer.write();
+        let first = lock
+            .create_mark(&Position::new(0, 6))
+            .expect("Failed to create mark");
+        let second = lock
+            .create_mark(&Position::new(1, 0))
+            .expect("Failed to create mark");
+        lock.set_mark_gravity(second, Gravity::Left)
+            .expect("Failed to set gravity");
+        lock.set_mark_position(first, &Position::new(0, 2))
+            .expect("Failed to set mark position");
+        lock.destroy_mark(first).expect("Failed to destroy mark");
+        drop(lock);
+
+        let ops = editor.recording().ops;
+        assert_eq!(
+            ops[2..],
+            [
+                Op::CreateMark {
+                    buffer: 0,
+                    mark: 0,
+                    start: Position::new(0, 6),
+                    end: None,
+                    gravity: Gravity::Right,
+                },
+                Op::CreateMark {
+                    buffer: 0,
+                    mark: 1,
+                    start: Position::new(1, 0),
+                    end: None,
+                    gravity: Gravity::Right,
+                },
+                Op::SetMarkGravity {
+                    buffer: 0,
+                    mark: 1,
+                    gravity: Gravity::Left,
+                },
+                Op::SetMarkPosition {
+                    buffer: 0,
+                    mark: 0,
+                    position: Position::new(0, 2),
+                },
+                Op::DestroyMark { buffer: 0, mark: 0 },
+            ]
+        );
+
+        assert_replays(&editor, &buffer);
+
+        let mut history = editor.history(editor.inner()).expect("Failed to replay");
+        assert_eq!(history.replayer().mark(0, 0), None);
+
+        history.step_back().expect("Failed to step");
+        let mark = history.replayer().mark(0, 0).expect("Mark missing");
+        assert_eq!(
+            history.buffers()[0]
+                .replay_mark_position(mark)
+                .expect("Failed to get mark position"),
+            Position::new(0, 2)
+        );
+
+        history.seek(3).expect("Failed to seek");
+        let mark = history.replayer().mark(0, 0).expect("Mark missing");
+        assert_eq!(
+            history.buffers()[0]
+                .replay_mark_position(mark)
+                .expect("Failed to get mark position"),
+            Position::new(0, 6)
+        );
+        assert_eq!(history.replayer().mark(0, 1), None);
+
+        let mut destroyed = editor.recording();
+        destroyed.ops.push(Op::DestroyMark { buffer: 0, mark: 0 });
+        assert!(replay(editor.inner(), &destroyed).is_err());
+    }
+
+    #[macro_export]
+    macro_rules! eel_ops_recorder_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::ops_recorder::ReplayBufferHandle },
+                module_path: $crate::ops_recorder::tests,
+                prefix: $prefix,
+                tests: [
+                    test_ops_recorder,
+                    test_ops_recorder_existing_buffer,
+                    test_ops_recorder_history,
+                ],
+            );
+
+            $crate::eel_ops_recorder_cursor_tests!($test_tag, $editor_factory, $prefix);
+            $crate::eel_ops_recorder_mark_tests!($test_tag, $editor_factory, $prefix);
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_ops_recorder_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(feature = "cursor")]
+    macro_rules! eel_ops_recorder_cursor_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    E::BufferHandle: $crate::cursor::CursorBufferHandle
+                        + $crate::ops_recorder::ReplayBufferHandle
+                },
+                module_path: $crate::ops_recorder::tests,
+                prefix: $prefix,
+                tests: [test_ops_recorder_cursor],
+            );
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "cursor"))]
+    macro_rules! eel_ops_recorder_cursor_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {};
+    }
+
+    #[macro_export]
+    #[cfg(feature = "mark")]
+    macro_rules! eel_ops_recorder_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    E::BufferHandle: $crate::mark::MarkBufferHandle
+                        + $crate::ops_recorder::ReplayBufferHandle
+                },
+                module_path: $crate::ops_recorder::tests,
+                prefix: $prefix,
+                tests: [test_ops_recorder_marks],
+            );
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_ops_recorder_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {};
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 /// Represents a coordinate location within a buffer.
 ///
 /// This struct uses a 0-indexed coordinate system where `row` corresponds to the vertical
@@ -15,6 +17,7 @@
 /// located immediately after the last character of the line.
 /// `col` on an empty line will always be 0.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub row: usize,
     pub col: usize,
@@ -90,3 +93,66 @@ impl From<Position> for (usize, usize) {
         (position.row, position.col)
     }
 }
+
+/// Range between two positions, always ordered so that `start <= end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PosRange {
+    start: Position,
+    end: Position,
+}
+
+impl PosRange {
+    /// Positions in either order, they're swapped if `a` is after `b`.
+    pub fn new(a: Position, b: Position) -> Self {
+        if a <= b {
+            Self { start: a, end: b }
+        } else {
+            Self { start: b, end: a }
+        }
+    }
+
+    pub fn start(&self) -> &Position {
+        &self.start
+    }
+
+    pub fn end(&self) -> &Position {
+        &self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether `position` is within the range, the end is exclusive.
+    pub fn contains(&self, position: &Position) -> bool {
+        self.start <= *position && *position < self.end
+    }
+
+    pub fn into_positions(self) -> (Position, Position) {
+        (self.start, self.end)
+    }
+}
+
+impl From<(Position, Position)> for PosRange {
+    fn from((a, b): (Position, Position)) -> Self {
+        Self::new(a, b)
+    }
+}
+
+impl From<(&Position, &Position)> for PosRange {
+    fn from((a, b): (&Position, &Position)) -> Self {
+        Self::new(a.clone(), b.clone())
+    }
+}
+
+impl From<Range<Position>> for PosRange {
+    fn from(range: Range<Position>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl From<&PosRange> for PosRange {
+    fn from(range: &PosRange) -> Self {
+        range.clone()
+    }
+}
@@ -34,4 +34,8 @@ where
 
         self.buffer_lock.set_cursor(&pos)
     }
+
+    fn push_jump(&mut self) -> Result<()> {
+        self.buffer_lock.push_jump()
+    }
 }
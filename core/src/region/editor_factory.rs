@@ -1,3 +1,5 @@
+use std::sync::{Mutex, PoisonError};
+
 use crate::{
     Editor, Position, Result,
     buffer::{BufferHandle, WriteBuffer},
@@ -6,9 +8,15 @@ use crate::{
     test_utils::{EditorFactory, new_buffer_with_content},
 };
 
-pub struct RegionEditor<E: Editor> {
+pub struct RegionEditor<E>
+where
+    E: Editor,
+    E::BufferHandle: MarkBufferHandle,
+{
     editor: E,
     empty: bool,
+    regions: Mutex<Vec<BufferRegion<E::BufferHandle>>>,
+    current: Mutex<Option<BufferRegion<E::BufferHandle>>>,
 }
 
 impl<E> Editor for RegionEditor<E>
@@ -32,27 +40,117 @@ Fourth line"#
         );
 
         let region = if self.empty {
-            BufferRegion::lock_new(&buffer, &Position::new(0, 0), &Position::new(0, 0))?
+            BufferRegion::lock_new(&buffer, (Position::new(0, 0), Position::new(0, 0)))?
         } else {
-            BufferRegion::lock_new(&buffer, &Position::new(1, 2), &Position::new(2, 5))?
+            BufferRegion::lock_new(&buffer, (Position::new(1, 2), Position::new(2, 5)))?
         };
 
-        region.write().set_content("")?;
+        region.write_timeout()?.set_content("")?;
+
+        self.regions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(region.clone());
 
         Ok(region)
     }
 
-    // Not required for buffer tests
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.editor.execute_normal(keys)
+    }
+
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        self.editor.detect_filetype(path, content)
+    }
+
+    fn capabilities(&self) -> crate::Capabilities {
+        self.editor.capabilities()
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<crate::OptionValue>> {
+        self.editor.get_option(name)
+    }
+
+    fn set_option(&self, name: &str, value: crate::OptionValue) -> Result<()> {
+        self.editor.set_option(name, value)
+    }
+
+    fn on_idle(
+        &self,
+        delay: std::time::Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.editor.on_idle(delay, callback)
+    }
+
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        self.editor.tasks()
+    }
+
+    #[cfg(feature = "ui")]
+    fn prompt(&self, spec: &crate::ui::PromptSpec) -> Result<crate::ui::UserResponse> {
+        self.editor.prompt(spec)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.editor.jump_back()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.editor.jump_forward()
+    }
 
     fn current_buffer(&self) -> Result<Self::BufferHandle> {
-        unimplemented!()
+        let current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+
+        Ok(current
+            .clone()
+            .ok_or_else(|| crate::buffer::Error::Custom("No current region".into()))?)
     }
 
     fn set_current_buffer(
         &self,
-        _buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
     ) -> Result<()> {
-        unimplemented!()
+        self.editor.set_current_buffer(&mut buffer.buffer_lock)?;
+
+        let region = self
+            .regions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|r| r.start == buffer.start)
+            .cloned();
+
+        *self.current.lock().unwrap_or_else(PoisonError::into_inner) = region;
+
+        Ok(())
+    }
+
+    /// Regions created by this editor.
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>> {
+        Ok(self
+            .regions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+
+    /// Region of the inner editor's buffer with that name, if it was created here.
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>> {
+        let Some(buffer) = self.editor.buffer_by_name(name)? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .regions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|region| *region.buffer() == buffer)
+            .cloned())
     }
 }
 
@@ -66,5 +164,7 @@ where
     move || RegionEditor {
         editor: editor_factory.create_editor(),
         empty,
+        regions: Mutex::default(),
+        current: Mutex::default(),
     }
 }
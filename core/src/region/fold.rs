@@ -0,0 +1,107 @@
+use crate::{
+    Position, Result,
+    buffer::{ReadBuffer, ReadBufferLock, WriteBufferLock},
+    fold::{Fold, FoldReadBuffer, FoldWriteBuffer},
+    mark::{MarkBufferHandle, MarkReadBuffer, MarkWriteBuffer},
+    region::{BufferRegion, BufferRegionAccess},
+};
+
+impl<B> BufferRegion<B>
+where
+    B: MarkBufferHandle,
+    B::ReadBuffer: FoldReadBuffer,
+    B::WriteBuffer: FoldWriteBuffer,
+{
+    /// Folds the rows currently spanned by the region.
+    ///
+    /// Folds don't grow with the region, calling this again re-aligns the fold after the
+    /// region changed.
+    pub fn fold(&self) -> Result<()> {
+        let (start, end) = self.bounds()?;
+
+        self.buffer().write_timeout()?.fold(start.row, end.row)
+    }
+
+    pub fn unfold(&self) -> Result<()> {
+        let (start, end) = self.bounds()?;
+
+        self.buffer().write_timeout()?.unfold(start.row, end.row)
+    }
+
+    pub fn is_folded(&self) -> Result<bool> {
+        let (start, _) = self.bounds()?;
+
+        self.buffer().read_timeout()?.is_folded(start.row)
+    }
+}
+
+impl<'a, B, Buf, L> BufferRegionAccess<'a, B, Buf, L>
+where
+    B: MarkBufferHandle,
+    Buf: MarkReadBuffer<MarkId = B::MarkId>,
+    L: ReadBufferLock<ReadBuffer = Buf> + 'a,
+{
+    fn real_row(&self, row: usize) -> Result<usize> {
+        let pos = Position::new(row, 0);
+        self.validate_pos(&pos)?;
+
+        Ok(self.real_position(&pos)?.row)
+    }
+}
+
+impl<'a, B, Buf, L> FoldReadBuffer for BufferRegionAccess<'a, B, Buf, L>
+where
+    B: MarkBufferHandle,
+    Buf: MarkReadBuffer<MarkId = B::MarkId>,
+    Buf: FoldReadBuffer,
+    L: ReadBufferLock<ReadBuffer = Buf> + 'a,
+{
+    fn is_folded(&self, row: usize) -> Result<bool> {
+        let row = self.real_row(row)?;
+
+        self.buffer_lock.is_folded(row)
+    }
+
+    fn folds_in(&self, start_row: usize, end_row: usize) -> Result<Vec<Fold>> {
+        let (offset, last) = (self.real_row(0)?, self.line_count()? - 1);
+
+        let folds = self
+            .buffer_lock
+            .folds_in(self.real_row(start_row)?, self.real_row(end_row)?)?
+            .into_iter()
+            .map(|fold| Fold {
+                start_row: fold.start_row.saturating_sub(offset),
+                end_row: (fold.end_row - offset).min(last),
+                ..fold
+            })
+            .collect();
+
+        Ok(folds)
+    }
+}
+
+impl<'a, B, Buf, L> FoldWriteBuffer for BufferRegionAccess<'a, B, Buf, L>
+where
+    B: MarkBufferHandle,
+    Buf: MarkWriteBuffer<MarkId = B::MarkId>,
+    Buf: FoldWriteBuffer,
+    L: WriteBufferLock<WriteBuffer = Buf> + 'a,
+{
+    fn fold(&mut self, start_row: usize, end_row: usize) -> Result<()> {
+        let (start_row, end_row) = (self.real_row(start_row)?, self.real_row(end_row)?);
+
+        self.buffer_lock.fold(start_row, end_row)
+    }
+
+    fn unfold(&mut self, start_row: usize, end_row: usize) -> Result<()> {
+        let (start_row, end_row) = (self.real_row(start_row)?, self.real_row(end_row)?);
+
+        self.buffer_lock.unfold(start_row, end_row)
+    }
+
+    fn toggle_fold(&mut self, row: usize) -> Result<Option<bool>> {
+        let row = self.real_row(row)?;
+
+        self.buffer_lock.toggle_fold(row)
+    }
+}
@@ -18,6 +18,13 @@ where
 
         self.region_position(&pos)
     }
+
+    fn get_mark_end(&self, id: Self::MarkId) -> Result<Option<Position>> {
+        self.buffer_lock
+            .get_mark_end(id)?
+            .map(|pos| self.region_position(&pos))
+            .transpose()
+    }
 }
 
 impl<'a, B, Buf, L> MarkWriteBuffer for BufferRegionAccess<'a, B, Buf, L>
@@ -36,6 +43,19 @@ where
         self.buffer_lock.destroy_mark(id)
     }
 
+    fn create_mark_with(
+        &mut self,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+    ) -> Result<Self::MarkId> {
+        let start = self.real_position(start)?;
+        let end = end.map(|pos| self.real_position(pos)).transpose()?;
+
+        self.buffer_lock
+            .create_mark_with(&start, end.as_ref(), gravity)
+    }
+
     fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()> {
         let pos = self.real_position(pos)?;
 
@@ -1,12 +1,20 @@
 use std::{
     marker::PhantomData,
     ops::{Bound, RangeBounds},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
 use crate::{
-    Position, Result,
-    buffer::{BufferHandle, ReadBuffer, ReadBufferLock, WriteBuffer, WriteBufferLock},
-    mark::{Gravity, Mark, MarkBufferHandle, MarkReadBuffer, MarkWriteBuffer},
+    PosRange, Position, Result,
+    buffer::{
+        BufferData, BufferHandle, ReadBuffer, ReadBufferLock, WeakBufferHandle, WriteBuffer,
+        WriteBufferLock,
+    },
+    indent::IndentSettings,
+    mark::{Gravity, Mark, MarkBufferHandle, MarkReadBuffer, MarkWriteBuffer, WeakMark},
 };
 
 pub struct BufferRegionAccess<'a, B, Buf, L>
@@ -18,6 +26,7 @@ where
     start: Mark<B>,
     end: Mark<B>,
     buffer_lock: L,
+    read_only: bool,
     _mark: PhantomData<&'a ()>,
 }
 
@@ -40,6 +49,20 @@ where
         })
     }
 
+    /// Validated range in the underlying buffer for a write through the region.
+    fn real_range(&self, range: &PosRange) -> Result<PosRange> {
+        if self.read_only {
+            Err(crate::buffer::Error::ReadOnly)?;
+        }
+
+        self.validate_range(range.start(), range.end())?;
+
+        Ok(PosRange::new(
+            self.real_position(range.start())?,
+            self.real_position(range.end())?,
+        ))
+    }
+
     pub fn region_position(&self, pos: &Position) -> Result<Position> {
         let start_pos = self.start.read(&*self.buffer_lock).get_position()?;
 
@@ -69,22 +92,44 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Content of a region saved by [`BufferRegion::checkpoint`].
+#[derive(Debug, Clone)]
+pub struct RegionCheckpoint<B: MarkBufferHandle> {
+    start: B::MarkId,
+    content: String,
+}
+
+impl<B: MarkBufferHandle> RegionCheckpoint<B> {
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct BufferRegion<B: MarkBufferHandle> {
     start: Mark<B>,
     end: Mark<B>,
     buffer: B,
+    read_only: Arc<AtomicBool>,
 }
 
+impl<B: MarkBufferHandle> PartialEq for BufferRegion<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end && self.buffer == other.buffer
+    }
+}
+
+impl<B: MarkBufferHandle> Eq for BufferRegion<B> {}
+
 impl<B: MarkBufferHandle> BufferRegion<B> {
     pub fn new(
         buffer: &B,
-        start: &Position,
-        end: &Position,
+        range: impl Into<PosRange>,
         mut buffer_lock: impl WriteBufferLock<WriteBuffer = B::WriteBuffer>,
     ) -> Result<Self> {
-        let start = Mark::new(buffer, start, &mut *buffer_lock)?;
-        let end = Mark::new(buffer, end, &mut *buffer_lock)?;
+        let range = range.into();
+        let start = Mark::new(buffer, range.start(), &mut *buffer_lock)?;
+        let end = Mark::new(buffer, range.end(), &mut *buffer_lock)?;
 
         start.write(&mut *buffer_lock).set_gravity(Gravity::Left)?;
 
@@ -94,13 +139,67 @@ impl<B: MarkBufferHandle> BufferRegion<B> {
             start,
             end,
             buffer: buffer.clone(),
+            read_only: Default::default(),
         })
     }
 
-    pub fn lock_new(buffer: &B, start: &Position, end: &Position) -> Result<Self> {
-        let lock = buffer.write();
+    pub fn lock_new(buffer: &B, range: impl Into<PosRange>) -> Result<Self> {
+        let lock = buffer.write_timeout()?;
+
+        Self::new(buffer, range, lock)
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    /// Makes `set_text` through this region (and all its clones) fail with
+    /// [`Error::ReadOnly`](crate::buffer::Error::ReadOnly).
+    ///
+    /// Edits made directly through the underlying buffer are not affected, backends may
+    /// guard against those separately.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Release);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Acquire)
+    }
+
+    /// Start and end of the region in the underlying buffer.
+    pub fn bounds(&self) -> Result<(Position, Position)> {
+        let lock = self.buffer.read_timeout()?;
 
-        Self::new(buffer, start, end, lock)
+        Ok((
+            self.start.read(&*lock).get_position()?,
+            self.end.read(&*lock).get_position()?,
+        ))
+    }
+
+    /// Saves the content to [`restore`](Self::restore) later, e.g. to revert a preview.
+    pub fn checkpoint(&self) -> Result<RegionCheckpoint<B>> {
+        Ok(RegionCheckpoint {
+            start: self.start.id(),
+            content: self.read_timeout()?.get_content()?,
+        })
+    }
+
+    /// Replaces the content with the checkpoint's, wherever the region's marks moved since.
+    /// Checkpoints can be restored any number of times, by any clone of the region.
+    pub fn restore(&self, checkpoint: &RegionCheckpoint<B>) -> Result<()> {
+        if checkpoint.start != self.start.id() {
+            Err(crate::buffer::Error::Custom(
+                "Checkpoint of another region".into(),
+            ))?;
+        }
+
+        let mut region = self.write_timeout()?;
+
+        if region.get_content()? != checkpoint.content {
+            region.set_content(&checkpoint.content)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -150,16 +249,35 @@ where
             .get_lines(start_bound..end_bound)?
             .collect();
 
+        // Clamping, so marks left inside a character (or past the end of the line) by edits
+        // on the underlying buffer can't make slicing panic
         if partial_last_line && let Some(l) = lines.last_mut() {
-            l.truncate(end_pos.col);
+            l.truncate(l.floor_char_boundary(end_pos.col));
         }
 
         if partial_first_line && let Some(l) = lines.first_mut() {
-            *l = l.split_off(start_pos.col);
+            *l = l.split_off(l.floor_char_boundary(start_pos.col));
         }
 
         Ok(lines.into_iter())
     }
+
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        self.validate_range(range.start(), range.end())?;
+
+        self.buffer_lock.get_text_in(&PosRange::new(
+            self.real_position(range.start())?,
+            self.real_position(range.end())?,
+        ))
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.buffer_lock.changedtick()
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        self.buffer_lock.indent_settings()
+    }
 }
 
 impl<'a, B, Buf, L> WriteBuffer for BufferRegionAccess<'a, B, Buf, L>
@@ -168,14 +286,53 @@ where
     Buf: MarkWriteBuffer<MarkId = B::MarkId>,
     L: WriteBufferLock<WriteBuffer = Buf> + 'a,
 {
-    fn set_text(&mut self, start: &Position, end: &Position, text: &str) -> Result<()> {
-        self.validate_pos(start)?;
-        self.validate_pos(end)?;
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        let real_range = self.real_range(range)?;
+
+        self.buffer_lock.set_text_in(&real_range, text)
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        let real_range = self.real_range(range)?;
+
+        self.buffer_lock
+            .set_text_if_unchanged(expected_tick, &real_range, text)
+    }
+}
+
+pub struct WeakBufferRegion<B: MarkBufferHandle> {
+    start: WeakMark<B>,
+    end: WeakMark<B>,
+    buffer: B::WeakHandle,
+    read_only: Arc<AtomicBool>,
+}
+
+impl<B: MarkBufferHandle> Clone for WeakBufferRegion<B> {
+    fn clone(&self) -> Self {
+        Self {
+            start: self.start.clone(),
+            end: self.end.clone(),
+            buffer: self.buffer.clone(),
+            read_only: self.read_only.clone(),
+        }
+    }
+}
 
-        let abs_start = self.real_position(start)?;
-        let abs_end = self.real_position(end)?;
+impl<B: MarkBufferHandle> WeakBufferHandle for WeakBufferRegion<B> {
+    type Handle = BufferRegion<B>;
 
-        self.buffer_lock.set_text(&abs_start, &abs_end, text)
+    fn upgrade(&self) -> Option<Self::Handle> {
+        Some(BufferRegion {
+            start: self.start.upgrade()?,
+            end: self.end.upgrade()?,
+            buffer: self.buffer.upgrade()?,
+            read_only: self.read_only.clone(),
+        })
     }
 }
 
@@ -184,6 +341,7 @@ impl<B: MarkBufferHandle> BufferHandle for BufferRegion<B> {
     type WriteBuffer = BufferRegionAccess<'static, B, B::WriteBuffer, B::WriteBufferLock>;
     type ReadBufferLock = Box<Self::ReadBuffer>;
     type WriteBufferLock = Box<Self::WriteBuffer>;
+    type WeakHandle = WeakBufferRegion<B>;
 
     fn read(&self) -> Self::ReadBufferLock {
         let buffer = self.buffer.clone();
@@ -194,6 +352,7 @@ impl<B: MarkBufferHandle> BufferHandle for BufferRegion<B> {
             start,
             end,
             buffer_lock: buffer.read(),
+            read_only: self.is_read_only(),
             _mark: Default::default(),
         })
     }
@@ -207,9 +366,73 @@ impl<B: MarkBufferHandle> BufferHandle for BufferRegion<B> {
             start,
             end,
             buffer_lock: buffer.write(),
+            read_only: self.is_read_only(),
+            _mark: Default::default(),
+        })
+    }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        Ok(Box::new(BufferRegionAccess {
+            start: self.start.clone(),
+            end: self.end.clone(),
+            buffer_lock: self.buffer.read_timeout()?,
+            read_only: self.is_read_only(),
             _mark: Default::default(),
+        }))
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        Ok(Box::new(BufferRegionAccess {
+            start: self.start.clone(),
+            end: self.end.clone(),
+            buffer_lock: self.buffer.write_timeout()?,
+            read_only: self.is_read_only(),
+            _mark: Default::default(),
+        }))
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        let BufferRegionAccess {
+            start,
+            end,
+            buffer_lock,
+            read_only,
+            _mark,
+        } = *lock;
+
+        Box::new(BufferRegionAccess {
+            start,
+            end,
+            buffer_lock: self.buffer.downgrade_lock(buffer_lock),
+            read_only,
+            _mark,
         })
     }
+
+    fn data(&self) -> &BufferData {
+        self.buffer.data()
+    }
+
+    fn downgrade(&self) -> Self::WeakHandle {
+        WeakBufferRegion {
+            start: self.start.downgrade(),
+            end: self.end.downgrade(),
+            buffer: self.buffer.downgrade(),
+            read_only: self.read_only.clone(),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.buffer.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.buffer.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.buffer.changedtick()
+    }
 }
 
 mod mark;
@@ -217,13 +440,17 @@ mod mark;
 #[cfg(feature = "cursor")]
 mod cursor;
 
+#[cfg(feature = "fold")]
+mod fold;
+
 #[cfg(feature = "tests")]
 pub mod editor_factory;
 
 #[cfg(feature = "tests")]
 pub mod tests {
     use crate::{
-        CompleteBufferHandle, Editor, assert_buffer_error, test_utils::new_buffer_with_content,
+        CompleteBufferHandle, Editor, assert_buffer_content, assert_buffer_error,
+        test_utils::new_buffer_with_content,
     };
 
     use super::*;
@@ -242,7 +469,7 @@ pub mod tests {
             B: CompleteBufferHandle,
         {
             let region =
-                BufferRegion::lock_new(&buffer, &Position::origin(), &Position::origin()).unwrap();
+                BufferRegion::lock_new(&buffer, (Position::origin(), Position::origin())).unwrap();
             _check_trait(region);
         }
     }
@@ -260,7 +487,7 @@ Third line
 Fourth line"#,
         );
 
-        let region = BufferRegion::lock_new(&buffer, &Position::new(1, 2), &Position::new(2, 5))
+        let region = BufferRegion::lock_new(&buffer, (Position::new(1, 2), Position::new(2, 5)))
             .expect("Failed to create region");
 
         (buffer, region)
@@ -461,7 +688,7 @@ Fourth line"#,
         );
 
         let mut region =
-            BufferRegion::lock_new(&buffer, &Position::new(1, 11), &Position::new(1, 11))
+            BufferRegion::lock_new(&buffer, (Position::new(1, 11), Position::new(1, 11)))
                 .expect("Failed to create region")
                 .write();
 
@@ -492,15 +719,167 @@ Fourth line"#
         );
     }
 
+    pub fn test_region_multibyte<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "Zażółć\n中文字符 ok\n🦀 crab 🦀");
+
+        let region = BufferRegion::lock_new(&buffer, (Position::new(0, 2), Position::new(2, 4)))
+            .expect("Failed to create region");
+
+        {
+            let region = region.read();
+
+            assert_eq!(
+                region.get_content().expect("Failed to get content"),
+                "żółć\n中文字符 ok\n🦀"
+            );
+            assert_eq!(region.line_len(0).expect("Failed to get line length"), 8);
+            assert_eq!(region.line_len(2).expect("Failed to get line length"), 4);
+
+            assert_eq!(
+                region
+                    .get_text((Position::new(0, 2), Position::new(1, 6)))
+                    .expect("Failed to get text"),
+                "ółć\n中文"
+            );
+            assert_eq!(
+                region
+                    .region_position(&Position::new(0, 4))
+                    .expect("Failed to convert position"),
+                Position::new(0, 2)
+            );
+
+            assert_buffer_error!(
+                region.get_text((Position::new(0, 1), Position::new(0, 2))),
+                crate::Error::Buffer(crate::buffer::Error::NotCharBoundary { row: 0, col: 1 })
+            );
+        }
+
+        region
+            .write()
+            .set_text((Position::new(1, 0), Position::new(1, 12)), "漢字")
+            .expect("Failed to set text");
+        region.write().append("✨").expect("Failed to append");
+
+        // Edits before the region shift its start by bytes, not characters
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 1)), "🦀")
+            .expect("Failed to set text");
+
+        assert_eq!(
+            region.read().get_content().expect("Failed to get content"),
+            "żółć\n漢字 ok\n🦀✨"
+        );
+        assert_eq!(
+            region.bounds().expect("Failed to get bounds"),
+            (Position::new(0, 5), Position::new(2, 7))
+        );
+        assert_buffer_content!(buffer, "🦀ażółć\n漢字 ok\n🦀✨ crab 🦀");
+    }
+
+    pub fn test_region_read_only<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let (buffer, region) = init_test_region(&editor);
+
+        region.set_read_only(true);
+        assert!(region.is_read_only());
+
+        assert_buffer_error!(
+            region.write().append("!"),
+            crate::Error::Buffer(crate::buffer::Error::ReadOnly)
+        );
+
+        // Clones share the flag
+        let other = region.clone();
+        assert_buffer_error!(
+            other.write().set_content(""),
+            crate::Error::Buffer(crate::buffer::Error::ReadOnly)
+        );
+
+        assert_eq!(
+            region.read().get_content().expect("Failed to get content"),
+            "cond line\nThird"
+        );
+
+        // The rest of the buffer is still writable
+        buffer
+            .write()
+            .set_line(0, "First row")
+            .expect("Failed to set line");
+
+        region.set_read_only(false);
+        assert!(!other.is_read_only());
+
+        region.write().append("!").expect("Failed to append");
+
+        assert_eq!(
+            buffer.read().get_content().expect("Failed to get content"),
+            r#"First row
+Second line
+Third! line
+Fourth line"#
+        );
+    }
+
+    pub fn test_region_checkpoint<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: MarkBufferHandle,
+    {
+        let (buffer, region) = init_test_region(&editor);
+
+        let checkpoint = region.checkpoint().expect("Failed to checkpoint");
+        assert_eq!(checkpoint.content(), "cond line\nThird");
+
+        region
+            .write()
+            .set_content("preview")
+            .expect("Failed to set content");
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "Zeroth line\n")
+            .expect("Failed to set text");
+
+        region.restore(&checkpoint).expect("Failed to restore");
+        assert_eq!(
+            buffer.read().get_content().expect("Failed to get content"),
+            r#"Zeroth line
+First line
+Second line
+Third line
+Fourth line"#
+        );
+
+        // Restoring again, through a clone
+        region.write().append(" edited").expect("Failed to append");
+        region
+            .clone()
+            .restore(&checkpoint)
+            .expect("Failed to restore");
+        assert_eq!(
+            region.read().get_content().expect("Failed to get content"),
+            "cond line\nThird"
+        );
+
+        let other = BufferRegion::lock_new(&buffer, (Position::new(0, 0), Position::new(0, 4)))
+            .expect("Failed to create region");
+        assert!(other.restore(&checkpoint).is_err());
+    }
+
     #[macro_export]
     macro_rules! eel_region_tests {
-        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
-            $crate::eel_tests!(
-                test_tag: $test_tag,
-                editor_factory: $editor_factory,
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
                 editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
                 module_path: $crate::region::tests,
-                prefix: $prefix,
                 tests: [
                     test_region_line_count,
                     test_region_get_lines,
@@ -508,7 +887,22 @@ Fourth line"#
                     test_region_empty,
                     test_region_region_position,
                     test_region_real_position,
+                    test_region_read_only,
+                    test_region_checkpoint,
+                    test_region_multibyte,
                 ],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_region_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_region_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
             );
 
             $crate::test_utils::paste! {
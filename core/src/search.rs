@@ -0,0 +1,294 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, Sender, channel},
+};
+
+use crate::{
+    Editor, Position, Result,
+    buffer::{BufferHandle, ReadBuffer},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchSource<B> {
+    Buffer(B),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit<B> {
+    pub source: SearchSource<B>,
+    pub start: Position,
+    pub end: Position,
+    pub line: String,
+}
+
+/// Literal text search over open buffers and files under `roots`.
+///
+/// Files open in a buffer are only searched through the buffer, hidden files and directories
+/// are skipped, as are files that aren't valid UTF-8.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// ASCII only, so match columns stay byte offsets into the original line.
+    pub ignore_case: bool,
+    pub roots: Vec<PathBuf>,
+}
+
+impl SearchQuery {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Columns of the matches in `line`.
+    pub(crate) fn matches(&self, line: &str) -> Vec<usize> {
+        if self.pattern.is_empty() {
+            return Vec::new();
+        }
+
+        if self.ignore_case {
+            line.to_ascii_lowercase()
+                .match_indices(&self.pattern.to_ascii_lowercase())
+                .map(|(col, _)| col)
+                .collect()
+        } else {
+            line.match_indices(&self.pattern)
+                .map(|(col, _)| col)
+                .collect()
+        }
+    }
+
+    /// Sends hits for every matching line, returns `false` once the receiver is gone.
+    fn search_lines<B>(
+        &self,
+        lines: impl Iterator<Item = String>,
+        source: impl Fn() -> SearchSource<B>,
+        hits: &Sender<SearchHit<B>>,
+    ) -> bool {
+        for (row, line) in lines.enumerate() {
+            for col in self.matches(&line) {
+                let hit = SearchHit {
+                    source: source(),
+                    start: Position::new(row, col),
+                    end: Position::new(row, col + self.pattern.len()),
+                    line: line.clone(),
+                };
+
+                if hits.send(hit).is_err() {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn search_dir<B>(
+        &self,
+        dir: &Path,
+        skip: &HashSet<PathBuf>,
+        hits: &Sender<SearchHit<B>>,
+    ) -> bool {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return true;
+        };
+
+        // Symlinks aren't followed into directories, they could loop back to a parent
+        let mut entries: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| (e.path(), e.file_type().is_ok_and(|t| t.is_dir())))
+            .collect();
+        entries.sort();
+
+        for (path, is_dir) in entries {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+            if hidden {
+                continue;
+            }
+
+            let keep_going = if is_dir {
+                self.search_dir(&path, skip, hits)
+            } else {
+                self.search_file(&path, skip, hits)
+            };
+
+            if !keep_going {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn search_file<B>(
+        &self,
+        path: &Path,
+        skip: &HashSet<PathBuf>,
+        hits: &Sender<SearchHit<B>>,
+    ) -> bool {
+        if skip.contains(&canonical(path)) {
+            return true;
+        }
+
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return true;
+        };
+
+        self.search_lines(
+            content.split('\n').map(str::to_string),
+            || SearchSource::File(path.to_path_buf()),
+            hits,
+        )
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Buffers are searched before returning, files are searched on a separate thread streaming
+/// hits as they are found. The stream ends once everything was searched.
+pub fn search<E: Editor>(
+    editor: &E,
+    query: SearchQuery,
+) -> Result<Receiver<SearchHit<E::BufferHandle>>> {
+    let (hits, receiver) = channel();
+
+    let mut open_paths = HashSet::new();
+
+    for buffer in editor.buffers()? {
+        if let Some(name) = editor.buffer_name(&buffer)? {
+            open_paths.insert(canonical(Path::new(&name)));
+        }
+
+        let lines: Vec<String> = buffer.read_timeout()?.get_all_lines()?.collect();
+
+        query.search_lines(
+            lines.into_iter(),
+            || SearchSource::Buffer(buffer.clone()),
+            &hits,
+        );
+    }
+
+    std::thread::spawn(move || {
+        for root in &query.roots {
+            let keep_going = if root.is_dir() {
+                query.search_dir(root, &open_paths, &hits)
+            } else {
+                query.search_file(root, &open_paths, &hits)
+            };
+
+            if !keep_going {
+                break;
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    use crate::test_utils::new_buffer_with_content;
+
+    fn temp_dir() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "eel-search-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        std::fs::create_dir_all(dir.join("nested")).expect("Failed to create temp dir");
+
+        dir
+    }
+
+    pub fn test_search(editor: impl Editor) {
+        let dir = temp_dir();
+
+        std::fs::write(dir.join("a.txt"), "Hello world\nsay hello, hello")
+            .expect("Failed to write");
+        std::fs::write(dir.join("nested/b.txt"), "nothing here").expect("Failed to write");
+        std::fs::write(dir.join(".hidden"), "hello").expect("Failed to write");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("nested/loop")).expect("Failed to symlink");
+
+        let buffer = new_buffer_with_content(&editor, "first\nwell, hello there");
+
+        let mut query = SearchQuery::new("hello");
+        query.roots.push(dir.clone());
+
+        let hits: Vec<_> = search(&editor, query.clone())
+            .expect("Failed to search")
+            .into_iter()
+            .collect();
+
+        let buffer_hits: Vec<_> = hits
+            .iter()
+            .filter(|h| h.source == SearchSource::Buffer(buffer.clone()))
+            .collect();
+
+        assert_eq!(buffer_hits.len(), 1);
+        assert_eq!(buffer_hits[0].start, Position::new(1, 6));
+        assert_eq!(buffer_hits[0].end, Position::new(1, 11));
+        assert_eq!(buffer_hits[0].line, "well, hello there");
+
+        let file_hits: Vec<_> = hits
+            .iter()
+            .filter_map(|h| match &h.source {
+                SearchSource::File(path) => Some((path.clone(), h.start.clone())),
+                SearchSource::Buffer(_) => None,
+            })
+            .collect();
+
+        assert_eq!(
+            file_hits,
+            [
+                (dir.join("a.txt"), Position::new(1, 4)),
+                (dir.join("a.txt"), Position::new(1, 11)),
+            ]
+        );
+
+        query.ignore_case = true;
+
+        let file_hits = search(&editor, query)
+            .expect("Failed to search")
+            .into_iter()
+            .filter(|h| matches!(h.source, SearchSource::File(_)))
+            .count();
+
+        assert_eq!(file_hits, 3);
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[macro_export]
+    macro_rules! eel_search_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::search::tests,
+                prefix: $prefix,
+                tests: [test_search],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_search_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,968 @@
+//! Serves an [`Editor`] over JSON-RPC 2.0, so other processes (tests, scripts, agents) can
+//! drive it through the eel traits.
+//!
+//! Messages are framed like LSP messages, with a `Content-Length` header. Buffers, marks and
+//! regions are referred to by ids handed out by the server, kept alive until released.
+//!
+//! Methods, with their params:
+//! - `editor/currentBuffer`, `editor/newBuffer`, `editor/buffers`: `{}`
+//! - `editor/bufferByName`: `{ name }`
+//! - `editor/bufferName`, `editor/setCurrentBuffer`: `{ buffer }`
+//! - `buffer/getContent`, `buffer/lineCount`, `buffer/changedtick`, `buffer/release`:
+//!   `{ buffer }`
+//! - `buffer/setContent`: `{ buffer, text }`
+//! - `buffer/getText`: `{ buffer, start, end }`
+//! - `buffer/setText`: `{ buffer, start, end, text }`
+//! - `cursor/get`: `{ buffer }`, `cursor/set`: `{ buffer, position }`, see [`Server::cursor`]
+//! - `mark/create`: `{ buffer, position }`, `mark/get`, `mark/release`: `{ mark }`,
+//!   `mark/set`: `{ mark, position }`, see [`Server::mark`]
+//! - `region/create`: `{ buffer, start, end }`, `region/bounds`, `region/getContent`,
+//!   `region/release`: `{ region }`, `region/setContent`: `{ region, text }`, see
+//!   [`Server::region`]
+//!
+//! Positions are `{ row, col }` objects, columns being byte offsets.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tracing::{trace, warn};
+
+use crate::{
+    Editor, Position,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Errors returned by the editor.
+const EDITOR_ERROR: i64 = -32000;
+const UNKNOWN_ID: i64 = -32001;
+
+/// Longest message body read, longer ones are rejected before allocating for them.
+pub const MAX_MESSAGE_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Writes a message framed like an LSP message, with a `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+
+    write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+/// `None` once the stream ends.
+pub fn read_message(reader: &mut impl BufRead) -> std::io::Result<Option<Value>> {
+    let Some(body) = read_body(reader)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Unparsed body of the next message, `None` once the stream ends.
+pub fn read_body(reader: &mut impl BufRead) -> std::io::Result<Option<Vec<u8>>> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    let mut length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let header = line.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid(format!("Invalid header: {header}")))?,
+            );
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid("Missing Content-Length header".into()))?;
+
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(invalid(format!(
+            "Message of {length} bytes is longer than {MAX_MESSAGE_LENGTH}"
+        )));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(body))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<crate::Error> for RpcError {
+    fn from(value: crate::Error) -> Self {
+        Self::new(EDITOR_ERROR, value.to_string())
+    }
+}
+
+type MethodResult<R> = std::result::Result<R, RpcError>;
+type Method = Box<dyn Fn(Value) -> MethodResult<Value> + Send + Sync>;
+
+/// Objects handed out to clients, by id.
+struct Handles<T> {
+    kind: &'static str,
+    next_id: AtomicU64,
+    handles: Mutex<HashMap<u64, T>>,
+}
+
+impl<T: Clone> Handles<T> {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            next_id: AtomicU64::new(1),
+            handles: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, T>> {
+        self.handles.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[cfg(feature = "mark")]
+    fn insert(&self, handle: T) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(id, handle);
+
+        id
+    }
+
+    fn get(&self, id: u64) -> MethodResult<T> {
+        self.lock()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| RpcError::new(UNKNOWN_ID, format!("Unknown {}: {id}", self.kind)))
+    }
+
+    fn remove(&self, id: u64) -> MethodResult<T> {
+        self.lock()
+            .remove(&id)
+            .ok_or_else(|| RpcError::new(UNKNOWN_ID, format!("Unknown {}: {id}", self.kind)))
+    }
+}
+
+impl<T: Eq + Clone> Handles<T> {
+    /// Same id for the same object, so clients can compare them.
+    fn id(&self, handle: T) -> u64 {
+        let mut handles = self.lock();
+
+        if let Some((&id, _)) = handles.iter().find(|(_, h)| **h == handle) {
+            return id;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        handles.insert(id, handle);
+
+        id
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct NoParams {}
+
+#[derive(serde::Deserialize)]
+struct NameParams {
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct BufferParams {
+    buffer: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct ContentParams {
+    buffer: u64,
+    text: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TextParams {
+    buffer: u64,
+    start: Position,
+    end: Position,
+    text: Option<String>,
+}
+
+#[cfg(any(feature = "cursor", feature = "mark"))]
+#[derive(serde::Deserialize)]
+struct PositionParams {
+    buffer: u64,
+    position: Position,
+}
+
+/// See the [module docs](self) for the protocol.
+///
+/// Buffer methods are always there, the others are opt-in, so editors only have to satisfy
+/// the trait bounds of what they serve:
+///
+/// ```ignore
+/// Server::new(editor).cursor().mark().region().spawn_stdio()?;
+/// ```
+pub struct Server<E: Editor> {
+    editor: Arc<E>,
+    buffers: Arc<Handles<E::BufferHandle>>,
+    methods: HashMap<&'static str, Method>,
+}
+
+impl<E: Editor> std::fmt::Debug for Server<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut methods: Vec<_> = self.methods.keys().collect();
+        methods.sort();
+
+        f.debug_struct("Server").field("methods", &methods).finish()
+    }
+}
+
+impl<E: Editor> Server<E> {
+    pub fn new(editor: E) -> Self {
+        let mut server = Self {
+            editor: Arc::new(editor),
+            buffers: Arc::new(Handles::new("buffer")),
+            methods: HashMap::new(),
+        };
+
+        server.editor_methods();
+        server.buffer_methods();
+
+        server
+    }
+
+    /// Registers a method, `handler` gets the deserialized params.
+    pub fn method<P, R>(
+        &mut self,
+        name: &'static str,
+        handler: impl Fn(P) -> MethodResult<R> + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+    {
+        self.methods.insert(
+            name,
+            Box::new(move |params| {
+                let params = serde_json::from_value(params)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {e}")))?;
+
+                serde_json::to_value(handler(params)?)
+                    .map_err(|e| RpcError::new(EDITOR_ERROR, e.to_string()))
+            }),
+        );
+
+        self
+    }
+
+    /// Looks up a buffer by the id handed out to the client.
+    pub fn buffer(&self, id: u64) -> MethodResult<E::BufferHandle> {
+        self.buffers.get(id)
+    }
+
+    fn editor_methods(&mut self) {
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/currentBuffer", move |_: NoParams| {
+            Ok(buffers.id(editor.current_buffer()?))
+        });
+
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/newBuffer", move |_: NoParams| {
+            Ok(buffers.id(editor.new_buffer()?))
+        });
+
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/buffers", move |_: NoParams| {
+            Ok(editor
+                .buffers()?
+                .into_iter()
+                .map(|b| buffers.id(b))
+                .collect::<Vec<_>>())
+        });
+
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/bufferByName", move |p: NameParams| {
+            Ok(editor.buffer_by_name(&p.name)?.map(|b| buffers.id(b)))
+        });
+
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/bufferName", move |p: BufferParams| {
+            Ok(editor.buffer_name(&buffers.get(p.buffer)?)?)
+        });
+
+        let (editor, buffers) = (self.editor.clone(), self.buffers.clone());
+        self.method("editor/setCurrentBuffer", move |p: BufferParams| {
+            let buffer = buffers.get(p.buffer)?;
+
+            Ok(editor.set_current_buffer(&mut *buffer.write_timeout()?)?)
+        });
+    }
+
+    fn buffer_methods(&mut self) {
+        let buffers = self.buffers.clone();
+        self.method("buffer/getContent", move |p: BufferParams| {
+            Ok(buffers.get(p.buffer)?.read_timeout()?.get_content()?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/setContent", move |p: ContentParams| {
+            Ok(buffers
+                .get(p.buffer)?
+                .write_timeout()?
+                .set_content(&p.text)?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/getText", move |p: TextParams| {
+            Ok(buffers
+                .get(p.buffer)?
+                .read_timeout()?
+                .get_text((&p.start, &p.end))?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/setText", move |p: TextParams| {
+            let text = p.text.unwrap_or_default();
+
+            Ok(buffers
+                .get(p.buffer)?
+                .write_timeout()?
+                .set_text((&p.start, &p.end), &text)?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/lineCount", move |p: BufferParams| {
+            Ok(buffers.get(p.buffer)?.read_timeout()?.line_count()?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/changedtick", move |p: BufferParams| {
+            Ok(buffers.get(p.buffer)?.changedtick()?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("buffer/release", move |p: BufferParams| {
+            buffers.remove(p.buffer).map(drop)
+        });
+    }
+
+    /// Handles a request or notification, returning the response to requests.
+    pub fn handle(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some(method) => {
+                trace!(method, "Server request");
+
+                match self.methods.get(method) {
+                    Some(handler) => handler(message.get("params").cloned().unwrap_or(json!({}))),
+                    None => Err(RpcError::new(
+                        METHOD_NOT_FOUND,
+                        format!("Method not found: {method}"),
+                    )),
+                }
+            }
+            None => Err(RpcError::new(INVALID_REQUEST, "Missing method")),
+        };
+
+        // Notifications get no response, even on errors
+        let id = id?;
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        })
+    }
+
+    /// Handles messages until the stream ends, one at a time.
+    ///
+    /// Messages that aren't valid JSON get a parse error, invalid framing ends the stream.
+    pub fn serve(&self, reader: impl Read, mut writer: impl Write) -> std::io::Result<()> {
+        let mut reader = BufReader::new(reader);
+
+        while let Some(body) = read_body(&mut reader)? {
+            let response = match serde_json::from_slice(&body) {
+                Ok(message) => self.handle(message),
+                Err(e) => Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": format!("Parse error: {e}") },
+                })),
+            };
+
+            if let Some(response) = response {
+                write_message(&mut writer, &response)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serves on a separate thread, the editor being reached through its own dispatching.
+    pub fn spawn(
+        self,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> std::io::Result<JoinHandle<std::io::Result<()>>> {
+        std::thread::Builder::new()
+            .name("eel-server".into())
+            .spawn(move || {
+                let result = self.serve(reader, writer);
+
+                if let Err(e) = &result {
+                    warn!("Server stopped: {e}");
+                }
+
+                result
+            })
+    }
+
+    pub fn spawn_stdio(self) -> std::io::Result<JoinHandle<std::io::Result<()>>> {
+        self.spawn(std::io::stdin(), std::io::stdout())
+    }
+}
+
+#[cfg(feature = "cursor")]
+impl<E> Server<E>
+where
+    E: Editor,
+    <E::BufferHandle as BufferHandle>::ReadBuffer: crate::cursor::CursorReadBuffer,
+    <E::BufferHandle as BufferHandle>::WriteBuffer: crate::cursor::CursorWriteBuffer,
+{
+    pub fn cursor(mut self) -> Self {
+        use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+        let buffers = self.buffers.clone();
+        self.method("cursor/get", move |p: BufferParams| {
+            Ok(buffers.get(p.buffer)?.read_timeout()?.get_cursor()?)
+        });
+
+        let buffers = self.buffers.clone();
+        self.method("cursor/set", move |p: PositionParams| {
+            Ok(buffers
+                .get(p.buffer)?
+                .write_timeout()?
+                .set_cursor(&p.position)?)
+        });
+
+        self
+    }
+}
+
+#[cfg(feature = "mark")]
+#[derive(serde::Deserialize)]
+struct MarkParams {
+    mark: u64,
+    position: Option<Position>,
+}
+
+#[cfg(feature = "mark")]
+impl<E> Server<E>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    pub fn mark(mut self) -> Self {
+        use crate::mark::Mark;
+
+        // Released marks are destroyed once nothing else holds them
+        let marks = Arc::new(Handles::<(E::BufferHandle, Mark<E::BufferHandle>)>::new(
+            "mark",
+        ));
+
+        let (buffers, handles) = (self.buffers.clone(), marks.clone());
+        self.method("mark/create", move |p: PositionParams| {
+            let buffer = buffers.get(p.buffer)?;
+            let mark = Mark::lock_new(&buffer, &p.position)?;
+
+            Ok(handles.insert((buffer, mark)))
+        });
+
+        let handles = marks.clone();
+        self.method("mark/get", move |p: MarkParams| {
+            let (_, mark) = handles.get(p.mark)?;
+
+            Ok(mark.lock_read().get_position()?)
+        });
+
+        let handles = marks.clone();
+        self.method("mark/set", move |p: MarkParams| {
+            let (buffer, mark) = handles.get(p.mark)?;
+            let position = p
+                .position
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing position"))?;
+
+            Ok(mark
+                .write(buffer.write_timeout()?)
+                .set_position(&position)?)
+        });
+
+        self.method("mark/release", move |p: MarkParams| {
+            marks.remove(p.mark).map(drop)
+        });
+
+        self
+    }
+}
+
+#[cfg(feature = "region")]
+#[derive(serde::Deserialize)]
+struct RegionParams {
+    region: u64,
+    text: Option<String>,
+}
+
+#[cfg(feature = "region")]
+impl<E> Server<E>
+where
+    E: Editor,
+    E::BufferHandle: crate::mark::MarkBufferHandle,
+{
+    pub fn region(mut self) -> Self {
+        use crate::region::BufferRegion;
+
+        let regions = Arc::new(Handles::<BufferRegion<E::BufferHandle>>::new("region"));
+
+        let (buffers, handles) = (self.buffers.clone(), regions.clone());
+        self.method("region/create", move |p: TextParams| {
+            let buffer = buffers.get(p.buffer)?;
+
+            Ok(handles.insert(BufferRegion::lock_new(&buffer, (&p.start, &p.end))?))
+        });
+
+        let handles = regions.clone();
+        self.method("region/bounds", move |p: RegionParams| {
+            Ok(handles.get(p.region)?.bounds()?)
+        });
+
+        let handles = regions.clone();
+        self.method("region/getContent", move |p: RegionParams| {
+            Ok(handles.get(p.region)?.read_timeout()?.get_content()?)
+        });
+
+        let handles = regions.clone();
+        self.method("region/setContent", move |p: RegionParams| {
+            let text = p
+                .text
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing text"))?;
+
+            Ok(handles.get(p.region)?.write_timeout()?.set_content(&text)?)
+        });
+
+        self.method("region/release", move |p: RegionParams| {
+            regions.remove(p.region).map(drop)
+        });
+
+        self
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    fn request<E: Editor>(server: &Server<E>, method: &str, params: Value) -> Value {
+        let response = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .expect("Request without response");
+
+        match response.get("error") {
+            Some(error) => panic!("{method} failed: {error}"),
+            None => response["result"].clone(),
+        }
+    }
+
+    fn error_code<E: Editor>(server: &Server<E>, method: &str, params: Value) -> i64 {
+        let response = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .expect("Request without response");
+
+        response["error"]["code"]
+            .as_i64()
+            .unwrap_or_else(|| panic!("{method} didn't fail: {response}"))
+    }
+
+    pub fn test_server_buffer(editor: impl Editor) {
+        let server = Server::new(editor);
+
+        let buffer = request(&server, "editor/newBuffer", json!({}));
+        request(
+            &server,
+            "buffer/setContent",
+            json!({ "buffer": buffer, "text": "Hello\nworld" }),
+        );
+
+        assert_eq!(
+            request(&server, "buffer/getContent", json!({ "buffer": buffer })),
+            json!("Hello\nworld")
+        );
+        assert_eq!(
+            request(&server, "buffer/lineCount", json!({ "buffer": buffer })),
+            json!(2)
+        );
+
+        request(
+            &server,
+            "buffer/setText",
+            json!({
+                "buffer": buffer,
+                "start": { "row": 1, "col": 0 },
+                "end": { "row": 1, "col": 5 },
+                "text": "there",
+            }),
+        );
+        assert_eq!(
+            request(
+                &server,
+                "buffer/getText",
+                json!({
+                    "buffer": buffer,
+                    "start": { "row": 0, "col": 3 },
+                    "end": { "row": 1, "col": 2 },
+                }),
+            ),
+            json!("lo\nth")
+        );
+
+        // Same buffer, same id
+        request(
+            &server,
+            "editor/setCurrentBuffer",
+            json!({ "buffer": buffer }),
+        );
+        assert_eq!(request(&server, "editor/currentBuffer", json!({})), buffer);
+        assert!(
+            request(&server, "editor/buffers", json!({}))
+                .as_array()
+                .expect("Buffers are a list")
+                .contains(&buffer)
+        );
+
+        // Released ids are gone, the buffer isn't
+        let handle = server
+            .buffer(buffer.as_u64().expect("Buffer id is a number"))
+            .expect("Unknown buffer");
+        request(&server, "buffer/release", json!({ "buffer": buffer }));
+        assert_eq!(
+            error_code(&server, "buffer/getContent", json!({ "buffer": buffer })),
+            UNKNOWN_ID
+        );
+        assert_eq!(
+            handle.read().get_content().expect("Failed to get content"),
+            "Hello\nthere"
+        );
+    }
+
+    pub fn test_server_errors(editor: impl Editor) {
+        let server = Server::new(editor);
+
+        assert_eq!(
+            error_code(&server, "editor/notAMethod", json!({})),
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            error_code(&server, "buffer/getContent", json!({ "buf": 1 })),
+            INVALID_PARAMS
+        );
+
+        let buffer = request(&server, "editor/newBuffer", json!({}));
+        assert_eq!(
+            error_code(
+                &server,
+                "buffer/getText",
+                json!({
+                    "buffer": buffer,
+                    "start": { "row": 5, "col": 0 },
+                    "end": { "row": 5, "col": 0 },
+                }),
+            ),
+            EDITOR_ERROR
+        );
+
+        // No response to notifications
+        assert!(
+            server
+                .handle(json!({ "jsonrpc": "2.0", "method": "editor/newBuffer" }))
+                .is_none()
+        );
+    }
+
+    pub fn test_server_stream(editor: impl Editor) {
+        let (server_reader, mut client_writer) = std::io::pipe().expect("Failed to create pipe");
+        let (client_reader, server_writer) = std::io::pipe().expect("Failed to create pipe");
+
+        let server = Server::new(editor)
+            .spawn(server_reader, server_writer)
+            .expect("Failed to spawn server");
+
+        let mut client_reader = BufReader::new(client_reader);
+        let mut call = move |id: u64, method: &str, params: Value| {
+            let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            write_message(&mut client_writer, &message).expect("Failed to send request");
+
+            let response = read_message(&mut client_reader)
+                .expect("Failed to read response")
+                .expect("Server closed the stream");
+            assert_eq!(response["id"], id);
+
+            response["result"].clone()
+        };
+
+        let buffer = call(1, "editor/newBuffer", json!({}));
+        call(
+            2,
+            "buffer/setContent",
+            json!({ "buffer": buffer, "text": "Remote" }),
+        );
+        assert_eq!(
+            call(3, "buffer/getContent", json!({ "buffer": buffer })),
+            json!("Remote")
+        );
+
+        // Closing the stream stops the server
+        drop(call);
+
+        server
+            .join()
+            .expect("Server thread panicked")
+            .expect("Server failed");
+    }
+
+    /// A message that isn't JSON gets an error, and the server keeps going.
+    pub fn test_server_parse_error(editor: impl Editor) {
+        let (server_reader, mut client_writer) = std::io::pipe().expect("Failed to create pipe");
+        let (client_reader, server_writer) = std::io::pipe().expect("Failed to create pipe");
+
+        let server = Server::new(editor)
+            .spawn(server_reader, server_writer)
+            .expect("Failed to spawn server");
+        let mut client_reader = BufReader::new(client_reader);
+
+        let garbage = b"{ not json";
+        write!(client_writer, "Content-Length: {}\r\n\r\n", garbage.len())
+            .and_then(|()| client_writer.write_all(garbage))
+            .expect("Failed to send garbage");
+
+        let response = read_message(&mut client_reader)
+            .expect("Failed to read response")
+            .expect("Server closed the stream");
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": "editor/newBuffer" });
+        write_message(&mut client_writer, &message).expect("Failed to send request");
+
+        let response = read_message(&mut client_reader)
+            .expect("Failed to read response")
+            .expect("Server closed the stream");
+        assert_eq!(response["id"], 1);
+        assert!(response.get("result").is_some(), "{response}");
+
+        drop(client_writer);
+        server
+            .join()
+            .expect("Server thread panicked")
+            .expect("Server failed");
+    }
+
+    #[cfg(feature = "mark")]
+    pub fn test_server_marks<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        let server = Server::new(editor).mark();
+
+        let buffer = request(&server, "editor/newBuffer", json!({}));
+        request(
+            &server,
+            "buffer/setContent",
+            json!({ "buffer": buffer, "text": "Hello world" }),
+        );
+
+        let mark = request(
+            &server,
+            "mark/create",
+            json!({ "buffer": buffer, "position": { "row": 0, "col": 6 } }),
+        );
+
+        request(
+            &server,
+            "buffer/setText",
+            json!({
+                "buffer": buffer,
+                "start": { "row": 0, "col": 0 },
+                "end": { "row": 0, "col": 0 },
+                "text": ">> ",
+            }),
+        );
+        assert_eq!(
+            request(&server, "mark/get", json!({ "mark": mark })),
+            json!({ "row": 0, "col": 9 })
+        );
+
+        request(
+            &server,
+            "mark/set",
+            json!({ "mark": mark, "position": { "row": 0, "col": 1 } }),
+        );
+        assert_eq!(
+            request(&server, "mark/get", json!({ "mark": mark })),
+            json!({ "row": 0, "col": 1 })
+        );
+
+        request(&server, "mark/release", json!({ "mark": mark }));
+        assert_eq!(
+            error_code(&server, "mark/get", json!({ "mark": mark })),
+            UNKNOWN_ID
+        );
+
+        #[cfg(feature = "region")]
+        {
+            let server = server.region();
+
+            let region = request(
+                &server,
+                "region/create",
+                json!({
+                    "buffer": buffer,
+                    "start": { "row": 0, "col": 3 },
+                    "end": { "row": 0, "col": 8 },
+                }),
+            );
+
+            assert_eq!(
+                request(&server, "region/getContent", json!({ "region": region })),
+                json!("Hello")
+            );
+
+            request(
+                &server,
+                "region/setContent",
+                json!({ "region": region, "text": "Goodbye" }),
+            );
+            assert_eq!(
+                request(&server, "buffer/getContent", json!({ "buffer": buffer })),
+                json!(">> Goodbye world")
+            );
+            assert_eq!(
+                request(&server, "region/bounds", json!({ "region": region })),
+                json!([{ "row": 0, "col": 3 }, { "row": 0, "col": 10 }])
+            );
+        }
+    }
+
+    #[macro_export]
+    #[cfg(feature = "mark")]
+    macro_rules! eel_server_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::server::tests,
+                prefix: $prefix,
+                tests: [
+                    test_server_buffer,
+                    test_server_errors,
+                    test_server_stream,
+                    test_server_parse_error,
+                ],
+            );
+
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::server::tests,
+                prefix: $prefix,
+                tests: [test_server_marks],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_server_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_server_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::server::tests,
+                prefix: $prefix,
+                tests: [
+                    test_server_buffer,
+                    test_server_errors,
+                    test_server_stream,
+                    test_server_parse_error,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_server_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn message_length_limit() {
+        let mut reader = Cursor::new(b"Content-Length: 18446744073709551615\r\n\r\n".to_vec());
+        let error = read_message(&mut reader).expect_err("Read an oversized message");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let header = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_LENGTH + 1);
+        assert!(read_message(&mut Cursor::new(header.into_bytes())).is_err());
+    }
+}
@@ -0,0 +1,750 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{
+    Editor, Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Session IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Session format error: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BufferSession {
+    pub name: Option<String>,
+    pub content: String,
+    pub cursor: Option<Position>,
+    #[serde(default)]
+    pub marks: BTreeMap<String, Position>,
+    #[serde(default)]
+    pub regions: BTreeMap<String, (Position, Position)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    pub buffers: Vec<BufferSession>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).map_err(Error::from)?;
+
+        Ok(serde_json::from_reader(std::io::BufReader::new(file)).map_err(Error::from)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(Error::from)?;
+
+        Ok(std::fs::write(path, json).map_err(Error::from)?)
+    }
+}
+
+/// Named values (marks, regions) attached to a buffer through [`BufferHandle::data`], so they
+/// can be saved with the session.
+///
+/// Marks and regions keep a strong handle to their buffer, the buffer is only released once
+/// the backend clears its data.
+pub struct Named<T> {
+    values: Mutex<BTreeMap<String, T>>,
+}
+
+impl<T> Default for Named<T> {
+    fn default() -> Self {
+        Self {
+            values: Mutex::default(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Named<T> {
+    pub fn of(buffer: &impl BufferHandle) -> Arc<Self> {
+        buffer.data().get_or_insert_with(Self::default)
+    }
+
+    fn values(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, T>> {
+        self.values.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn insert(&self, name: impl Into<String>, value: T) -> Option<T> {
+        self.values().insert(name.into(), value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<T> {
+        self.values().get(name).cloned()
+    }
+
+    pub fn remove(&self, name: &str) -> Option<T> {
+        self.values().remove(name)
+    }
+
+    pub fn entries(&self) -> Vec<(String, T)> {
+        self.values()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "cursor")]
+mod cursor {
+    use super::*;
+
+    use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+    pub trait CursorSession {
+        fn save_cursor(&self) -> Result<Option<Position>>;
+        fn restore_cursor(&self, cursor: &Position) -> Result<()>;
+    }
+
+    impl<B> CursorSession for B
+    where
+        B: BufferHandle,
+        B::ReadBuffer: CursorReadBuffer,
+        B::WriteBuffer: CursorWriteBuffer,
+    {
+        fn save_cursor(&self) -> Result<Option<Position>> {
+            Ok(Some(self.read_timeout()?.get_cursor()?))
+        }
+
+        fn restore_cursor(&self, cursor: &Position) -> Result<()> {
+            self.write_timeout()?.set_cursor(cursor)
+        }
+    }
+}
+
+#[cfg(not(feature = "cursor"))]
+mod cursor {
+    use super::*;
+
+    pub trait CursorSession {
+        fn save_cursor(&self) -> Result<Option<Position>> {
+            Ok(None)
+        }
+
+        fn restore_cursor(&self, _cursor: &Position) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<T> CursorSession for T {}
+}
+
+#[cfg(feature = "mark")]
+mod mark {
+    use super::*;
+
+    use crate::mark::{Mark, MarkBufferHandle};
+
+    pub type NamedMarks<B> = Named<Mark<B>>;
+
+    pub trait MarkSession {
+        fn save_marks(&self) -> Result<BTreeMap<String, Position>>;
+        fn restore_marks(&self, marks: &BTreeMap<String, Position>) -> Result<()>;
+    }
+
+    impl<B: MarkBufferHandle> MarkSession for B {
+        fn save_marks(&self) -> Result<BTreeMap<String, Position>> {
+            let lock = self.read_timeout()?;
+
+            NamedMarks::<B>::of(self)
+                .entries()
+                .into_iter()
+                .map(|(name, mark)| Ok((name, mark.read(&*lock).get_position()?)))
+                .collect()
+        }
+
+        fn restore_marks(&self, marks: &BTreeMap<String, Position>) -> Result<()> {
+            let named = NamedMarks::of(self);
+
+            for (name, position) in marks {
+                named.insert(name, Mark::lock_new(self, position)?);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// FNV-1a, unlike [`std::hash::DefaultHasher`] it's guaranteed to stay the same across
+    /// Rust versions, which matters for saved hashes.
+    fn line_hash(line: &str) -> u64 {
+        line.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    pub struct SavedMark {
+        pub position: Position,
+        /// Hash of the mark's line, to find it again if the file changed in between.
+        pub line_hash: u64,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct RestoredMarks {
+        /// Found at the saved position.
+        pub kept: Vec<String>,
+        /// Found on another line with the same content.
+        pub moved: Vec<String>,
+        /// Line not found, not restored.
+        pub lost: Vec<String>,
+    }
+
+    /// Named marks of all buffers by buffer name, saved without the buffer content (unlike
+    /// [`Session`]), so they can be restored onto files that changed since.
+    ///
+    /// A mark is restored at its saved position if its line is unchanged, otherwise on the
+    /// closest line with the same content, if there's one.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    pub struct MarkStore {
+        pub buffers: BTreeMap<String, BTreeMap<String, SavedMark>>,
+    }
+
+    impl MarkStore {
+        /// An empty store if `path` doesn't exist.
+        pub fn load(path: &Path) -> Result<Self> {
+            match std::fs::File::open(path) {
+                Ok(file) => {
+                    Ok(serde_json::from_reader(std::io::BufReader::new(file))
+                        .map_err(Error::from)?)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+                Err(e) => Err(Error::from(e))?,
+            }
+        }
+
+        pub fn save(&self, path: &Path) -> Result<()> {
+            let json = serde_json::to_string_pretty(self).map_err(Error::from)?;
+
+            Ok(std::fs::write(path, json).map_err(Error::from)?)
+        }
+
+        /// Replaces the saved marks of `name` with the buffer's named marks.
+        pub fn update_buffer<B: MarkBufferHandle>(&mut self, name: &str, buffer: &B) -> Result<()> {
+            let lock = buffer.read_timeout()?;
+
+            let marks = NamedMarks::<B>::of(buffer)
+                .entries()
+                .into_iter()
+                .map(|(mark_name, mark)| {
+                    let position = mark.read(&*lock).get_position()?;
+                    let line_hash = line_hash(&lock.get_line(position.row)?);
+
+                    Ok((
+                        mark_name,
+                        SavedMark {
+                            position,
+                            line_hash,
+                        },
+                    ))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()?;
+
+            if marks.is_empty() {
+                self.buffers.remove(name);
+            } else {
+                self.buffers.insert(name.to_string(), marks);
+            }
+
+            Ok(())
+        }
+
+        /// Updates the marks of all loaded buffers with a name, keeping the others.
+        ///
+        /// Marks of buffers loaded without restoring them first get dropped.
+        pub fn update<E>(&mut self, editor: &E) -> Result<()>
+        where
+            E: Editor,
+            E::BufferHandle: MarkBufferHandle,
+        {
+            for buffer in editor.buffers()? {
+                if let Some(name) = editor.buffer_name(&buffer)? {
+                    self.update_buffer(&name, &buffer)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Adds the saved marks of `name` to the buffer's named marks.
+        pub fn restore_buffer<B: MarkBufferHandle>(
+            &self,
+            name: &str,
+            buffer: &B,
+        ) -> Result<RestoredMarks> {
+            let mut restored = RestoredMarks::default();
+
+            let Some(marks) = self.buffers.get(name) else {
+                return Ok(restored);
+            };
+
+            let hashes = buffer
+                .read_timeout()?
+                .get_all_lines()?
+                .map(|line| line_hash(&line))
+                .collect::<Vec<_>>();
+
+            let named = NamedMarks::of(buffer);
+
+            for (mark_name, saved) in marks {
+                let row = saved.position.row;
+
+                let found = if hashes.get(row) == Some(&saved.line_hash) {
+                    restored.kept.push(mark_name.clone());
+                    Some(row)
+                } else {
+                    let closest = hashes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, hash)| **hash == saved.line_hash)
+                        .min_by_key(|(i, _)| i.abs_diff(row))
+                        .map(|(i, _)| i);
+
+                    match closest {
+                        Some(_) => restored.moved.push(mark_name.clone()),
+                        None => restored.lost.push(mark_name.clone()),
+                    }
+
+                    closest
+                };
+
+                if let Some(row) = found {
+                    let position = Position::new(row, saved.position.col);
+                    named.insert(mark_name, Mark::lock_new(buffer, &position)?);
+                }
+            }
+
+            Ok(restored)
+        }
+
+        /// Restores the marks of all loaded buffers with a name.
+        pub fn restore<E>(&self, editor: &E) -> Result<RestoredMarks>
+        where
+            E: Editor,
+            E::BufferHandle: MarkBufferHandle,
+        {
+            let mut restored = RestoredMarks::default();
+
+            for buffer in editor.buffers()? {
+                let Some(name) = editor.buffer_name(&buffer)? else {
+                    continue;
+                };
+
+                let buffer_restored = self.restore_buffer(&name, &buffer)?;
+
+                restored.kept.extend(buffer_restored.kept);
+                restored.moved.extend(buffer_restored.moved);
+                restored.lost.extend(buffer_restored.lost);
+            }
+
+            Ok(restored)
+        }
+    }
+}
+
+#[cfg(not(feature = "mark"))]
+mod mark {
+    use super::*;
+
+    pub trait MarkSession {
+        fn save_marks(&self) -> Result<BTreeMap<String, Position>> {
+            Ok(BTreeMap::new())
+        }
+
+        fn restore_marks(&self, _marks: &BTreeMap<String, Position>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<T> MarkSession for T {}
+}
+
+#[cfg(feature = "region")]
+mod region {
+    use super::*;
+
+    use crate::{mark::MarkBufferHandle, region::BufferRegion};
+
+    pub type NamedRegions<B> = Named<BufferRegion<B>>;
+
+    pub trait RegionSession {
+        fn save_regions(&self) -> Result<BTreeMap<String, (Position, Position)>>;
+        fn restore_regions(&self, regions: &BTreeMap<String, (Position, Position)>) -> Result<()>;
+    }
+
+    impl<B: MarkBufferHandle> RegionSession for B {
+        fn save_regions(&self) -> Result<BTreeMap<String, (Position, Position)>> {
+            NamedRegions::<B>::of(self)
+                .entries()
+                .into_iter()
+                .map(|(name, region)| Ok((name, region.bounds()?)))
+                .collect()
+        }
+
+        fn restore_regions(&self, regions: &BTreeMap<String, (Position, Position)>) -> Result<()> {
+            let named = NamedRegions::of(self);
+
+            for (name, (start, end)) in regions {
+                named.insert(name, BufferRegion::lock_new(self, (start, end))?);
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(feature = "region"))]
+mod region {
+    use super::*;
+
+    pub trait RegionSession {
+        fn save_regions(&self) -> Result<BTreeMap<String, (Position, Position)>> {
+            Ok(BTreeMap::new())
+        }
+
+        fn restore_regions(&self, _regions: &BTreeMap<String, (Position, Position)>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<T> RegionSession for T {}
+}
+
+pub use cursor::CursorSession;
+pub use mark::MarkSession;
+pub use region::RegionSession;
+
+#[cfg(feature = "mark")]
+pub use mark::{MarkStore, NamedMarks, RestoredMarks, SavedMark};
+#[cfg(feature = "region")]
+pub use region::NamedRegions;
+
+/// Buffers whose state can be saved, covering whatever features are enabled.
+pub trait SessionBufferHandle: BufferHandle + CursorSession + MarkSession + RegionSession {}
+
+impl<B> SessionBufferHandle for B where B: BufferHandle + CursorSession + MarkSession + RegionSession
+{}
+
+pub fn capture<E>(editor: &E) -> Result<Session>
+where
+    E: Editor,
+    E::BufferHandle: SessionBufferHandle,
+{
+    let buffers = editor
+        .buffers()?
+        .into_iter()
+        .map(|buffer| {
+            Ok(BufferSession {
+                name: editor.buffer_name(&buffer)?,
+                content: buffer.read_timeout()?.get_content()?,
+                cursor: buffer.save_cursor()?,
+                marks: buffer.save_marks()?,
+                regions: buffer.save_regions()?,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Session { buffers })
+}
+
+/// Named buffers that are open already are reused, the rest gets recreated as new buffers.
+///
+/// Returns the restored buffers in the same order as in the session.
+pub fn restore<E>(editor: &E, session: &Session) -> Result<Vec<E::BufferHandle>>
+where
+    E: Editor,
+    E::BufferHandle: SessionBufferHandle,
+{
+    session
+        .buffers
+        .iter()
+        .map(|state| {
+            let existing = match &state.name {
+                Some(name) => editor.buffer_by_name(name)?,
+                None => None,
+            };
+
+            let buffer = match existing {
+                Some(buffer) => buffer,
+                None => editor.new_buffer()?,
+            };
+
+            {
+                let mut lock = buffer.write_timeout()?;
+
+                if lock.get_content()? != state.content {
+                    lock.set_content(&state.content)?;
+                }
+            }
+
+            if let Some(cursor) = &state.cursor {
+                buffer.restore_cursor(cursor)?;
+            }
+
+            buffer.restore_marks(&state.marks)?;
+            buffer.restore_regions(&state.regions)?;
+
+            Ok(buffer)
+        })
+        .collect()
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{assert_buffer_content, test_utils::new_buffer_with_content};
+
+    fn session_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("eel-session-{}-{name}.json", std::process::id()))
+    }
+
+    pub fn test_session_roundtrip<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SessionBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let path = session_path("roundtrip");
+        editor.save_session(&path).expect("Failed to save session");
+
+        let session = Session::load(&path).expect("Failed to load session");
+        std::fs::remove_file(&path).expect("Failed to remove session file");
+
+        let state = session
+            .buffers
+            .iter()
+            .find(|b| b.content == "First line\nSecond line")
+            .expect("Buffer missing from session");
+
+        let restored = restore(
+            &editor,
+            &Session {
+                buffers: vec![state.clone()],
+            },
+        )
+        .expect("Failed to restore session");
+
+        assert_eq!(restored.len(), 1);
+        assert!(restored[0] != buffer);
+        assert_buffer_content!(restored[0], "First line\nSecond line");
+    }
+
+    #[cfg(feature = "region")]
+    pub fn test_session_marks_regions<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle + SessionBufferHandle,
+    {
+        use crate::{mark::Mark, region::BufferRegion};
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        NamedMarks::of(&buffer).insert(
+            "start",
+            Mark::lock_new(&buffer, &Position::new(1, 0)).expect("Failed to create mark"),
+        );
+        NamedRegions::of(&buffer).insert(
+            "word",
+            BufferRegion::lock_new(&buffer, (Position::new(0, 6), Position::new(0, 10)))
+                .expect("Failed to create region"),
+        );
+
+        let state = BufferSession {
+            name: None,
+            content: buffer.read().get_content().expect("Failed to get content"),
+            cursor: buffer.save_cursor().expect("Failed to save cursor"),
+            marks: buffer.save_marks().expect("Failed to save marks"),
+            regions: buffer.save_regions().expect("Failed to save regions"),
+        };
+
+        assert_eq!(state.marks["start"], Position::new(1, 0));
+        assert_eq!(
+            state.regions["word"],
+            (Position::new(0, 6), Position::new(0, 10))
+        );
+
+        let restored = restore(
+            &editor,
+            &Session {
+                buffers: vec![state],
+            },
+        )
+        .expect("Failed to restore session")
+        .remove(0);
+
+        let mark = NamedMarks::<E::BufferHandle>::of(&restored)
+            .get("start")
+            .expect("Mark wasn't restored");
+        assert_eq!(
+            mark.lock_read()
+                .get_position()
+                .expect("Failed to get position"),
+            Position::new(1, 0)
+        );
+
+        let region = NamedRegions::<E::BufferHandle>::of(&restored)
+            .get("word")
+            .expect("Region wasn't restored");
+        assert_buffer_content!(region, "line");
+    }
+
+    #[cfg(feature = "mark")]
+    pub fn test_session_mark_store<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: crate::mark::MarkBufferHandle,
+    {
+        use crate::mark::Mark;
+
+        let buffer = new_buffer_with_content(&editor, "use std::io;\nfn main() {\n    todo!()\n}");
+
+        let named = NamedMarks::of(&buffer);
+        for (name, position) in [
+            ("import", Position::new(0, 4)),
+            ("main", Position::new(1, 3)),
+            ("todo", Position::new(2, 4)),
+        ] {
+            named.insert(
+                name,
+                Mark::lock_new(&buffer, &position).expect("Failed to create mark"),
+            );
+        }
+
+        let mut store = MarkStore::default();
+        store
+            .update_buffer("main.rs", &buffer)
+            .expect("Failed to update marks");
+
+        let path = session_path("marks");
+        store.save(&path).expect("Failed to save marks");
+        let loaded = MarkStore::load(&path).expect("Failed to load marks");
+        std::fs::remove_file(&path).expect("Failed to remove marks file");
+
+        assert_eq!(loaded, store);
+        assert_eq!(
+            MarkStore::load(&session_path("missing")).expect("Failed to load marks"),
+            MarkStore::default()
+        );
+
+        // The file changed in between
+        let changed = new_buffer_with_content(
+            &editor,
+            "use std::io;\nfn run() {\n    let x = 1;\n    todo!()\n}",
+        );
+
+        let restored = loaded
+            .restore_buffer("main.rs", &changed)
+            .expect("Failed to restore marks");
+        assert_eq!(
+            restored,
+            RestoredMarks {
+                kept: vec!["import".into()],
+                moved: vec!["todo".into()],
+                lost: vec!["main".into()],
+            }
+        );
+
+        let named = NamedMarks::<E::BufferHandle>::of(&changed);
+        assert!(named.get("main").is_none());
+        assert_eq!(
+            named
+                .get("todo")
+                .expect("Mark wasn't restored")
+                .lock_read()
+                .get_position()
+                .expect("Failed to get position"),
+            Position::new(3, 4)
+        );
+
+        let empty = new_buffer_with_content(&editor, "");
+        store
+            .update_buffer("main.rs", &empty)
+            .expect("Failed to update marks");
+        assert!(store.buffers.is_empty());
+    }
+
+    #[macro_export]
+    #[cfg(feature = "mark")]
+    macro_rules! eel_session_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::mark::MarkBufferHandle },
+                module_path: $crate::session::tests,
+                prefix: $prefix,
+                tests: [test_session_mark_store],
+            );
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "mark"))]
+    macro_rules! eel_session_mark_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {};
+    }
+
+    #[macro_export]
+    #[cfg(feature = "region")]
+    macro_rules! eel_session_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::session::SessionBufferHandle },
+                module_path: $crate::session::tests,
+                prefix: $prefix,
+                tests: [test_session_roundtrip],
+            );
+
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    E::BufferHandle: $crate::mark::MarkBufferHandle
+                        + $crate::session::SessionBufferHandle
+                },
+                module_path: $crate::session::tests,
+                prefix: $prefix,
+                tests: [test_session_marks_regions],
+            );
+
+            $crate::eel_session_mark_tests!($test_tag, $editor_factory, $prefix);
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_session_tests!($test_tag, $editor_factory, "");
+        };
+    }
+
+    #[macro_export]
+    #[cfg(not(feature = "region"))]
+    macro_rules! eel_session_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::session::SessionBufferHandle },
+                module_path: $crate::session::tests,
+                prefix: $prefix,
+                tests: [test_session_roundtrip],
+            );
+
+            $crate::eel_session_mark_tests!($test_tag, $editor_factory, $prefix);
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_session_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
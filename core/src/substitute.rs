@@ -0,0 +1,414 @@
+use tracing::debug;
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{ReadBuffer, clear_on_drop},
+    decoration::{DecorationId, DecorationReadBuffer, DecorationWriteBuffer, VirtualLine},
+    mark::{Gravity, MarkBufferHandle, MarkReadBuffer, MarkWriteBuffer},
+    region::BufferRegion,
+    search::SearchQuery,
+    tracing::ResultExt,
+    workspace::TextEdit,
+};
+
+/// Highlight of the text a [`Substitution`] replaces.
+pub const MATCH_HIGHLIGHT: &str = "IncSearch";
+/// Highlight of the replacement shown after each match.
+pub const REPLACEMENT_HIGHLIGHT: &str = "Substitute";
+
+pub trait SubstituteBufferHandle:
+    MarkBufferHandle<MReadBuffer = Self::SReadBuffer, MWriteBuffer = Self::SWriteBuffer>
+{
+    type DecorationId: DecorationId;
+    type SReadBuffer: MarkReadBuffer<MarkId = Self::MarkId>
+        + DecorationReadBuffer<DecorationId = Self::DecorationId>;
+    type SWriteBuffer: MarkWriteBuffer<MarkId = Self::MarkId>
+        + DecorationWriteBuffer<DecorationId = Self::DecorationId>;
+}
+
+impl<B, D> SubstituteBufferHandle for B
+where
+    B: MarkBufferHandle,
+    D: DecorationId,
+    B::MReadBuffer: DecorationReadBuffer<DecorationId = D>,
+    B::MWriteBuffer: DecorationWriteBuffer<DecorationId = D>,
+{
+    type DecorationId = D;
+    type SReadBuffer = B::MReadBuffer;
+    type SWriteBuffer = B::MWriteBuffer;
+}
+
+#[derive(Debug)]
+struct Match<B: SubstituteBufferHandle> {
+    start: B::MarkId,
+    end: B::MarkId,
+    highlight: B::DecorationId,
+    replacement: Option<B::DecorationId>,
+}
+
+/// Search and replace shown as a preview until [committed](Substitution::commit): matches are
+/// highlighted and followed by their replacement, without changing the buffer.
+///
+/// Matches are anchored to marks, so they follow edits made during the preview. Meant to be
+/// [updated](Substitution::update) as the user types the pattern or replacement. The preview
+/// is removed when dropped.
+#[derive(Debug)]
+pub struct Substitution<B: SubstituteBufferHandle> {
+    buffer: B,
+    /// Bounds of the region the matches are limited to.
+    region: Option<(B::MarkId, B::MarkId)>,
+    query: SearchQuery,
+    replacement: String,
+    matches: Vec<Match<B>>,
+    done: bool,
+}
+
+impl<B: SubstituteBufferHandle> Substitution<B> {
+    /// Previews replacing every match of `query` in the buffer, its `roots` are ignored.
+    pub fn preview(buffer: &B, query: SearchQuery, replacement: &str) -> Result<Self> {
+        Self::start(buffer.clone(), None, query, replacement)
+    }
+
+    /// Like [`Substitution::preview`], limited to matches inside the region wherever it moves.
+    pub fn preview_in(
+        region: &BufferRegion<B>,
+        query: SearchQuery,
+        replacement: &str,
+    ) -> Result<Self> {
+        let buffer = region.buffer().clone();
+        let (start, end) = region.bounds()?;
+
+        // Same gravity as the region's marks
+        let mut lock = buffer.write_timeout()?;
+        let bounds = (
+            lock.create_mark_with(&start, None, Gravity::Left)?,
+            lock.create_mark_with(&end, None, Gravity::Right)?,
+        );
+        drop(lock);
+
+        Self::start(buffer, Some(bounds), query, replacement)
+    }
+
+    fn start(
+        buffer: B,
+        region: Option<(B::MarkId, B::MarkId)>,
+        query: SearchQuery,
+        replacement: &str,
+    ) -> Result<Self> {
+        let mut substitution = Self {
+            buffer,
+            region,
+            query,
+            replacement: replacement.to_string(),
+            matches: Vec::new(),
+            done: false,
+        };
+
+        substitution.render()?;
+
+        Ok(substitution)
+    }
+
+    pub fn query(&self) -> &SearchQuery {
+        &self.query
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// Searches again with the new pattern and replacement.
+    pub fn update(&mut self, query: SearchQuery, replacement: &str) -> Result<()> {
+        self.query = query;
+        self.replacement = replacement.to_string();
+
+        self.render()
+    }
+
+    /// Current ranges of the matches, by position.
+    pub fn matches(&self) -> Result<Vec<PosRange>> {
+        let lock = self.buffer.read_timeout()?;
+
+        self.matches
+            .iter()
+            .map(|m| {
+                Ok(PosRange::new(
+                    lock.get_mark_position(m.start)?,
+                    lock.get_mark_position(m.end)?,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    fn bounds(&self, buffer: &B::WriteBuffer) -> Result<(Position, Position)> {
+        match self.region {
+            Some((start, end)) => Ok((
+                buffer.get_mark_position(start)?,
+                buffer.get_mark_position(end)?,
+            )),
+            None => Ok((Position::origin(), buffer.max_pos()?)),
+        }
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let mut lock = self.buffer.write_timeout()?;
+        let buffer = &mut *lock;
+
+        self.clear(buffer)?;
+
+        let (start, end) = self.bounds(buffer)?;
+        let lines = buffer
+            .get_lines(start.row..(end.row + 1))?
+            .collect::<Vec<_>>();
+
+        let preview = VirtualLine::new()
+            .highlighted(self.replacement.replace('\n', "\\n"), REPLACEMENT_HIGHLIGHT);
+
+        for (row, line) in (start.row..).zip(lines) {
+            for col in self.query.matches(&line) {
+                let match_start = Position::new(row, col);
+                let match_end = Position::new(row, col + self.query.pattern.len());
+
+                if match_start < start || match_end > end {
+                    continue;
+                }
+
+                // Text inserted at either end stays out of the match
+                let start_mark = buffer.create_mark_with(&match_start, None, Gravity::Right)?;
+                let end_mark = buffer.create_mark_with(&match_end, None, Gravity::Left)?;
+
+                let highlight =
+                    buffer.highlight_range((&match_start, &match_end), MATCH_HIGHLIGHT)?;
+                let replacement = match self.replacement.is_empty() {
+                    true => None,
+                    false => Some(buffer.add_virtual_text(&match_end, preview.clone())?),
+                };
+
+                self.matches.push(Match {
+                    start: start_mark,
+                    end: end_mark,
+                    highlight,
+                    replacement,
+                });
+            }
+        }
+
+        debug!("Previewing {} substitutions", self.matches.len());
+
+        Ok(())
+    }
+
+    fn clear(&mut self, buffer: &mut B::WriteBuffer) -> Result<()> {
+        for m in self.matches.drain(..) {
+            buffer.remove_highlight(m.highlight)?;
+
+            if let Some(id) = m.replacement {
+                buffer.remove_virtual_text(id)?;
+            }
+
+            buffer.destroy_mark(m.start)?;
+            buffer.destroy_mark(m.end)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, buffer: &mut B::WriteBuffer) -> Result<()> {
+        self.done = true;
+        self.clear(buffer)?;
+
+        if let Some((start, end)) = self.region.take() {
+            buffer.destroy_mark(start)?;
+            buffer.destroy_mark(end)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the matches still holding matching text in one transaction, see
+    /// [`workspace::apply_to`](crate::workspace::apply_to). Returns how many were replaced.
+    pub fn commit(mut self) -> Result<usize> {
+        let mut lock = self.buffer.write_timeout()?;
+        let buffer = &mut *lock;
+
+        let mut edits = Vec::new();
+        for m in &self.matches {
+            let (start, end) = (
+                buffer.get_mark_position(m.start)?,
+                buffer.get_mark_position(m.end)?,
+            );
+
+            let text = buffer.get_text((&start, &end))?;
+            if self.query.matches(&text) == [0] && text.len() == self.query.pattern.len() {
+                edits.push(TextEdit::new(start, end, self.replacement.as_str()));
+            }
+        }
+
+        self.finish(buffer)?;
+
+        crate::workspace::apply_to(buffer, &edits)?;
+
+        Ok(edits.len())
+    }
+
+    /// Removes the preview right away, unlike dropping.
+    pub fn abort(mut self) -> Result<()> {
+        let mut lock = self.buffer.write_timeout()?;
+
+        self.finish(&mut *lock)
+    }
+}
+
+impl<B: SubstituteBufferHandle> Drop for Substitution<B> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        debug!("Removing substitution preview");
+
+        let (matches, region) = (std::mem::take(&mut self.matches), self.region.take());
+        clear_on_drop(self.buffer.clone(), "substitution", move |buffer| {
+            for mark in region.into_iter().flat_map(|(start, end)| [start, end]) {
+                _ = buffer
+                    .destroy_mark(mark)
+                    .log_err_msg("Failed to destroy substitution mark");
+            }
+
+            for m in matches {
+                _ = buffer
+                    .remove_highlight(m.highlight)
+                    .log_err_msg("Failed to remove substitution highlight");
+
+                if let Some(id) = m.replacement {
+                    _ = buffer
+                        .remove_virtual_text(id)
+                        .log_err_msg("Failed to remove substitution preview");
+                }
+
+                _ = buffer
+                    .destroy_mark(m.start)
+                    .log_err_msg("Failed to destroy substitution mark");
+                _ = buffer
+                    .destroy_mark(m.end)
+                    .log_err_msg("Failed to destroy substitution mark");
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use crate::{
+        Editor, assert_buffer_content,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+
+    use super::*;
+
+    pub fn test_substitute_commit<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SubstituteBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "let a = 1;\nlet b = a + a;");
+
+        let mut substitution =
+            Substitution::preview(&buffer, SearchQuery::new("a"), "x").expect("Failed to preview");
+
+        assert_eq!(substitution.len(), 3);
+        assert_buffer_content!(buffer, "let a = 1;\nlet b = a + a;");
+
+        // Matches follow edits made during the preview
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(0, 0)), "// top\n")
+            .expect("Failed to set text");
+
+        assert_eq!(
+            substitution.matches().expect("Failed to get matches"),
+            [
+                PosRange::new(Position::new(1, 4), Position::new(1, 5)),
+                PosRange::new(Position::new(2, 8), Position::new(2, 9)),
+                PosRange::new(Position::new(2, 12), Position::new(2, 13)),
+            ]
+        );
+
+        let mut query = SearchQuery::new("A");
+        query.ignore_case = true;
+        substitution
+            .update(query, "value")
+            .expect("Failed to update");
+        assert_eq!(substitution.replacement(), "value");
+        assert_eq!(substitution.len(), 3);
+
+        // Matches whose text changed are skipped
+        buffer
+            .write()
+            .set_text((Position::new(2, 12), Position::new(2, 13)), "c")
+            .expect("Failed to set text");
+
+        assert_eq!(substitution.commit().expect("Failed to commit"), 2);
+        assert_buffer_content!(buffer, "// top\nlet value = 1;\nlet b = value + c;");
+    }
+
+    pub fn test_substitute_region<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SubstituteBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "a a\na a\na a");
+
+        let region = BufferRegion::lock_new(&buffer, (Position::new(0, 2), Position::new(1, 1)))
+            .expect("Failed to create region");
+
+        let substitution = Substitution::preview_in(&region, SearchQuery::new("a"), "b")
+            .expect("Failed to preview");
+        assert_eq!(
+            substitution.matches().expect("Failed to get matches"),
+            [
+                PosRange::new(Position::new(0, 2), Position::new(0, 3)),
+                PosRange::new(Position::new(1, 0), Position::new(1, 1)),
+            ]
+        );
+
+        substitution.abort().expect("Failed to abort");
+        assert_buffer_content!(buffer, "a a\na a\na a");
+
+        let substitution = Substitution::preview_in(&region, SearchQuery::new("a"), "")
+            .expect("Failed to preview");
+        assert_eq!(substitution.commit().expect("Failed to commit"), 2);
+        assert_buffer_content!(buffer, "a \n a\na a");
+
+        let substitution =
+            Substitution::preview(&buffer, SearchQuery::new(""), "b").expect("Failed to preview");
+        assert!(substitution.is_empty());
+    }
+
+    #[macro_export]
+    macro_rules! eel_substitute_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::substitute::SubstituteBufferHandle },
+                module_path: $crate::substitute::tests,
+                prefix: $prefix,
+                tests: [test_substitute_commit, test_substitute_region],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_substitute_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
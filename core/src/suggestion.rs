@@ -0,0 +1,406 @@
+use tracing::debug;
+
+use crate::{
+    Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer, clear_on_drop},
+    cursor::{CursorReadBuffer, CursorWriteBuffer},
+    decoration::{
+        DecorationId, DecorationReadBuffer, DecorationWriteBuffer, Placement, VirtualLine,
+    },
+    mark::{Gravity, MarkId, MarkReadBuffer, MarkWriteBuffer},
+    tracing::ResultExt,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Suggestion was invalidated")]
+    Invalidated,
+}
+
+pub trait SuggestionBufferHandle:
+    BufferHandle<ReadBuffer = Self::SReadBuffer, WriteBuffer = Self::SWriteBuffer>
+{
+    type MarkId: MarkId;
+    type DecorationId: DecorationId;
+    type SReadBuffer: CursorReadBuffer
+        + MarkReadBuffer<MarkId = Self::MarkId>
+        + DecorationReadBuffer<DecorationId = Self::DecorationId>;
+    type SWriteBuffer: CursorWriteBuffer
+        + MarkWriteBuffer<MarkId = Self::MarkId>
+        + DecorationWriteBuffer<DecorationId = Self::DecorationId>;
+}
+
+impl<B, M, D> SuggestionBufferHandle for B
+where
+    B: BufferHandle,
+    M: MarkId,
+    D: DecorationId,
+    B::ReadBuffer:
+        CursorReadBuffer + MarkReadBuffer<MarkId = M> + DecorationReadBuffer<DecorationId = D>,
+    B::WriteBuffer:
+        CursorWriteBuffer + MarkWriteBuffer<MarkId = M> + DecorationWriteBuffer<DecorationId = D>,
+{
+    type MarkId = M;
+    type DecorationId = D;
+    type SReadBuffer = B::ReadBuffer;
+    type SWriteBuffer = B::WriteBuffer;
+}
+
+/// Suggested text shown at a position without being part of the buffer until accepted.
+///
+/// The first line is shown inline and the rest as virtual lines below it. Editors don't report
+/// cursor movement, so [`GhostText::refresh`] should be called whenever the cursor moves or
+/// the buffer changes. The suggestion is hidden when dropped.
+#[derive(Debug)]
+pub struct GhostText<B: SuggestionBufferHandle> {
+    buffer: B,
+    anchor: B::MarkId,
+    text: String,
+    highlight: Option<String>,
+    inline: Option<B::DecorationId>,
+    lines: Option<B::DecorationId>,
+    hidden: bool,
+}
+
+/// Shows `text` at `position`, which should be the cursor position.
+pub fn show_ghost_text<B: SuggestionBufferHandle>(
+    buffer: &B,
+    position: &Position,
+    text: &str,
+) -> Result<GhostText<B>> {
+    GhostText::show(buffer, position, text, None)
+}
+
+impl<B: SuggestionBufferHandle> GhostText<B> {
+    pub fn show(
+        buffer: &B,
+        position: &Position,
+        text: &str,
+        highlight: Option<&str>,
+    ) -> Result<Self> {
+        let mut lock = buffer.write_timeout()?;
+
+        // Left gravity keeps the anchor before the text typed at it
+        let anchor = lock.create_mark_with(position, None, Gravity::Left)?;
+
+        let mut ghost_text = Self {
+            buffer: buffer.clone(),
+            anchor,
+            text: text.to_string(),
+            highlight: highlight.map(String::from),
+            inline: None,
+            lines: None,
+            hidden: false,
+        };
+
+        ghost_text.render(&mut *lock, position)?;
+        drop(lock);
+
+        Ok(ghost_text)
+    }
+
+    /// Part of the suggestion not typed yet.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Where the suggestion gets inserted.
+    pub fn position(&self) -> Result<Position> {
+        self.buffer.read_timeout()?.get_mark_position(self.anchor)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn chunk(&self, text: &str) -> VirtualLine {
+        match &self.highlight {
+            Some(highlight) => VirtualLine::new().highlighted(text, highlight),
+            None => VirtualLine::new().text(text),
+        }
+    }
+
+    fn render(&mut self, buffer: &mut B::WriteBuffer, position: &Position) -> Result<()> {
+        self.clear(buffer)?;
+
+        let mut lines = self.text.split('\n');
+
+        let first = lines.next().unwrap_or_default();
+        if !first.is_empty() {
+            self.inline = Some(buffer.add_virtual_text(position, self.chunk(first))?);
+        }
+
+        let rest: Vec<_> = lines.map(|line| self.chunk(line)).collect();
+        if !rest.is_empty() {
+            self.lines = Some(buffer.add_virtual_lines(position.row, rest, Placement::Below)?);
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, buffer: &mut B::WriteBuffer) -> Result<()> {
+        if let Some(id) = self.inline.take() {
+            buffer.remove_virtual_text(id)?;
+        }
+
+        if let Some(id) = self.lines.take() {
+            buffer.remove_virtual_lines(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn hide(&mut self, buffer: &mut B::WriteBuffer) -> Result<()> {
+        self.hidden = true;
+
+        self.clear(buffer)?;
+        buffer.destroy_mark(self.anchor)
+    }
+
+    /// Checks the suggestion against the cursor, returning whether it's still shown.
+    ///
+    /// Text typed at the anchor that matches the start of the suggestion is consumed from it,
+    /// anything else hides it for good.
+    pub fn refresh(&mut self) -> Result<bool> {
+        let mut lock = self.buffer.write_timeout()?;
+
+        self.refresh_locked(&mut *lock)
+    }
+
+    fn refresh_locked(&mut self, buffer: &mut B::WriteBuffer) -> Result<bool> {
+        if self.hidden {
+            return Ok(false);
+        }
+
+        let anchor = buffer.get_mark_position(self.anchor)?;
+        let cursor = buffer.get_cursor()?;
+
+        if cursor == anchor {
+            return Ok(true);
+        }
+
+        if cursor.row != anchor.row || cursor.col < anchor.col {
+            self.hide(buffer)?;
+            return Ok(false);
+        }
+
+        let typed = buffer.get_text((&anchor, &cursor))?;
+        let Some(rest) = self.text.strip_prefix(&typed) else {
+            self.hide(buffer)?;
+            return Ok(false);
+        };
+
+        debug!("Typed {typed:?} of the suggestion");
+
+        self.text = rest.to_string();
+        if self.text.is_empty() {
+            self.hide(buffer)?;
+            return Ok(false);
+        }
+
+        buffer.set_mark_position(self.anchor, &cursor)?;
+        self.render(buffer, &cursor)?;
+
+        Ok(true)
+    }
+
+    /// Inserts the rest of the suggestion at the anchor and moves the cursor after it, fails
+    /// if the suggestion was invalidated.
+    pub fn accept(mut self) -> Result<()> {
+        let mut lock = self.buffer.write_timeout()?;
+
+        if !self.refresh_locked(&mut *lock)? {
+            Err(Error::Invalidated)?;
+        }
+
+        let position = lock.get_mark_position(self.anchor)?;
+        self.hide(&mut *lock)?;
+
+        lock.set_text((&position, &position), &self.text)?;
+        lock.set_cursor(&position.offset(&Position::max_text_pos(&self.text)))
+    }
+
+    /// Hides the suggestion right away, unlike dropping.
+    pub fn dismiss(mut self) -> Result<()> {
+        let mut lock = self.buffer.write_timeout()?;
+
+        self.hide(&mut *lock)
+    }
+}
+
+impl<B: SuggestionBufferHandle> Drop for GhostText<B> {
+    fn drop(&mut self) {
+        if self.hidden {
+            return;
+        }
+
+        debug!("Hiding ghost text");
+
+        let (anchor, inline, lines) = (self.anchor, self.inline, self.lines);
+        clear_on_drop(self.buffer.clone(), "ghost text", move |buffer| {
+            _ = buffer
+                .destroy_mark(anchor)
+                .log_err_msg("Failed to destroy ghost text anchor");
+
+            if let Some(id) = inline {
+                _ = buffer
+                    .remove_virtual_text(id)
+                    .log_err_msg("Failed to hide ghost text");
+            }
+
+            if let Some(id) = lines {
+                _ = buffer
+                    .remove_virtual_lines(id)
+                    .log_err_msg("Failed to hide ghost text");
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use crate::{Editor, assert_buffer_content, test_utils::new_buffer_with_content};
+
+    use super::*;
+
+    pub fn test_ghost_text_accept<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SuggestionBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "fn main() {\n}");
+        let position = Position::new(0, 11);
+
+        buffer
+            .write()
+            .set_cursor(&position)
+            .expect("Failed to set cursor");
+
+        let ghost_text = show_ghost_text(&buffer, &position, "\n    todo!()")
+            .expect("Failed to show ghost text");
+
+        assert_buffer_content!(buffer, "fn main() {\n}");
+        assert_eq!(ghost_text.text(), "\n    todo!()");
+
+        ghost_text.accept().expect("Failed to accept suggestion");
+
+        assert_buffer_content!(buffer, "fn main() {\n    todo!()\n}");
+        assert_eq!(
+            buffer.read().get_cursor().expect("Failed to get cursor"),
+            Position::new(1, 11)
+        );
+    }
+
+    pub fn test_ghost_text_typing<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SuggestionBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "let x = ");
+        let position = Position::new(0, 8);
+
+        buffer
+            .write()
+            .set_cursor(&position)
+            .expect("Failed to set cursor");
+
+        let mut ghost_text =
+            show_ghost_text(&buffer, &position, "vec![1];").expect("Failed to show ghost text");
+
+        assert!(ghost_text.refresh().expect("Failed to refresh"));
+
+        let mut lock = buffer.write();
+        lock.set_text((&position, &position), "vec")
+            .expect("Failed to set text");
+        lock.set_cursor(&Position::new(0, 11))
+            .expect("Failed to set cursor");
+        drop(lock);
+
+        assert!(ghost_text.refresh().expect("Failed to refresh"));
+        assert_eq!(ghost_text.text(), "![1];");
+        assert_eq!(
+            ghost_text.position().expect("Failed to get position"),
+            Position::new(0, 11)
+        );
+
+        ghost_text.accept().expect("Failed to accept suggestion");
+
+        assert_buffer_content!(buffer, "let x = vec![1];");
+    }
+
+    pub fn test_ghost_text_invalidation<E>(editor: E)
+    where
+        E: Editor,
+        E::BufferHandle: SuggestionBufferHandle,
+    {
+        let buffer = new_buffer_with_content(&editor, "let x = \nlet y = ");
+        let position = Position::new(0, 8);
+
+        buffer
+            .write()
+            .set_cursor(&position)
+            .expect("Failed to set cursor");
+
+        let mut ghost_text =
+            show_ghost_text(&buffer, &position, "1;").expect("Failed to show ghost text");
+
+        buffer
+            .write()
+            .set_cursor(&Position::new(1, 8))
+            .expect("Failed to set cursor");
+
+        assert!(!ghost_text.refresh().expect("Failed to refresh"));
+        assert!(ghost_text.is_hidden());
+
+        // Moving back doesn't bring it back
+        buffer
+            .write()
+            .set_cursor(&position)
+            .expect("Failed to set cursor");
+
+        assert!(!ghost_text.refresh().expect("Failed to refresh"));
+        assert!(ghost_text.accept().is_err());
+        assert_buffer_content!(buffer, "let x = \nlet y = ");
+
+        let mut ghost_text =
+            show_ghost_text(&buffer, &position, "1;").expect("Failed to show ghost text");
+
+        let mut lock = buffer.write();
+        lock.set_text((&position, &position), "2")
+            .expect("Failed to set text");
+        lock.set_cursor(&Position::new(0, 9))
+            .expect("Failed to set cursor");
+        drop(lock);
+
+        assert!(!ghost_text.refresh().expect("Failed to refresh"));
+
+        show_ghost_text(&buffer, &position, "1;")
+            .expect("Failed to show ghost text")
+            .dismiss()
+            .expect("Failed to dismiss suggestion");
+
+        assert_buffer_content!(buffer, "let x = 2\nlet y = ");
+    }
+
+    #[macro_export]
+    macro_rules! eel_suggestion_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: { E::BufferHandle: $crate::suggestion::SuggestionBufferHandle },
+                module_path: $crate::suggestion::tests,
+                prefix: $prefix,
+                tests: [
+                    test_ghost_text_accept,
+                    test_ghost_text_typing,
+                    test_ghost_text_invalidation,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_suggestion_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
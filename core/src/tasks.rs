@@ -0,0 +1,420 @@
+use std::{
+    collections::BTreeMap,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex, MutexGuard, OnceLock, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use crate::Result;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Task was cancelled")]
+    Cancelled,
+
+    #[error("No task named {0}")]
+    UnknownTask(String),
+
+    #[error("Failed to spawn task thread: {0}")]
+    Spawn(#[from] std::io::Error),
+}
+
+/// Cancellation is cooperative, jobs are expected to check the token between steps.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Fails with [`Error::Cancelled`] once cancelled, for jobs to bail out with `?`.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)?;
+        }
+
+        Ok(())
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    Running,
+    Finished,
+    Failed(String),
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self, TaskStatus::Running)
+    }
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskStatus::Running => write!(f, "running"),
+            TaskStatus::Finished => write!(f, "finished"),
+            TaskStatus::Failed(message) => write!(f, "failed: {message}"),
+            TaskStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskInfo {
+    pub name: String,
+    pub status: TaskStatus,
+    /// Start of the latest run.
+    pub started: Instant,
+    /// Time the latest run took, or has been running for.
+    pub elapsed: Duration,
+    pub runs: usize,
+}
+
+type Job = Arc<dyn Fn(&CancelToken) -> Result<()> + Send + Sync>;
+
+#[derive(Debug)]
+struct Run {
+    token: CancelToken,
+    started: Instant,
+    /// The status with the time it was set, shared with the run's thread
+    status: Arc<Mutex<(TaskStatus, Instant)>>,
+}
+
+struct Task {
+    job: Job,
+    run: Run,
+    runs: usize,
+}
+
+/// Tracks named jobs running on their own threads, so they can be listed, cancelled and
+/// restarted.
+///
+/// Names are unique, spawning a job under a name in use cancels the previous one.
+#[derive(Default)]
+pub struct Tasks {
+    tasks: Mutex<BTreeMap<String, Task>>,
+}
+
+impl std::fmt::Debug for Tasks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tasks")
+            .field("tasks", &self.tasks().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Supervisor used by [`crate::Editor::tasks`] unless the backend has its own.
+    pub fn global() -> &'static Tasks {
+        static TASKS: OnceLock<Tasks> = OnceLock::new();
+
+        TASKS.get_or_init(Tasks::new)
+    }
+
+    fn tasks(&self) -> MutexGuard<'_, BTreeMap<String, Task>> {
+        lock(&self.tasks)
+    }
+
+    fn start(name: &str, job: Job) -> Result<Run> {
+        let run = Run {
+            token: CancelToken::default(),
+            started: Instant::now(),
+            status: Arc::new(Mutex::new((TaskStatus::Running, Instant::now()))),
+        };
+
+        let (task_name, token, status) = (name.to_string(), run.token.clone(), run.status.clone());
+
+        std::thread::Builder::new()
+            .name(format!("eel-task-{name}"))
+            .spawn(move || {
+                let result = std::panic::catch_unwind(AssertUnwindSafe(|| job(&token)));
+
+                let result = match result {
+                    _ if token.is_cancelled() => TaskStatus::Cancelled,
+                    Ok(Ok(())) => TaskStatus::Finished,
+                    Ok(Err(e)) => {
+                        warn!("Task {task_name} failed: {e}");
+                        TaskStatus::Failed(e.to_string())
+                    }
+                    Err(_) => {
+                        warn!("Task {task_name} panicked");
+                        TaskStatus::Failed("panicked".into())
+                    }
+                };
+
+                debug!("Task {task_name} ended: {result}");
+
+                *lock(&status) = (result, Instant::now());
+            })
+            .map_err(Error::from)?;
+
+        Ok(run)
+    }
+
+    /// Runs `job` on a new thread, it gets the token cancelling it.
+    pub fn spawn(
+        &self,
+        name: &str,
+        job: impl Fn(&CancelToken) -> Result<()> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let job: Job = Arc::new(job);
+        let mut tasks = self.tasks();
+
+        let runs = match tasks.remove(name) {
+            Some(previous) => {
+                previous.run.token.cancel();
+                previous.runs
+            }
+            None => 0,
+        };
+
+        let run = Self::start(name, job.clone())?;
+
+        tasks.insert(
+            name.to_string(),
+            Task {
+                job,
+                run,
+                runs: runs + 1,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Returns whether the task was running.
+    pub fn cancel(&self, name: &str) -> bool {
+        let tasks = self.tasks();
+
+        let Some(task) = tasks.get(name) else {
+            return false;
+        };
+
+        let running = lock(&task.run.status).0.is_running();
+        task.run.token.cancel();
+
+        running
+    }
+
+    /// Cancels the task if it's running and runs its job again.
+    pub fn restart(&self, name: &str) -> Result<()> {
+        let mut tasks = self.tasks();
+
+        let task = tasks
+            .get_mut(name)
+            .ok_or_else(|| Error::UnknownTask(name.to_string()))?;
+
+        task.run.token.cancel();
+        task.run = Self::start(name, task.job.clone())?;
+        task.runs += 1;
+
+        Ok(())
+    }
+
+    pub fn status(&self, name: &str) -> Option<TaskStatus> {
+        let tasks = self.tasks();
+
+        tasks.get(name).map(|task| lock(&task.run.status).0.clone())
+    }
+
+    /// All tasks sorted by name, including the ones that ended.
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.tasks()
+            .iter()
+            .map(|(name, task)| {
+                let (status, changed) = lock(&task.run.status).clone();
+
+                let elapsed = match status {
+                    TaskStatus::Running => task.run.started.elapsed(),
+                    _ => changed.saturating_duration_since(task.run.started),
+                };
+
+                TaskInfo {
+                    name: name.clone(),
+                    status,
+                    started: task.run.started,
+                    elapsed,
+                    runs: task.runs,
+                }
+            })
+            .collect()
+    }
+
+    /// Drops the tasks that aren't running, returning how many.
+    pub fn clear_ended(&self) -> usize {
+        let mut tasks = self.tasks();
+        let before = tasks.len();
+
+        tasks.retain(|_, task| lock(&task.run.status).0.is_running());
+
+        before - tasks.len()
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use crate::Editor;
+
+    use super::*;
+
+    fn wait_for(tasks: &Tasks, name: &str, status: TaskStatus) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while tasks.status(name).as_ref() != Some(&status) {
+            assert!(
+                Instant::now() < deadline,
+                "Task {name} is {:?}, expected {status:?}",
+                tasks.status(name)
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Runs until cancelled.
+    fn looping(token: &CancelToken) -> Result<()> {
+        loop {
+            token.check()?;
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    pub fn test_tasks_status(editor: impl Editor) {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let tasks = editor.tasks();
+        let name = format!("eel-test-{}", COUNTER.fetch_add(1, Ordering::Relaxed));
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let rx = Mutex::new(rx);
+        tasks
+            .spawn(&name, move |_| {
+                _ = lock(&rx).recv();
+                Ok(())
+            })
+            .expect("Failed to spawn task");
+
+        assert_eq!(tasks.status(&name), Some(TaskStatus::Running));
+        assert!(
+            tasks
+                .list()
+                .iter()
+                .any(|info| info.name == name && info.runs == 1)
+        );
+
+        drop(tx);
+        wait_for(tasks, &name, TaskStatus::Finished);
+
+        let local = Tasks::new();
+
+        local
+            .spawn("failing", |_| {
+                Err(crate::buffer::Error::Custom("Lint failed".into()))?
+            })
+            .expect("Failed to spawn task");
+        wait_for(
+            &local,
+            "failing",
+            TaskStatus::Failed("Buffer error: Error: Lint failed".into()),
+        );
+
+        local
+            .spawn("panicking", |_| panic!("Task panic"))
+            .expect("Failed to spawn task");
+        wait_for(&local, "panicking", TaskStatus::Failed("panicked".into()));
+
+        assert_eq!(local.status("missing"), None);
+        assert_eq!(
+            local
+                .list()
+                .into_iter()
+                .map(|info| info.name)
+                .collect::<Vec<_>>(),
+            ["failing", "panicking"]
+        );
+
+        assert_eq!(local.clear_ended(), 2);
+        assert!(local.list().is_empty());
+    }
+
+    pub fn test_tasks_cancel_restart(_editor: impl Editor) {
+        let tasks = Tasks::new();
+        let started = Arc::new(AtomicUsize::new(0));
+
+        let counter = started.clone();
+        tasks
+            .spawn("lint", move |token| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                looping(token)
+            })
+            .expect("Failed to spawn task");
+
+        assert!(tasks.cancel("lint"));
+        wait_for(&tasks, "lint", TaskStatus::Cancelled);
+        assert!(!tasks.cancel("lint"));
+        assert!(!tasks.cancel("missing"));
+
+        tasks.restart("lint").expect("Failed to restart task");
+        assert_eq!(tasks.status("lint"), Some(TaskStatus::Running));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while started.load(Ordering::SeqCst) < 2 {
+            assert!(Instant::now() < deadline, "Task wasn't restarted");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        // Spawning under the same name replaces the running job
+        tasks
+            .spawn("lint", |_| Ok(()))
+            .expect("Failed to spawn task");
+        wait_for(&tasks, "lint", TaskStatus::Finished);
+
+        let info = tasks.list().pop().expect("Task missing");
+        assert_eq!(info.runs, 3);
+
+        assert!(tasks.restart("missing").is_err());
+    }
+
+    #[macro_export]
+    macro_rules! eel_tasks_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::tasks::tests,
+                prefix: $prefix,
+                tests: [test_tasks_status, test_tasks_cancel_restart],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_tasks_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::Write as _;
+
+use itertools::Itertools as _;
+
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+fn diff_ops(expected: &[&str], actual: &[&str]) -> Vec<Op> {
+    let (n, m) = (expected.len(), actual.len());
+
+    // lcs[i][j] - LCS length of expected[i..] and actual[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(Op::Insert(j));
+            j += 1;
+        } else {
+            ops.push(Op::Delete(i));
+            i += 1;
+        }
+    }
+
+    ops
+}
+
+/// Makes whitespace visible: spaces become `·`, tabs `→` and every line ends with `⏎`
+/// (except the last one, which isn't followed by a newline).
+fn visible(line: &str, last: bool) -> String {
+    let mut line = line.replace(' ', "·").replace('\t', "→");
+
+    if !last {
+        line.push('⏎');
+    }
+
+    line
+}
+
+/// Renders a unified diff between `expected` and `actual`, with whitespace made visible.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+    let expected = expected.split('\n').collect_vec();
+    let actual = actual.split('\n').collect_vec();
+
+    let e_line = |i: usize| visible(expected[i], i + 1 == expected.len());
+    let a_line = |j: usize| visible(actual[j], j + 1 == actual.len());
+
+    let ops = diff_ops(&expected, &actual);
+
+    let changes = ops
+        .iter()
+        .positions(|op| !matches!(op, Op::Equal(..)))
+        .collect_vec();
+
+    // Group changes into hunks, merging the ones with overlapping context
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for c in changes {
+        let start = c.saturating_sub(CONTEXT);
+        let end = (c + CONTEXT + 1).min(ops.len());
+
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+
+        let e_start = hunk.iter().find_map(|op| match op {
+            Op::Equal(i, _) | Op::Delete(i) => Some(*i),
+            Op::Insert(_) => None,
+        });
+        let a_start = hunk.iter().find_map(|op| match op {
+            Op::Equal(_, j) | Op::Insert(j) => Some(*j),
+            Op::Delete(_) => None,
+        });
+        let e_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let a_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+
+        _ = writeln!(
+            out,
+            "@@ -{},{e_len} +{},{a_len} @@",
+            e_start.map_or(0, |i| i + 1),
+            a_start.map_or(0, |j| j + 1),
+        );
+
+        for op in hunk {
+            _ = match op {
+                Op::Equal(i, _) => writeln!(out, " {}", e_line(*i)),
+                Op::Delete(i) => writeln!(out, "-{}", e_line(*i)),
+                Op::Insert(j) => writeln!(out, "+{}", a_line(*j)),
+            };
+        }
+    }
+
+    out
+}
+
+#[cfg(feature = "cursor")]
+/// Inverse of [`super::parse_buffer_state`], inserts the `|` cursor marker into content.
+pub fn format_buffer_state(content: &str, cursor: &crate::Position) -> String {
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(row, line)| {
+            if row == cursor.row && cursor.col <= line.len() && line.is_char_boundary(cursor.col) {
+                format!("{}|{}", &line[..cursor.col], &line[cursor.col..])
+            } else {
+                line.to_string()
+            }
+        })
+        .join("\n")
+}
+
+#[track_caller]
+pub fn assert_content_eq(actual: &str, expected: &str) {
+    if actual != expected {
+        panic!(
+            "Buffer content mismatch\n{}",
+            unified_diff(expected, actual)
+        );
+    }
+}
+
+#[cfg(feature = "cursor")]
+#[track_caller]
+pub fn assert_state_eq(
+    actual_content: &str,
+    actual_cursor: &crate::Position,
+    expected_state: &str,
+) {
+    let (content, cursor) = super::parse_buffer_state(expected_state);
+
+    if actual_content != content || *actual_cursor != cursor {
+        panic!(
+            "Buffer state mismatch (expected cursor {cursor:?}, actual {actual_cursor:?})\n{}",
+            unified_diff(
+                expected_state,
+                &format_buffer_state(actual_content, actual_cursor)
+            )
+        );
+    }
+}
@@ -0,0 +1,559 @@
+use std::{
+    ops::RangeBounds,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    Capabilities, Editor, Position, Result,
+    buffer::{
+        BufferData, BufferHandle, ReadBuffer, ReadBufferLock, WeakBufferHandle, WriteBuffer,
+        WriteBufferLock,
+    },
+    indent::IndentSettings,
+    test_utils::EditorFactory,
+};
+
+#[derive(thiserror::Error, Debug)]
+#[error("Injected fault: {0}")]
+pub struct InjectedFault(pub String);
+
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// Every Nth `set_text` call fails with [`InjectedFault`] (counted across all buffers).
+    pub fail_set_text_every: Option<usize>,
+    /// Delay applied before acquiring every buffer lock.
+    pub lock_delay: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct Faults {
+    config: FaultConfig,
+    set_text_calls: AtomicUsize,
+    injected: AtomicUsize,
+}
+
+impl Faults {
+    fn delay_lock(&self) {
+        if let Some(delay) = self.config.lock_delay {
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn check_set_text(&self) -> Result<()> {
+        let call = self.set_text_calls.fetch_add(1, Ordering::SeqCst) + 1;
+
+        match self.config.fail_set_text_every {
+            Some(n) if n > 0 && call.is_multiple_of(n) => {
+                self.injected.fetch_add(1, Ordering::SeqCst);
+
+                Err(crate::buffer::Error::Custom(Box::new(InjectedFault(
+                    format!("set_text call #{call}"),
+                ))))?
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Wraps any [`Editor`] and injects failures configured by [`FaultConfig`], so error paths
+/// (retries, rollbacks) can be tested against a real backend.
+#[derive(Debug)]
+pub struct FaultyEditor<E: Editor> {
+    inner: E,
+    faults: Arc<Faults>,
+}
+
+impl<E: Editor> FaultyEditor<E> {
+    pub fn new(inner: E, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            faults: Arc::new(Faults {
+                config,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Number of failures injected so far.
+    pub fn injected_faults(&self) -> usize {
+        self.faults.injected.load(Ordering::SeqCst)
+    }
+
+    fn wrap(&self, inner: E::BufferHandle) -> FaultyBufferHandle<E::BufferHandle> {
+        FaultyBufferHandle {
+            inner,
+            faults: self.faults.clone(),
+        }
+    }
+}
+
+impl<E: Editor> Editor for FaultyEditor<E> {
+    type BufferHandle = FaultyBufferHandle<E::BufferHandle>;
+
+    fn current_buffer(&self) -> Result<Self::BufferHandle> {
+        Ok(self.wrap(self.inner.current_buffer()?))
+    }
+
+    fn new_buffer(&self) -> Result<Self::BufferHandle> {
+        Ok(self.wrap(self.inner.new_buffer()?))
+    }
+
+    fn set_current_buffer(
+        &self,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+    ) -> Result<()> {
+        self.inner.set_current_buffer(&mut buffer.buffer_lock)
+    }
+
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>> {
+        Ok(self
+            .inner
+            .buffers()?
+            .into_iter()
+            .map(|b| self.wrap(b))
+            .collect())
+    }
+
+    fn buffer_name(&self, buffer: &Self::BufferHandle) -> Result<Option<String>> {
+        self.inner.buffer_name(&buffer.inner)
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>> {
+        Ok(self.inner.buffer_by_name(name)?.map(|b| self.wrap(b)))
+    }
+
+    fn buffer_by_path(&self, path: &std::path::Path) -> Result<Option<Self::BufferHandle>> {
+        Ok(self.inner.buffer_by_path(path)?.map(|b| self.wrap(b)))
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<crate::OptionValue>> {
+        self.inner.get_option(name)
+    }
+
+    fn set_option(&self, name: &str, value: crate::OptionValue) -> Result<()> {
+        self.inner.set_option(name, value)
+    }
+
+    fn on_idle(
+        &self,
+        delay: std::time::Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.inner.on_idle(delay, callback)
+    }
+
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        self.inner.tasks()
+    }
+
+    #[cfg(feature = "ui")]
+    fn prompt(&self, spec: &crate::ui::PromptSpec) -> Result<crate::ui::UserResponse> {
+        self.inner.prompt(spec)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.inner.jump_back()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.inner.jump_forward()
+    }
+
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.inner.execute_normal(keys)
+    }
+
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        self.inner.detect_filetype(path, content)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FaultyBufferHandle<B: BufferHandle> {
+    inner: B,
+    faults: Arc<Faults>,
+}
+
+impl<B: BufferHandle> PartialEq for FaultyBufferHandle<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<B: BufferHandle> Eq for FaultyBufferHandle<B> {}
+
+impl<B: BufferHandle> FaultyBufferHandle<B> {
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FaultyWeakBufferHandle<B: BufferHandle> {
+    inner: B::WeakHandle,
+    faults: Arc<Faults>,
+}
+
+impl<B: BufferHandle> WeakBufferHandle for FaultyWeakBufferHandle<B> {
+    type Handle = FaultyBufferHandle<B>;
+
+    fn upgrade(&self) -> Option<Self::Handle> {
+        Some(FaultyBufferHandle {
+            inner: self.inner.upgrade()?,
+            faults: self.faults.clone(),
+        })
+    }
+}
+
+pub struct FaultyBuffer<L> {
+    buffer_lock: L,
+    faults: Arc<Faults>,
+}
+
+impl<L: ReadBufferLock> ReadBuffer for FaultyBuffer<L> {
+    fn line_count(&self) -> Result<usize> {
+        self.buffer_lock.line_count()
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        self.buffer_lock.get_lines(range)
+    }
+
+    fn get_text_in(&self, range: &crate::PosRange) -> Result<String> {
+        self.buffer_lock.get_text_in(range)
+    }
+
+    fn line_len(&self, row: usize) -> Result<usize> {
+        self.buffer_lock.line_len(row)
+    }
+
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        self.buffer_lock.char_at(position)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.buffer_lock.changedtick()
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        self.buffer_lock.indent_settings()
+    }
+}
+
+impl<L: WriteBufferLock> WriteBuffer for FaultyBuffer<L> {
+    fn set_text_in(&mut self, range: &crate::PosRange, text: &str) -> Result<()> {
+        self.faults.check_set_text()?;
+
+        self.buffer_lock.set_text_in(range, text)
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &crate::PosRange,
+        text: &str,
+    ) -> Result<()> {
+        self.faults.check_set_text()?;
+
+        self.buffer_lock
+            .set_text_if_unchanged(expected_tick, range, text)
+    }
+}
+
+impl<B: BufferHandle> BufferHandle for FaultyBufferHandle<B> {
+    type ReadBuffer = FaultyBuffer<B::ReadBufferLock>;
+    type WriteBuffer = FaultyBuffer<B::WriteBufferLock>;
+    type ReadBufferLock = Box<Self::ReadBuffer>;
+    type WriteBufferLock = Box<Self::WriteBuffer>;
+    type WeakHandle = FaultyWeakBufferHandle<B>;
+
+    fn read(&self) -> Self::ReadBufferLock {
+        self.faults.delay_lock();
+
+        Box::new(FaultyBuffer {
+            buffer_lock: self.inner.read(),
+            faults: self.faults.clone(),
+        })
+    }
+
+    fn write(&self) -> Self::WriteBufferLock {
+        self.faults.delay_lock();
+
+        Box::new(FaultyBuffer {
+            buffer_lock: self.inner.write(),
+            faults: self.faults.clone(),
+        })
+    }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        self.faults.delay_lock();
+
+        Ok(Box::new(FaultyBuffer {
+            buffer_lock: self.inner.read_timeout()?,
+            faults: self.faults.clone(),
+        }))
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        self.faults.delay_lock();
+
+        Ok(Box::new(FaultyBuffer {
+            buffer_lock: self.inner.write_timeout()?,
+            faults: self.faults.clone(),
+        }))
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        let FaultyBuffer {
+            buffer_lock,
+            faults,
+        } = *lock;
+
+        Box::new(FaultyBuffer {
+            buffer_lock: self.inner.downgrade_lock(buffer_lock),
+            faults,
+        })
+    }
+
+    fn data(&self) -> &BufferData {
+        self.inner.data()
+    }
+
+    fn downgrade(&self) -> Self::WeakHandle {
+        FaultyWeakBufferHandle {
+            inner: self.inner.downgrade(),
+            faults: self.faults.clone(),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.inner.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.inner.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.inner.changedtick()
+    }
+}
+
+#[cfg(feature = "cursor")]
+mod cursor {
+    use super::*;
+
+    use crate::cursor::{CursorReadBuffer, CursorWriteBuffer};
+
+    impl<L> CursorReadBuffer for FaultyBuffer<L>
+    where
+        L: ReadBufferLock,
+        L::ReadBuffer: CursorReadBuffer,
+    {
+        fn get_cursor(&self) -> Result<Position> {
+            self.buffer_lock.get_cursor()
+        }
+    }
+
+    impl<L> CursorWriteBuffer for FaultyBuffer<L>
+    where
+        L: WriteBufferLock,
+        L::WriteBuffer: CursorWriteBuffer,
+    {
+        fn set_cursor(&mut self, position: &Position) -> Result<()> {
+            self.buffer_lock.set_cursor(position)
+        }
+
+        fn push_jump(&mut self) -> Result<()> {
+            self.buffer_lock.push_jump()
+        }
+    }
+}
+
+#[cfg(feature = "mark")]
+mod mark {
+    use super::*;
+
+    use crate::mark::{Gravity, MarkReadBuffer, MarkWriteBuffer};
+
+    impl<L> MarkReadBuffer for FaultyBuffer<L>
+    where
+        L: ReadBufferLock,
+        L::ReadBuffer: MarkReadBuffer,
+    {
+        type MarkId = <L::ReadBuffer as MarkReadBuffer>::MarkId;
+
+        fn get_mark_position(&self, id: Self::MarkId) -> Result<Position> {
+            self.buffer_lock.get_mark_position(id)
+        }
+
+        fn get_mark_end(&self, id: Self::MarkId) -> Result<Option<Position>> {
+            self.buffer_lock.get_mark_end(id)
+        }
+    }
+
+    impl<L> MarkWriteBuffer for FaultyBuffer<L>
+    where
+        L: WriteBufferLock,
+        L::WriteBuffer: MarkWriteBuffer,
+    {
+        fn create_mark(&mut self, pos: &Position) -> Result<Self::MarkId> {
+            self.buffer_lock.create_mark(pos)
+        }
+
+        fn destroy_mark(&mut self, id: Self::MarkId) -> Result<()> {
+            self.buffer_lock.destroy_mark(id)
+        }
+
+        fn create_mark_with(
+            &mut self,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<Self::MarkId> {
+            self.buffer_lock.create_mark_with(start, end, gravity)
+        }
+
+        fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()> {
+            self.buffer_lock.set_mark_position(id, pos)
+        }
+
+        fn set_mark_gravity(&mut self, id: Self::MarkId, gravity: Gravity) -> Result<()> {
+            self.buffer_lock.set_mark_gravity(id, gravity)
+        }
+    }
+}
+
+pub fn faulty_editor_factory<E: EditorFactory + 'static>(
+    editor_factory: E,
+    config: FaultConfig,
+) -> impl EditorFactory<Editor = FaultyEditor<E::Editor>> {
+    move || FaultyEditor::new(editor_factory.create_editor(), config.clone())
+}
+
+pub mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    use crate::{assert_buffer_content, test_utils::new_buffer_with_content};
+
+    pub fn test_faulty_set_text(editor: impl Editor) {
+        let editor = FaultyEditor::new(
+            editor,
+            FaultConfig {
+                fail_set_text_every: Some(3),
+                ..Default::default()
+            },
+        );
+
+        // new_buffer_with_content calls set_text once
+        let buffer = new_buffer_with_content(&editor, "First line");
+
+        buffer.write().append(", second").expect("Failed to append");
+
+        let result = buffer.write().append(", third");
+
+        match result {
+            Err(crate::Error::Buffer(crate::buffer::Error::Custom(e))) => {
+                assert!(e.is::<InjectedFault>(), "Unexpected error: {e}");
+            }
+            r => panic!("Expected injected fault, got: {r:?}"),
+        }
+
+        assert_buffer_content!(buffer, "First line, second");
+        assert_eq!(editor.injected_faults(), 1);
+
+        buffer.write().append(", third").expect("Failed to append");
+
+        assert_buffer_content!(buffer, "First line, second, third");
+    }
+
+    pub fn test_faulty_lock_delay(editor: impl Editor) {
+        let delay = Duration::from_millis(20);
+
+        let editor = FaultyEditor::new(
+            editor,
+            FaultConfig {
+                lock_delay: Some(delay),
+                ..Default::default()
+            },
+        );
+
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+
+        let start = Instant::now();
+        drop(buffer.read());
+        drop(buffer.write());
+
+        assert!(start.elapsed() >= delay * 2, "Locks weren't delayed");
+    }
+
+    #[macro_export]
+    macro_rules! eel_faulty_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::test_utils::faulty::tests,
+                prefix: $prefix,
+                tests: [
+                    test_faulty_set_text,
+                    test_faulty_lock_delay,
+                ],
+            );
+
+            // Without any faults configured the wrapper should be transparent
+            $crate::test_utils::paste! {
+                $crate::eel_buffer_tests!(
+                    $test_tag,
+                    $crate::test_utils::faulty::faulty_editor_factory(
+                        $editor_factory,
+                        ::std::default::Default::default(),
+                    ),
+                    [< $prefix test_faulty_ >]
+                );
+
+                $crate::eel_cursor_tests!(
+                    $test_tag,
+                    $crate::test_utils::faulty::faulty_editor_factory(
+                        $editor_factory,
+                        ::std::default::Default::default(),
+                    ),
+                    [< $prefix test_faulty_ >]
+                );
+
+                $crate::eel_mark_tests!(
+                    $test_tag,
+                    $crate::test_utils::faulty::faulty_editor_factory(
+                        $editor_factory,
+                        ::std::default::Default::default(),
+                    ),
+                    [< $prefix test_faulty_ >]
+                );
+            }
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_faulty_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    buffer::{BufferHandle, WriteBuffer},
+    editor::Editor,
+};
+
+use super::new_buffer_with_content;
+
+const LOREM: &[&str] = &[
+    "Lorem ipsum dolor sit amet, consectetur adipiscing elit.",
+    "Sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.",
+    "Ut enim ad minim veniam, quis nostrud exercitation ullamco.",
+    "Duis aute irure dolor in reprehenderit in voluptate velit esse.",
+    "Excepteur sint occaecat cupidatat non proident, sunt in culpa.",
+];
+
+/// Multi-byte characters of different widths, combining marks and a wide emoji sequence.
+const UNICODE: &str = "zażółć gęślą jaźń\n\
+    Ελληνικά και русский текст\n\
+    日本語のテキスト、中文\n\
+    e\u{301}\u{301} combining, 👩\u{200d}💻 joined\n\
+    \tmixed ascii ✓ ünïcödé";
+
+/// Starting content of a buffer, for tests running on varied states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    content: String,
+}
+
+impl Fixture {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new("")
+    }
+
+    /// `lines` lines of lorem ipsum, without a trailing newline.
+    pub fn lorem(lines: usize) -> Self {
+        Self::new(
+            LOREM
+                .iter()
+                .cycle()
+                .take(lines)
+                .copied()
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    pub fn unicode() -> Self {
+        Self::new(UNICODE)
+    }
+
+    /// [`Fixture::lorem`] with `\r\n` line endings, the `\r` is part of the line content.
+    pub fn crlf(lines: usize) -> Self {
+        let mut content = Self::lorem(lines).content.replace('\n', "\r\n");
+        if lines > 0 {
+            content.push_str("\r\n");
+        }
+
+        Self::new(content)
+    }
+
+    /// Every builtin fixture, for tests that should hold for all of them.
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::empty(),
+            Self::lorem(1),
+            Self::lorem(20),
+            Self::unicode(),
+            Self::crlf(3),
+        ]
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn new_buffer<E: Editor>(&self, editor: &E) -> E::BufferHandle {
+        new_buffer_with_content(editor, &self.content)
+    }
+
+    /// New buffer with the fixture content, made the current one.
+    pub fn set_current<E: Editor>(&self, editor: &E) -> E::BufferHandle {
+        let buffer = self.new_buffer(editor);
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        buffer
+    }
+
+    /// Replaces the content of an existing buffer.
+    pub fn apply<B: BufferHandle>(&self, buffer: &B) {
+        buffer
+            .write()
+            .set_content(&self.content)
+            .expect("Failed to set buffer content");
+    }
+}
+
+impl From<&str> for Fixture {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
@@ -6,6 +6,12 @@ use crate::{
 #[doc(hidden)]
 pub use paste::paste;
 
+pub mod diff;
+pub mod faulty;
+pub mod fixture;
+#[cfg(feature = "ui")]
+pub mod scripted;
+
 #[macro_export]
 macro_rules! assert_buffer_content {
     ($buffer:expr, $content:expr) => {{
@@ -13,7 +19,10 @@ macro_rules! assert_buffer_content {
 
         let buffer = $buffer.read();
         let content = buffer.get_content().expect("Failed to get buffer content");
-        assert_eq!(content, $content)
+        $crate::test_utils::diff::assert_content_eq(
+            &content,
+            ::std::convert::AsRef::<str>::as_ref(&$content),
+        )
     }};
 }
 
@@ -67,9 +76,12 @@ mod cursor {
     #[macro_export]
     macro_rules! assert_buffer_state {
         ($buffer:expr, $state: expr) => {{
-            let (content, position) = $crate::test_utils::parse_buffer_state($state);
-            $crate::assert_buffer_content!($buffer, content);
-            $crate::assert_cursor_pos!($buffer, position);
+            use $crate::{buffer::ReadBuffer as _, cursor::CursorReadBuffer as _};
+
+            let buffer = $buffer.read();
+            let content = buffer.get_content().expect("Failed to get buffer content");
+            let cursor = buffer.get_cursor().expect("Failed to get cursor");
+            $crate::test_utils::diff::assert_state_eq(&content, &cursor, $state)
         }};
     }
 
@@ -149,11 +161,20 @@ mod cursor {
 
 #[cfg(feature = "cursor")]
 pub use cursor::*;
+pub use fixture::Fixture;
 
 pub trait EditorFactory {
     type Editor: Editor;
 
     fn create_editor(&self) -> Self::Editor;
+
+    /// Editor whose current buffer is a new one with the fixture content.
+    fn create_editor_with(&self, fixture: Fixture) -> Self::Editor {
+        let editor = self.create_editor();
+        fixture.set_current(&editor);
+
+        editor
+    }
 }
 
 impl<F, E> EditorFactory for F
@@ -206,13 +227,63 @@ macro_rules! eel_tests {
         }
     };
 
+    // Tests listed as `test_name: fixture` start with the fixture as the current buffer
+    (@test
+        test_tag: $test_tag:path,
+        editor_factory: $editor_factory:expr,
+        editor_bounds: { $( $editor_bounds:tt )* },
+        module_path: $module_path:path,
+        prefix: $prefix:tt,
+        test: $test_name:ident: $fixture:expr$(,)?
+    ) => {
+        $crate::eel_tests!(@test
+            test_tag: $test_tag,
+            editor_factory: || $crate::test_utils::EditorFactory::create_editor_with(
+                &$editor_factory,
+                $fixture,
+            ),
+            editor_bounds: { $( $editor_bounds )* },
+            module_path: $module_path,
+            prefix: $prefix,
+            test: $test_name,
+        );
+    };
+
+    (@list_entry
+        editor_bounds: { $( $editor_bounds:tt )* },
+        module_path: $module_path:path,
+        test: $test_name:ident$(,)?
+    ) => {{
+        use $module_path as module;
+        (stringify!($test_name), module::$test_name as fn(_))
+    }};
+
+    (@list_entry
+        editor_bounds: { $( $editor_bounds:tt )* },
+        module_path: $module_path:path,
+        test: $test_name:ident: $fixture:expr$(,)?
+    ) => {{
+        fn $test_name<E>(editor: E)
+        where
+            E: $crate::Editor,
+            $( $editor_bounds )*
+        {
+            use $module_path as module;
+
+            $fixture.set_current(&editor);
+            module::$test_name(editor);
+        }
+
+        (stringify!($test_name), $test_name as fn(_))
+    }};
+
     (
         test_tag: $test_tag:path,
         editor_factory: $editor_factory:expr,
         editor_bounds: $editor_bounds:tt,
         module_path: $module_path:path,
         prefix: $prefix:tt,
-        tests: [ $( $test_name:ident ),* $(,)? ],
+        tests: [ $( $test_name:ident $(: $fixture:expr)? ),* $(,)? ],
     ) => {
         $(
             $crate::eel_tests!(@test
@@ -221,8 +292,45 @@ macro_rules! eel_tests {
                 editor_bounds: $editor_bounds,
                 module_path: $module_path,
                 prefix: $prefix,
-                test: $test_name,
+                test: $test_name $(: $fixture)?,
             );
         )*
     };
+
+    // Order used by the `@tests` arms of the suites, which add their own fields last
+    (
+        test_tag: $test_tag:path,
+        editor_factory: $editor_factory:expr,
+        prefix: $prefix:tt,
+        editor_bounds: $editor_bounds:tt,
+        module_path: $module_path:path,
+        tests: $tests:tt,
+    ) => {
+        $crate::eel_tests!(
+            test_tag: $test_tag,
+            editor_factory: $editor_factory,
+            editor_bounds: $editor_bounds,
+            module_path: $module_path,
+            prefix: $prefix,
+            tests: $tests,
+        );
+    };
+
+    // The tests of a suite as `(name, test)` pairs, see the `@list` arms of the suites
+    (
+        list: {},
+        editor_bounds: $editor_bounds:tt,
+        module_path: $module_path:path,
+        tests: [ $( $test_name:ident $(: $fixture:expr)? ),* $(,)? ],
+    ) => {
+        vec![
+            $(
+                $crate::eel_tests!(@list_entry
+                    editor_bounds: $editor_bounds,
+                    module_path: $module_path,
+                    test: $test_name $(: $fixture)?,
+                )
+            ),*
+        ]
+    };
 }
@@ -0,0 +1,110 @@
+use crate::{
+    Capabilities, Editor, OptionValue, Result,
+    buffer::BufferHandle,
+    ui::{PromptSpec, ScriptedPrompts, UserResponse},
+};
+
+/// Wraps any [`Editor`], answering its prompts with [`ScriptedPrompts`] instead of asking the
+/// user.
+pub struct ScriptedEditor<E: Editor> {
+    inner: E,
+    prompts: ScriptedPrompts,
+}
+
+impl<E: Editor> ScriptedEditor<E> {
+    pub fn new(inner: E, responses: impl IntoIterator<Item = UserResponse>) -> Self {
+        Self {
+            inner,
+            prompts: ScriptedPrompts::new(responses),
+        }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn prompts(&self) -> &ScriptedPrompts {
+        &self.prompts
+    }
+}
+
+impl<E: Editor> Editor for ScriptedEditor<E> {
+    type BufferHandle = E::BufferHandle;
+
+    fn current_buffer(&self) -> Result<Self::BufferHandle> {
+        self.inner.current_buffer()
+    }
+
+    fn new_buffer(&self) -> Result<Self::BufferHandle> {
+        self.inner.new_buffer()
+    }
+
+    fn set_current_buffer(
+        &self,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+    ) -> Result<()> {
+        self.inner.set_current_buffer(buffer)
+    }
+
+    fn buffers(&self) -> Result<Vec<Self::BufferHandle>> {
+        self.inner.buffers()
+    }
+
+    fn buffer_name(&self, buffer: &Self::BufferHandle) -> Result<Option<String>> {
+        self.inner.buffer_name(buffer)
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<Self::BufferHandle>> {
+        self.inner.buffer_by_name(name)
+    }
+
+    fn buffer_by_path(&self, path: &std::path::Path) -> Result<Option<Self::BufferHandle>> {
+        self.inner.buffer_by_path(path)
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<OptionValue>> {
+        self.inner.get_option(name)
+    }
+
+    fn set_option(&self, name: &str, value: OptionValue) -> Result<()> {
+        self.inner.set_option(name, value)
+    }
+
+    fn on_idle(
+        &self,
+        delay: std::time::Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.inner.on_idle(delay, callback)
+    }
+
+    fn tasks(&self) -> &crate::tasks::Tasks {
+        self.inner.tasks()
+    }
+
+    fn prompt(&self, spec: &PromptSpec) -> Result<UserResponse> {
+        self.prompts.respond(spec)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.inner.jump_back()
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.inner.jump_forward()
+    }
+
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.inner.execute_normal(keys)
+    }
+
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        self.inner.detect_filetype(path, content)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+}
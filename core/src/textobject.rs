@@ -0,0 +1,301 @@
+use crate::{Position, Result, buffer::ReadBuffer};
+
+#[cfg(feature = "cursor")]
+use crate::{Editor, buffer::BufferHandle, cursor::CursorBufferHandle};
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Blank
+        } else if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.chars().all(char::is_whitespace)
+}
+
+/// Run of characters of the same class (word characters, other non-blank characters or
+/// blanks) around `position`, like vim's `iw`. `None` at the end of the line.
+pub fn word_at<B: ReadBuffer + ?Sized>(
+    buffer: &B,
+    position: &Position,
+) -> Result<Option<(Position, Position)>> {
+    buffer.validate_pos(position)?;
+
+    let line = buffer.get_line(position.row)?;
+
+    let Some(c) = line.get(position.col..).and_then(|s| s.chars().next()) else {
+        return Ok(None);
+    };
+
+    let class = CharClass::of(c);
+
+    let start = line[..position.col]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| CharClass::of(*c) == class)
+        .last()
+        .map_or(position.col, |(i, _)| i);
+
+    let end = line[position.col..]
+        .char_indices()
+        .find(|(_, c)| CharClass::of(*c) != class)
+        .map_or(line.len(), |(i, _)| position.col + i);
+
+    Ok(Some((
+        Position::new(position.row, start),
+        Position::new(position.row, end),
+    )))
+}
+
+/// Rows around `position` which are all blank or all non-blank, like vim's `ip`.
+pub fn paragraph_at<B: ReadBuffer + ?Sized>(
+    buffer: &B,
+    position: &Position,
+) -> Result<(Position, Position)> {
+    buffer.validate_pos(position)?;
+
+    let lines: Vec<String> = buffer.get_all_lines()?.collect();
+    let row = position.row;
+
+    let blank = is_blank(&lines[row]);
+    let same = |l: &&String| is_blank(l) == blank;
+
+    let start = row - lines[..row].iter().rev().take_while(same).count();
+    let end = row + lines[row + 1..].iter().take_while(same).count();
+
+    Ok((
+        Position::new(start, 0),
+        Position::new(end, lines[end].len()),
+    ))
+}
+
+/// Bracket matching the one at `position`, skipping nested pairs, like vim's `%` (without
+/// searching forward for a bracket). `None` if there's no bracket at the position or it's
+/// unmatched.
+pub fn matching_bracket<B: ReadBuffer + ?Sized>(
+    buffer: &B,
+    position: &Position,
+) -> Result<Option<Position>> {
+    let Some(c) = buffer.char_at(position)? else {
+        return Ok(None);
+    };
+
+    let Some(&(open, close)) = BRACKETS.iter().find(|(o, cl)| c == *o || c == *cl) else {
+        return Ok(None);
+    };
+
+    // Scanning away from the bracket, which is the first `nested` one seen
+    let forward = c == open;
+    let (nested, matching) = if forward {
+        (open, close)
+    } else {
+        (close, open)
+    };
+
+    let mut depth = 0usize;
+    let mut check = |row: usize, col: usize, c: char| {
+        if c == nested {
+            depth += 1;
+        } else if c == matching {
+            depth -= 1;
+
+            if depth == 0 {
+                return Some(Position::new(row, col));
+            }
+        }
+
+        None
+    };
+
+    if forward {
+        let lines = buffer.get_lines(position.row..buffer.line_count()?)?;
+
+        for (i, line) in lines.enumerate() {
+            let row = position.row + i;
+            let skip = if i == 0 { position.col } else { 0 };
+
+            for (col, c) in line[skip..].char_indices() {
+                if let Some(found) = check(row, skip + col, c) {
+                    return Ok(Some(found));
+                }
+            }
+        }
+    } else {
+        let lines: Vec<String> = buffer.get_lines(0..position.row + 1)?.collect();
+
+        for (row, line) in lines.iter().enumerate().rev() {
+            let take = if row == position.row {
+                position.col + c.len_utf8()
+            } else {
+                line.len()
+            };
+
+            for (col, c) in line[..take].char_indices().rev() {
+                if let Some(found) = check(row, col, c) {
+                    return Ok(Some(found));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Deletes the word under the cursor of the current buffer like `diw`, through the editor's
+/// normal mode when it has one.
+#[cfg(feature = "cursor")]
+pub fn delete_inner_word<E>(editor: &E) -> Result<()>
+where
+    E: Editor,
+    E::BufferHandle: CursorBufferHandle,
+{
+    use crate::{
+        buffer::WriteBuffer,
+        cursor::{CursorReadBuffer, CursorWriteBuffer},
+    };
+
+    match editor.execute_normal("diw") {
+        Err(crate::Error::Unsupported(_)) => {}
+        result => return result,
+    }
+
+    let buffer = editor.current_buffer()?;
+    let mut buffer = buffer.write_timeout()?;
+
+    let cursor = buffer.get_cursor()?;
+    let Some((start, end)) = word_at(&*buffer, &cursor)? else {
+        return Ok(());
+    };
+
+    buffer.set_text((&start, &end), "")?;
+    buffer.set_cursor(&start)
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{Editor, buffer::BufferHandle, test_utils::new_buffer_with_content};
+
+    fn range(start: (usize, usize), end: (usize, usize)) -> (Position, Position) {
+        (Position::new(start.0, start.1), Position::new(end.0, end.1))
+    }
+
+    pub fn test_textobject_word(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "let żółw_2 = foo(a);\n");
+        let buffer = buffer.read();
+
+        let word = |col| {
+            buffer
+                .word_at(&Position::new(0, col))
+                .expect("Failed to get word")
+        };
+
+        assert_eq!(word(0), Some(range((0, 0), (0, 3))));
+        assert_eq!(word(2), Some(range((0, 0), (0, 3))));
+        assert_eq!(word(3), Some(range((0, 3), (0, 4))));
+        assert_eq!(word(6), Some(range((0, 4), (0, 13))));
+        assert_eq!(word(14), Some(range((0, 14), (0, 15))));
+        assert_eq!(word(16), Some(range((0, 16), (0, 19))));
+        assert_eq!(word(19), Some(range((0, 19), (0, 20))));
+        assert_eq!(word(22), Some(range((0, 21), (0, 23))));
+        assert_eq!(word(23), None);
+
+        assert_eq!(
+            buffer
+                .word_at(&Position::new(1, 0))
+                .expect("Failed to get word"),
+            None
+        );
+        assert!(buffer.word_at(&Position::new(2, 0)).is_err());
+    }
+
+    pub fn test_textobject_paragraph(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "a\nbb\n\n  \nc\ndd");
+        let buffer = buffer.read();
+
+        let paragraph = |row| {
+            buffer
+                .paragraph_at(&Position::new(row, 0))
+                .expect("Failed to get paragraph")
+        };
+
+        assert_eq!(paragraph(0), range((0, 0), (1, 2)));
+        assert_eq!(paragraph(1), range((0, 0), (1, 2)));
+        assert_eq!(paragraph(2), range((2, 0), (3, 2)));
+        assert_eq!(paragraph(3), range((2, 0), (3, 2)));
+        assert_eq!(paragraph(5), range((4, 0), (5, 2)));
+
+        assert!(buffer.paragraph_at(&Position::new(6, 0)).is_err());
+    }
+
+    pub fn test_textobject_bracket(editor: impl Editor) {
+        let buffer = new_buffer_with_content(&editor, "fn f(a: [u8; 2]) {\n    g(\"ą)\");\n}");
+        let buffer = buffer.read();
+
+        let matching = |row, col| {
+            buffer
+                .matching_bracket(&Position::new(row, col))
+                .expect("Failed to match bracket")
+        };
+
+        assert_eq!(matching(0, 4), Some(Position::new(0, 15)));
+        assert_eq!(matching(0, 15), Some(Position::new(0, 4)));
+        assert_eq!(matching(0, 8), Some(Position::new(0, 14)));
+        assert_eq!(matching(0, 17), Some(Position::new(2, 0)));
+        assert_eq!(matching(2, 0), Some(Position::new(0, 17)));
+
+        // Brackets in strings aren't special
+        assert_eq!(matching(1, 5), Some(Position::new(1, 9)));
+        assert_eq!(matching(1, 9), Some(Position::new(1, 5)));
+
+        assert_eq!(matching(0, 0), None);
+        assert_eq!(matching(0, 18), None);
+
+        let unmatched = new_buffer_with_content(&editor, "(()");
+        assert_eq!(
+            unmatched
+                .read()
+                .matching_bracket(&Position::origin())
+                .expect("Failed to match bracket"),
+            None
+        );
+    }
+
+    #[macro_export]
+    macro_rules! eel_textobject_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::textobject::tests,
+                prefix: $prefix,
+                tests: [
+                    test_textobject_word,
+                    test_textobject_paragraph,
+                    test_textobject_bracket,
+                ],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_textobject_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
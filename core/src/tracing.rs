@@ -1,10 +1,22 @@
+use std::sync::OnceLock;
+
 use tracing::{debug, error, level_filters::LevelFilter};
 use tracing_subscriber::{
-    EnvFilter, Layer, Registry, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+    filter::Directive,
+    layer::{Layered, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
 };
 
+use crate::Result;
+
 pub type TracingLayer = Box<dyn Layer<Registry> + Send + Sync>;
 
+type FilterHandle = reload::Handle<EnvFilter, Layered<Vec<TracingLayer>, Registry>>;
+
+static ENV_FILTER: OnceLock<FilterHandle> = OnceLock::new();
+
 pub fn file_log_layer(log_dir: impl Into<String>) -> TracingLayer {
     let file_appender = tracing_appender::rolling::daily(log_dir.into(), "log");
     let (writer, guard) = tracing_appender::non_blocking(file_appender);
@@ -20,6 +32,9 @@ pub fn init_tracing(layers: impl Into<Vec<TracingLayer>>) {
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
 
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    ENV_FILTER.set(handle).ok();
+
     tracing_subscriber::registry()
         .with(layers)
         .with(env_filter)
@@ -28,6 +43,29 @@ pub fn init_tracing(layers: impl Into<Vec<TracingLayer>>) {
     debug!("Tracing initialized");
 }
 
+/// Overrides the level of `target` (of all targets if `None`) in the filter installed by
+/// [`init_tracing`].
+pub fn set_log_level(target: Option<&str>, level: LevelFilter) -> Result<()> {
+    let directive: Directive = match target {
+        Some(target) => format!("{target}={level}").parse().map_err(|e| {
+            crate::buffer::Error::Custom(format!("Invalid log target {target}: {e}").into())
+        })?,
+        None => level.into(),
+    };
+
+    let handle = ENV_FILTER.get().ok_or(crate::buffer::Error::Custom(
+        "Tracing is not initialized".into(),
+    ))?;
+
+    handle
+        .modify(|filter| *filter = std::mem::take(filter).add_directive(directive))
+        .map_err(|e| {
+            crate::buffer::Error::Custom(format!("Failed to set log level: {e}").into())
+        })?;
+
+    Ok(())
+}
+
 pub trait ResultExt {
     fn log_err(self) -> Self;
     fn log_err_msg(self, message: &str) -> Self;
@@ -0,0 +1,240 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::Result;
+
+/// Question asked through [`Editor::prompt`](crate::Editor::prompt).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSpec {
+    /// Free text, `default` being pre-filled.
+    Input {
+        prompt: String,
+        default: Option<String>,
+    },
+    /// Yes or no.
+    Confirm { prompt: String },
+    /// One of `items`.
+    Pick { prompt: String, items: Vec<String> },
+}
+
+impl PromptSpec {
+    pub fn input(prompt: impl Into<String>) -> Self {
+        Self::Input {
+            prompt: prompt.into(),
+            default: None,
+        }
+    }
+
+    pub fn confirm(prompt: impl Into<String>) -> Self {
+        Self::Confirm {
+            prompt: prompt.into(),
+        }
+    }
+
+    pub fn pick(
+        prompt: impl Into<String>,
+        items: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self::Pick {
+            prompt: prompt.into(),
+            items: items.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Pre-filled text of an input prompt, ignored by the other kinds.
+    pub fn with_default(mut self, text: impl Into<String>) -> Self {
+        if let Self::Input { default, .. } = &mut self {
+            *default = Some(text.into());
+        }
+
+        self
+    }
+
+    pub fn prompt(&self) -> &str {
+        match self {
+            Self::Input { prompt, .. } | Self::Confirm { prompt } | Self::Pick { prompt, .. } => {
+                prompt
+            }
+        }
+    }
+
+    /// Checks that `response` answers this prompt, e.g. that a picked index is in range.
+    pub fn check(&self, response: &UserResponse) -> Result<()> {
+        let valid = match (self, response) {
+            (_, UserResponse::Cancelled) => true,
+            (Self::Input { .. }, UserResponse::Text(_)) => true,
+            (Self::Confirm { .. }, UserResponse::Confirmed(_)) => true,
+            (Self::Pick { items, .. }, UserResponse::Picked(i)) => *i < items.len(),
+            _ => false,
+        };
+
+        if !valid {
+            Err(crate::buffer::Error::Custom(
+                format!("Response {response:?} doesn't answer prompt {self:?}").into(),
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserResponse {
+    Text(String),
+    Confirmed(bool),
+    /// Index into the items of a [`PromptSpec::Pick`].
+    Picked(usize),
+    /// The user dismissed the prompt.
+    Cancelled,
+}
+
+/// Answers prompts with queued responses, for backends without a user (e.g. in tests).
+#[derive(Debug, Default)]
+pub struct ScriptedPrompts {
+    responses: Mutex<VecDeque<UserResponse>>,
+    asked: Mutex<Vec<PromptSpec>>,
+}
+
+impl ScriptedPrompts {
+    pub fn new(responses: impl IntoIterator<Item = UserResponse>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter().collect()),
+            asked: Mutex::default(),
+        }
+    }
+
+    pub fn push(&self, response: UserResponse) {
+        self.responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(response);
+    }
+
+    /// Next queued response, failing if there is none or it doesn't answer `spec`.
+    pub fn respond(&self, spec: &PromptSpec) -> Result<UserResponse> {
+        self.asked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(spec.clone());
+
+        let response = self
+            .responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
+            .ok_or_else(|| {
+                crate::buffer::Error::Custom(
+                    format!("No scripted response for {:?}", spec.prompt()).into(),
+                )
+            })?;
+
+        spec.check(&response)?;
+
+        Ok(response)
+    }
+
+    /// Prompts asked so far, in order.
+    pub fn asked(&self) -> Vec<PromptSpec> {
+        self.asked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor, Position, assert_buffer_content, assert_buffer_error,
+        buffer::{BufferHandle, WriteBuffer},
+        test_utils::{new_buffer_with_content, scripted::ScriptedEditor},
+    };
+
+    /// Asks for a new name and whether to apply it, like a rename command would.
+    fn rename<E: Editor>(editor: &E, buffer: &E::BufferHandle) -> Result<bool> {
+        let UserResponse::Text(name) =
+            editor.prompt(&PromptSpec::input("New name").with_default("foo"))?
+        else {
+            return Ok(false);
+        };
+
+        if editor.prompt(&PromptSpec::confirm(format!("Rename to {name}?")))?
+            != UserResponse::Confirmed(true)
+        {
+            return Ok(false);
+        }
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 3), Position::new(0, 6)), &name)?;
+
+        Ok(true)
+    }
+
+    pub fn test_ui_prompt(editor: impl Editor) {
+        let editor = ScriptedEditor::new(
+            editor,
+            [
+                UserResponse::Text("bar".into()),
+                UserResponse::Confirmed(true),
+                UserResponse::Cancelled,
+                UserResponse::Picked(1),
+            ],
+        );
+        let buffer = new_buffer_with_content(&editor, "fn foo() {}");
+
+        assert!(rename(&editor, &buffer).expect("Failed to rename"));
+        assert_buffer_content!(buffer, "fn bar() {}");
+
+        assert!(!rename(&editor, &buffer).expect("Failed to rename"));
+        assert_buffer_content!(buffer, "fn bar() {}");
+
+        assert_eq!(
+            editor.prompts().asked()[..2],
+            [
+                PromptSpec::input("New name").with_default("foo"),
+                PromptSpec::confirm("Rename to bar?"),
+            ]
+        );
+
+        // Responses not answering the prompt are rejected
+        assert_buffer_error!(
+            editor.prompt(&PromptSpec::pick("Pick one", ["only"])),
+            crate::Error::Buffer(crate::buffer::Error::Custom(_))
+        );
+        assert_buffer_error!(
+            editor.prompt(&PromptSpec::confirm("Nothing queued")),
+            crate::Error::Buffer(crate::buffer::Error::Custom(_))
+        );
+
+        editor.prompts().push(UserResponse::Picked(1));
+        assert_eq!(
+            editor
+                .prompt(&PromptSpec::pick("Pick one", ["first", "second"]))
+                .expect("Failed to prompt"),
+            UserResponse::Picked(1)
+        );
+    }
+
+    #[macro_export]
+    macro_rules! eel_ui_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {},
+                module_path: $crate::ui::tests,
+                prefix: $prefix,
+                tests: [test_ui_prompt],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_ui_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use crate::{
+    Result,
+    buffer::{BufferHandle, WriteBuffer},
+};
+
+/// A change in the undo history, the state after it is identified by its `seq`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoNode {
+    pub seq: u64,
+    pub time: SystemTime,
+    /// Changes made after this one, a new branch starts when changes are made after undoing.
+    /// Ordered by `seq`.
+    pub children: Vec<UndoNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoTree {
+    /// Changes made to the original text, ordered by `seq`.
+    pub roots: Vec<UndoNode>,
+    /// Change the buffer is at, `0` before the first one.
+    pub current: u64,
+    /// Latest change, `0` if there are none.
+    pub last: u64,
+}
+
+impl UndoTree {
+    /// Builds the tree from `(seq, time, parent_seq)` triples, `0` being the original text.
+    pub fn from_parents(
+        current: u64,
+        changes: impl IntoIterator<Item = (u64, SystemTime, u64)>,
+    ) -> Self {
+        let mut children: BTreeMap<u64, Vec<(u64, SystemTime)>> = BTreeMap::new();
+        let mut last = 0;
+
+        for (seq, time, parent) in changes {
+            children.entry(parent).or_default().push((seq, time));
+            last = last.max(seq);
+        }
+
+        fn build(
+            parent: u64,
+            children: &mut BTreeMap<u64, Vec<(u64, SystemTime)>>,
+        ) -> Vec<UndoNode> {
+            let mut nodes: Vec<_> = children
+                .remove(&parent)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(seq, time)| UndoNode {
+                    seq,
+                    time,
+                    children: build(seq, children),
+                })
+                .collect();
+
+            nodes.sort_by_key(|node| node.seq);
+            nodes
+        }
+
+        Self {
+            roots: build(0, &mut children),
+            current,
+            last,
+        }
+    }
+
+    pub fn find(&self, seq: u64) -> Option<&UndoNode> {
+        let mut nodes = self.roots.iter().collect::<Vec<_>>();
+
+        while let Some(node) = nodes.pop() {
+            if node.seq == seq {
+                return Some(node);
+            }
+
+            nodes.extend(&node.children);
+        }
+
+        None
+    }
+}
+
+pub trait UndoBuffer: WriteBuffer {
+    fn undo_tree(&self) -> Result<UndoTree>;
+
+    /// Restores the text to the state after change `seq`, `0` is the original text.
+    fn undo_to(&mut self, seq: u64) -> Result<()>;
+}
+
+pub trait UndoBufferHandle: BufferHandle<WriteBuffer = Self::UWriteBuffer> {
+    type UWriteBuffer: UndoBuffer;
+}
+
+impl<B> UndoBufferHandle for B
+where
+    B: BufferHandle,
+    B::WriteBuffer: UndoBuffer,
+{
+    type UWriteBuffer = B::WriteBuffer;
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor, assert_buffer_content, buffer::ReadBuffer, test_utils::new_buffer_with_content,
+    };
+
+    pub fn test_undo_tree<E>(editor: E)
+    where
+        E: Editor,
+        <E::BufferHandle as BufferHandle>::WriteBuffer: UndoBuffer,
+    {
+        let buffer = new_buffer_with_content(&editor, "a");
+        let undo_tree = || buffer.write().undo_tree().expect("Failed to get undo tree");
+        let append = |text: &str| {
+            let mut buffer = buffer.write();
+            let end = buffer.max_pos().expect("Failed to get end");
+            buffer
+                .set_text((&end, &end), text)
+                .expect("Failed to set text");
+        };
+
+        let base = undo_tree().current;
+        append("b");
+        append("c");
+
+        let tree = undo_tree();
+        assert_eq!((tree.current, tree.last), (base + 2, base + 2));
+        assert!(
+            tree.find(base + 2)
+                .is_some_and(|node| node.children.is_empty())
+        );
+
+        buffer.write().undo_to(base + 1).expect("Failed to undo");
+        assert_buffer_content!(buffer, "ab");
+        assert_eq!(undo_tree().current, base + 1);
+
+        // Changing undone text starts a branch
+        append("d");
+
+        let tree = undo_tree();
+        assert_eq!((tree.current, tree.last), (base + 3, base + 3));
+        let children = tree
+            .find(base + 1)
+            .expect("Change wasn't found")
+            .children
+            .iter()
+            .map(|node| node.seq)
+            .collect::<Vec<_>>();
+        assert_eq!(children, [base + 2, base + 3]);
+
+        buffer.write().undo_to(base + 2).expect("Failed to undo");
+        assert_buffer_content!(buffer, "abc");
+
+        buffer.write().undo_to(base).expect("Failed to undo");
+        assert_buffer_content!(buffer, "a");
+        assert!(undo_tree().find(base + 3).is_some());
+    }
+
+    #[macro_export]
+    macro_rules! eel_undo_tests {
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_tests!(
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                editor_bounds: {
+                    <E::BufferHandle as $crate::buffer::BufferHandle>::WriteBuffer: $crate::undo::UndoBuffer,
+                },
+                module_path: $crate::undo::tests,
+                prefix: $prefix,
+                tests: [test_undo_tree],
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_undo_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::warn;
+
+use crate::{
+    PosRange, Position, Result,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+/// Replacement of a range, positions referring to the buffer before any edit of the same
+/// workspace edit is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: PosRange,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: Position, end: Position, text: impl Into<String>) -> Self {
+        Self {
+            range: PosRange::new(start, end),
+            text: text.into(),
+        }
+    }
+}
+
+/// Number given to a buffer the first time it's part of a workspace edit, kept in its
+/// [`BufferData`](crate::buffer::BufferData) so all handles to it share it.
+struct LockOrder(u64);
+
+impl LockOrder {
+    fn of(buffer: &impl BufferHandle) -> u64 {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        buffer
+            .data()
+            .get_or_insert_with(|| Self(NEXT.fetch_add(1, Ordering::Relaxed)))
+            .0
+    }
+}
+
+/// Validates the edits and sorts them by position, inserts at the same position keeping their
+/// order.
+fn check_edits<'a, B: ReadBuffer + ?Sized>(
+    buffer: &B,
+    edits: &'a [TextEdit],
+) -> Result<Vec<&'a TextEdit>> {
+    for edit in edits {
+        buffer.validate_range(edit.range.start(), edit.range.end())?;
+    }
+
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by(|a, b| (a.range.start(), a.range.end()).cmp(&(b.range.start(), b.range.end())));
+
+    if let Some(pair) = sorted
+        .windows(2)
+        .find(|pair| pair[0].range.end() > pair[1].range.start())
+    {
+        Err(crate::buffer::Error::Custom(
+            format!(
+                "Overlapping edits: {:?} and {:?}",
+                pair[0].range, pair[1].range
+            )
+            .into(),
+        ))?;
+    }
+
+    Ok(sorted)
+}
+
+/// Applies the edits of every buffer, recording the edits undoing them into `undo`.
+fn apply_all<W: WriteBuffer + ?Sized>(
+    buffers: &mut [&mut W],
+    edits: &[Vec<&TextEdit>],
+    undo: &mut Vec<(usize, TextEdit)>,
+) -> Result<()> {
+    for (i, edits) in edits.iter().enumerate() {
+        // Last edits first, so the positions of the earlier ones stay valid
+        for edit in edits.iter().rev() {
+            let old_text = buffers[i].get_text_in(&edit.range)?;
+
+            buffers[i].set_text_in(&edit.range, &edit.text)?;
+
+            let start = edit.range.start().clone();
+            let new_end = start.offset(&Position::max_text_pos(&edit.text));
+
+            undo.push((i, TextEdit::new(start, new_end, old_text)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reverts edits recorded by [`apply_all`], latest first.
+fn roll_back<W: WriteBuffer + ?Sized>(buffers: &mut [&mut W], undo: Vec<(usize, TextEdit)>) {
+    for (i, edit) in undo.into_iter().rev() {
+        if let Err(e) = buffers[i].set_text_in(&edit.range, &edit.text) {
+            warn!("Failed to roll back workspace edit: {e}");
+        }
+    }
+}
+
+/// Applies edits to a single locked buffer as one transaction, like
+/// [`Editor::apply_workspace_edit`](crate::Editor::apply_workspace_edit).
+pub fn apply_to<W: WriteBuffer + ?Sized>(buffer: &mut W, edits: &[TextEdit]) -> Result<()> {
+    let sorted = check_edits(&*buffer, edits)?;
+
+    let mut buffers = [buffer];
+    let mut undo = Vec::new();
+    let result = apply_all(&mut buffers, &[sorted], &mut undo);
+
+    if result.is_err() {
+        roll_back(&mut buffers, undo);
+    }
+
+    result
+}
+
+/// See [`Editor::apply_workspace_edit`](crate::Editor::apply_workspace_edit).
+pub fn apply<B: BufferHandle>(edits: Vec<(B, Vec<TextEdit>)>) -> Result<()> {
+    let mut targets: Vec<(B, Vec<TextEdit>)> = Vec::new();
+
+    for (buffer, edits) in edits {
+        match targets.iter_mut().find(|(b, _)| *b == buffer) {
+            Some((_, existing)) => existing.extend(edits),
+            None => targets.push((buffer, edits)),
+        }
+    }
+
+    // Locking in a global order that never changes, so concurrent workspace edits can't
+    // deadlock
+    targets.sort_by_key(|(buffer, _)| LockOrder::of(buffer));
+
+    let mut locks: Vec<_> = targets.iter().map(|(buffer, _)| buffer.write()).collect();
+    let mut buffers: Vec<&mut B::WriteBuffer> = locks.iter_mut().map(|lock| &mut **lock).collect();
+
+    let sorted = targets
+        .iter()
+        .zip(&buffers)
+        .map(|((_, edits), buffer)| check_edits(&**buffer, edits))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut undo = Vec::new();
+    let result = apply_all(&mut buffers, &sorted, &mut undo);
+
+    if result.is_err() {
+        roll_back(&mut buffers, undo);
+    }
+
+    result
+}
+
+#[cfg(feature = "tests")]
+pub mod tests {
+    use super::*;
+
+    use crate::{
+        Editor, assert_buffer_content, assert_buffer_error,
+        test_utils::{
+            faulty::{FaultConfig, FaultyEditor},
+            new_buffer_with_content,
+        },
+    };
+
+    fn edit(start: (usize, usize), end: (usize, usize), text: &str) -> TextEdit {
+        TextEdit::new(
+            Position::new(start.0, start.1),
+            Position::new(end.0, end.1),
+            text,
+        )
+    }
+
+    pub fn test_workspace_edit(editor: impl Editor) {
+        let first = new_buffer_with_content(&editor, "fn foo() {}\nfoo();");
+        let second = new_buffer_with_content(&editor, "use a::foo;\n\nfoo(); foo();");
+
+        editor
+            .apply_workspace_edit(vec![
+                (first.clone(), vec![edit((1, 0), (1, 3), "bar")]),
+                (
+                    second.clone(),
+                    vec![
+                        edit((2, 7), (2, 10), "bar"),
+                        edit((0, 7), (0, 10), "bar"),
+                        edit((2, 0), (2, 3), "bar"),
+                    ],
+                ),
+                // Edits of the same buffer are merged, inserts at one position keep their order
+                (
+                    first.clone(),
+                    vec![
+                        edit((0, 3), (0, 6), "bar"),
+                        edit((1, 6), (1, 6), " //"),
+                        edit((1, 6), (1, 6), " renamed"),
+                    ],
+                ),
+            ])
+            .expect("Failed to apply workspace edit");
+
+        assert_buffer_content!(first, "fn bar() {}\nbar(); // renamed");
+        assert_buffer_content!(second, "use a::bar;\n\nbar(); bar();");
+
+        // Nothing is applied if any edit is invalid
+        assert_buffer_error!(
+            editor.apply_workspace_edit(vec![
+                (first.clone(), vec![edit((0, 0), (0, 2), "")]),
+                (second.clone(), vec![edit((3, 0), (3, 0), "")]),
+            ]),
+            crate::Error::Buffer(crate::buffer::Error::RowOutOfBounds { row: 3, limit: 2 })
+        );
+
+        assert_buffer_error!(
+            editor.apply_workspace_edit(vec![(
+                second.clone(),
+                vec![edit((2, 0), (2, 5), ""), edit((2, 4), (2, 8), "")],
+            )]),
+            crate::Error::Buffer(crate::buffer::Error::Custom(_))
+        );
+
+        assert_buffer_content!(first, "fn bar() {}\nbar(); // renamed");
+        assert_buffer_content!(second, "use a::bar;\n\nbar(); bar();");
+    }
+
+    pub fn test_workspace_edit_rollback(editor: impl Editor) {
+        let editor = FaultyEditor::new(
+            editor,
+            FaultConfig {
+                fail_set_text_every: Some(5),
+                ..Default::default()
+            },
+        );
+
+        // new_buffer_with_content calls set_text once per buffer
+        let first = new_buffer_with_content(&editor, "First line\nSecond line");
+        let second = new_buffer_with_content(&editor, "Third line");
+
+        // The fifth set_text call fails, after the edits of one buffer were applied
+        assert_buffer_error!(
+            editor.apply_workspace_edit(vec![
+                (
+                    first.clone(),
+                    vec![edit((0, 0), (0, 5), "1st"), edit((1, 0), (1, 6), "2nd")],
+                ),
+                (
+                    second.clone(),
+                    vec![edit((0, 0), (0, 5), "3rd"), edit((0, 6), (0, 10), "row")],
+                ),
+            ]),
+            crate::Error::Buffer(crate::buffer::Error::Custom(_))
+        );
+        assert_eq!(editor.injected_faults(), 1);
+
+        assert_buffer_content!(first, "First line\nSecond line");
+        assert_buffer_content!(second, "Third line");
+    }
+
+    #[macro_export]
+    macro_rules! eel_workspace_tests {
+        (@tests $($target:tt)*) => {
+            $crate::eel_tests! {
+                $($target)*
+                editor_bounds: {},
+                module_path: $crate::workspace::tests,
+                tests: [test_workspace_edit, test_workspace_edit_rollback],
+            }
+        };
+
+        (@list) => {
+            $crate::eel_workspace_tests!(@tests list: {},)
+        };
+
+        ($test_tag:path, $editor_factory:expr, $prefix:tt) => {
+            $crate::eel_workspace_tests!(@tests
+                test_tag: $test_tag,
+                editor_factory: $editor_factory,
+                prefix: $prefix,
+            );
+        };
+
+        ($test_tag:path, $editor_factory:expr) => {
+            $crate::eel_workspace_tests!($test_tag, $editor_factory, "");
+        };
+    }
+}
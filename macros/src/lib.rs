@@ -0,0 +1,317 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Data, DataStruct, DeriveInput, Expr, Fields, GenericArgument, Ident, Path, PathArguments, Type,
+    parse_macro_input,
+};
+
+#[derive(deluxe::ParseAttributes)]
+#[deluxe(attributes(eel))]
+struct PlatformErrorArgs {
+    /// Types going into `eel::Error` through this error, e.g. ones wrapped with `#[from]`.
+    #[deluxe(default)]
+    chain: Vec<Path>,
+    /// Helper trait turning results with any wrapped error into results with this one.
+    #[deluxe(default)]
+    result_trait: Option<Ident>,
+    #[deluxe(default)]
+    result_method: Option<Ident>,
+}
+
+#[derive(deluxe::ParseAttributes)]
+#[deluxe(attributes(eel))]
+struct VariantArgs {
+    /// Error stored as its message, for errors which aren't `Send + Sync`.
+    #[deluxe(default)]
+    from_display: Option<Type>,
+}
+
+/// Implements `PlatformError` for a backend's error enum, along with the conversions listed in
+/// `#[eel(...)]` attributes:
+///
+/// ```ignore
+/// #[derive(thiserror::Error, Debug, EelPlatformError)]
+/// #[eel(chain(dispatcher::Error), result_trait = IntoNvimResult, result_method = into_nvim)]
+/// pub enum Error {
+///     #[error("Dispatcher error: {0}")]
+///     Dispatcher(#[from] dispatcher::Error),
+///
+///     #[error("Nvim MLua error: {0}")]
+///     #[eel(from_display = mlua::Error)]
+///     MLua(String),
+/// }
+/// ```
+///
+/// - `chain(T, ...)`: `From<T> for eel::Error`, going through this error, so `?` works on `T`
+///   in functions returning `eel::Result`. `T` has to be local to the crate.
+/// - `result_trait`, `result_method`: a trait with the method turning any `Result<T, E>` into
+///   `Result<T, Self>`, if `Self: From<E>`.
+/// - `from_display = T` on a variant with a single `String` field: `From<T>`, storing the
+///   error's message.
+#[proc_macro_derive(EelPlatformError, attributes(eel))]
+pub fn derive_platform_error(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match platform_error(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+fn platform_error(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let args: PlatformErrorArgs = deluxe::parse_attributes(&input)?;
+
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "EelPlatformError can only be derived for enums",
+        ));
+    };
+
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    let mut tokens = quote! {
+        impl ::eel::error::PlatformError for #name {}
+    };
+
+    for source in &args.chain {
+        tokens.extend(quote! {
+            impl ::std::convert::From<#source> for ::eel::Error {
+                fn from(value: #source) -> Self {
+                    #name::from(value).into()
+                }
+            }
+        });
+    }
+
+    for variant in &data.variants {
+        let variant_args: VariantArgs = deluxe::parse_attributes(variant)?;
+
+        let Some(source) = variant_args.from_display else {
+            continue;
+        };
+
+        if !matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                "from_display needs a variant with a single unnamed field",
+            ));
+        }
+
+        let variant = &variant.ident;
+
+        tokens.extend(quote! {
+            impl ::std::convert::From<#source> for #name {
+                fn from(value: #source) -> Self {
+                    Self::#variant(value.to_string())
+                }
+            }
+        });
+    }
+
+    match (args.result_trait, args.result_method) {
+        (Some(result_trait), Some(result_method)) => tokens.extend(quote! {
+            #vis trait #result_trait<T> {
+                fn #result_method(self) -> ::std::result::Result<T, #name>;
+            }
+
+            impl<T, E> #result_trait<T> for ::std::result::Result<T, E>
+            where
+                #name: ::std::convert::From<E>,
+            {
+                fn #result_method(self) -> ::std::result::Result<T, #name> {
+                    self.map_err(#name::from)
+                }
+            }
+        }),
+        (None, None) => {}
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "result_trait and result_method have to be given together",
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(deluxe::ParseAttributes)]
+#[deluxe(attributes(eel))]
+struct FieldArgs {
+    #[deluxe(default)]
+    range: bool,
+    #[deluxe(default)]
+    bang: bool,
+    #[deluxe(default)]
+    flag: bool,
+    #[deluxe(default)]
+    complete: Option<Expr>,
+}
+
+/// Implements `eel::commands::CommandArgs` for a struct with named fields, see the
+/// `eel::commands` docs.
+#[proc_macro_derive(EelCommandArgs, attributes(eel))]
+pub fn derive_command_args(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match command_args(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+/// Inner type of `Option<T>` or `Vec<T>`.
+fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn command_args(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    let Data::Struct(DataStruct {
+        fields: Fields::Named(fields),
+        ..
+    }) = &input.data
+    else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "EelCommandArgs can only be derived for structs with named fields",
+        ));
+    };
+
+    let commands = quote!(::eel::commands);
+
+    let (mut range, mut bang) = (false, false);
+    let mut flags = Vec::new();
+    let mut completers = Vec::new();
+    let mut usage = Vec::new();
+    let mut values = Vec::new();
+
+    // Required positionals can't follow optional ones, nothing can follow the variadic one
+    let (mut optional, mut variadic) = (false, false);
+
+    for field in &fields.named {
+        let args: FieldArgs = deluxe::parse_attributes(field)?;
+        let ident = field.ident.as_ref().expect("Fields are named");
+        let arg_name = ident.to_string();
+
+        let value = if args.range {
+            range = true;
+
+            if wrapped_type(&field.ty, "Option").is_some() {
+                quote!(parser.range())
+            } else {
+                quote!(parser.required_range()?)
+            }
+        } else if args.bang {
+            bang = true;
+
+            quote!(parser.bang())
+        } else if args.flag {
+            let flag = format!("--{}", arg_name.replace('_', "-"));
+            usage.push(format!("[{flag}]"));
+            let value = quote!(parser.flag(#flag));
+            flags.push(flag);
+
+            value
+        } else {
+            if variadic {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "No positional can follow a Vec one",
+                ));
+            }
+
+            let completer = match &args.complete {
+                Some(complete) => quote!(::std::option::Option::Some(
+                    #complete as #commands::Completer
+                )),
+                None => quote!(::std::option::Option::None),
+            };
+            completers.push(completer);
+
+            if wrapped_type(&field.ty, "Vec").is_some() {
+                variadic = true;
+                usage.push(format!("[{arg_name}...]"));
+
+                quote!(parser.rest(#arg_name)?)
+            } else if wrapped_type(&field.ty, "Option").is_some() {
+                optional = true;
+                usage.push(format!("[{arg_name}]"));
+
+                quote!(parser.optional(#arg_name)?)
+            } else if optional {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "A required positional can't follow an optional one",
+                ));
+            } else {
+                usage.push(format!("{{{arg_name}}}"));
+
+                quote!(parser.required(#arg_name)?)
+            }
+        };
+
+        if args.complete.is_some() && (args.range || args.bang || args.flag) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "Only positionals can be completed",
+            ));
+        }
+
+        values.push(quote!(#ident: #value));
+    }
+
+    let has_args = !usage.is_empty();
+    let usage = usage.join(" ");
+
+    Ok(quote! {
+        impl #commands::CommandArgs for #name {
+            fn spec() -> #commands::Spec {
+                #commands::Spec {
+                    args: #has_args,
+                    range: #range,
+                    bang: #bang,
+                    usage: #usage,
+                }
+            }
+
+            fn parse(
+                invocation: #commands::Invocation,
+            ) -> ::std::result::Result<Self, #commands::Error> {
+                let mut parser = #commands::Parser::new(invocation);
+
+                let args = Self {
+                    #(#values,)*
+                };
+                parser.finish()?;
+
+                ::std::result::Result::Ok(args)
+            }
+
+            fn complete(
+                preceding: &[::std::string::String],
+                lead: &str,
+            ) -> ::std::vec::Vec<::std::string::String> {
+                #commands::complete(preceding, lead, &[#(#flags),*], &[#(#completers),*], #variadic)
+            }
+        }
+    })
+}
@@ -6,21 +6,44 @@ use syn::{Expr, Ident, ItemFn, parse_macro_input, spanned::Spanned};
 #[derive(deluxe::ParseMetaItem)]
 #[deluxe(attributes(nvim_test))]
 struct NvimTestArgs {
-    editor_factory: Expr,
+    /// Optional for tests without an editor argument.
+    #[deluxe(default)]
+    editor_factory: Option<Expr>,
+    /// Called on the nvim thread before the test.
+    #[deluxe(default)]
+    setup: Option<Expr>,
+    /// Called on the nvim thread after the test, unless it failed.
+    #[deluxe(default)]
+    teardown: Option<Expr>,
 }
 
 #[proc_macro_attribute]
 pub fn nvim_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut function = parse_macro_input!(item as ItemFn);
 
-    let editor_factory = {
-        let args: NvimTestArgs = match deluxe::parse(attr) {
-            Ok(args) => args,
-            Err(e) => return e.into_compile_error().into(),
-        };
-        args.editor_factory
+    let args: NvimTestArgs = match deluxe::parse(attr) {
+        Ok(args) => args,
+        Err(e) => return e.into_compile_error().into(),
     };
 
+    let with_editor = !function.sig.inputs.is_empty();
+
+    let editor_factory = match args.editor_factory {
+        Some(editor_factory) => quote! { #editor_factory },
+        None if with_editor => {
+            return syn::Error::new(
+                function.sig.inputs.span(),
+                "editor_factory is required for tests with an editor argument",
+            )
+            .into_compile_error()
+            .into();
+        }
+        None => quote! { crate::test_utils::nvim_editor_factory },
+    };
+
+    let setup = args.setup.map(|setup| quote! { (#setup)(); });
+    let teardown = args.teardown.map(|teardown| quote! { (#teardown)(); });
+
     // Identifier of nvim_oxi test function
     let test_ident = Ident::new(&function.sig.ident.to_string(), Span::call_site());
 
@@ -30,13 +53,30 @@ pub fn nvim_test(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let return_type = function.sig.output.clone();
 
+    let test = if with_editor {
+        quote! { #new_ident }
+    } else {
+        quote! {{
+            fn ignore_editor<E>(_editor: E) #return_type {
+                #new_ident()
+            }
+
+            ignore_editor
+        }}
+    };
+
     quote! {
         #function
 
         #[::nvim_oxi::test]
         fn #test_ident() #return_type {
             let editor_factory = #editor_factory;
-            crate::test_utils::run_nvim_test(#new_ident, editor_factory)
+
+            #setup
+            let result = crate::test_utils::run_nvim_test(#test, editor_factory);
+            #teardown
+
+            result
         }
     }
     .into()
@@ -0,0 +1,88 @@
+use eel::{
+    OptionValue, Position, Result,
+    buffer::{BufferHandle, WriteBuffer},
+    comment::{CommentMode, CommentSpec},
+};
+
+use super::NvimBufferHandle;
+
+impl NvimBufferHandle {
+    /// Comment markers from the buffer's `commentstring`, which depends on its filetype.
+    pub fn comment_spec(&self, mode: CommentMode) -> Result<CommentSpec> {
+        let commentstring = match self.get_option("commentstring")? {
+            OptionValue::String(s) => s,
+            value => Err(eel::buffer::Error::Custom(
+                format!("Unexpected commentstring: {value:?}").into(),
+            ))?,
+        };
+
+        let spec = CommentSpec::from_commentstring(&commentstring, mode).ok_or_else(|| {
+            eel::buffer::Error::Custom(format!("Invalid commentstring: {commentstring:?}").into())
+        })?;
+
+        Ok(spec)
+    }
+
+    /// [`WriteBuffer::toggle_comment`] with markers from [`NvimBufferHandle::comment_spec`].
+    pub fn toggle_comment(
+        &self,
+        start: &Position,
+        end: &Position,
+        mode: CommentMode,
+    ) -> Result<bool> {
+        let spec = self.comment_spec(mode)?;
+
+        self.write().toggle_comment(start, end, &spec)
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{assert_buffer_content, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn filetype_comment(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "local a = 1\nlocal b = 2");
+
+        // Normally set by the filetype plugin
+        buffer
+            .set_option("commentstring", "-- %s".into())
+            .expect("Failed to set commentstring");
+
+        assert!(
+            buffer
+                .toggle_comment(
+                    &Position::new(0, 0),
+                    &Position::new(1, 0),
+                    CommentMode::Line
+                )
+                .expect("Failed to toggle comment")
+        );
+        assert_buffer_content!(buffer, "-- local a = 1\n-- local b = 2");
+
+        buffer
+            .set_option("commentstring", "/*%s*/".into())
+            .expect("Failed to set commentstring");
+
+        assert!(
+            buffer
+                .toggle_comment(
+                    &Position::new(1, 3),
+                    &Position::new(1, 8),
+                    CommentMode::Block
+                )
+                .expect("Failed to toggle comment")
+        );
+        assert_buffer_content!(buffer, "-- local a = 1\n-- /*local*/ b = 2");
+
+        buffer
+            .set_option("commentstring", "".into())
+            .expect("Failed to set commentstring");
+        assert!(buffer.comment_spec(CommentMode::Line).is_err());
+    }
+}
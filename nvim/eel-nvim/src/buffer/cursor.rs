@@ -1,31 +1,96 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use nvim_oxi::api::opts::CreateAutocmdOpts;
+
 use eel::{
     Position, Result,
     buffer::ReadBuffer,
     cursor::{CursorReadBuffer, CursorWriteBuffer},
+    dispatch::MainThreadDispatcher,
 };
 
 use crate::{
+    dispatcher::Dispatcher,
     error::{Error as NvimError, IntoNvimResult as _},
     window::NvimWindow,
 };
 
 use super::{NativePosition, NvimBuffer};
 
+/// Bumped whenever a window may start or stop showing a buffer, invalidating all cached
+/// windows.
+static WINDOW_GENERATION: AtomicU64 = AtomicU64::new(0);
+static WATCHING_WINDOWS: AtomicBool = AtomicBool::new(false);
+
+/// Generation and window (if any) found by the last scan.
+pub(crate) type CachedWindow = (u64, Option<i32>);
+
+/// Registers the autocmds maintaining [`WINDOW_GENERATION`], once per nvim instance. Until this
+/// succeeds each lookup scans all windows.
+pub(crate) fn watch_windows(dispatcher: &Dispatcher) -> Result<()> {
+    if WATCHING_WINDOWS.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+
+    let result = dispatcher.dispatch(|| {
+        let opts = CreateAutocmdOpts::builder()
+            .callback(|_| {
+                WINDOW_GENERATION.fetch_add(1, Ordering::AcqRel);
+                Ok::<_, NvimError>(false)
+            })
+            .build();
+
+        nvim_oxi::api::create_autocmd(
+            ["WinEnter", "WinClosed", "BufWinEnter", "BufWinLeave"],
+            &opts,
+        )
+        .into_nvim()
+    });
+
+    if !matches!(result, Ok(Ok(_))) {
+        WATCHING_WINDOWS.store(false, Ordering::Release);
+    }
+
+    result??;
+
+    Ok(())
+}
+
 impl NvimBuffer {
     fn get_window(&self) -> Result<Option<NvimWindow>> {
-        let handle = self.handle;
-
-        let nvim_window = self.dispatcher.dispatch(move || {
-            nvim_oxi::api::list_wins().find(|win| {
-                if let Ok(buf) = win.get_buf() {
-                    buf.handle() == handle
-                } else {
-                    false
-                }
-            })
-        })?;
+        // Loaded before scanning, so a change during the scan invalidates its result
+        let generation = WINDOW_GENERATION.load(Ordering::Acquire);
+
+        let cached = WATCHING_WINDOWS
+            .load(Ordering::Acquire)
+            .then(|| *self.window_cache.lock())
+            .flatten()
+            .filter(|(g, _)| *g == generation);
 
-        Ok(nvim_window.map(|w| NvimWindow::wrap(w, self.dispatcher.clone())))
+        let window = match cached {
+            Some((_, window)) => window,
+            None => {
+                let handle = self.handle;
+
+                let window = self.dispatcher.dispatch(move || {
+                    nvim_oxi::api::list_wins()
+                        .find(|win| {
+                            if let Ok(buf) = win.get_buf() {
+                                buf.handle() == handle
+                            } else {
+                                false
+                            }
+                        })
+                        .map(|win| win.handle())
+                })?;
+
+                *self.window_cache.lock() = Some((generation, window));
+
+                window
+            }
+        };
+
+        Ok(window.map(|w| NvimWindow::wrap(w.into(), self.dispatcher.clone())))
     }
 }
 
@@ -63,4 +128,69 @@ impl CursorWriteBuffer for NvimBuffer {
 
         Ok(())
     }
+
+    /// Jump lists belong to windows, so this does nothing for hidden buffers.
+    fn push_jump(&mut self) -> Result<()> {
+        match self.get_window()? {
+            Some(w) => w.push_jump(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{Editor, buffer::BufferHandle, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn window_cache(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        let other = new_buffer_with_content(&editor, "Other first\nOther second");
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        let generation = WINDOW_GENERATION.load(Ordering::Acquire);
+
+        for col in 0..3 {
+            buffer
+                .write()
+                .set_cursor(&Position::new(1, col))
+                .expect("Failed to set cursor");
+        }
+
+        assert_eq!(WINDOW_GENERATION.load(Ordering::Acquire), generation);
+        assert!(buffer.read().window_cache.lock().is_some());
+
+        editor
+            .set_current_buffer(&mut other.write())
+            .expect("Failed to set current buffer");
+        assert!(WINDOW_GENERATION.load(Ordering::Acquire) > generation);
+
+        let other_cursor = other.read().get_cursor().expect("Failed to get cursor");
+
+        // Hidden now, the window showing `other` must not move
+        buffer
+            .write()
+            .set_cursor(&Position::new(0, 4))
+            .expect("Failed to set cursor");
+
+        assert!(
+            buffer
+                .read()
+                .get_window()
+                .expect("Failed to get window")
+                .is_none()
+        );
+        assert_eq!(
+            other.read().get_cursor().expect("Failed to get cursor"),
+            other_cursor
+        );
+    }
 }
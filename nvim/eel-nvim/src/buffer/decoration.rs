@@ -0,0 +1,341 @@
+use nvim_oxi::api::{
+    opts::{GetExtmarkByIdOpts, SetExtmarkOpts, SetExtmarkOptsBuilder},
+    types::ExtmarkVirtTextPosition,
+};
+
+use eel::{
+    PosRange, Position, Result,
+    buffer::ReadBuffer,
+    decoration::{
+        DecorationId, DecorationReadBuffer, DecorationWriteBuffer, Placement, VirtualLine,
+    },
+    dispatch::MainThreadDispatcher,
+};
+
+use crate::{error::IntoNvimResult as _, namespace::Namespace};
+
+use super::NvimBuffer;
+
+/// Extmark holding the `virt_lines`, `virt_text` or highlight, in the `eel` namespace or in a
+/// plugin's [`Namespace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvimDecorationId {
+    namespace: u32,
+    extmark: u32,
+}
+
+impl NvimDecorationId {
+    pub fn namespace(&self) -> u32 {
+        self.namespace
+    }
+
+    pub fn extmark(&self) -> u32 {
+        self.extmark
+    }
+}
+
+impl DecorationId for NvimDecorationId {}
+
+fn chunks(line: VirtualLine) -> Vec<(String, Vec<String>)> {
+    line.chunks
+        .into_iter()
+        .map(|(text, highlight)| (text, highlight.into_iter().collect()))
+        .collect()
+}
+
+fn virt_lines_opts(lines: Vec<VirtualLine>, placement: Placement) -> SetExtmarkOptsBuilder {
+    let chunks = lines.into_iter().map(chunks).collect::<Vec<_>>();
+
+    let mut opts = SetExtmarkOpts::builder();
+    opts.virt_lines(chunks)
+        .virt_lines_above(placement == Placement::Above);
+
+    opts
+}
+
+impl DecorationReadBuffer for NvimBuffer {
+    type DecorationId = NvimDecorationId;
+
+    fn virtual_lines_row(&self, id: NvimDecorationId) -> Result<usize> {
+        let buf = self.inner_buf();
+
+        let (row, _, _) = self
+            .dispatcher
+            .dispatch(move || {
+                buf.get_extmark_by_id(id.namespace, id.extmark, &GetExtmarkByIdOpts::default())
+            })?
+            .into_nvim()?;
+
+        Ok(row)
+    }
+
+    fn virtual_text_position(&self, id: NvimDecorationId) -> Result<Position> {
+        let buf = self.inner_buf();
+
+        let (row, col, _) = self
+            .dispatcher
+            .dispatch(move || {
+                buf.get_extmark_by_id(id.namespace, id.extmark, &GetExtmarkByIdOpts::default())
+            })?
+            .into_nvim()?;
+
+        Ok(Position::new(row, col))
+    }
+
+    fn highlighted_range(&self, id: NvimDecorationId) -> Result<PosRange> {
+        let buf = self.inner_buf();
+
+        let (row, col, infos) = self
+            .dispatcher
+            .dispatch(move || {
+                buf.get_extmark_by_id(
+                    id.namespace,
+                    id.extmark,
+                    &GetExtmarkByIdOpts::builder().details(true).build(),
+                )
+            })?
+            .into_nvim()?;
+
+        let start = Position::new(row, col);
+
+        // Line highlights don't have an end
+        match infos.and_then(|i| i.end_row.zip(i.end_col)) {
+            Some((row, col)) => Ok(PosRange::new(start, Position::new(row, col))),
+            None => Ok(PosRange::new(Position::new(row, 0), self.max_row_pos(row)?)),
+        }
+    }
+}
+
+impl DecorationWriteBuffer for NvimBuffer {
+    fn add_virtual_lines(
+        &mut self,
+        row: usize,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<NvimDecorationId> {
+        Namespace::eel(&self.dispatcher)?.add_virtual_lines(self, row, lines, placement)
+    }
+
+    fn set_virtual_lines(
+        &mut self,
+        id: NvimDecorationId,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<()> {
+        let mut buf = self.inner_buf();
+
+        self.dispatcher.dispatch(move || {
+            let (row, col, _) = buf
+                .get_extmark_by_id(id.namespace, id.extmark, &GetExtmarkByIdOpts::default())
+                .into_nvim()?;
+
+            let opts = virt_lines_opts(lines, placement).id(id.extmark).build();
+
+            buf.set_extmark(id.namespace, row, col, &opts)
+                .map(|_| ())
+                .into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    fn remove_virtual_lines(&mut self, id: NvimDecorationId) -> Result<()> {
+        let mut buf = self.inner_buf();
+
+        self.dispatcher
+            .dispatch(move || buf.del_extmark(id.namespace, id.extmark))?
+            .into_nvim()?;
+
+        Ok(())
+    }
+
+    fn add_virtual_text(
+        &mut self,
+        position: &Position,
+        text: VirtualLine,
+    ) -> Result<NvimDecorationId> {
+        Namespace::eel(&self.dispatcher)?.add_virtual_text(self, position, text)
+    }
+
+    fn remove_virtual_text(&mut self, id: NvimDecorationId) -> Result<()> {
+        self.remove_virtual_lines(id)
+    }
+
+    fn highlight_range(
+        &mut self,
+        range: impl Into<PosRange>,
+        group: &str,
+    ) -> Result<NvimDecorationId> {
+        Namespace::eel(&self.dispatcher)?.highlight_range(self, range, group)
+    }
+
+    fn highlight_line(&mut self, row: usize, group: &str) -> Result<NvimDecorationId> {
+        Namespace::eel(&self.dispatcher)?.highlight_line(self, row, group)
+    }
+
+    fn remove_highlight(&mut self, id: NvimDecorationId) -> Result<()> {
+        self.remove_virtual_lines(id)
+    }
+}
+
+impl Namespace {
+    fn add_decoration(
+        &self,
+        buffer: &NvimBuffer,
+        position: Position,
+        opts: impl FnOnce() -> SetExtmarkOpts + Send + 'static,
+    ) -> Result<NvimDecorationId> {
+        let (namespace, mut buf) = (self.id(), buffer.inner_buf());
+
+        let extmark = buffer
+            .dispatcher
+            .dispatch(move || buf.set_extmark(namespace, position.row, position.col, &opts()))?
+            .into_nvim()?;
+
+        Ok(NvimDecorationId { namespace, extmark })
+    }
+
+    /// See [`DecorationWriteBuffer::add_virtual_lines`].
+    pub fn add_virtual_lines(
+        &self,
+        buffer: &mut NvimBuffer,
+        row: usize,
+        lines: Vec<VirtualLine>,
+        placement: Placement,
+    ) -> Result<NvimDecorationId> {
+        let position = Position::new(row, 0);
+        buffer.validate_pos(&position)?;
+
+        self.add_decoration(buffer, position, move || {
+            virt_lines_opts(lines, placement).build()
+        })
+    }
+
+    /// See [`DecorationWriteBuffer::add_virtual_text`].
+    pub fn add_virtual_text(
+        &self,
+        buffer: &mut NvimBuffer,
+        position: &Position,
+        text: VirtualLine,
+    ) -> Result<NvimDecorationId> {
+        buffer.validate_pos(position)?;
+
+        self.add_decoration(buffer, position.clone(), move || {
+            SetExtmarkOpts::builder()
+                .virt_text(chunks(text))
+                .virt_text_pos(ExtmarkVirtTextPosition::Inline)
+                .build()
+        })
+    }
+
+    /// See [`DecorationWriteBuffer::highlight_range`].
+    pub fn highlight_range(
+        &self,
+        buffer: &mut NvimBuffer,
+        range: impl Into<PosRange>,
+        group: &str,
+    ) -> Result<NvimDecorationId> {
+        let (start, end) = range.into().into_positions();
+        buffer.validate_range(&start, &end)?;
+
+        let group = group.to_string();
+
+        self.add_decoration(buffer, start, move || {
+            SetExtmarkOpts::builder()
+                .end_row(end.row)
+                .end_col(end.col)
+                .right_gravity(true)
+                .end_right_gravity(false)
+                .hl_group(group.as_str())
+                .hl_eol(true)
+                .build()
+        })
+    }
+
+    /// See [`DecorationWriteBuffer::highlight_line`].
+    pub fn highlight_line(
+        &self,
+        buffer: &mut NvimBuffer,
+        row: usize,
+        group: &str,
+    ) -> Result<NvimDecorationId> {
+        let position = Position::new(row, 0);
+        buffer.validate_pos(&position)?;
+
+        let group = group.to_string();
+
+        self.add_decoration(buffer, position, move || {
+            SetExtmarkOpts::builder()
+                .line_hl_group(group.as_str())
+                .build()
+        })
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{
+        buffer::BufferHandle,
+        decoration::{Placement, VirtualLine, VirtualLines},
+        test_utils::new_buffer_with_content,
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn virt_lines(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        let lines = VirtualLines::lock_new(
+            &buffer,
+            0,
+            vec![
+                "- old".into(),
+                VirtualLine::new().highlighted("+ ", "DiffAdd").text("new"),
+            ],
+            Placement::Above,
+        )
+        .expect("Failed to add virtual lines");
+
+        let get_virt_lines = || -> (bool, Vec<String>) {
+            editor
+                .exec_lua(
+                    r#"
+                    local buf, ns = ...
+                    local marks = vim.api.nvim_buf_get_extmarks(buf, ns, 0, -1, { details = true })
+                    local details = marks[#marks][4]
+
+                    local lines = {}
+                    for _, line in ipairs(details.virt_lines) do
+                        local text = ""
+                        for _, chunk in ipairs(line) do
+                            text = text .. chunk[1]
+                        end
+                        table.insert(lines, text)
+                    end
+
+                    return details.virt_lines_above, lines
+                    "#,
+                    (
+                        buffer.read().inner_buf().handle(),
+                        editor
+                            .dispatch(crate::editor::get_eel_namespace)
+                            .expect("Failed to dispatch"),
+                    ),
+                )
+                .expect("Failed to get virtual lines")
+        };
+
+        assert_eq!(
+            get_virt_lines(),
+            (true, vec!["- old".into(), "+ new".into()])
+        );
+
+        lines
+            .update(vec!["- older".into()])
+            .expect("Failed to update virtual lines");
+
+        assert_eq!(get_virt_lines(), (true, vec!["- older".into()]));
+    }
+}
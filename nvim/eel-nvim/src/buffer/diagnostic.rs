@@ -0,0 +1,182 @@
+use eel::{PosRange, Position, Result, dispatch::MainThreadDispatcher};
+
+use crate::{
+    error::Error as NvimError,
+    lua::{
+        lua_get_global_path,
+        mlua::{self, FromLua, Function, IntoLua, Lua, Value},
+    },
+};
+
+use super::NvimBuffer;
+
+/// Matches `vim.diagnostic.severity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+    Info = 3,
+    Hint = 4,
+}
+
+/// Entry of `vim.diagnostic`, with 0-based positions (columns in bytes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub range: PosRange,
+    pub severity: Severity,
+    pub message: String,
+    pub source: Option<String>,
+    pub code: Option<String>,
+}
+
+impl FromLua for Diagnostic {
+    fn from_lua(value: Value, _lua: &Lua) -> mlua::Result<Self> {
+        let table = value.as_table().ok_or(mlua::Error::RuntimeError(
+            "Diagnostic is not a table".into(),
+        ))?;
+
+        let (row, col): (usize, usize) = (table.get("lnum")?, table.get("col")?);
+        let end_row: Option<usize> = table.get("end_lnum")?;
+        let end_col: Option<usize> = table.get("end_col")?;
+
+        let severity = match table.get::<Option<u8>>("severity")?.unwrap_or(1) {
+            1 => Severity::Error,
+            2 => Severity::Warning,
+            3 => Severity::Info,
+            _ => Severity::Hint,
+        };
+
+        // LSP codes may be numbers
+        let code = match table.get::<Value>("code")? {
+            Value::Nil => None,
+            code => Some(code.to_string()?),
+        };
+
+        Ok(Self {
+            range: PosRange::new(
+                Position::new(row, col),
+                Position::new(end_row.unwrap_or(row), end_col.unwrap_or(col)),
+            ),
+            severity,
+            message: table.get("message")?,
+            source: table.get("source")?,
+            code,
+        })
+    }
+}
+
+impl IntoLua for Diagnostic {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        let table = lua.create_table()?;
+
+        let (start, end) = self.range.into_positions();
+
+        table.set("lnum", start.row)?;
+        table.set("col", start.col)?;
+        table.set("end_lnum", end.row)?;
+        table.set("end_col", end.col)?;
+        table.set("severity", self.severity as u8)?;
+        table.set("message", self.message)?;
+        table.set("source", self.source)?;
+        table.set("code", self.code)?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+impl NvimBuffer {
+    /// Diagnostics from all namespaces.
+    pub fn diagnostics(&self) -> Result<Vec<Diagnostic>> {
+        let handle = self.handle;
+
+        let diagnostics = self.dispatcher.dispatch(move || {
+            lua_get_global_path::<Function>("vim.diagnostic.get")?
+                .call::<Vec<Diagnostic>>(handle)
+                .map_err(NvimError::from)
+        })??;
+
+        Ok(diagnostics)
+    }
+
+    /// Replaces all diagnostics of `namespace` (created if missing) in the buffer.
+    pub fn set_diagnostics(&mut self, namespace: &str, entries: Vec<Diagnostic>) -> Result<()> {
+        let handle = self.handle;
+        let namespace = namespace.to_string();
+
+        self.dispatcher.dispatch(move || {
+            let namespace = nvim_oxi::api::create_namespace(&namespace);
+
+            lua_get_global_path::<Function>("vim.diagnostic.set")?
+                .call::<()>((namespace, handle, entries))
+                .map_err(NvimError::from)
+        })??;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{buffer::BufferHandle, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn diagnostics_roundtrip(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "let x = 1;\nlet y = x +;");
+
+        assert!(
+            buffer
+                .read()
+                .diagnostics()
+                .expect("Failed to get diagnostics")
+                .is_empty()
+        );
+
+        let entries = vec![
+            Diagnostic {
+                range: PosRange::new(Position::new(1, 10), Position::new(1, 11)),
+                severity: Severity::Error,
+                message: "expected expression".into(),
+                source: Some("rustc".into()),
+                code: Some("E0001".into()),
+            },
+            Diagnostic {
+                range: PosRange::new(Position::new(0, 4), Position::new(0, 5)),
+                severity: Severity::Warning,
+                message: "unused variable".into(),
+                source: None,
+                code: None,
+            },
+        ];
+
+        buffer
+            .write()
+            .set_diagnostics("eel-test", entries.clone())
+            .expect("Failed to set diagnostics");
+
+        let mut diagnostics = buffer
+            .read()
+            .diagnostics()
+            .expect("Failed to get diagnostics");
+        diagnostics.sort_by(|a, b| a.range.start().cmp(b.range.start()));
+
+        assert_eq!(diagnostics, [entries[1].clone(), entries[0].clone()]);
+
+        buffer
+            .write()
+            .set_diagnostics("eel-test", Vec::new())
+            .expect("Failed to clear diagnostics");
+
+        assert!(
+            buffer
+                .read()
+                .diagnostics()
+                .expect("Failed to get diagnostics")
+                .is_empty()
+        );
+    }
+}
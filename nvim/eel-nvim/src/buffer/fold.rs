@@ -0,0 +1,252 @@
+use nvim_oxi::api::{Window, opts::OptionOpts};
+
+use eel::{
+    Position, Result,
+    buffer::ReadBuffer,
+    dispatch::MainThreadDispatcher,
+    fold::{Fold, FoldReadBuffer, FoldWriteBuffer},
+};
+
+use crate::{
+    error::{Error as NvimError, IntoNvimResult as _},
+    lua::mlua,
+};
+
+use super::NvimBuffer;
+
+/// Folds are window-local, so they're applied in every window showing the buffer.
+fn buffer_windows(handle: i32) -> Vec<Window> {
+    nvim_oxi::api::list_wins()
+        .filter(|win| win.get_buf().is_ok_and(|buf| buf.handle() == handle))
+        .collect()
+}
+
+/// Deletes folds containing any of the rows (1-based), keeping the cursor in place.
+fn delete_folds(win: &mut Window, start: usize, end: usize) -> std::result::Result<(), NvimError> {
+    let (row, col) = win.get_cursor()?;
+
+    nvim_oxi::api::command(&format!("silent! {start},{end}normal! zD"))?;
+
+    win.set_cursor(row, col)?;
+
+    Ok(())
+}
+
+/// Lists the folds overlapping rows `first..=last` (1-based) of a window.
+///
+/// Folds are found by closing them one level at a time with `zc`, which changes their state.
+/// That's done in a hidden float, it starts with a copy of the window's folds and is thrown
+/// away afterwards, so the window's own folds stay as they were, in and outside the rows.
+const FOLDS_IN: &str = r#"
+local win, first, last = ...
+
+local scratch = vim.api.nvim_win_call(win, function()
+    return vim.api.nvim_open_win(0, false, {
+        relative = "editor", row = 0, col = 0, width = 1, height = 1,
+        focusable = false, hide = true, noautocmd = true,
+    })
+end)
+
+local ok, folds = pcall(vim.api.nvim_win_call, scratch, function()
+    local hidden = {}
+    for row = first, last do
+        hidden[row] = { vim.fn.foldclosed(row), vim.fn.foldclosedend(row) }
+    end
+
+    local folds, seen = {}, {}
+    for row = first, last do
+        vim.cmd("silent! %foldopen!")
+
+        local previous
+        while true do
+            vim.cmd("silent! " .. row .. "normal! zc")
+
+            local start, finish = vim.fn.foldclosed(row), vim.fn.foldclosedend(row)
+            if start == -1 or (previous and previous[1] == start and previous[2] == finish) then
+                break
+            end
+            previous = { start, finish }
+
+            local key = start .. ":" .. finish
+            if not seen[key] then
+                seen[key] = true
+
+                local outer = hidden[math.max(start, first)]
+                local closed = outer[1] ~= -1 and outer[1] <= start and outer[2] >= finish
+                table.insert(folds, { start - 1, finish - 1, closed })
+            end
+        end
+    end
+
+    return folds
+end)
+
+vim.api.nvim_win_close(scratch, true)
+if not ok then
+    error(folds)
+end
+
+table.sort(folds, function(a, b)
+    return a[1] < b[1] or (a[1] == b[1] and a[2] > b[2])
+end)
+
+return folds
+"#;
+
+impl FoldReadBuffer for NvimBuffer {
+    fn is_folded(&self, row: usize) -> Result<bool> {
+        self.validate_pos(&Position::new(row, 0))?;
+
+        let handle = self.handle;
+
+        let closed = self.dispatcher.dispatch(move || {
+            let Some(win) = buffer_windows(handle).into_iter().next() else {
+                return Ok(-1);
+            };
+
+            win.call(move |_| {
+                nvim_oxi::api::call_function::<_, i64>("foldclosed", (row as i64 + 1,))
+            })
+            .into_nvim()
+        })??;
+
+        Ok(closed != -1)
+    }
+
+    fn folds_in(&self, start_row: usize, end_row: usize) -> Result<Vec<Fold>> {
+        self.validate_pos(&Position::new(start_row, 0))?;
+        self.validate_pos(&Position::new(end_row, 0))?;
+
+        let handle = self.handle;
+        let (first, last) = (start_row + 1, end_row + 1);
+
+        let folds = self.dispatcher.dispatch(move || {
+            let Some(win) = buffer_windows(handle).into_iter().next() else {
+                return Ok(Vec::new());
+            };
+
+            mlua::lua()
+                .load(FOLDS_IN)
+                .call::<Vec<mlua::Table>>((win.handle(), first, last))?
+                .into_iter()
+                .map(|fold| Ok(Fold::new(fold.get(1)?, fold.get(2)?, fold.get(3)?)))
+                .collect::<mlua::Result<Vec<_>>>()
+                .map_err(NvimError::from)
+        })??;
+
+        Ok(folds)
+    }
+}
+
+impl FoldWriteBuffer for NvimBuffer {
+    /// Fails if no window shows the buffer, there's nowhere to create the fold.
+    fn fold(&mut self, start_row: usize, end_row: usize) -> Result<()> {
+        self.validate_pos(&Position::new(start_row, 0))?;
+        self.validate_pos(&Position::new(end_row, 0))?;
+
+        let handle = self.handle;
+        let (start, end) = (start_row + 1, end_row + 1);
+
+        let shown = self.dispatcher.dispatch(move || {
+            let windows = buffer_windows(handle);
+
+            for win in &windows {
+                nvim_oxi::api::set_option_value(
+                    "foldmethod",
+                    "manual",
+                    &OptionOpts::builder().win(win.clone()).build(),
+                )?;
+
+                let target = win.clone();
+                win.call::<_, _, ()>(move |_| {
+                    let mut win = target;
+                    delete_folds(&mut win, start, end)?;
+                    nvim_oxi::api::command(&format!("{start},{end}fold")).into_nvim()
+                })?;
+            }
+
+            Ok::<_, NvimError>(!windows.is_empty())
+        })??;
+
+        if !shown {
+            Err(eel::buffer::Error::Custom(
+                "Can't fold a buffer no window shows".into(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn unfold(&mut self, start_row: usize, end_row: usize) -> Result<()> {
+        self.validate_pos(&Position::new(start_row, 0))?;
+        self.validate_pos(&Position::new(end_row, 0))?;
+
+        let handle = self.handle;
+        let (start, end) = (start_row + 1, end_row + 1);
+
+        self.dispatcher.dispatch(move || {
+            for win in buffer_windows(handle) {
+                let target = win.clone();
+                win.call::<_, _, ()>(move |_| {
+                    let mut win = target;
+                    delete_folds(&mut win, start, end)
+                })?;
+            }
+
+            Ok::<_, NvimError>(())
+        })??;
+
+        Ok(())
+    }
+
+    fn toggle_fold(&mut self, row: usize) -> Result<Option<bool>> {
+        self.validate_pos(&Position::new(row, 0))?;
+
+        let handle = self.handle;
+        let line = row as i64 + 1;
+
+        let folded = self.dispatcher.dispatch(move || {
+            let mut folded = None;
+
+            for win in buffer_windows(handle) {
+                let target = win.clone();
+                let state = win.call(move |_| {
+                    let mut win = target;
+
+                    if nvim_oxi::api::call_function::<_, i64>("foldlevel", (line,))? == 0 {
+                        return Ok(None);
+                    }
+
+                    let (cursor_row, cursor_col) = win.get_cursor()?;
+                    nvim_oxi::api::command(&format!("{line}normal! za"))?;
+                    win.set_cursor(cursor_row, cursor_col)?;
+
+                    let closed = nvim_oxi::api::call_function::<_, i64>("foldclosed", (line,))?;
+
+                    Ok::<_, NvimError>(Some(closed != -1))
+                })?;
+
+                folded = folded.or(state);
+            }
+
+            Ok::<_, NvimError>(folded)
+        })??;
+
+        Ok(folded)
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{buffer::BufferHandle, fold::FoldWriteBuffer, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn fold_hidden_buffer(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+
+        assert!(buffer.write().fold(0, 1).is_err());
+    }
+}
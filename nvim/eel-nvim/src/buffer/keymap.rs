@@ -0,0 +1,164 @@
+use nvim_oxi::api::{
+    opts::{CreateCommandOpts, SetKeymapOpts},
+    types::{CommandArgs, CommandNArgs, Mode},
+};
+
+use eel::{Result, dispatch::MainThreadDispatcher};
+
+use crate::error::IntoNvimResult as _;
+
+use super::NvimBufferHandle;
+
+// Mappings and commands are buffer-local, so nvim drops them (and the callbacks) together with
+// the buffer on wipeout.
+impl NvimBufferHandle {
+    /// Maps `lhs` in `mode` to `callback`, only in this buffer.
+    pub fn set_keymap<F>(&self, mode: Mode, lhs: &str, mut callback: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()> + Send + 'static,
+    {
+        let mut buf = self.inner_buf();
+        let lhs = lhs.to_string();
+
+        self.dispatcher.dispatch(move || {
+            let opts = SetKeymapOpts::builder()
+                .callback(move |()| callback())
+                .noremap(true)
+                .silent(true)
+                .build();
+
+            buf.set_keymap(mode, &lhs, "", &opts).into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    pub fn del_keymap(&self, mode: Mode, lhs: &str) -> Result<()> {
+        let mut buf = self.inner_buf();
+        let lhs = lhs.to_string();
+
+        self.dispatcher
+            .dispatch(move || buf.del_keymap(mode, &lhs).into_nvim())??;
+
+        Ok(())
+    }
+
+    /// Creates the user command `name` accepting any number of arguments, only in this buffer.
+    pub fn create_buffer_command<F>(&self, name: &str, callback: F) -> Result<()>
+    where
+        F: FnMut(CommandArgs) -> Result<()> + Send + 'static,
+    {
+        let mut buf = self.inner_buf();
+        let name = name.to_string();
+
+        self.dispatcher.dispatch(move || {
+            let opts = CreateCommandOpts::builder()
+                .nargs(CommandNArgs::Any)
+                .force(true)
+                .build();
+
+            buf.create_user_command(&name, callback, &opts).into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    pub fn del_buffer_command(&self, name: &str) -> Result<()> {
+        let mut buf = self.inner_buf();
+        let name = name.to_string();
+
+        self.dispatcher
+            .dispatch(move || buf.del_user_command(&name).into_nvim())??;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use eel::{Editor, buffer::BufferHandle, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn buffer_keymap(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "");
+        let other = new_buffer_with_content(&editor, "");
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        buffer
+            .set_keymap(Mode::Normal, "gX", move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            })
+            .expect("Failed to set keymap");
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+        editor.exec("normal gXgX").expect("Failed to run keymap");
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+
+        editor
+            .set_current_buffer(&mut other.write())
+            .expect("Failed to set current buffer");
+        editor.exec("normal gX").expect("Failed to run keymap");
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+        buffer
+            .del_keymap(Mode::Normal, "gX")
+            .expect("Failed to delete keymap");
+        editor.exec("normal gX").expect("Failed to run keymap");
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn buffer_command(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "");
+        let other = new_buffer_with_content(&editor, "");
+
+        let args = Arc::new(Mutex::new(Vec::new()));
+        let received = args.clone();
+        buffer
+            .create_buffer_command("EelTestCommand", move |a| {
+                received.lock().expect("Poisoned").push(a.fargs);
+                Ok(())
+            })
+            .expect("Failed to create command");
+
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+        editor
+            .exec("EelTestCommand a b")
+            .expect("Failed to run command");
+        assert_eq!(*args.lock().expect("Poisoned"), [vec!["a", "b"]]);
+
+        editor
+            .set_current_buffer(&mut other.write())
+            .expect("Failed to set current buffer");
+        assert!(editor.exec("EelTestCommand").is_err());
+
+        let buf = buffer.read().inner_buf();
+        editor
+            .exec(&format!("bwipeout! {}", buf.handle()))
+            .expect("Failed to wipe out buffer");
+
+        let exists: i64 = editor
+            .exec_lua("return vim.fn.exists(':EelTestCommand')", ())
+            .expect("Failed to exec lua");
+        assert_eq!(exists, 0);
+    }
+}
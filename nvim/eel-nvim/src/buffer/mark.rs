@@ -2,31 +2,33 @@ use nvim_oxi::api::opts::{GetExtmarkByIdOpts, SetExtmarkOpts};
 
 use eel::{
     Position, Result,
+    dispatch::MainThreadDispatcher,
     mark::{Gravity, MarkId, MarkReadBuffer, MarkWriteBuffer},
 };
 
-use crate::{editor::get_eel_namespace, error::Error as NvimError, error::IntoNvimResult as _};
+use crate::{error::Error as NvimError, error::IntoNvimResult as _, namespace::Namespace};
 
-use super::{NativePosition, NvimBuffer};
+use super::NvimBuffer;
 
+/// Extmark in the default `eel` namespace or in a plugin's
+/// [`Namespace`](crate::namespace::Namespace).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct NvimMarkId(u32);
+pub struct NvimMarkId {
+    namespace: u32,
+    extmark: u32,
+}
 
-impl From<u32> for NvimMarkId {
-    fn from(value: u32) -> Self {
-        NvimMarkId(value)
+impl NvimMarkId {
+    pub(crate) fn new(namespace: u32, extmark: u32) -> Self {
+        Self { namespace, extmark }
     }
-}
 
-impl From<NvimMarkId> for u32 {
-    fn from(value: NvimMarkId) -> Self {
-        value.0
+    pub fn namespace(&self) -> u32 {
+        self.namespace
     }
-}
 
-impl From<&NvimMarkId> for u32 {
-    fn from(value: &NvimMarkId) -> Self {
-        value.0
+    pub fn extmark(&self) -> u32 {
+        self.extmark
     }
 }
 
@@ -41,61 +43,62 @@ impl MarkReadBuffer for NvimBuffer {
         let (row, col, _) = self
             .dispatcher
             .dispatch(move || {
-                buf.get_extmark_by_id(
-                    get_eel_namespace(),
-                    id.into(),
-                    &GetExtmarkByIdOpts::default(),
-                )
+                buf.get_extmark_by_id(id.namespace, id.extmark, &GetExtmarkByIdOpts::default())
             })?
             .into_nvim()?;
 
         Ok(Position::new(row, col))
     }
-}
 
-impl MarkWriteBuffer for NvimBuffer {
-    fn create_mark(&mut self, pos: &Position) -> Result<NvimMarkId> {
-        let native_pos: NativePosition = pos.clone().into();
-        let mut buf = self.inner_buf();
+    fn get_mark_end(&self, id: Self::MarkId) -> Result<Option<Position>> {
+        let buf = self.inner_buf();
 
-        let extmark_id = self
+        let (_, _, infos) = self
             .dispatcher
             .dispatch(move || {
-                buf.set_extmark(
-                    get_eel_namespace(),
-                    native_pos.row - 1,
-                    native_pos.col - 1,
-                    &SetExtmarkOpts::default(),
+                buf.get_extmark_by_id(
+                    id.namespace,
+                    id.extmark,
+                    &GetExtmarkByIdOpts::builder().details(true).build(),
                 )
             })?
             .into_nvim()?;
 
-        Ok(extmark_id.into())
+        Ok(infos
+            .and_then(|i| i.end_row.zip(i.end_col))
+            .map(|(row, col)| Position::new(row, col)))
+    }
+}
+
+impl MarkWriteBuffer for NvimBuffer {
+    fn create_mark(&mut self, pos: &Position) -> Result<NvimMarkId> {
+        Namespace::eel(&self.dispatcher)?.create_mark(self, pos)
     }
 
     fn destroy_mark(&mut self, id: Self::MarkId) -> Result<()> {
         let mut buf = self.inner_buf();
 
         self.dispatcher
-            .dispatch(move || buf.del_extmark(get_eel_namespace(), id.into()))?
+            .dispatch(move || buf.del_extmark(id.namespace, id.extmark))?
             .into_nvim()?;
 
         Ok(())
     }
+    fn create_mark_with(
+        &mut self,
+        start: &Position,
+        end: Option<&Position>,
+        gravity: Gravity,
+    ) -> Result<NvimMarkId> {
+        Namespace::eel(&self.dispatcher)?.create_mark_with(self, start, end, gravity)
+    }
+
     fn set_mark_position(&mut self, id: Self::MarkId, pos: &Position) -> Result<()> {
-        let native_pos: NativePosition = pos.clone().into();
+        let pos = pos.clone();
         let mut buf = self.inner_buf();
 
         self.dispatcher
-            .dispatch(move || {
-                buf.set_extmark(
-                    get_eel_namespace(),
-                    native_pos.row - 1,
-                    native_pos.col - 1,
-                    &SetExtmarkOpts::builder().id(id.into()).build(),
-                )
-            })?
-            .into_nvim()?;
+            .dispatch(move || reset_extmark(&mut buf, id, Some(pos), None))??;
 
         Ok(())
     }
@@ -103,29 +106,92 @@ impl MarkWriteBuffer for NvimBuffer {
     fn set_mark_gravity(&mut self, id: Self::MarkId, gravity: Gravity) -> Result<()> {
         let mut buf = self.inner_buf();
 
-        let pos = self.get_mark_position(id)?;
-
-        self.dispatcher.dispatch(move || {
-            // TODO: In my opinion you shouldn't have to delete an extmark and create a new one to change options,
-            //       but it doesn't work otherwise. Should investigate.
-            buf.del_extmark(get_eel_namespace(), id.into())?;
-
-            buf.set_extmark(
-                get_eel_namespace(),
-                pos.row,
-                pos.col,
-                &SetExtmarkOpts::builder()
-                    .id(id.into())
-                    .right_gravity(match gravity {
-                        Gravity::Left => false,
-                        Gravity::Right => true,
-                    })
-                    .build(),
-            )?;
-
-            Ok::<_, NvimError>(())
-        })??;
+        self.dispatcher
+            .dispatch(move || reset_extmark(&mut buf, id, None, Some(gravity)))??;
 
         Ok(())
     }
 }
+
+/// Creates an extmark spanning `start` to `end`, highlighted with `hl_group` if given.
+pub(crate) fn set_extmark(
+    buf: &mut nvim_oxi::api::Buffer,
+    namespace: u32,
+    start: &Position,
+    end: Option<&Position>,
+    gravity: Gravity,
+    hl_group: Option<&str>,
+) -> Result<NvimMarkId> {
+    let mut opts = SetExtmarkOpts::builder();
+    opts.right_gravity(gravity.start_right());
+
+    if let Some(end) = end {
+        opts.end_row(end.row)
+            .end_col(end.col)
+            .end_right_gravity(gravity.end_right());
+    }
+
+    if let Some(hl_group) = hl_group {
+        opts.hl_group(hl_group);
+    }
+
+    let extmark = buf
+        .set_extmark(namespace, start.row, start.col, &opts.build())
+        .into_nvim()?;
+
+    Ok(NvimMarkId::new(namespace, extmark))
+}
+
+/// Sets the extmark's position or gravity, keeping its extent and everything not overridden.
+fn reset_extmark(
+    buf: &mut nvim_oxi::api::Buffer,
+    id: NvimMarkId,
+    pos: Option<Position>,
+    gravity: Option<Gravity>,
+) -> std::result::Result<(), NvimError> {
+    let (row, col, infos) = buf.get_extmark_by_id(
+        id.namespace,
+        id.extmark,
+        &GetExtmarkByIdOpts::builder().details(true).build(),
+    )?;
+
+    let pos = pos.unwrap_or(Position::new(row, col));
+
+    let mut opts = SetExtmarkOpts::builder();
+    opts.id(id.extmark);
+
+    let end = infos
+        .as_ref()
+        .and_then(|i| i.end_row.zip(i.end_col))
+        .map(|(row, col)| Position::new(row, col));
+
+    match (gravity, &infos) {
+        (Some(gravity), _) => {
+            opts.right_gravity(gravity.start_right());
+
+            if end.is_some() {
+                opts.end_right_gravity(gravity.end_right());
+            }
+        }
+        (None, Some(infos)) => {
+            opts.right_gravity(infos.right_gravity);
+
+            if let Some(end_right) = infos.end_right_gravity {
+                opts.end_right_gravity(end_right);
+            }
+        }
+        (None, None) => {}
+    }
+
+    if let Some(end) = end {
+        opts.end_row(end.row).end_col(end.col);
+    }
+
+    // TODO: In my opinion you shouldn't have to delete an extmark and create a new one to change options,
+    //       but it doesn't work otherwise. Should investigate.
+    buf.del_extmark(id.namespace, id.extmark)?;
+
+    buf.set_extmark(id.namespace, pos.row, pos.col, &opts.build())?;
+
+    Ok(())
+}
@@ -1,13 +1,23 @@
-use std::{ops::RangeBounds, sync::Arc};
+use std::{
+    ops::RangeBounds,
+    sync::{Arc, Weak},
+};
 
+use nvim_oxi::api::opts::OptionOpts;
 use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, RwLock};
 use tracing::trace;
 
-use crate::{dispatcher::Dispatcher, error::Error as NvimError};
+use crate::{dispatcher::Dispatcher, error::Error as NvimError, option};
 
 use eel::{
-    Position, Result,
-    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+    EditorConfig, OptionValue, PosRange, Position, Result,
+    buffer::{
+        BufferData, BufferHandle, ChangeHooks, CloseHooks, ReadBuffer, WeakBufferHandle,
+        WriteBuffer,
+    },
+    column::{Column, ColumnTable},
+    dispatch::MainThreadDispatcher,
+    indent::IndentSettings,
 };
 
 /// Represents a coordinate location within a Neovim buffer.
@@ -33,6 +43,24 @@ pub struct NativePosition {
     pub col: usize,
 }
 
+impl NativePosition {
+    /// `position` with its col counted in `unit`, `table` being of its line. The plain
+    /// conversions treat cols as bytes.
+    pub fn from_position_in(position: &Position, unit: Column, table: &ColumnTable) -> Self {
+        let col = table.convert(position.col, unit, Column::Byte);
+
+        Position::new(position.row, col).into()
+    }
+
+    /// Inverse of [`NativePosition::from_position_in`].
+    pub fn to_position_in(self, unit: Column, table: &ColumnTable) -> Position {
+        let position: Position = self.into();
+        let col = table.convert(position.col, Column::Byte, unit);
+
+        Position::new(position.row, col)
+    }
+}
+
 impl From<(usize, usize)> for NativePosition {
     fn from((row, col): (usize, usize)) -> Self {
         NativePosition { row, col }
@@ -60,6 +88,8 @@ impl From<NativePosition> for Position {
 pub struct NvimBuffer {
     handle: i32,
     dispatcher: Arc<Dispatcher>,
+    #[cfg(feature = "cursor")]
+    window_cache: parking_lot::Mutex<Option<cursor::CachedWindow>>,
 }
 
 impl NvimBuffer {
@@ -67,6 +97,8 @@ impl NvimBuffer {
         NvimBuffer {
             handle: buffer.handle(),
             dispatcher,
+            #[cfg(feature = "cursor")]
+            window_cache: Default::default(),
         }
     }
 
@@ -98,19 +130,186 @@ impl ReadBuffer for NvimBuffer {
 
         Ok(lines.into_iter())
     }
+
+    fn line_len(&self, row: usize) -> Result<usize> {
+        let line_count = self.line_count()?;
+
+        if row >= line_count {
+            Err(eel::buffer::Error::RowOutOfBounds {
+                row: row as isize,
+                limit: line_count - 1,
+            })?;
+        }
+
+        let buf = self.inner_buf();
+
+        let len = self.dispatcher.dispatch(move || {
+            // The offset after the last line may count an EOL that isn't there
+            if row + 1 == line_count {
+                let line = buf.get_lines(row..(row + 1), true)?.next();
+
+                return Ok::<_, NvimError>(line.map_or(0, |l| l.as_bytes().len()));
+            }
+
+            Ok(buf.get_offset(row + 1)? - buf.get_offset(row)? - 1)
+        })??;
+
+        Ok(len)
+    }
+
+    fn char_at(&self, position: &Position) -> Result<Option<char>> {
+        let len = self.line_len(position.row)?;
+
+        if position.col > len {
+            Err(eel::buffer::Error::ColOutOfBounds {
+                col: position.col as isize,
+                limit: len,
+            })?;
+        }
+
+        if position.col == len {
+            return Ok(None);
+        }
+
+        let buf = self.inner_buf();
+        let Position { row, col } = position.clone();
+
+        let c = self.dispatcher.dispatch(move || {
+            // A char is at most 4 bytes long
+            let text = buf
+                .get_text(row..row, col, (col + 4).min(len), &Default::default())?
+                .next();
+
+            let Some(text) = text else {
+                return Ok::<_, NvimError>(None);
+            };
+
+            let bytes = text.as_bytes();
+            let valid = match std::str::from_utf8(bytes) {
+                Ok(s) => s,
+                Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).expect("Prefix is valid"),
+            };
+
+            Ok(valid.chars().next())
+        })??;
+
+        Ok(c)
+    }
+
+    fn get_text_in(&self, range: &PosRange) -> Result<String> {
+        self.validate_range(range.start(), range.end())?;
+
+        let (start, end) = range.clone().into_positions();
+
+        let buf = self.inner_buf();
+
+        let text = self.dispatcher.dispatch(move || {
+            let lines = buf
+                .get_text(start.row..end.row, start.col, end.col, &Default::default())
+                .map_err(NvimError::from)?
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+
+            Ok::<_, NvimError>(lines.join("\n"))
+        })??;
+
+        Ok(text)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        let buf = self.inner_buf();
+
+        let tick = self
+            .dispatcher
+            .dispatch(move || buf.get_changedtick())?
+            .map_err(NvimError::from)?;
+
+        Ok(tick.into())
+    }
+
+    fn indent_settings(&self) -> Result<IndentSettings> {
+        let buf = self.inner_buf();
+
+        let settings = self.dispatcher.dispatch(move || {
+            let opts = OptionOpts::builder().buffer(buf).build();
+
+            let tab_width = nvim_oxi::api::get_option_value::<i64>("tabstop", &opts)?;
+            let shift_width = match nvim_oxi::api::get_option_value::<i64>("shiftwidth", &opts)? {
+                // 'shiftwidth' follows 'tabstop' when zero
+                0 => tab_width,
+                shift_width => shift_width,
+            };
+
+            Ok::<_, NvimError>(IndentSettings {
+                shift_width: shift_width as usize,
+                tab_width: tab_width as usize,
+                expand_tab: nvim_oxi::api::get_option_value("expandtab", &opts)?,
+            })
+        })??;
+
+        Ok(settings)
+    }
 }
 
-impl WriteBuffer for NvimBuffer {
-    fn set_text(&mut self, start: &Position, end: &Position, text: &str) -> Result<()> {
-        self.validate_pos(start)?;
-        self.validate_pos(end)?;
+/// Lines sent to nvim per `nvim_buf_set_lines` call, so huge pastes don't build one huge array.
+const SET_LINES_CHUNK: usize = 4096;
+
+/// Replaces `rows` with the lines of `text`, which is empty or ends with a line break.
+fn set_lines_chunked(
+    buf: &mut nvim_oxi::api::Buffer,
+    rows: std::ops::Range<usize>,
+    text: &str,
+) -> std::result::Result<(), NvimError> {
+    let mut lines = text
+        .strip_suffix('\n')
+        .into_iter()
+        .flat_map(|t| t.split('\n'));
+    let mut replaced = rows;
+
+    loop {
+        let mut sent = 0;
+
+        buf.set_lines(
+            replaced.clone(),
+            true,
+            lines.by_ref().take(SET_LINES_CHUNK).inspect(|_| sent += 1),
+        )?;
+
+        if sent < SET_LINES_CHUNK {
+            return Ok(());
+        }
+
+        // Following chunks are inserted after the previous one
+        let row = replaced.start + sent;
+        replaced = row..row;
+    }
+}
+
+impl NvimBuffer {
+    /// Checks `b:changedtick` against `expected_tick` (if any) and edits in one dispatch, so
+    /// the user can't type in between.
+    fn edit(&self, expected_tick: Option<u64>, range: &PosRange, text: &str) -> Result<()> {
+        self.validate_range(range.start(), range.end())?;
 
         let mut buf = self.inner_buf();
         let text = text.to_string();
-        let native_start: NativePosition = start.clone().into();
-        let native_end: NativePosition = end.clone().into();
+        let native_start: NativePosition = range.start().clone().into();
+        let native_end: NativePosition = range.end().clone().into();
+
+        // Whole rows replaced by whole lines, e.g. linewise pastes
+        let whole_lines = native_start.col == 1
+            && native_end.col == 1
+            && (text.is_empty() || text.ends_with('\n'));
+
+        let edited = self.dispatcher.dispatch(move || {
+            if let Some(expected) = expected_tick {
+                let actual = buf.get_changedtick()?.into();
+
+                if actual != expected {
+                    return Ok(Err(eel::buffer::Error::Conflict { expected, actual }));
+                }
+            }
 
-        self.dispatcher.dispatch(move || {
             nvim_oxi::api::set_option_value(
                 "modified",
                 true,
@@ -119,21 +318,44 @@ impl WriteBuffer for NvimBuffer {
                     .build(),
             )?;
 
-            buf.set_text(
-                (native_start.row - 1)..(native_end.row - 1),
-                native_start.col - 1,
-                native_end.col - 1,
-                text.split("\n"),
-            )?;
+            if whole_lines {
+                set_lines_chunked(
+                    &mut buf,
+                    (native_start.row - 1)..(native_end.row - 1),
+                    &text,
+                )?;
+            } else {
+                buf.set_text(
+                    (native_start.row - 1)..(native_end.row - 1),
+                    native_start.col - 1,
+                    native_end.col - 1,
+                    text.split("\n"),
+                )?;
+            }
 
             // We only have to redraw if the buffer is visible, not sure if checking buffer
             // visibility would be faster though.
             nvim_oxi::api::command("redraw")?;
 
-            Ok::<_, NvimError>(())
+            Ok::<_, NvimError>(Ok(()))
         })??;
 
-        Ok(())
+        Ok(edited?)
+    }
+}
+
+impl WriteBuffer for NvimBuffer {
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        self.edit(None, range, text)
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        self.edit(Some(expected_tick), range, text)
     }
 }
 
@@ -143,15 +365,103 @@ pub struct NvimBufferHandle {
     id: i32,
     #[derivative(Debug = "ignore", PartialEq = "ignore")]
     buffer_lock: Arc<RwLock<NvimBuffer>>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    data: Arc<BufferData>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    close_hooks: Arc<CloseHooks>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    change_hooks: Arc<ChangeHooks>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    dispatcher: Arc<Dispatcher>,
 }
 
 impl NvimBufferHandle {
     pub(crate) fn new(buffer: NvimBuffer) -> Self {
         Self {
             id: buffer.inner_buf().handle(),
+            dispatcher: buffer.dispatcher.clone(),
             buffer_lock: Arc::new(RwLock::new(buffer)),
+            data: Arc::default(),
+            close_hooks: Arc::default(),
+            change_hooks: Arc::default(),
         }
     }
+
+    pub(crate) fn inner_buf(&self) -> nvim_oxi::api::Buffer {
+        self.id.into()
+    }
+
+    /// Like [`BufferHandle::read`], but `None` instead of waiting for a writer. Code on the nvim
+    /// thread can't wait, the writer may be waiting for the nvim thread itself.
+    pub fn try_read(&self) -> Option<<Self as BufferHandle>::ReadBufferLock> {
+        self.buffer_lock.try_read_arc()
+    }
+
+    /// Like [`BufferHandle::write`], but `None` instead of waiting, see
+    /// [`NvimBufferHandle::try_read`].
+    pub fn try_write(&self) -> Option<<Self as BufferHandle>::WriteBufferLock> {
+        self.buffer_lock.try_write_arc()
+    }
+
+    /// Buffer-local option, e.g. `filetype` or `commentstring`.
+    pub fn get_option(&self, name: &str) -> Result<OptionValue> {
+        let buf = self.inner_buf();
+        let name = name.to_string();
+
+        let value = self
+            .dispatcher
+            .dispatch(move || option::get(&name, &OptionOpts::builder().buffer(buf).build()))??;
+
+        Ok(value)
+    }
+
+    pub fn set_option(&self, name: &str, value: OptionValue) -> Result<()> {
+        let buf = self.inner_buf();
+        let name = name.to_string();
+
+        self.dispatcher.dispatch(move || {
+            option::set(&name, value, &OptionOpts::builder().buffer(buf).build())
+        })??;
+
+        Ok(())
+    }
+
+    /// Called on `BufWipeout`, clears user data and runs the `on_close` callbacks.
+    pub(crate) fn close(&self) {
+        self.data.clear();
+        self.change_hooks.close();
+        self.close_hooks.close();
+    }
+
+    /// Called from `on_lines`, runs the `on_change` callbacks.
+    pub(crate) fn changed(&self) {
+        self.change_hooks.changed();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NvimWeakBufferHandle {
+    id: i32,
+    buffer_lock: Weak<RwLock<NvimBuffer>>,
+    data: Weak<BufferData>,
+    close_hooks: Weak<CloseHooks>,
+    change_hooks: Weak<ChangeHooks>,
+    dispatcher: Arc<Dispatcher>,
+}
+
+impl WeakBufferHandle for NvimWeakBufferHandle {
+    type Handle = NvimBufferHandle;
+
+    fn upgrade(&self) -> Option<NvimBufferHandle> {
+        Some(NvimBufferHandle {
+            id: self.id,
+            buffer_lock: self.buffer_lock.upgrade()?,
+            data: self.data.upgrade()?,
+            close_hooks: self.close_hooks.upgrade()?,
+            change_hooks: self.change_hooks.upgrade()?,
+            dispatcher: self.dispatcher.clone(),
+        })
+    }
 }
 
 impl BufferHandle for NvimBufferHandle {
@@ -159,6 +469,7 @@ impl BufferHandle for NvimBufferHandle {
     type WriteBuffer = NvimBuffer;
     type ReadBufferLock = ArcRwLockReadGuard<parking_lot::RawRwLock, Self::ReadBuffer>;
     type WriteBufferLock = ArcRwLockWriteGuard<parking_lot::RawRwLock, Self::WriteBuffer>;
+    type WeakHandle = NvimWeakBufferHandle;
 
     fn read(&self) -> Self::ReadBufferLock {
         let lock = self.buffer_lock.clone();
@@ -166,6 +477,9 @@ impl BufferHandle for NvimBufferHandle {
 
         trace!(buffer_id = id, "Read-locking buffer");
 
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
         let lock = lock.read_arc();
 
         trace!(buffer_id = id, "Buffer read-locked");
@@ -179,12 +493,87 @@ impl BufferHandle for NvimBufferHandle {
 
         trace!(buffer_id = id, "Write-locking buffer");
 
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
         let lock = lock.write_arc();
 
         trace!(buffer_id = id, "Buffer write-locked");
 
         lock
     }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        let Some(timeout) = EditorConfig::current().lock_timeout else {
+            return Ok(self.read());
+        };
+
+        trace!(buffer_id = self.id, ?timeout, "Read-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        Ok(self
+            .buffer_lock
+            .try_read_arc_for(timeout)
+            .ok_or(eel::buffer::Error::LockTimeout(timeout))?)
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        let Some(timeout) = EditorConfig::current().lock_timeout else {
+            return Ok(self.write());
+        };
+
+        trace!(buffer_id = self.id, ?timeout, "Write-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        Ok(self
+            .buffer_lock
+            .try_write_arc_for(timeout)
+            .ok_or(eel::buffer::Error::LockTimeout(timeout))?)
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        trace!(buffer_id = self.id, "Downgrading buffer lock");
+
+        ArcRwLockWriteGuard::downgrade(lock)
+    }
+
+    fn data(&self) -> &BufferData {
+        &self.data
+    }
+
+    fn downgrade(&self) -> NvimWeakBufferHandle {
+        NvimWeakBufferHandle {
+            id: self.id,
+            buffer_lock: Arc::downgrade(&self.buffer_lock),
+            data: Arc::downgrade(&self.data),
+            close_hooks: Arc::downgrade(&self.close_hooks),
+            change_hooks: Arc::downgrade(&self.change_hooks),
+            dispatcher: self.dispatcher.clone(),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.close_hooks.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.change_hooks.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        let buf: nvim_oxi::api::Buffer = self.id.into();
+
+        let tick = self
+            .dispatcher
+            .dispatch(move || buf.get_changedtick())?
+            .map_err(NvimError::from)?;
+
+        Ok(tick.into())
+    }
 }
 
 #[cfg(feature = "cursor")]
@@ -193,11 +582,60 @@ pub mod cursor;
 #[cfg(feature = "mark")]
 pub mod mark;
 
+#[cfg(feature = "region")]
+pub mod region;
+
+#[cfg(feature = "fold")]
+mod fold;
+
+#[cfg(feature = "undo")]
+mod undo;
+
+#[cfg(feature = "decoration")]
+pub mod decoration;
+
+mod comment;
+pub mod diagnostic;
+mod keymap;
+
 #[cfg(feature = "nvim-tests")]
 mod tests {
-    use eel::{Editor, eel_full_tests};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use eel::{
+        Editor, Position,
+        buffer::{BufferHandle, ReadBuffer},
+        column::{Column, ColumnTable},
+        eel_full_tests,
+        test_utils::new_buffer_with_content,
+    };
     use eel_nvim_macros::nvim_test;
 
+    use super::NativePosition;
+    use crate::editor::NvimEditor;
+
+    #[nvim_test(editor_factory = crate::test_utils::nvim_editor_factory)]
+    fn native_position_columns(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "zażółć gęślą");
+        let line = buffer.read().get_line(0).expect("Failed to get line");
+        let table = ColumnTable::new(&line);
+
+        let native = NativePosition::from_position_in(&Position::new(0, 4), Column::Char, &table);
+        let col: usize = editor
+            .exec_lua(
+                "local line, col = ...; return vim.fn.charidx(line, col - 1)",
+                (line.clone(), native.col),
+            )
+            .expect("Failed to get char index");
+
+        assert_eq!(native.col, "zażó".len() + 1);
+        assert_eq!(col, 4);
+        assert_eq!(
+            native.to_position_in(Column::Char, &table),
+            Position::new(0, 4)
+        );
+    }
+
     #[nvim_test(editor_factory = crate::test_utils::nvim_editor_factory)]
     fn basic_test(_editor: impl Editor) {
         let var_key = "test_value";
@@ -209,6 +647,111 @@ mod tests {
         assert_eq!(value, original_value);
     }
 
+    static FIXTURE: AtomicUsize = AtomicUsize::new(0);
+
+    fn fixture_setup() {
+        FIXTURE.store(1, Ordering::Relaxed);
+    }
+
+    fn fixture_teardown() {
+        assert_eq!(FIXTURE.load(Ordering::Relaxed), 2);
+    }
+
+    #[nvim_test(setup = fixture_setup, teardown = fixture_teardown)]
+    fn setup_teardown() {
+        assert_eq!(FIXTURE.load(Ordering::Relaxed), 1);
+        FIXTURE.store(2, Ordering::Relaxed);
+    }
+
+    #[nvim_test(editor_factory = crate::test_utils::nvim_editor_factory)]
+    fn large_paste(editor: impl Editor) {
+        use eel::{
+            Position, assert_buffer_content,
+            buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+            test_utils::new_buffer_with_content,
+        };
+
+        let buffer = new_buffer_with_content(&editor, "first\nlast");
+
+        let lines: Vec<String> = (0..3 * super::SET_LINES_CHUNK + 1)
+            .map(|i| format!("line {i}"))
+            .collect();
+        let text = lines.join("\n") + "\n";
+
+        buffer
+            .write()
+            .set_text((Position::new(1, 0), Position::new(1, 0)), &text)
+            .expect("Failed to paste");
+
+        let content: Vec<String> = buffer
+            .read()
+            .get_all_lines()
+            .expect("Failed to get lines")
+            .collect();
+
+        assert_eq!(content.len(), lines.len() + 2);
+        assert_eq!(content[1..=lines.len()], lines[..]);
+        assert_eq!(content.last().map(String::as_str), Some("last"));
+
+        // Replacing and deleting whole rows
+        buffer
+            .write()
+            .set_text(
+                (&Position::new(1, 0), &Position::new(content.len() - 1, 0)),
+                "a\n\n",
+            )
+            .expect("Failed to replace lines");
+        assert_buffer_content!(buffer, "first\na\n\nlast");
+
+        buffer
+            .write()
+            .set_text((Position::new(0, 0), Position::new(3, 0)), "")
+            .expect("Failed to delete lines");
+        assert_buffer_content!(buffer, "last");
+    }
+
+    #[nvim_test(editor_factory = crate::test_utils::nvim_editor_factory)]
+    fn indent_settings(editor: NvimEditor) {
+        use eel::indent::IndentSettings;
+
+        let buffer = new_buffer_with_content(&editor, "");
+        let set = |name: &str, value: eel::OptionValue| {
+            buffer
+                .set_option(name, value)
+                .expect("Failed to set option");
+        };
+
+        set("tabstop", 8.into());
+        set("shiftwidth", 2.into());
+        set("expandtab", false.into());
+
+        let settings = || {
+            buffer
+                .read()
+                .indent_settings()
+                .expect("Failed to get settings")
+        };
+        assert_eq!(
+            settings(),
+            IndentSettings {
+                shift_width: 2,
+                tab_width: 8,
+                expand_tab: false,
+            }
+        );
+
+        set("shiftwidth", 0.into());
+        set("expandtab", true.into());
+        assert_eq!(
+            settings(),
+            IndentSettings {
+                shift_width: 8,
+                tab_width: 8,
+                expand_tab: true,
+            }
+        );
+    }
+
     eel_full_tests!(
         ::eel_nvim_macros::nvim_test,
         crate::test_utils::nvim_editor_factory
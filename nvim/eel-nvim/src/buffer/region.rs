@@ -0,0 +1,118 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    mpsc::channel,
+};
+
+use nvim_oxi::api::opts::BufAttachOpts;
+use tracing::{trace, warn};
+
+use eel::{
+    Result,
+    buffer::{BufferHandle, ReadBuffer, WeakBufferHandle, WriteBuffer},
+    dispatch::MainThreadDispatcher,
+    region::BufferRegion,
+};
+
+use crate::error::{Error as NvimError, IntoNvimResult};
+
+use super::NvimBufferHandle;
+
+fn restore(region: &BufferRegion<NvimBufferHandle>, snapshot: &str) -> Result<()> {
+    if region.read().get_content()? == snapshot {
+        return Ok(());
+    }
+
+    let (start, end) = region.bounds()?;
+
+    trace!(?start, ?end, "Reverting edit of read-only region");
+
+    region.buffer().write().set_text((&start, &end), snapshot)
+}
+
+/// Reverts any edit of `region` made while it is read-only (see
+/// [`BufferRegion::set_read_only`]), including ones made by the user.
+///
+/// The region's current content is restored. The guard detaches once the region is dropped
+/// or made writable again. Reverting happens asynchronously, after nvim has applied the edit,
+/// on a worker thread shared by all the edits.
+pub fn guard_read_only(region: &BufferRegion<NvimBufferHandle>) -> Result<()> {
+    let snapshot: Arc<str> = region.read().get_content()?.into();
+    let weak = region.downgrade();
+    let detach = Arc::new(AtomicBool::new(false));
+
+    let buf = region.buffer().inner_buf();
+
+    // The buffer lock may be held by a thread waiting on the main loop, so the on_lines
+    // callback can't touch the region directly. The worker ends with the callback.
+    let (notify, edits) = channel::<()>();
+    let worker_detach = detach.clone();
+    std::thread::spawn(move || {
+        while edits.recv().is_ok() {
+            // A single restore covers all the edits so far
+            while edits.try_recv().is_ok() {}
+
+            let Some(region) = weak.upgrade().filter(BufferRegion::is_read_only) else {
+                worker_detach.store(true, Ordering::Release);
+                return;
+            };
+
+            if let Err(e) = restore(&region, &snapshot) {
+                warn!("Failed to revert read-only region: {e}");
+            }
+        }
+    });
+
+    region.buffer().dispatcher.dispatch(move || {
+        let opts = BufAttachOpts::builder()
+            .on_lines(move |_| {
+                let detached = detach.load(Ordering::Acquire) || notify.send(()).is_err();
+
+                Ok::<_, NvimError>(detached)
+            })
+            .build();
+
+        buf.attach(false, &opts).into_nvim()
+    })??;
+
+    Ok(())
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use eel::{Position, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn read_only_region_reverts_edits(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "> prompt\ninput");
+
+        let region = BufferRegion::lock_new(&buffer, (Position::new(0, 0), Position::new(0, 8)))
+            .expect("Failed to create region");
+        region.set_read_only(true);
+        guard_read_only(&region).expect("Failed to guard region");
+
+        let mut buf = buffer.read().inner_buf();
+        editor
+            .dispatch(move || buf.set_text(0..0, 2, 8, ["edited"]))
+            .expect("Failed to dispatch")
+            .expect("Failed to set text");
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while buffer.read().get_line(0).expect("Failed to get line") != "> prompt" {
+            assert!(Instant::now() < deadline, "Edit was not reverted");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            buffer.read().get_content().expect("Failed to get content"),
+            "> prompt\ninput"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, SystemTime};
+
+use eel::{
+    Result,
+    dispatch::MainThreadDispatcher,
+    undo::{UndoBuffer, UndoTree},
+};
+
+use crate::{
+    error::{Error as NvimError, IntoNvimResult as _},
+    lua::mlua,
+};
+
+use super::NvimBuffer;
+
+/// Flattens `undotree()` into `{ seq, time, parent }` entries.
+///
+/// `entries` is the chain of changes leading to the latest one, every entry's `alt` lists the
+/// chains branching off before it, i.e. starting at its parent.
+const UNDO_TREE: &str = r#"
+local buf = ...
+
+local tree = vim.api.nvim_buf_call(buf, vim.fn.undotree)
+
+local changes = {}
+local function walk(entries, parent)
+    for _, entry in ipairs(entries) do
+        if entry.alt then
+            walk(entry.alt, parent)
+        end
+
+        table.insert(changes, { entry.seq, entry.time, parent })
+        parent = entry.seq
+    end
+end
+walk(tree.entries, 0)
+
+return tree.seq_cur, changes
+"#;
+
+impl UndoBuffer for NvimBuffer {
+    fn undo_tree(&self) -> Result<UndoTree> {
+        let handle = self.handle;
+
+        let (current, changes) = self.dispatcher.dispatch(move || {
+            let (current, changes) = mlua::lua()
+                .load(UNDO_TREE)
+                .call::<(u64, Vec<mlua::Table>)>(handle)?;
+
+            let changes = changes
+                .into_iter()
+                .map(|change| {
+                    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(change.get(2)?);
+                    Ok((change.get(1)?, time, change.get(3)?))
+                })
+                .collect::<mlua::Result<Vec<_>>>()?;
+
+            Ok::<_, NvimError>((current, changes))
+        })??;
+
+        Ok(UndoTree::from_parents(current, changes))
+    }
+
+    fn undo_to(&mut self, seq: u64) -> Result<()> {
+        let buf = self.inner_buf();
+
+        self.dispatcher.dispatch(move || {
+            buf.call::<_, _, ()>(move |_| nvim_oxi::api::command(&format!("silent undo {seq}")))
+                .into_nvim()
+        })??;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! [`eel::bus`] topics shared by every plugin in the nvim instance.
+//!
+//! Each plugin links its own copy of eel, so subscribers are kept in a Lua table,
+//! `package.loaded["eel.bus"].topics`, keyed by topic. A subscriber is a function called with
+//! the JSON payload, Lua plugins can subscribe by adding their own. It returns `false` if the
+//! payload isn't for it and `"unsubscribe"` to be removed, anything else counts as received.
+
+use eel::{
+    Result,
+    bus::{Delivery, Subscriber},
+    dispatch::MainThreadDispatcher,
+};
+
+use crate::{dispatcher::Dispatcher, error::Error as NvimError, lua::mlua};
+
+const SUBSCRIBE: &str = r#"
+local topic, subscriber = ...
+
+local bus = package.loaded["eel.bus"] or { topics = {} }
+package.loaded["eel.bus"] = bus
+
+bus.topics[topic] = bus.topics[topic] or {}
+table.insert(bus.topics[topic], subscriber)
+"#;
+
+const PUBLISH: &str = r#"
+local topic, payload = ...
+
+local bus = package.loaded["eel.bus"]
+local subscribers = bus and bus.topics[topic]
+if not subscribers then
+    return 0
+end
+
+local received, kept = 0, {}
+for _, subscriber in ipairs(subscribers) do
+    local ok, result = pcall(subscriber, payload)
+    if not ok then
+        vim.notify("eel bus subscriber to " .. topic .. " failed: " .. tostring(result), vim.log.levels.ERROR)
+        table.insert(kept, subscriber)
+    elseif result ~= "unsubscribe" then
+        table.insert(kept, subscriber)
+        if result ~= false then
+            received = received + 1
+        end
+    end
+end
+
+bus.topics[topic] = #kept > 0 and kept or nil
+
+return received
+"#;
+
+pub(crate) fn subscribe(
+    dispatcher: &Dispatcher,
+    topic: &str,
+    subscriber: Subscriber,
+) -> Result<()> {
+    let topic = topic.to_string();
+
+    dispatcher.dispatch(move || {
+        let lua = mlua::lua();
+        let subscriber = lua.create_function(move |lua, payload: String| {
+            Ok(match subscriber(&payload) {
+                Delivery::Received => mlua::Value::Boolean(true),
+                Delivery::Skipped => mlua::Value::Boolean(false),
+                Delivery::Unsubscribed => mlua::Value::String(lua.create_string("unsubscribe")?),
+            })
+        })?;
+
+        lua.load(SUBSCRIBE)
+            .call::<()>((topic, subscriber))
+            .map_err(NvimError::from)
+    })??;
+
+    Ok(())
+}
+
+pub(crate) fn publish(dispatcher: &Dispatcher, topic: &str, payload: String) -> Result<usize> {
+    let topic = topic.to_string();
+
+    let received = dispatcher.dispatch(move || {
+        mlua::lua()
+            .load(PUBLISH)
+            .call::<usize>((topic, payload))
+            .map_err(NvimError::from)
+    })??;
+
+    Ok(received)
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::Editor;
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    /// Other plugins only share the Lua registry, a Lua subscriber stands in for them.
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn lua_subscribers(editor: NvimEditor) {
+        editor
+            .exec_lua::<_, ()>(
+                r#"
+                local bus = package.loaded["eel.bus"] or { topics = {} }
+                package.loaded["eel.bus"] = bus
+                bus.topics.eel_lua_bus = { function(payload)
+                    vim.g.eel_lua_bus = vim.json.decode(payload).buffer
+                end }
+                "#,
+                (),
+            )
+            .expect("Failed to subscribe from Lua");
+
+        let receiver = editor
+            .subscribe::<String>("eel_lua_bus")
+            .expect("Failed to subscribe");
+
+        assert_eq!(
+            editor
+                .publish(
+                    "eel_lua_bus",
+                    &std::collections::HashMap::from([("buffer", 4)])
+                )
+                .ok(),
+            Some(1)
+        );
+        assert_eq!(
+            editor
+                .exec_lua::<_, i64>("return vim.g.eel_lua_bus", ())
+                .ok(),
+            Some(4)
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+}
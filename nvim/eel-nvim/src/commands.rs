@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use nvim_oxi::{
+    Function,
+    api::{
+        self as nvim_api,
+        opts::CreateCommandOpts,
+        types::{CommandArgs, CommandComplete, CommandNArgs, CommandRange},
+    },
+};
+
+use eel::{
+    Result,
+    commands::{Command, Invocation},
+};
+
+use crate::{editor::NvimEditor, error::IntoNvimResult as _};
+
+fn invocation(args: CommandArgs) -> Invocation {
+    Invocation {
+        range: (args.range > 0).then(|| args.line1.saturating_sub(1)..args.line2),
+        bang: args.bang,
+        args: args.fargs,
+    }
+}
+
+/// Arguments before the one being completed, `line` being the command line up to the cursor.
+fn preceding_args(line: &str, lead: &str) -> Vec<String> {
+    let mut args: Vec<String> = line.split_whitespace().skip(1).map(String::from).collect();
+
+    if !lead.is_empty() {
+        args.pop();
+    }
+
+    args
+}
+
+/// Creates the global user command for `command`, completing its arguments.
+pub fn create_command(editor: Arc<NvimEditor>, command: Command<NvimEditor>) -> Result<()> {
+    let command = Arc::new(command);
+    let spec = command.spec();
+
+    let command_editor = editor.clone();
+
+    editor.dispatch(move || {
+        let mut opts = CreateCommandOpts::builder();
+        opts.force(true).bang(spec.bang);
+
+        if spec.args {
+            let completing = command.clone();
+            let complete = Function::from_fn(move |(lead, line, pos): (String, String, usize)| {
+                let line = line.get(..pos).unwrap_or(&line);
+
+                completing.complete(&preceding_args(line, &lead), &lead)
+            });
+
+            opts.nargs(CommandNArgs::Any)
+                .complete(CommandComplete::CustomList(complete));
+        }
+
+        if spec.range {
+            opts.range(CommandRange::CurrentLine);
+        }
+
+        if let Some(description) = command.description() {
+            opts.desc(description);
+        }
+
+        let name = command.name().to_string();
+        nvim_api::create_user_command(
+            &name,
+            move |args: CommandArgs| {
+                command
+                    .run(command_editor.clone(), invocation(args))
+                    .map(|_| ())
+            },
+            &opts.build(),
+        )
+        .into_nvim()
+    })??;
+
+    Ok(())
+}
+
+pub fn del_command(editor: &NvimEditor, name: &str) -> Result<()> {
+    let name = name.to_string();
+
+    editor.dispatch(move || nvim_api::del_user_command(&name).into_nvim())??;
+
+    Ok(())
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::{
+        ops::Range,
+        sync::{Arc, mpsc},
+        time::Duration,
+    };
+
+    use eel::commands::{Command, EelCommandArgs};
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    fn complete_colors(_lead: &str) -> Vec<String> {
+        vec!["blue".into(), "green".into()]
+    }
+
+    #[derive(Debug, PartialEq, EelCommandArgs)]
+    struct TestArgs {
+        #[eel(range)]
+        rows: Option<Range<usize>>,
+        #[eel(bang)]
+        force: bool,
+        #[eel(flag)]
+        dry_run: bool,
+        #[eel(complete = complete_colors)]
+        color: String,
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn user_command(editor: NvimEditor) {
+        let editor = Arc::new(editor);
+
+        let (tx, rx) = mpsc::channel();
+        let command = Command::new("EelTestCommand", move |_: &NvimEditor, args: TestArgs| {
+            tx.send(args).expect("Failed to send args");
+            Ok(())
+        })
+        .with_description("Test command");
+
+        super::create_command(editor.clone(), command).expect("Failed to create command");
+
+        editor
+            .exec("2,3EelTestCommand! --dry-run blue")
+            .expect("Failed to run command");
+
+        let args = rx
+            .recv_timeout(Duration::from_secs(1))
+            .expect("Command didn't run");
+        assert_eq!(
+            args,
+            TestArgs {
+                rows: Some(1..3),
+                force: true,
+                dry_run: true,
+                color: "blue".into(),
+            }
+        );
+
+        assert!(editor.exec("EelTestCommand").is_err());
+
+        let completions: Vec<String> = editor
+            .exec_lua(
+                "return vim.fn.getcompletion('EelTestCommand --dry-run g', 'cmdline')",
+                (),
+            )
+            .expect("Failed to get completion");
+        assert_eq!(completions, ["green"]);
+
+        let completions: Vec<String> = editor
+            .exec_lua(
+                "return vim.fn.getcompletion('EelTestCommand --', 'cmdline')",
+                (),
+            )
+            .expect("Failed to get completion");
+        assert_eq!(completions, ["--dry-run"]);
+
+        super::del_command(&editor, "EelTestCommand").expect("Failed to delete command");
+        assert!(editor.exec("EelTestCommand blue").is_err());
+    }
+}
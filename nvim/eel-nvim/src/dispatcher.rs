@@ -1,9 +1,12 @@
-use std::{rc::Rc, sync::mpsc, thread::ThreadId};
+use std::{sync::Arc, thread::ThreadId};
 
-use tracing::{error, trace};
+use tracing::trace;
 
-use crate::error::Error as NvimError;
-use eel::{Error as EelError, Result};
+use crate::lua::{lua_get_global_path, mlua::Function};
+use eel::{
+    Result,
+    dispatch::{DispatchTransport, TaskQueue},
+};
 
 use nvim_oxi::{self, libuv::AsyncHandle};
 
@@ -12,131 +15,194 @@ pub enum Error {
     #[error("Nvim LibUV error: {0}")]
     NvimLibUV(#[from] nvim_oxi::libuv::Error),
 
-    #[error("Dispatch function send error")]
-    FuncSend,
+    #[error("Dispatch called from a fast event context on the nvim thread")]
+    FastContext,
+}
+
+/// Runs functions on the nvim thread, see [`eel::dispatch::MainThreadDispatcher`].
+pub type Dispatcher = eel::dispatch::Dispatcher<NvimTransport>;
+
+/// Fast events (e.g. luv callbacks) disallow most API functions.
+fn in_fast_event() -> bool {
+    lua_get_global_path::<Function>("vim.in_fast_event")
+        .and_then(|f| f.call::<bool>(()))
+        .unwrap_or(false)
+}
 
-    #[error("Result receive error: {0}")]
-    ResultRecv(#[from] mpsc::RecvError),
+fn run_dispatched(queue: Arc<TaskQueue>) {
+    if in_fast_event() {
+        trace!("Fast event context, rescheduling dispatched functions");
+
+        nvim_oxi::schedule(move |()| run_dispatched(queue));
+        return;
+    }
+
+    queue.run_pending();
 }
 
-pub struct Dispatcher {
+/// Wakes the nvim thread through a libuv async handle.
+pub struct NvimTransport {
     nvim_thread_id: ThreadId,
     async_handle: AsyncHandle,
-    func_tx: mpsc::Sender<Box<dyn FnOnce() + Send>>,
 }
 
-impl std::fmt::Debug for Dispatcher {
+impl std::fmt::Debug for NvimTransport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Dispatcher")
+        f.debug_struct("NvimTransport")
             .field("nvim_thread_id", &self.nvim_thread_id)
-            .field("func_tx", &self.func_tx)
             .finish()
     }
 }
 
-impl Dispatcher {
-    pub fn new(nvim_thread_id: ThreadId) -> Result<Dispatcher> {
-        let (tx, rx) = mpsc::channel::<Box<dyn FnOnce() + Send>>();
-
-        // In theory this function can be called on a different thread than the inner AsyncHandle
-        // function, and Rc is not Send. But we don't clone it and we pass it straight into the
-        // AsyncHandle, so using Rc should be fine.
-        let rx = Rc::new(rx);
-
+impl NvimTransport {
+    pub fn new(nvim_thread_id: ThreadId, queue: Arc<TaskQueue>) -> Result<Self> {
         let async_handle = AsyncHandle::new(move || {
             trace!("Async handle called, scheduling call on the main neovim thread");
 
-            let rx = rx.clone();
+            let queue = queue.clone();
 
             // We have to call vim.schedule because of libuv recursion issues causing crashes.
             nvim_oxi::schedule(move |()| {
-                trace!("Dispatched function called on the main neovim thread");
-
-                loop {
-                    match rx.try_recv() {
-                        Ok(f) => {
-                            trace!("Function received by async handle");
-                            f();
-                        }
-                        Err(mpsc::TryRecvError::Empty) => {
-                            trace!("Func channel empty");
-                            return;
-                        }
-                        Err(mpsc::TryRecvError::Disconnected) => {
-                            error!("Func channel disconnected");
-                            return;
-                        }
-                    }
-                }
+                trace!("Dispatched functions called on the main neovim thread");
+
+                run_dispatched(queue);
             });
         })
-        .map_err(|e| NvimError::from(Error::from(e)))?;
+        .map_err(Error::from)?;
 
-        Ok(Dispatcher {
+        Ok(Self {
             nvim_thread_id,
             async_handle,
-            func_tx: tx,
         })
     }
 
-    fn inner_dispatch<F, R>(&self, func: F) -> std::result::Result<R, Error>
-    where
-        F: FnOnce() -> R + Send + 'static,
-        R: Send + 'static,
-    {
-        if std::thread::current().id() == self.nvim_thread_id {
-            trace!("Dispatch called from nvim thread");
-
-            return Ok(func());
-        }
-
-        let (result_tx, result_rx) = mpsc::sync_channel::<R>(1);
+    pub fn dispatcher(nvim_thread_id: ThreadId) -> Result<Dispatcher> {
+        Dispatcher::new(|queue| Self::new(nvim_thread_id, queue))
+    }
+}
 
-        let nvim_tid = self.nvim_thread_id;
-        let dispatch_func = Box::new(move || {
-            if nvim_tid != std::thread::current().id() {
-                error!("Dispatched function called on non-nvim thread");
-                return;
-            }
+impl DispatchTransport for NvimTransport {
+    fn on_main_thread(&self) -> bool {
+        std::thread::current().id() == self.nvim_thread_id
+    }
 
-            trace!("Calling function on neovim thread");
+    fn wake(&self) -> Result<()> {
+        trace!("Calling async handle");
 
-            let result = func();
+        self.async_handle.send().map_err(Error::from)?;
 
-            trace!("Sending function result");
+        Ok(())
+    }
 
-            if result_tx.send(result).is_err() {
-                error!("Error while sending dispatch result");
-            }
-        });
+    fn check_inline(&self) -> Result<()> {
+        // Rescheduling would deadlock, as we'd block the thread the result comes from
+        if in_fast_event() {
+            Err(Error::FastContext)?;
+        }
 
-        trace!("Sending function to dispatch");
+        Ok(())
+    }
+}
 
-        if self.func_tx.send(dispatch_func).is_err() {
-            return Err(Error::FuncSend);
-        }
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::{
+        sync::{Arc, mpsc},
+        time::Duration,
+    };
+
+    use eel::dispatch::{MainThreadDispatcher, tests as dispatch_tests};
+    use eel_nvim_macros::nvim_test;
+
+    use super::{Dispatcher, Error, NvimTransport};
+    use crate::{
+        editor::NvimEditor, error::Error as NvimError, lua::mlua, test_utils::nvim_editor_factory,
+    };
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn fast_context(editor: NvimEditor) {
+        let (result_tx, result_rx) = mpsc::channel();
+
+        editor
+            .dispatch(move || {
+                let start_timer = || -> mlua::Result<()> {
+                    let dispatcher = NvimTransport::dispatcher(std::thread::current().id())
+                        .map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+
+                    let lua = mlua::lua();
+                    let callback = lua.create_function(move |_, ()| {
+                        let fast_context = match dispatcher.dispatch(|| ()) {
+                            Err(eel::Error::Platform(e)) => matches!(
+                                (&*e as &dyn std::error::Error).downcast_ref::<NvimError>(),
+                                Some(NvimError::Dispatcher(Error::FastContext))
+                            ),
+                            _ => false,
+                        };
+                        let _ = result_tx.send(fast_context);
+                        Ok(())
+                    })?;
+
+                    lua.load(
+                        "local callback = ...
+                        local timer = vim.uv.new_timer()
+                        timer:start(0, 0, function()
+                            timer:close()
+                            callback()
+                        end)",
+                    )
+                    .call::<()>(callback)
+                };
+
+                start_timer().map_err(NvimError::from)
+            })
+            .expect("Failed to dispatch")
+            .expect("Failed to start timer");
+
+        assert!(
+            result_rx
+                .recv_timeout(Duration::from_secs(1))
+                .expect("Timer callback wasn't called"),
+            "Dispatch in fast context should fail"
+        );
+
+        // Regular dispatch from other threads still works
+        assert_eq!(editor.dispatch(|| 42).expect("Failed to dispatch"), 42);
+    }
 
-        trace!("Calling async handle");
+    fn new_dispatcher(editor: &NvimEditor) -> Arc<Dispatcher> {
+        editor
+            .dispatch(|| NvimTransport::dispatcher(std::thread::current().id()).map(Arc::new))
+            .expect("Failed to dispatch")
+            .expect("Failed to create dispatcher")
+    }
 
-        if let Err(e) = self.async_handle.send() {
-            return Err(e.into());
-        }
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn nested_dispatch(editor: NvimEditor) {
+        dispatch_tests::test_nested_dispatch(new_dispatcher(&editor));
+    }
 
-        trace!("Awaiting result");
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn dispatch_stats(editor: NvimEditor) {
+        dispatch_tests::test_dispatch_stats(new_dispatcher(&editor));
+    }
 
-        let result = result_rx.recv()?;
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn concurrent_dispatch(editor: NvimEditor) {
+        dispatch_tests::test_concurrent_dispatch(new_dispatcher(&editor));
+    }
 
-        trace!("Result received");
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn async_dispatch(editor: NvimEditor) {
+        dispatch_tests::test_dispatch_async(new_dispatcher(&editor));
+    }
 
-        Ok::<_, Error>(result)
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn dispatch_timeout(editor: NvimEditor) {
+        dispatch_tests::test_dispatch_timeout(new_dispatcher(&editor));
     }
 
-    pub fn dispatch<F, R>(&self, func: F) -> Result<R>
-    where
-        F: FnOnce() -> R + Send + 'static,
-        R: Send + 'static,
-    {
-        self.inner_dispatch(func)
-            .map_err(|e| EelError::from(NvimError::from(e)))
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn block_on(editor: NvimEditor) {
+        dispatch_tests::test_block_on(new_dispatcher(&editor));
     }
 }
@@ -1,63 +1,194 @@
-use std::{collections::HashMap, sync::Arc, thread::ThreadId};
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::ThreadId,
+    time::Duration,
+};
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use tracing::trace;
 
-use eel::{Editor, Result, buffer::BufferHandle};
+use nvim_oxi::api::opts::{OptionOpts, OptionScope};
+
+use eel::{
+    Capabilities, Editor, OptionValue, Result,
+    buffer::{BufferHandle, WeakBufferHandle},
+    debounce::IdleTimer,
+    dispatch::MainThreadDispatcher,
+    tasks::Tasks,
+};
 
 use crate::{
     buffer::{NvimBuffer, NvimBufferHandle},
-    dispatcher::Dispatcher,
+    dispatcher::{Dispatcher, NvimTransport},
     error::{Error as NvimError, IntoNvimResult},
+    lua::mlua::{self, FromLuaMulti, IntoLuaMulti},
+    option,
+    version::{self, NvimVersion},
+    virtual_buffer::Providers,
+    window::NvimWindow,
 };
 
+type BufferMap = RwLock<HashMap<i32, NvimBufferHandle>>;
+
+/// Snapshot of [`NvimEditor::buffer_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferStoreStats {
+    pub entries: usize,
+    pub created: usize,
+    pub evicted: usize,
+}
+
+/// Holds one handle per buffer, so its data and hooks live until nvim wipes the buffer out.
 #[derive(Debug)]
 struct BufferStore {
-    buffers: RwLock<HashMap<i32, NvimBufferHandle>>,
+    buffers: Arc<BufferMap>,
+    created: AtomicUsize,
+    evicted: Arc<AtomicUsize>,
     dispatcher: Arc<Dispatcher>,
 }
 
 impl BufferStore {
     fn new(dispatcher: Arc<Dispatcher>) -> Self {
         Self {
-            buffers: RwLock::default(),
+            buffers: Arc::default(),
+            created: AtomicUsize::new(0),
+            evicted: Arc::default(),
             dispatcher,
         }
     }
 }
 
 impl BufferStore {
-    fn get_buffer_handle(&self, buffer: nvim_oxi::api::Buffer) -> NvimBufferHandle {
+    fn get_buffer_handle(&self, buffer: nvim_oxi::api::Buffer) -> Result<NvimBufferHandle> {
         let key = buffer.handle();
 
         if let Some(h) = self.buffers.read().get(&key) {
             trace!("Buffer handle exists already");
-            return h.clone();
+            return Ok(h.clone());
         }
 
-        self.buffers
-            .write()
-            .entry(key)
-            .or_insert_with(|| {
-                trace!("Creating new buffer handle");
-                NvimBufferHandle::new(NvimBuffer::new(buffer, self.dispatcher.clone()))
-            })
-            .clone()
+        // Checked again under the write lock, another thread may have created it meanwhile
+        let handle = match self.buffers.write().entry(key) {
+            Entry::Occupied(entry) => return Ok(entry.get().clone()),
+            Entry::Vacant(entry) => entry.insert(self.create_handle(buffer.clone())).clone(),
+        };
+
+        // Registered outside of the store lock, the callbacks on the nvim thread need it
+        let watched = self
+            .watch_wipeout(buffer.clone())
+            .and_then(|_| self.watch_changes(buffer, &handle));
+        if let Err(e) = watched {
+            self.buffers.write().remove(&key);
+            return Err(e);
+        }
+
+        Ok(handle)
+    }
+
+    fn create_handle(&self, buffer: nvim_oxi::api::Buffer) -> NvimBufferHandle {
+        trace!("Creating new buffer handle");
+
+        self.created.fetch_add(1, Ordering::Relaxed);
+
+        NvimBufferHandle::new(NvimBuffer::new(buffer, self.dispatcher.clone()))
+    }
+
+    fn stats(&self) -> BufferStoreStats {
+        BufferStoreStats {
+            entries: self.buffers.read().len(),
+            created: self.created.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Evicts the handle and closes it once nvim wipes the buffer out, so weak handles stop
+    /// upgrading and a new buffer reusing the same handle starts clean.
+    fn watch_wipeout(&self, buffer: nvim_oxi::api::Buffer) -> Result<()> {
+        let key = buffer.handle();
+        let buffers = Arc::downgrade(&self.buffers);
+        let evicted = self.evicted.clone();
+
+        self.dispatcher.dispatch(move || {
+            let opts = nvim_oxi::api::opts::CreateAutocmdOpts::builder()
+                .buffer(buffer)
+                .once(true)
+                .callback(move |_| {
+                    trace!(buffer_id = key, "Buffer wiped out");
+
+                    let handle = buffers.upgrade().and_then(|b| b.write().remove(&key));
+
+                    if let Some(handle) = handle {
+                        evicted.fetch_add(1, Ordering::Relaxed);
+                        handle.close();
+                    }
+
+                    Ok::<_, NvimError>(false)
+                })
+                .build();
+
+            nvim_oxi::api::create_autocmd(["BufWipeout"], &opts).into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    /// Drives the handle's `on_change` callbacks, detaching once it's gone.
+    fn watch_changes(
+        &self,
+        buffer: nvim_oxi::api::Buffer,
+        handle: &NvimBufferHandle,
+    ) -> Result<()> {
+        let handle = handle.downgrade();
+
+        self.dispatcher.dispatch(move || {
+            let opts = nvim_oxi::api::opts::BufAttachOpts::builder()
+                .on_lines(move |_| {
+                    let Some(handle) = handle.upgrade() else {
+                        return Ok::<_, NvimError>(true);
+                    };
+
+                    handle.changed();
+
+                    Ok(false)
+                })
+                .build();
+
+            buffer.attach(false, &opts).into_nvim()
+        })??;
+
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct NvimEditor {
     buffer_store: BufferStore,
-    dispatcher: Arc<Dispatcher>,
+    /// Created with its autocmds on the first `on_idle`
+    idle: Mutex<Option<Arc<IdleTimer>>>,
+    pub(crate) providers: Arc<Providers>,
+    pub(crate) tasks: Arc<Tasks>,
+    pub(crate) dispatcher: Arc<Dispatcher>,
 }
 
 impl NvimEditor {
+    /// Fails with [`NvimError::UnsupportedVersion`] on nvim older than [`NvimVersion::MIN`].
     pub fn new(nvim_thread_id: ThreadId) -> Result<Self> {
-        let dispatcher = Arc::new(Dispatcher::new(nvim_thread_id)?);
+        let dispatcher = Arc::new(NvimTransport::dispatcher(nvim_thread_id)?);
+        dispatcher.dispatch(version::check)??;
+
+        #[cfg(feature = "cursor")]
+        crate::buffer::cursor::watch_windows(&dispatcher)?;
 
         Ok(NvimEditor {
             buffer_store: BufferStore::new(dispatcher.clone()),
+            idle: Mutex::default(),
+            providers: Arc::default(),
+            tasks: Arc::default(),
             dispatcher,
         })
     }
@@ -73,6 +204,170 @@ impl NvimEditor {
     {
         self.dispatcher.dispatch(func)
     }
+
+    /// For synchronous Lua callbacks and autocommands calling into eel code which dispatches,
+    /// see [`MainThreadDispatcher::block_on`].
+    pub fn block_on<F, R>(&self, func: F, timeout: Duration) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.dispatcher.block_on(func, timeout)
+    }
+
+    /// Runs `<C-o>` or `<C-i>`, reporting whether the jump list position changed.
+    #[cfg(feature = "cursor")]
+    fn jump(&self, key: &'static str) -> Result<bool> {
+        self.exec_lua(
+            r#"
+            local before = vim.fn.getjumplist()[2]
+            vim.api.nvim_command([[execute "normal! \]] .. ... .. [["]])
+            return vim.fn.getjumplist()[2] ~= before
+            "#,
+            key,
+        )
+    }
+
+    /// Typing and cursor movement count as activity, while `CursorHold` and `InsertLeave` end
+    /// the idle period early.
+    fn idle_timer(&self) -> Result<Arc<IdleTimer>> {
+        if let Some(timer) = self.idle.lock().as_ref() {
+            return Ok(timer.clone());
+        }
+
+        // Not locked while dispatching, autocommands calling into eel would wait on it
+        let timer = Arc::new(IdleTimer::new());
+        let weak = Arc::downgrade(&timer);
+
+        self.dispatch(move || {
+            let autocmd = |events: &[&str], update: fn(&IdleTimer)| {
+                let timer = weak.clone();
+                let opts = nvim_oxi::api::opts::CreateAutocmdOpts::builder()
+                    .callback(move |_| {
+                        let Some(timer) = timer.upgrade() else {
+                            return Ok::<_, NvimError>(true);
+                        };
+
+                        update(&timer);
+
+                        Ok(false)
+                    })
+                    .build();
+
+                nvim_oxi::api::create_autocmd(events.iter().copied(), &opts).into_nvim()
+            };
+
+            autocmd(
+                &["CursorMoved", "CursorMovedI", "TextChanged", "TextChangedI"],
+                IdleTimer::activity,
+            )?;
+            autocmd(
+                &["CursorHold", "CursorHoldI", "InsertLeave"],
+                IdleTimer::settle,
+            )
+        })??;
+
+        // Another thread may have won the race, the autocommands of the dropped timer remove
+        // themselves on their next event
+        Ok(self.idle.lock().get_or_insert(timer).clone())
+    }
+
+    /// Number of functions sent to the nvim thread so far, see [`eel::dispatch::DispatchStats::sent`].
+    pub fn dispatch_count(&self) -> u64 {
+        self.dispatcher.stats().sent
+    }
+
+    pub fn buffer_stats(&self) -> BufferStoreStats {
+        self.buffer_store.stats()
+    }
+
+    /// Version of the running nvim, detected once.
+    pub fn version(&self) -> Result<NvimVersion> {
+        Ok(self.dispatch(version::current)??)
+    }
+
+    pub(crate) fn buffer_handle(&self, buffer: nvim_oxi::api::Buffer) -> Result<NvimBufferHandle> {
+        self.buffer_store.get_buffer_handle(buffer)
+    }
+
+    pub fn current_window(&self) -> Result<NvimWindow> {
+        let window = self.dispatch(nvim_oxi::api::get_current_win)?;
+
+        Ok(NvimWindow::wrap(window, self.dispatcher.clone()))
+    }
+
+    /// Runs Ex commands, returning their output.
+    pub fn exec(&self, cmd: &str) -> Result<String> {
+        let cmd = cmd.to_string();
+
+        let output = self.dispatch(move || {
+            let opts = nvim_oxi::api::opts::ExecOpts::builder()
+                .output(true)
+                .build();
+
+            let output = nvim_oxi::api::exec2(&cmd, &opts).into_nvim()?;
+
+            Ok::<_, NvimError>(output.map(|s| s.to_string()).unwrap_or_default())
+        })??;
+
+        Ok(output)
+    }
+
+    /// Runs a Lua chunk, `args` are available through `...`.
+    pub fn exec_lua<A, R>(&self, chunk: &str, args: A) -> Result<R>
+    where
+        A: IntoLuaMulti + Send + 'static,
+        R: FromLuaMulti + Send + 'static,
+    {
+        let chunk = chunk.to_string();
+
+        let value = self.dispatch(move || {
+            mlua::lua()
+                .load(chunk)
+                .call::<R>(args)
+                .map_err(NvimError::from)
+        })??;
+
+        Ok(value)
+    }
+
+    /// Maps `<Plug>(name)` in normal mode to `action`, run through `operatorfunc` so `.`
+    /// repeats it. `name` is used in a Lua identifier, so it's limited to `[A-Za-z0-9_]`.
+    ///
+    /// `action` runs on the nvim thread, its edits are part of the repeated change.
+    pub fn make_repeatable<F>(&self, name: &str, mut action: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()> + Send + 'static,
+    {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            Err(eel::buffer::Error::Custom(
+                format!("Invalid repeatable name: {name}").into(),
+            ))?;
+        }
+
+        let name = name.to_string();
+
+        self.dispatch(move || {
+            let lua = mlua::lua();
+            let action =
+                lua.create_function_mut(move |_, ()| action().map_err(mlua::Error::external))?;
+
+            lua.load(
+                r#"
+                local name, action = ...
+                _G["eel_repeatable_" .. name] = function() action() end
+                vim.keymap.set("n", "<Plug>(" .. name .. ")", function()
+                    vim.go.operatorfunc = "v:lua.eel_repeatable_" .. name
+                    return "g@l"
+                end, { expr = true, silent = true })
+                "#,
+            )
+            .call::<()>((name, action))
+            .map_err(NvimError::from)
+        })??;
+
+        Ok(())
+    }
 }
 
 impl Editor for NvimEditor {
@@ -81,7 +376,7 @@ impl Editor for NvimEditor {
     fn current_buffer(&self) -> Result<NvimBufferHandle> {
         let buf = self.dispatch(nvim_oxi::api::get_current_buf)?;
 
-        Ok(self.buffer_store.get_buffer_handle(buf))
+        self.buffer_store.get_buffer_handle(buf)
     }
 
     fn set_current_buffer(
@@ -107,11 +402,477 @@ impl Editor for NvimEditor {
             Ok::<_, NvimError>(buf)
         })??;
 
-        Ok(self.buffer_store.get_buffer_handle(buf))
+        self.buffer_store.get_buffer_handle(buf)
+    }
+
+    fn buffers(&self) -> Result<Vec<NvimBufferHandle>> {
+        let bufs = self.dispatch(|| {
+            nvim_oxi::api::list_bufs()
+                .filter(|buf| buf.is_loaded())
+                .collect::<Vec<_>>()
+        })?;
+
+        bufs.into_iter()
+            .map(|buf| self.buffer_store.get_buffer_handle(buf))
+            .collect()
+    }
+
+    fn buffer_name(&self, buffer: &NvimBufferHandle) -> Result<Option<String>> {
+        let buf = buffer.inner_buf();
+
+        let name = self.dispatch(move || buf.get_name().into_nvim())??;
+
+        Ok((!name.as_os_str().is_empty()).then(|| name.to_string_lossy().into_owned()))
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<NvimBufferHandle>> {
+        let name = normalize_path(Path::new(name));
+
+        let buf = self.dispatch(move || {
+            for buf in nvim_oxi::api::list_bufs() {
+                let buf_name = buf.get_name()?;
+
+                // Unnamed buffers would match the working directory after normalization
+                if !buf_name.as_os_str().is_empty() && normalize_path(&buf_name) == name {
+                    return Ok(Some(buf));
+                }
+            }
+
+            Ok::<_, NvimError>(None)
+        })??;
+
+        buf.map(|buf| self.buffer_store.get_buffer_handle(buf))
+            .transpose()
+    }
+
+    fn get_option(&self, name: &str) -> Result<Option<OptionValue>> {
+        let name = name.to_string();
+
+        let value = self.dispatch(move || {
+            let opts = OptionOpts::builder().scope(OptionScope::Global).build();
+
+            option::get(&name, &opts)
+        })??;
+
+        Ok(Some(value))
+    }
+
+    fn set_option(&self, name: &str, value: OptionValue) -> Result<()> {
+        let name = name.to_string();
+
+        self.dispatch(move || {
+            let opts = OptionOpts::builder().scope(OptionScope::Global).build();
+
+            option::set(&name, value, &opts)
+        })??;
+
+        Ok(())
+    }
+
+    fn on_idle(
+        &self,
+        delay: Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.idle_timer()?.on_idle(delay, callback);
+
+        Ok(())
+    }
+
+    /// Subscribers of every plugin in the nvim instance receive the value, see [`crate::bus`].
+    #[cfg(feature = "bus")]
+    fn publish<T: serde::Serialize>(&self, topic: &str, value: &T) -> Result<usize> {
+        crate::bus::publish(&self.dispatcher, topic, eel::bus::encode(value)?)
+    }
+
+    #[cfg(feature = "bus")]
+    fn subscribe<T: serde::de::DeserializeOwned + Send + 'static>(
+        &self,
+        topic: &str,
+    ) -> Result<std::sync::mpsc::Receiver<T>> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        crate::bus::subscribe(&self.dispatcher, topic, eel::bus::subscriber(sender))?;
+
+        Ok(receiver)
+    }
+
+    #[cfg(feature = "ui")]
+    fn prompt(&self, spec: &eel::ui::PromptSpec) -> Result<eel::ui::UserResponse> {
+        crate::ui::prompt(&self.dispatcher, spec)
+    }
+
+    /// Unlike the buffer cursor, this uses the current window even if the buffer is shown in
+    /// several.
+    #[cfg(feature = "cursor")]
+    fn cursor_position(&self) -> Result<eel::Position> {
+        use eel::buffer::ReadBuffer;
+
+        let position = self.current_window()?.get_cursor()?;
+
+        if self
+            .current_buffer()?
+            .read()
+            .get_line(position.row)?
+            .is_empty()
+        {
+            Ok(eel::Position::new(position.row, 0))
+        } else {
+            Ok(position)
+        }
+    }
+
+    #[cfg(feature = "cursor")]
+    fn set_cursor_position(&self, position: &eel::Position) -> Result<()> {
+        use eel::buffer::ReadBuffer;
+
+        self.current_buffer()?.read().validate_pos(position)?;
+
+        self.current_window()?.set_cursor(position)
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        self.jump("<C-o>")
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.jump("<C-i>")
+    }
+
+    /// Uses `vim.filetype.match`.
+    fn detect_filetype(&self, path: Option<&str>, content: Option<&str>) -> Result<Option<String>> {
+        let lines = content.map(|content| content.lines().map(str::to_string).collect::<Vec<_>>());
+
+        self.exec_lua(
+            r#"
+            local filename, contents = ...
+            if filename == nil and contents == nil then
+                return nil
+            end
+            return vim.filetype.match({ filename = filename, contents = contents })
+            "#,
+            (path.map(str::to_string), lines),
+        )
+    }
+
+    /// Kept per editor, the jobs of virtual buffers run here.
+    fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+
+    /// Special keys are taken literally, e.g. `"\x1b"` for `<Esc>`.
+    fn execute_normal(&self, keys: &str) -> Result<()> {
+        self.exec_lua(
+            "vim.cmd.normal({ args = { ... }, bang = true })",
+            keys.to_string(),
+        )
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            marks: cfg!(feature = "mark"),
+            cursor: cfg!(feature = "cursor"),
+            regions: cfg!(feature = "region"),
+            decorations: cfg!(feature = "decoration"),
+            undo: cfg!(feature = "undo"),
+            normal_mode: true,
+            vim_options: true,
+            ..Default::default()
+        }
     }
 }
 
+/// Makes the path absolute (relative to the nvim working directory) and resolves symlinks if
+/// the file exists, so different spellings of the same path match.
+fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
 #[allow(unused)]
 pub(crate) fn get_eel_namespace() -> u32 {
     nvim_oxi::api::create_namespace("eel")
 }
+
+#[cfg(all(feature = "nvim-tests", feature = "cursor", feature = "region"))]
+mod tests {
+    use std::time::Duration;
+
+    use eel::{
+        Capabilities, Editor,
+        bench::Bench,
+        buffer::{BufferHandle, ReadBuffer, WeakBufferHandle, WriteBuffer},
+        conformance::Conformance,
+        dispatch::MainThreadDispatcher,
+        test_utils::new_buffer_with_content,
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use super::NvimEditor;
+    use crate::test_utils::{nvim_editor_factory, run_nvim_test_with_timeout};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn capabilities(editor: impl Editor) {
+        assert_eq!(
+            editor.capabilities(),
+            Capabilities {
+                marks: true,
+                cursor: true,
+                regions: true,
+                decorations: true,
+                undo: true,
+                normal_mode: true,
+                vim_options: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn idle_events(editor: NvimEditor) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        editor
+            .on_idle(Duration::from_secs(60), move || tx.send(()).is_ok())
+            .expect("Failed to register idle callback");
+
+        editor
+            .exec("doautocmd InsertLeave")
+            .expect("Failed to trigger InsertLeave");
+        rx.recv_timeout(Duration::from_secs(1))
+            .expect("Idle callback didn't run on InsertLeave");
+
+        // Only once until there's activity
+        editor
+            .exec("doautocmd CursorHold")
+            .expect("Failed to trigger CursorHold");
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        editor
+            .exec("doautocmd CursorMoved | doautocmd CursorHold")
+            .expect("Failed to trigger autocmds");
+        rx.recv_timeout(Duration::from_secs(1))
+            .expect("Idle callback didn't run on CursorHold");
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn exec(editor: NvimEditor) {
+        assert_eq!(
+            editor.exec("echo 'Hello'").expect("Failed to exec"),
+            "Hello"
+        );
+
+        editor
+            .exec("let g:eel_exec_test = 42")
+            .expect("Failed to exec");
+        assert_eq!(editor.exec("silent let x = 1").expect("Failed to exec"), "");
+
+        let sum: i64 = editor
+            .exec_lua(
+                "local a, b = ...; return a + b + vim.g.eel_exec_test",
+                (1, 2),
+            )
+            .expect("Failed to exec lua");
+        assert_eq!(sum, 45);
+
+        assert!(editor.exec("definitely_not_a_command").is_err());
+        assert!(editor.exec_lua::<_, ()>("error('boom')", ()).is_err());
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn buffer_data_wipeout(editor: NvimEditor) {
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+        buffer.data().insert(42usize);
+
+        let buf = buffer.read().inner_buf();
+        editor
+            .exec(&format!("bwipeout! {}", buf.handle()))
+            .expect("Failed to wipe out buffer");
+
+        assert!(buffer.data().is_empty());
+        assert!(editor.buffer_store.buffers.read().is_empty());
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn weak_handle_wipeout(editor: NvimEditor) {
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+        let weak = buffer.downgrade();
+
+        let (closed_tx, closed_rx) = std::sync::mpsc::channel();
+        buffer.on_close(move || closed_tx.send(()).expect("Failed to send"));
+
+        let buf = buffer.read().inner_buf();
+        let evicted = editor.buffer_stats().evicted;
+
+        editor
+            .exec(&format!("bwipeout! {}", buf.handle()))
+            .expect("Failed to wipe out buffer");
+
+        closed_rx
+            .recv_timeout(Duration::from_millis(100))
+            .expect("on_close callback wasn't called");
+        assert_eq!(editor.buffer_stats().evicted, evicted + 1);
+
+        drop(buffer);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn store_entries(editor: NvimEditor) {
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+        buffer.data().insert(42usize);
+
+        let id = buffer.read().inner_buf().handle();
+        let stats = editor.buffer_stats();
+        drop(buffer);
+
+        // The handle is kept while the buffer exists, lookups don't watch it again
+        for _ in 0..3 {
+            let again = editor
+                .buffers()
+                .expect("Failed to list buffers")
+                .into_iter()
+                .find(|b| b.read().inner_buf().handle() == id)
+                .expect("Buffer not listed");
+            assert_eq!(again.data().get::<usize>().as_deref(), Some(&42));
+        }
+
+        let autocmds = editor
+            .exec_lua::<_, usize>(
+                "return #vim.api.nvim_get_autocmds({ event = 'BufWipeout', buffer = ... })",
+                id,
+            )
+            .expect("Failed to get autocmds");
+        assert_eq!(autocmds, 1);
+        assert_eq!(editor.buffer_stats(), stats);
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn buffer_by_name(editor: NvimEditor) {
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+
+        let mut buf = buffer.read().inner_buf();
+        editor
+            .dispatch(move || buf.set_name("eel_lookup_test.txt"))
+            .expect("Failed to dispatch")
+            .expect("Failed to set buffer name");
+
+        let found = editor
+            .buffer_by_name("eel_lookup_test.txt")
+            .expect("Failed to look up buffer");
+        assert!(found == Some(buffer.clone()));
+
+        let path = std::env::current_dir()
+            .expect("Failed to get cwd")
+            .join("eel_lookup_test.txt");
+        let found = editor
+            .buffer_by_path(&path)
+            .expect("Failed to look up buffer");
+        assert!(found == Some(buffer));
+
+        assert!(
+            editor
+                .buffer_by_name("eel_missing.txt")
+                .expect("Failed to look up buffer")
+                .is_none()
+        );
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn make_repeatable(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "");
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        let handle = buffer.clone();
+        editor
+            .make_repeatable("eel_repeat_test", move || {
+                let mut buffer = handle.write();
+                let content = buffer.get_content()?;
+
+                buffer.set_content(&format!("{content}x"))
+            })
+            .expect("Failed to make repeatable");
+
+        editor
+            .exec_lua::<_, ()>(
+                r#"
+                vim.cmd.normal(vim.keycode("<Plug>(eel_repeat_test)"))
+                vim.cmd.normal(".")
+                vim.cmd.normal(".")
+                "#,
+                (),
+            )
+            .expect("Failed to run repeatable");
+
+        assert_eq!(buffer.read().get_content().expect("Failed to read"), "xxx");
+
+        assert!(editor.make_repeatable("not valid", || Ok(())).is_err());
+    }
+
+    /// Smoke check of the benches, `bench_baseline` gives the numbers.
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn bench_report(editor: NvimEditor) {
+        let dispatcher = editor.dispatcher.clone();
+
+        let report = Bench::new(editor)
+            .iterations(2)
+            .dispatch_counter(move || dispatcher.stats().sent)
+            .append_many()
+            .random_set_text()
+            .mark_churn()
+            .region_reads()
+            .report();
+
+        for result in &report.results {
+            assert!(result.dispatches.is_some_and(|d| d > 0), "{report}");
+        }
+    }
+
+    /// The nvim counterpart of eel's memory backend bench, ignored as it takes a while:
+    /// `cargo test -p eel-nvim --features nvim-tests bench_baseline -- --ignored --nocapture`
+    #[nvim_oxi::test]
+    #[ignore]
+    fn bench_baseline() {
+        run_nvim_test_with_timeout(
+            |editor: NvimEditor| {
+                let dispatcher = editor.dispatcher.clone();
+
+                let report = Bench::new(editor)
+                    .iterations(1000)
+                    .dispatch_counter(move || dispatcher.stats().sent)
+                    .append_many()
+                    .random_set_text()
+                    .mark_churn()
+                    .region_reads()
+                    .report();
+
+                println!("nvim backend\n\n{report}");
+            },
+            nvim_editor_factory,
+            Duration::from_secs(120),
+        )
+    }
+
+    #[nvim_oxi::test]
+    fn conformance_report() {
+        run_nvim_test_with_timeout(
+            |editor| {
+                let report = Conformance::new(editor)
+                    .buffer()
+                    .cursor()
+                    .mark()
+                    .region()
+                    .region_cursor()
+                    .unicode_columns()
+                    .report();
+
+                assert!(report.failures().next().is_none(), "{report}");
+            },
+            nvim_editor_factory,
+            Duration::from_secs(30),
+        )
+    }
+}
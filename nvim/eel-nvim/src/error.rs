@@ -1,8 +1,13 @@
-use eel::error::PlatformError;
+use eel::error::EelPlatformError;
 
-use crate::dispatcher;
+use crate::{dispatcher, version::NvimVersion};
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, EelPlatformError)]
+#[eel(
+    chain(dispatcher::Error),
+    result_trait = IntoNvimResult,
+    result_method = into_nvim
+)]
 pub enum Error {
     #[error("Nvim API error: {0}")]
     Api(#[from] nvim_oxi::api::Error),
@@ -11,29 +16,12 @@ pub enum Error {
     Lua(#[from] nvim_oxi::lua::Error),
 
     #[error("Nvim MLua error: {0}")]
+    #[eel(from_display = nvim_oxi::mlua::Error)]
     MLua(String),
 
     #[error("Dispatcher error: {0}")]
     Dispatcher(#[from] dispatcher::Error),
-}
-
-impl From<nvim_oxi::mlua::Error> for Error {
-    fn from(value: nvim_oxi::mlua::Error) -> Self {
-        Self::MLua(value.to_string())
-    }
-}
 
-pub trait IntoNvimResult<T> {
-    fn into_nvim(self) -> std::result::Result<T, Error>;
+    #[error("Nvim {0} is not supported, eel-nvim needs at least {min}", min = NvimVersion::MIN)]
+    UnsupportedVersion(NvimVersion),
 }
-
-impl<T, E> IntoNvimResult<T> for std::result::Result<T, E>
-where
-    Error: From<E>,
-{
-    fn into_nvim(self) -> std::result::Result<T, Error> {
-        self.map_err(Error::from)
-    }
-}
-
-impl PlatformError for Error {}
@@ -0,0 +1,258 @@
+use std::{sync::Arc, thread::ThreadId, time::Duration};
+
+use parking_lot::Mutex;
+
+use eel::{EditorConfig, Result, tracing::TracingLayer};
+
+use crate::{
+    editor::NvimEditor,
+    error::Error as NvimError,
+    lua::{self, mlua::Table},
+    tracing::nvim_msg_layer_with_rate,
+};
+
+static EDITOR: Mutex<Option<Arc<NvimEditor>>> = Mutex::new(None);
+
+/// Overrides [`Config::log_dir`] in [`NvimEditorBuilder::env`].
+pub const LOG_DIR_ENV: &str = "EEL_LOG_DIR";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory of the daily rolling log file, no file logging if `None`.
+    pub log_dir: Option<String>,
+    /// Echo WARN and ERROR events, see [`nvim_msg_layer`](crate::tracing::nvim_msg_layer).
+    pub messages: bool,
+    pub max_messages_per_second: usize,
+    /// Registers the editor's [Lua module](crate::scripting) under this name.
+    pub lua_module: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_dir: None,
+            messages: true,
+            max_messages_per_second: crate::tracing::DEFAULT_MAX_PER_SECOND,
+            lua_module: None,
+        }
+    }
+}
+
+/// Builds the global editor from layered configuration, each layer overriding what the
+/// previous ones set:
+///
+/// ```ignore
+/// let editor = NvimEditorBuilder::new()
+///     .lua_setup(&opts)?
+///     .env()?
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NvimEditorBuilder {
+    config: Config,
+    editor_config: EditorConfig,
+    nvim_thread_id: Option<ThreadId>,
+}
+
+fn timeout(millis: Option<u64>, previous: Option<Duration>) -> Option<Duration> {
+    match millis {
+        Some(0) => None,
+        Some(millis) => Some(Duration::from_millis(millis)),
+        None => previous,
+    }
+}
+
+impl NvimEditorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn editor_config(mut self, editor_config: EditorConfig) -> Self {
+        self.editor_config = editor_config;
+        self
+    }
+
+    /// The nvim thread, the one calling [`NvimEditorBuilder::build`] by default.
+    pub fn nvim_thread(mut self, thread_id: ThreadId) -> Self {
+        self.nvim_thread_id = Some(thread_id);
+        self
+    }
+
+    /// Applies the keys set in a plugin's `setup` table, the [`Config`] fields and
+    /// `op_timeout` and `lock_timeout` in milliseconds, `0` meaning none.
+    pub fn lua_setup(mut self, opts: &Table) -> Result<Self> {
+        let layer = || -> lua::Result<Self> {
+            if let Some(log_dir) = opts.get("log_dir")? {
+                self.config.log_dir = Some(log_dir);
+            }
+            if let Some(messages) = opts.get("messages")? {
+                self.config.messages = messages;
+            }
+            if let Some(max) = opts.get("max_messages_per_second")? {
+                self.config.max_messages_per_second = max;
+            }
+            if let Some(lua_module) = opts.get("lua_module")? {
+                self.config.lua_module = Some(lua_module);
+            }
+
+            self.editor_config.op_timeout =
+                timeout(opts.get("op_timeout")?, self.editor_config.op_timeout);
+            self.editor_config.lock_timeout =
+                timeout(opts.get("lock_timeout")?, self.editor_config.lock_timeout);
+
+            Ok(self)
+        };
+
+        Ok(layer().map_err(NvimError::from)?)
+    }
+
+    /// Applies [`LOG_DIR_ENV`] and the timeouts of [`EditorConfig::with_env`].
+    pub fn env(mut self) -> Result<Self> {
+        if let Ok(log_dir) = std::env::var(LOG_DIR_ENV) {
+            self.config.log_dir = Some(log_dir);
+        }
+
+        self.editor_config = self.editor_config.with_env()?;
+
+        Ok(self)
+    }
+
+    /// Creates the global editor, sets the global [`EditorConfig`] and initializes tracing (if
+    /// any layer is configured), meant to be called from the plugin entry point.
+    ///
+    /// Later calls return the editor created by the first one and ignore the configuration.
+    pub fn build(self) -> Result<Arc<NvimEditor>> {
+        let mut global = EDITOR.lock();
+
+        if let Some(editor) = &*global {
+            return Ok(editor.clone());
+        }
+
+        let nvim_thread_id = self
+            .nvim_thread_id
+            .unwrap_or_else(|| std::thread::current().id());
+        let editor = Arc::new(NvimEditor::new(nvim_thread_id)?);
+
+        EditorConfig::set_global(self.editor_config);
+
+        let config = self.config;
+        let mut layers: Vec<TracingLayer> = Vec::new();
+
+        if let Some(log_dir) = config.log_dir {
+            layers.push(eel::tracing::file_log_layer(log_dir));
+        }
+
+        if config.messages {
+            layers.push(nvim_msg_layer_with_rate(
+                editor.clone(),
+                config.max_messages_per_second,
+            ));
+        }
+
+        if !layers.is_empty() {
+            eel::tracing::init_tracing(layers);
+        }
+
+        if let Some(name) = &config.lua_module {
+            crate::scripting::register(editor.clone(), name).map_err(NvimError::from)?;
+        }
+
+        *global = Some(editor.clone());
+
+        Ok(editor)
+    }
+}
+
+/// Builds the global editor from `config` alone, see [`NvimEditorBuilder::build`].
+pub fn init(config: Config) -> Result<Arc<NvimEditor>> {
+    NvimEditorBuilder::new().config(config).build()
+}
+
+/// The editor created by [`init`], `None` before it's called.
+pub fn editor() -> Option<Arc<NvimEditor>> {
+    EDITOR.lock().clone()
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::test_utils::nvim_editor_factory;
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn init_once(editor: NvimEditor) {
+        assert!(super::editor().is_none());
+
+        let config = Config {
+            messages: false,
+            ..Default::default()
+        };
+
+        // `init` creates the editor for the current thread
+        let (first, second) = editor
+            .dispatch(move || (init(config.clone()), init(config)))
+            .expect("Failed to dispatch");
+
+        let first = first.expect("Failed to init");
+        let second = second.expect("Failed to init");
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(
+            &super::editor().expect("Editor not initialized"),
+            &first
+        ));
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn builder_layers(editor: NvimEditor) {
+        let builder = editor
+            .dispatch(|| {
+                let opts = crate::lua::mlua::lua()
+                    .load("return { messages = false, op_timeout = 500, lock_timeout = 0 }")
+                    .eval::<Table>()
+                    .map_err(NvimError::from)?;
+
+                NvimEditorBuilder::new()
+                    .editor_config(EditorConfig {
+                        op_timeout: None,
+                        lock_timeout: Some(Duration::from_secs(1)),
+                    })
+                    .lua_setup(&opts)
+            })
+            .expect("Failed to dispatch")
+            .expect("Failed to apply setup table");
+
+        assert!(!builder.config.messages);
+        assert_eq!(builder.config.lua_module, None);
+        assert_eq!(
+            builder.editor_config,
+            EditorConfig {
+                op_timeout: Some(Duration::from_millis(500)),
+                lock_timeout: None,
+            }
+        );
+
+        let built = editor
+            .dispatch(move || builder.build())
+            .expect("Failed to dispatch")
+            .expect("Failed to build");
+
+        assert_eq!(
+            EditorConfig::global().op_timeout,
+            Some(Duration::from_millis(500))
+        );
+        assert!(Arc::ptr_eq(
+            &super::editor().expect("Editor not initialized"),
+            &built
+        ));
+
+        EditorConfig::set_global(EditorConfig::default());
+    }
+}
@@ -2,12 +2,25 @@ pub mod error;
 pub mod tracing;
 
 pub mod buffer;
+#[cfg(feature = "bus")]
+mod bus;
+pub mod commands;
 pub mod editor;
+pub mod ui;
 pub mod window;
 
 pub mod dispatcher;
+mod init;
 pub mod lua;
+pub mod namespace;
+mod option;
+pub mod scripting;
+pub mod status;
+pub mod tasks;
+pub mod version;
+pub mod virtual_buffer;
 
+pub use init::{Config, NvimEditorBuilder, editor, init};
 pub use nvim_oxi;
 
 #[cfg(feature = "nvim-tests")]
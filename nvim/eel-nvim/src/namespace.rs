@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use nvim_oxi::api as nvim_api;
+
+use eel::{Result, dispatch::MainThreadDispatcher};
+
+use crate::{
+    buffer::NvimBuffer, dispatcher::Dispatcher, editor::NvimEditor, error::IntoNvimResult as _,
+};
+
+/// Extmark namespace of a single plugin, created with [`NvimEditor::namespace`].
+///
+/// Marks, highlights and decorations created through it can be cleared without touching the
+/// ones of other plugins or of eel itself. The [`MarkWriteBuffer`](eel::mark::MarkWriteBuffer)
+/// and [`DecorationWriteBuffer`](eel::decoration::DecorationWriteBuffer) methods of buffers
+/// create theirs in the `eel` namespace. Ids of either work with the read and write buffer
+/// methods like any other.
+#[derive(Debug, Clone)]
+pub struct Namespace {
+    id: u32,
+    name: String,
+    dispatcher: Arc<Dispatcher>,
+}
+
+impl NvimEditor {
+    /// Namespace named `name`, the same one for every call with that name.
+    pub fn namespace(&self, name: &str) -> Result<Namespace> {
+        let nvim_name = name.to_string();
+        let id = self.dispatch(move || nvim_api::create_namespace(&nvim_name))?;
+
+        Ok(Namespace {
+            id,
+            name: name.to_string(),
+            dispatcher: self.dispatcher.clone(),
+        })
+    }
+}
+
+impl Namespace {
+    /// The `eel` namespace, its id is looked up once.
+    #[cfg(any(feature = "mark", feature = "decoration"))]
+    pub(crate) fn eel(dispatcher: &Arc<Dispatcher>) -> Result<Self> {
+        static ID: std::sync::OnceLock<u32> = std::sync::OnceLock::new();
+
+        let id = match ID.get() {
+            Some(id) => *id,
+            None => {
+                let id = dispatcher.dispatch(crate::editor::get_eel_namespace)?;
+                *ID.get_or_init(|| id)
+            }
+        };
+
+        Ok(Self {
+            id,
+            name: "eel".to_string(),
+            dispatcher: dispatcher.clone(),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Removes this namespace's marks and highlights from `buffer`.
+    pub fn clear(&self, buffer: &mut NvimBuffer) -> Result<()> {
+        let namespace = self.id;
+        let mut buf = buffer.inner_buf();
+
+        self.dispatcher
+            .dispatch(move || buf.clear_namespace(namespace, ..).into_nvim())??;
+
+        Ok(())
+    }
+
+    /// Removes this namespace's marks and highlights from all buffers.
+    pub fn clear_all(&self) -> Result<()> {
+        let namespace = self.id;
+
+        self.dispatcher.dispatch(move || {
+            for mut buf in nvim_api::list_bufs() {
+                buf.clear_namespace(namespace, ..).into_nvim()?;
+            }
+
+            Ok::<_, crate::error::Error>(())
+        })??;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mark")]
+mod mark {
+    use nvim_oxi::api::{opts::GetExtmarksOpts, types::ExtmarkPosition};
+
+    use eel::{Position, Result, dispatch::MainThreadDispatcher, mark::Gravity};
+
+    use super::Namespace;
+
+    use crate::{
+        buffer::{NvimBuffer, mark::NvimMarkId},
+        error::IntoNvimResult as _,
+    };
+
+    impl Namespace {
+        pub fn create_mark(&self, buffer: &mut NvimBuffer, pos: &Position) -> Result<NvimMarkId> {
+            self.create_mark_with(buffer, pos, None, Gravity::Right)
+        }
+
+        /// See [`MarkWriteBuffer::create_mark_with`](eel::mark::MarkWriteBuffer::create_mark_with).
+        pub fn create_mark_with(
+            &self,
+            buffer: &mut NvimBuffer,
+            start: &Position,
+            end: Option<&Position>,
+            gravity: Gravity,
+        ) -> Result<NvimMarkId> {
+            self.set_extmark(buffer, start, end.cloned(), gravity, None)
+        }
+
+        /// Highlights the text between `start` and `end` with `hl_group`, the highlight follows
+        /// edits like a mark.
+        pub fn highlight(
+            &self,
+            buffer: &mut NvimBuffer,
+            start: &Position,
+            end: &Position,
+            hl_group: &str,
+        ) -> Result<NvimMarkId> {
+            self.set_extmark(
+                buffer,
+                start,
+                Some(end.clone()),
+                Gravity::None,
+                Some(hl_group.to_string()),
+            )
+        }
+
+        fn set_extmark(
+            &self,
+            buffer: &mut NvimBuffer,
+            start: &Position,
+            end: Option<Position>,
+            gravity: Gravity,
+            hl_group: Option<String>,
+        ) -> Result<NvimMarkId> {
+            let (namespace, start) = (self.id, start.clone());
+            let mut buf = buffer.inner_buf();
+
+            self.dispatcher.dispatch(move || {
+                crate::buffer::mark::set_extmark(
+                    &mut buf,
+                    namespace,
+                    &start,
+                    end.as_ref(),
+                    gravity,
+                    hl_group.as_deref(),
+                )
+            })?
+        }
+
+        /// Marks and highlights of this namespace in `buffer`.
+        pub fn marks(&self, buffer: &NvimBuffer) -> Result<Vec<NvimMarkId>> {
+            let namespace = self.id;
+            let buf = buffer.inner_buf();
+
+            let extmarks = self.dispatcher.dispatch(move || {
+                let end = (buf.line_count()?, 0);
+
+                buf.get_extmarks(
+                    namespace,
+                    ExtmarkPosition::ByTuple((0, 0)),
+                    ExtmarkPosition::ByTuple(end),
+                    &GetExtmarksOpts::default(),
+                )
+                .map(|extmarks| extmarks.map(|(id, ..)| id).collect::<Vec<_>>())
+            })?;
+
+            Ok(extmarks
+                .into_nvim()?
+                .into_iter()
+                .map(|extmark| NvimMarkId::new(namespace, extmark))
+                .collect())
+        }
+    }
+}
+
+#[cfg(all(feature = "nvim-tests", feature = "mark"))]
+mod tests {
+    use eel::{
+        Position,
+        buffer::BufferHandle,
+        mark::{MarkReadBuffer, MarkWriteBuffer},
+        test_utils::new_buffer_with_content,
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn namespace_isolation(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "Hello world");
+        let first = editor
+            .namespace("eel-test-first")
+            .expect("Failed to create namespace");
+        let second = editor
+            .namespace("eel-test-second")
+            .expect("Failed to create namespace");
+
+        assert_eq!(
+            editor
+                .namespace("eel-test-first")
+                .expect("Failed to create namespace")
+                .id(),
+            first.id()
+        );
+
+        let mut lock = buffer.write();
+        let eel_mark = lock
+            .create_mark(&Position::new(0, 1))
+            .expect("Failed to create mark");
+        let first_mark = first
+            .create_mark(&mut lock, &Position::new(0, 2))
+            .expect("Failed to create mark");
+        let highlight = first
+            .highlight(
+                &mut lock,
+                &Position::new(0, 0),
+                &Position::new(0, 5),
+                "Search",
+            )
+            .expect("Failed to highlight");
+        let second_mark = second
+            .create_mark(&mut lock, &Position::new(0, 3))
+            .expect("Failed to create mark");
+
+        assert_eq!(
+            lock.get_mark_position(first_mark)
+                .expect("Failed to get mark position"),
+            Position::new(0, 2)
+        );
+        assert_eq!(
+            lock.get_mark_end(highlight)
+                .expect("Failed to get mark end"),
+            Some(Position::new(0, 5))
+        );
+        assert_eq!(
+            first.marks(&lock).expect("Failed to get marks"),
+            [highlight, first_mark]
+        );
+
+        first.clear(&mut lock).expect("Failed to clear namespace");
+
+        assert!(first.marks(&lock).expect("Failed to get marks").is_empty());
+        assert!(lock.get_mark_position(first_mark).is_err());
+        assert_eq!(
+            lock.get_mark_position(second_mark)
+                .expect("Failed to get mark position"),
+            Position::new(0, 3)
+        );
+        assert_eq!(
+            lock.get_mark_position(eel_mark)
+                .expect("Failed to get mark position"),
+            Position::new(0, 1)
+        );
+
+        lock.destroy_mark(second_mark)
+            .expect("Failed to destroy mark");
+        assert!(second.marks(&lock).expect("Failed to get marks").is_empty());
+
+        drop(lock);
+        first
+            .create_mark(&mut buffer.write(), &Position::new(0, 0))
+            .expect("Failed to create mark");
+        first.clear_all().expect("Failed to clear namespace");
+        assert!(
+            first
+                .marks(&buffer.read())
+                .expect("Failed to get marks")
+                .is_empty()
+        );
+    }
+
+    #[cfg(feature = "decoration")]
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn namespace_decorations(editor: NvimEditor) {
+        use eel::decoration::{DecorationReadBuffer, DecorationWriteBuffer};
+
+        let buffer = new_buffer_with_content(&editor, "Hello world");
+        let namespace = editor
+            .namespace("eel-test-decorations")
+            .expect("Failed to create namespace");
+
+        let mut lock = buffer.write();
+        let eel_highlight = lock
+            .highlight_line(0, "Search")
+            .expect("Failed to highlight");
+        let highlight = namespace
+            .highlight_range(
+                &mut lock,
+                (Position::new(0, 0), Position::new(0, 5)),
+                "Search",
+            )
+            .expect("Failed to highlight");
+
+        assert_eq!(highlight.namespace(), namespace.id());
+        assert_ne!(eel_highlight.namespace(), namespace.id());
+        assert_eq!(
+            lock.highlighted_range(highlight)
+                .expect("Failed to get highlight")
+                .end(),
+            &Position::new(0, 5)
+        );
+
+        namespace
+            .clear(&mut lock)
+            .expect("Failed to clear namespace");
+
+        assert!(lock.highlighted_range(highlight).is_err());
+        assert!(lock.highlighted_range(eel_highlight).is_ok());
+        lock.remove_highlight(eel_highlight)
+            .expect("Failed to remove highlight");
+    }
+}
@@ -0,0 +1,37 @@
+use nvim_oxi::{
+    Object, ObjectKind,
+    api::opts::OptionOpts,
+    conversion::{Error as ConversionError, FromObject},
+};
+
+use eel::OptionValue;
+
+use crate::error::{Error as NvimError, IntoNvimResult};
+
+fn from_object(object: Object) -> Result<OptionValue, ConversionError> {
+    Ok(match object.kind() {
+        ObjectKind::Boolean => OptionValue::Bool(bool::from_object(object)?),
+        ObjectKind::Integer => OptionValue::Int(i64::from_object(object)?),
+        _ => OptionValue::String(String::from_object(object)?),
+    })
+}
+
+fn to_object(value: OptionValue) -> Object {
+    match value {
+        OptionValue::Bool(b) => b.into(),
+        OptionValue::Int(i) => i.into(),
+        OptionValue::String(s) => s.into(),
+    }
+}
+
+/// Must be called on the main thread.
+pub(crate) fn get(name: &str, opts: &OptionOpts) -> Result<OptionValue, NvimError> {
+    let object: Object = nvim_oxi::api::get_option_value(name, opts)?;
+
+    from_object(object).map_err(|e| NvimError::Api(nvim_oxi::api::Error::from(e)))
+}
+
+/// Must be called on the main thread.
+pub(crate) fn set(name: &str, value: OptionValue, opts: &OptionOpts) -> Result<(), NvimError> {
+    nvim_oxi::api::set_option_value(name, to_object(value), opts).into_nvim()
+}
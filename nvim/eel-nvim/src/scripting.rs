@@ -0,0 +1,301 @@
+//! Lua module exposing the editor, so Lua config code edits buffers through the same locks
+//! as Rust plugins instead of racing them through the raw API.
+//!
+//! ```lua
+//! local eel = require("eel")
+//! local buffer = eel.current_buffer()
+//! buffer:set_text({ row = 0, col = 0 }, { row = 0, col = 0 }, "-- ")
+//! print(buffer:get_content(), vim.inspect(buffer:get_cursor()))
+//! ```
+//!
+//! Components registered with [`NvimEditor::register_status_component`] are in `status`.
+//!
+//! Positions are `{ row, col }` tables, 0-based with byte columns, as in eel. Lua runs on the
+//! nvim thread, which can't wait for a lock held by another thread (that thread may itself be
+//! waiting for the nvim thread), so calls on a locked buffer fail instead of blocking.
+
+use std::sync::Arc;
+
+use eel::{
+    Editor, Position,
+    buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+};
+
+use crate::{
+    buffer::NvimBufferHandle,
+    editor::NvimEditor,
+    lua::{
+        self,
+        mlua::{self, IntoLua, Lua, MetaMethod, Table, UserData, UserDataMethods, UserDataRef},
+    },
+};
+
+fn lua_error(e: eel::Error) -> mlua::Error {
+    mlua::Error::external(e)
+}
+
+fn locked() -> mlua::Error {
+    mlua::Error::RuntimeError("Buffer is locked by a running eel operation".into())
+}
+
+fn position(table: Table) -> lua::Result<Position> {
+    Ok(Position::new(table.get("row")?, table.get("col")?))
+}
+
+#[cfg(feature = "cursor")]
+fn position_table(lua: &Lua, position: &Position) -> lua::Result<Table> {
+    lua.create_table_from([("row", position.row), ("col", position.col)])
+}
+
+/// Buffer handle passed to Lua.
+#[derive(Debug, Clone)]
+pub struct LuaBuffer(pub NvimBufferHandle);
+
+impl LuaBuffer {
+    fn read(&self) -> lua::Result<<NvimBufferHandle as BufferHandle>::ReadBufferLock> {
+        self.0.try_read().ok_or_else(locked)
+    }
+
+    fn write(&self) -> lua::Result<<NvimBufferHandle as BufferHandle>::WriteBufferLock> {
+        self.0.try_write().ok_or_else(locked)
+    }
+}
+
+impl UserData for LuaBuffer {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("id", |_, this, ()| Ok(this.0.inner_buf().handle()));
+
+        methods.add_method("line_count", |_, this, ()| {
+            this.read()?.line_count().map_err(lua_error)
+        });
+
+        methods.add_method("changedtick", |_, this, ()| {
+            this.0.changedtick().map_err(lua_error)
+        });
+
+        methods.add_method("get_content", |_, this, ()| {
+            this.read()?.get_content().map_err(lua_error)
+        });
+
+        methods.add_method("set_content", |_, this, text: String| {
+            this.write()?.set_content(&text).map_err(lua_error)
+        });
+
+        methods.add_method("get_text", |_, this, (start, end): (Table, Table)| {
+            this.read()?
+                .get_text((&position(start)?, &position(end)?))
+                .map_err(lua_error)
+        });
+
+        methods.add_method(
+            "set_text",
+            |_, this, (start, end, text): (Table, Table, String)| {
+                this.write()?
+                    .set_text((&position(start)?, &position(end)?), &text)
+                    .map_err(lua_error)
+            },
+        );
+
+        #[cfg(feature = "cursor")]
+        methods.add_method("get_cursor", |lua, this, ()| {
+            use eel::cursor::CursorReadBuffer;
+
+            position_table(lua, &this.read()?.get_cursor().map_err(lua_error)?)
+        });
+
+        #[cfg(feature = "cursor")]
+        methods.add_method("set_cursor", |_, this, position_arg: Table| {
+            use eel::cursor::CursorWriteBuffer;
+
+            this.write()?
+                .set_cursor(&position(position_arg)?)
+                .map_err(lua_error)
+        });
+
+        methods.add_meta_method(MetaMethod::Eq, |_, this, other: UserDataRef<LuaBuffer>| {
+            Ok(this.0 == other.0)
+        });
+    }
+}
+
+/// Table of editor functions, buffers are [`LuaBuffer`]s.
+pub fn lua_module(lua: &Lua, editor: Arc<NvimEditor>) -> lua::Result<Table> {
+    let module = lua.create_table()?;
+
+    let e = editor.clone();
+    module.set(
+        "current_buffer",
+        lua.create_function(move |_, ()| e.current_buffer().map(LuaBuffer).map_err(lua_error))?,
+    )?;
+
+    let e = editor.clone();
+    module.set(
+        "new_buffer",
+        lua.create_function(move |_, ()| e.new_buffer().map(LuaBuffer).map_err(lua_error))?,
+    )?;
+
+    let e = editor.clone();
+    module.set(
+        "buffers",
+        lua.create_function(move |lua, ()| {
+            let buffers = e.buffers().map_err(lua_error)?;
+
+            lua.create_sequence_from(buffers.into_iter().map(LuaBuffer))
+        })?,
+    )?;
+
+    let e = editor.clone();
+    module.set(
+        "buffer_by_name",
+        lua.create_function(move |_, name: String| {
+            Ok(e.buffer_by_name(&name).map_err(lua_error)?.map(LuaBuffer))
+        })?,
+    )?;
+
+    let e = editor.clone();
+    module.set(
+        "buffer_name",
+        lua.create_function(move |_, buffer: UserDataRef<LuaBuffer>| {
+            e.buffer_name(&buffer.0).map_err(lua_error)
+        })?,
+    )?;
+
+    module.set("status", crate::status::status_table(lua)?)?;
+
+    module.set(
+        "set_current_buffer",
+        lua.create_function(move |_, buffer: UserDataRef<LuaBuffer>| {
+            let mut lock = buffer.write()?;
+
+            editor.set_current_buffer(&mut lock).map_err(lua_error)
+        })?,
+    )?;
+
+    Ok(module)
+}
+
+/// Makes [`lua_module`] available to `require(name)`, to be called on the nvim thread.
+pub fn register(editor: Arc<NvimEditor>, name: &str) -> lua::Result<()> {
+    let lua = mlua::lua();
+    let loaded: Table = lua::lua_get_global_path("package.loaded")?;
+
+    loaded.set(name, lua_module(&lua, editor)?.into_lua(&lua)?)
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::sync::Arc;
+
+    use eel::{
+        Editor,
+        buffer::{BufferHandle, ReadBuffer},
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, error::Error as NvimError, test_utils::nvim_editor_factory};
+
+    fn register(editor: NvimEditor) -> Arc<NvimEditor> {
+        let editor = Arc::new(editor);
+
+        let registered = editor.clone();
+        editor
+            .dispatch(move || super::register(registered, "eel_test").map_err(NvimError::from))
+            .expect("Failed to dispatch")
+            .expect("Failed to register module");
+
+        editor
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn lua_buffer(editor: NvimEditor) {
+        let editor = register(editor);
+
+        let content: String = editor
+            .exec_lua(
+                r#"
+                local eel = require("eel_test")
+                local buffer = eel.new_buffer()
+                eel.set_current_buffer(buffer)
+
+                buffer:set_content("Hello\nworld")
+                buffer:set_text({ row = 1, col = 0 }, { row = 1, col = 5 }, "there")
+
+                assert(eel.current_buffer() == buffer)
+                assert(buffer:line_count() == 2)
+
+                return buffer:get_text({ row = 0, col = 3 }, { row = 1, col = 5 })
+                "#,
+                (),
+            )
+            .expect("Failed to run Lua");
+
+        assert_eq!(content, "lo\nthere");
+        assert_eq!(
+            editor
+                .current_buffer()
+                .expect("Failed to get current buffer")
+                .read()
+                .get_content()
+                .expect("Failed to get content"),
+            "Hello\nthere"
+        );
+    }
+
+    #[cfg(feature = "cursor")]
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn lua_cursor(editor: NvimEditor) {
+        let editor = register(editor);
+
+        editor
+            .exec_lua::<_, ()>(
+                r#"
+                local eel = require("eel_test")
+                local buffer = eel.new_buffer()
+                eel.set_current_buffer(buffer)
+
+                buffer:set_content("Hello\nworld")
+                buffer:set_cursor({ row = 1, col = 2 })
+
+                assert(vim.deep_equal(buffer:get_cursor(), { row = 1, col = 2 }))
+                "#,
+                (),
+            )
+            .expect("Failed to run Lua");
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn lua_locked_buffer(editor: NvimEditor) {
+        let editor = register(editor);
+
+        let buffer = editor.new_buffer().expect("Failed to create buffer");
+        let lock = buffer.write();
+
+        // Fails instead of deadlocking
+        let id = buffer.inner_buf().handle();
+        let message: String = editor
+            .exec_lua(
+                r#"
+                local eel = require("eel_test")
+                local id = ...
+
+                for _, buffer in ipairs(eel.buffers()) do
+                    if buffer:id() == id then
+                        local ok, err = pcall(buffer.set_content, buffer, "x")
+                        assert(not ok)
+                        return tostring(err)
+                    end
+                end
+                "#,
+                id,
+            )
+            .expect("Failed to run Lua");
+
+        assert!(message.contains("locked"), "{message}");
+
+        drop(lock);
+        assert_eq!(
+            buffer.read().get_content().expect("Failed to get content"),
+            ""
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Components for statuslines, winbars and plugins like lualine, rendered in the background so
+//! drawing never waits for them.
+//!
+//! ```lua
+//! vim.o.statusline = "%{v:lua.require'eel'.status.diagnostics()}"
+//! ```
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tracing::warn;
+
+use eel::{Editor, Result, dispatch::MainThreadDispatcher, tasks::CancelToken};
+
+use crate::{
+    editor::NvimEditor,
+    error::Error as NvimError,
+    lua::{
+        self,
+        mlua::{self, Lua, Table},
+    },
+};
+
+const REGISTRY_KEY: &str = "eel_status_components";
+
+/// Table of the registered components, exposed as `status` by the [Lua module](crate::scripting).
+pub(crate) fn status_table(lua: &Lua) -> lua::Result<Table> {
+    if let Some(table) = lua.named_registry_value::<Option<Table>>(REGISTRY_KEY)? {
+        return Ok(table);
+    }
+
+    let table = lua.create_table()?;
+    lua.set_named_registry_value(REGISTRY_KEY, &table)?;
+
+    Ok(table)
+}
+
+fn sleep(token: &CancelToken, duration: Duration) -> Result<()> {
+    let deadline = Instant::now() + duration;
+
+    loop {
+        token.check()?;
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+
+        std::thread::sleep((deadline - now).min(Duration::from_millis(50)));
+    }
+}
+
+impl NvimEditor {
+    /// Exposes `status.<name>()` in the Lua module, returning the latest text of `render`
+    /// (empty until its first run) without waiting for it.
+    ///
+    /// `render` runs every `interval` as the `status:<name>` [task](eel::tasks), status lines
+    /// are redrawn when the text changes. Failures are logged and keep the previous text.
+    /// Registering a name again replaces the component.
+    pub fn register_status_component(
+        &self,
+        name: &str,
+        interval: Duration,
+        render: impl Fn() -> Result<String> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let text = Arc::new(Mutex::new(String::new()));
+
+        let (lua_name, lua_text) = (name.to_string(), text.clone());
+        self.dispatch(move || {
+            let lua = mlua::lua();
+            let component = lua.create_function(move |_, ()| Ok(lua_text.lock().clone()))?;
+
+            status_table(&lua)?
+                .set(lua_name, component)
+                .map_err(NvimError::from)
+        })??;
+
+        let (dispatcher, component) = (self.dispatcher.clone(), name.to_string());
+        self.tasks().spawn(&format!("status:{name}"), move |token| {
+            loop {
+                match render() {
+                    Ok(rendered) => {
+                        let changed = {
+                            let mut text = text.lock();
+                            let changed = *text != rendered;
+                            *text = rendered;
+                            changed
+                        };
+
+                        if changed {
+                            dispatcher.dispatch_async(|| {
+                                if let Err(e) = nvim_oxi::api::command("redrawstatus!") {
+                                    warn!("Failed to redraw status lines: {e}");
+                                }
+                            })?;
+                        }
+                    }
+                    Err(e) => warn!("Status component {component} failed: {e}"),
+                }
+
+                sleep(token, interval)?;
+            }
+        })
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    use eel::{Editor, tasks::TaskStatus};
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, error::Error as NvimError, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn status_component(editor: NvimEditor) {
+        let editor = Arc::new(editor);
+
+        let registered = editor.clone();
+        editor
+            .dispatch(move || {
+                crate::scripting::register(registered, "eel_status_test").map_err(NvimError::from)
+            })
+            .expect("Failed to dispatch")
+            .expect("Failed to register module");
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let counter = renders.clone();
+        editor
+            .register_status_component("eel_test_renders", Duration::from_millis(10), move || {
+                Ok(format!(
+                    "{} renders",
+                    counter.fetch_add(1, Ordering::SeqCst) + 1
+                ))
+            })
+            .expect("Failed to register component");
+
+        let status = || -> String {
+            editor
+                .exec_lua(
+                    r#"return require("eel_status_test").status.eel_test_renders()"#,
+                    (),
+                )
+                .expect("Failed to get status")
+        };
+
+        // Refreshed in the background
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while renders.load(Ordering::SeqCst) < 3 {
+            assert!(Instant::now() < deadline, "Component wasn't refreshed");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(status().ends_with(" renders"), "{}", status());
+
+        assert!(editor.tasks().cancel("status:eel_test_renders"));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while editor.tasks().status("status:eel_test_renders") != Some(TaskStatus::Cancelled) {
+            assert!(Instant::now() < deadline, "Component wasn't cancelled");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let last = renders.load(Ordering::SeqCst);
+        assert_eq!(status(), format!("{last} renders"));
+    }
+}
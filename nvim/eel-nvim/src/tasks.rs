@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use nvim_oxi::api::types::Mode;
+
+use eel::{Editor, Result, commands::Command, tasks::TaskInfo};
+
+use crate::{commands::create_command, editor::NvimEditor, ui::ScratchBuffer};
+
+fn render(info: &TaskInfo) -> String {
+    format!(
+        "{}\t{}\t{} run(s)\t{:.1?}",
+        info.name, info.status, info.runs, info.elapsed
+    )
+}
+
+/// Registers `:EelTasks`, listing the editor's tasks in a scratch buffer where `c` cancels
+/// and `r` restarts the task under the cursor.
+pub fn create_tasks_command(editor: Arc<NvimEditor>) -> Result<()> {
+    let command_editor = editor.clone();
+
+    let command = Command::new("EelTasks", move |editor: &NvimEditor, (): ()| {
+        let cancel_editor = command_editor.clone();
+        let restart_editor = command_editor.clone();
+
+        ScratchBuffer::new(render)
+            .name("eel://tasks")
+            .keymap(Mode::Normal, "c", move |info: &TaskInfo| {
+                cancel_editor.tasks().cancel(&info.name);
+                Ok(())
+            })
+            .keymap(Mode::Normal, "r", move |info: &TaskInfo| {
+                restart_editor.tasks().restart(&info.name)
+            })
+            .open(editor, editor.tasks().list())
+            .map(|_| ())
+    })
+    .with_description("List eel background tasks");
+
+    create_command(editor, command)
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use eel::{Editor, buffer::BufferHandle, buffer::ReadBuffer, tasks::TaskStatus};
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn tasks_command(editor: NvimEditor) {
+        let editor = Arc::new(editor);
+        super::create_tasks_command(editor.clone()).expect("Failed to create command");
+
+        editor
+            .tasks()
+            .spawn("eel-nvim-test", |token| {
+                loop {
+                    token.check()?;
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+            .expect("Failed to spawn task");
+
+        let before = editor.current_buffer().expect("Failed to get buffer");
+        editor.exec("EelTasks").expect("Failed to run command");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let scratch = loop {
+            let current = editor.current_buffer().expect("Failed to get buffer");
+            if current != before {
+                break current;
+            }
+
+            assert!(Instant::now() < deadline, "Tasks weren't listed");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        let content = scratch.read().get_content().expect("Failed to get content");
+        assert!(
+            content
+                .lines()
+                .any(|line| line.starts_with("eel-nvim-test\trunning\t1 run(s)")),
+            "Unexpected listing: {content}"
+        );
+
+        let row = content
+            .lines()
+            .position(|line| line.starts_with("eel-nvim-test"))
+            .expect("Task missing");
+        editor
+            .exec(&format!("normal! {}G", row + 1))
+            .expect("Failed to move cursor");
+        editor.exec("normal c").expect("Failed to cancel task");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while editor.tasks().status("eel-nvim-test") != Some(TaskStatus::Cancelled) {
+            assert!(Instant::now() < deadline, "Task wasn't cancelled");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -1,14 +1,34 @@
-use std::sync::{Arc, mpsc};
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{Arc, mpsc},
+    time::Duration,
+};
 
 use eel::{
     Editor,
     test_utils::{EditorFactory, EditorTest},
 };
-use tracing::debug;
+use tracing::{Level, debug, warn};
+
+use crate::{
+    editor::NvimEditor,
+    error::Error as NvimError,
+    lua::{lua_get_global_path, mlua::Table},
+};
 
-use crate::{editor::NvimEditor, lua::lua_get_global_path};
+const DEFAULT_TEST_TIMEOUT: Duration = Duration::from_millis(1000);
 
 pub fn run_nvim_test<E, EF, T, R>(test: T, editor_factory: EF) -> R
+where
+    E: Editor,
+    EF: EditorFactory<Editor = E>,
+    T: EditorTest<E, R>,
+    R: Send + 'static,
+{
+    run_nvim_test_with_timeout(test, editor_factory, DEFAULT_TEST_TIMEOUT)
+}
+
+pub fn run_nvim_test_with_timeout<E, EF, T, R>(test: T, editor_factory: EF, timeout: Duration) -> R
 where
     E: Editor,
     EF: EditorFactory<Editor = E>,
@@ -19,19 +39,37 @@ where
 
     let editor = editor_factory.create_editor();
 
-    let (send, recv) = mpsc::channel();
-
-    let test_handle = {
-        std::thread::spawn(move || {
+    let result = wait_for_thread(
+        move || {
             debug!("Running test");
 
             let result = test.run(editor);
 
             debug!("Test successfully finished");
 
-            send.send(result).expect("Test result send error");
-        })
-    };
+            result
+        },
+        timeout,
+    )
+    .expect("Test timed out");
+
+    result.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Runs `func` on a new thread, processing nvim events until it finishes. Returns `None` if
+/// it didn't finish in time.
+fn wait_for_thread<F, R>(func: F, timeout: Duration) -> Option<std::thread::Result<R>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (send, recv) = mpsc::channel();
+
+    let test_handle = std::thread::spawn(move || {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(func));
+
+        send.send(result).expect("Test result send error");
+    });
 
     let test_handle = Arc::new(test_handle);
 
@@ -46,14 +84,375 @@ where
     };
 
     let wait_result: bool = wait_func
-        .call((1000, cond_func))
+        .call((timeout.as_millis() as u64, cond_func))
         .expect("Failed to call vim.wait");
 
-    assert!(wait_result, "Test timed out");
+    if !wait_result {
+        return None;
+    }
+
+    Some(recv.try_recv().expect("Failed to get test result"))
+}
+
+type SuiteTest<F> = (&'static str, fn(&NvimEditor, &F));
 
-    recv.try_recv().expect("Failed to get test result")
+/// Runs several tests one after another in a single nvim instance, sharing the editor and a
+/// fixture created once, to avoid paying for nvim startup per test.
+///
+/// The state is reset before each test, by [`reset_editor_state`] unless set otherwise. Failing
+/// tests don't stop the suite, they are all reported at the end.
+pub struct NvimTestSuite<F> {
+    setup: Box<dyn FnOnce(&NvimEditor) -> F + Send>,
+    reset: fn(&NvimEditor, &F),
+    tests: Vec<SuiteTest<F>>,
+    timeout: Duration,
+}
+
+impl<F: Send + Sync + 'static> NvimTestSuite<F> {
+    pub fn new(setup: impl FnOnce(&NvimEditor) -> F + Send + 'static) -> Self {
+        Self {
+            setup: Box::new(setup),
+            reset: |editor, _| reset_editor_state(editor),
+            tests: Vec::new(),
+            timeout: DEFAULT_TEST_TIMEOUT,
+        }
+    }
+
+    pub fn reset(mut self, reset: fn(&NvimEditor, &F)) -> Self {
+        self.reset = reset;
+        self
+    }
+
+    /// Timeout of each test, including the reset before it.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn test(mut self, name: &'static str, test: fn(&NvimEditor, &F)) -> Self {
+        self.tests.push((name, test));
+        self
+    }
+
+    pub fn run(self) {
+        eel::tracing::init_tracing([eel::tracing::file_log_layer("/tmp/eel")]);
+
+        let editor = Arc::new(nvim_editor_factory());
+
+        let setup = self.setup;
+        let fixture = {
+            let editor = editor.clone();
+            wait_for_thread(move || Arc::new(setup(&editor)), self.timeout)
+                .expect("Suite setup timed out")
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        };
+
+        let mut failed = Vec::new();
+
+        for (name, test) in self.tests {
+            debug!("Running suite test {name}");
+
+            let (editor, fixture, reset) = (editor.clone(), fixture.clone(), self.reset);
+            let result = wait_for_thread(
+                move || {
+                    reset(&editor, &fixture);
+                    test(&editor, &fixture);
+                },
+                self.timeout,
+            );
+
+            match result {
+                Some(Ok(())) => debug!("Suite test {name} finished"),
+                Some(Err(_)) => failed.push(name),
+                // The test still runs and would interfere with the next ones
+                None => panic!("Suite test {name} timed out"),
+            }
+        }
+
+        assert!(failed.is_empty(), "Suite tests failed: {failed:?}");
+    }
+}
+
+/// Leaves insert mode, closes other windows and removes the eel decorations. Buffers are
+/// kept, for fixtures to reuse.
+pub fn reset_editor_state(editor: &NvimEditor) {
+    editor
+        .exec_lua::<_, ()>(
+            r#"
+            vim.cmd("silent! stopinsert")
+            vim.cmd("silent! only!")
+
+            local ns = vim.api.nvim_create_namespace("eel")
+            for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+                vim.api.nvim_buf_clear_namespace(buf, ns, 0, -1)
+            end
+            "#,
+            (),
+        )
+        .expect("Failed to reset editor state");
 }
 
 pub fn nvim_editor_factory() -> NvimEditor {
     NvimEditor::new_on_current().expect("Failed to initialize editor")
 }
+
+/// Types `keys` (in `<Esc>` notation) with mappings applied, returning once nvim processed them,
+/// including autocmds they trigger. Insert mode is left at the end.
+pub fn simulate_keys(editor: &NvimEditor, keys: &str) {
+    editor
+        .exec_lua::<_, ()>(
+            r#"vim.api.nvim_feedkeys(vim.keycode(...), "mtx", false)"#,
+            keys.to_string(),
+        )
+        .expect("Failed to simulate keys");
+}
+
+/// Records `vim.notify` calls and shown messages (including `nvim_echo` without history) until
+/// dropped.
+pub struct MessageCapture<'a> {
+    editor: &'a NvimEditor,
+}
+
+pub fn capture_messages(editor: &NvimEditor) -> MessageCapture<'_> {
+    editor
+        .exec_lua::<_, ()>(
+            r#"
+            local captured = { notifications = {}, messages = {} }
+            _G.eel_captured_messages = captured
+
+            captured.notify = vim.notify
+            vim.notify = function(msg, level, opts)
+                table.insert(captured.notifications, { msg = msg, level = level or vim.log.levels.INFO })
+                return captured.notify(msg, level, opts)
+            end
+
+            captured.ns = vim.api.nvim_create_namespace("eel_message_capture")
+            vim.ui_attach(captured.ns, { ext_messages = true }, function(event, _, content)
+                if event == "msg_show" then
+                    local text = {}
+                    for _, chunk in ipairs(content) do
+                        table.insert(text, chunk[2])
+                    end
+                    table.insert(captured.messages, table.concat(text))
+                end
+            end)
+            "#,
+            (),
+        )
+        .expect("Failed to capture messages");
+
+    MessageCapture { editor }
+}
+
+impl MessageCapture<'_> {
+    pub fn notifications(&self) -> Vec<(Level, String)> {
+        self.editor
+            .dispatch(|| {
+                let notifications: Table =
+                    lua_get_global_path("eel_captured_messages.notifications")?;
+
+                notifications
+                    .sequence_values::<Table>()
+                    .map(|n| {
+                        let n = n?;
+                        let level = match n.get::<u8>("level")? {
+                            0 => Level::TRACE,
+                            1 => Level::DEBUG,
+                            2 => Level::INFO,
+                            3 => Level::WARN,
+                            _ => Level::ERROR,
+                        };
+
+                        Ok((level, n.get("msg")?))
+                    })
+                    .collect::<Result<Vec<_>, NvimError>>()
+            })
+            .expect("Failed to dispatch")
+            .expect("Failed to get notifications")
+    }
+
+    /// Messages are sent to UIs on redraw, so this redraws first.
+    pub fn messages(&self) -> Vec<String> {
+        self.editor
+            .exec_lua(
+                "vim.cmd.redraw(); return eel_captured_messages.messages",
+                (),
+            )
+            .expect("Failed to get messages")
+    }
+}
+
+impl Drop for MessageCapture<'_> {
+    /// Panicking here would abort a test already unwinding, failures are only logged.
+    fn drop(&mut self) {
+        let restored = self.editor.exec_lua::<_, ()>(
+            r#"
+            local captured = eel_captured_messages
+            vim.notify = captured.notify
+            vim.ui_detach(captured.ns)
+            _G.eel_captured_messages = nil
+            "#,
+            (),
+        );
+
+        if let Err(e) = restored {
+            warn!("Failed to stop capturing messages: {e}");
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! assert_notified {
+    ($capture:expr, $level:expr, $pattern:expr) => {{
+        let notifications = $capture.notifications();
+
+        assert!(
+            notifications
+                .iter()
+                .any(|(level, message)| *level == $level && message.contains($pattern)),
+            "No {} notification containing {:?}, got: {:?}",
+            $level,
+            $pattern,
+            notifications
+        );
+    }};
+}
+
+#[macro_export]
+macro_rules! assert_echoed {
+    ($capture:expr, $pattern:expr) => {{
+        let messages = $capture.messages();
+
+        assert!(
+            messages.iter().any(|message| message.contains($pattern)),
+            "No message containing {:?}, got: {:?}",
+            $pattern,
+            messages
+        );
+    }};
+}
+
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[cfg(feature = "cursor")]
+    use eel::{
+        assert_buffer_state,
+        test_utils::{new_buffer_with_state, set_buffer_state},
+    };
+    use eel::{buffer::BufferHandle, test_utils::new_buffer_with_content};
+    use eel_nvim_macros::nvim_test;
+    use nvim_oxi::api::types::Mode;
+
+    use super::*;
+
+    #[cfg(feature = "cursor")]
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn keys_edit(editor: NvimEditor) {
+        let buffer = new_buffer_with_state(&editor, "Hello |world\nSecond line");
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        simulate_keys(&editor, "ciwfoo<Esc>");
+        assert_buffer_state!(buffer, "Hello fo|o\nSecond line");
+
+        simulate_keys(&editor, "jdd0");
+        assert_buffer_state!(buffer, "|Hello foo");
+
+        simulate_keys(&editor, "Abar<Esc>");
+        assert_buffer_state!(buffer, "Hello fooba|r");
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn keys_mapping(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(&editor, "");
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        buffer
+            .set_keymap(Mode::Normal, "<leader>x", move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            })
+            .expect("Failed to set keymap");
+
+        simulate_keys(&editor, "<leader>x<leader>x");
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn message_capture(editor: NvimEditor) {
+        {
+            let capture = capture_messages(&editor);
+
+            editor
+                .exec_lua::<_, ()>(
+                    r#"
+                    vim.notify("Something happened", vim.log.levels.WARN)
+                    vim.api.nvim_echo({ { "Echoed without history" } }, false, {})
+                    "#,
+                    (),
+                )
+                .expect("Failed to exec lua");
+
+            assert_notified!(capture, Level::WARN, "happened");
+            assert_echoed!(capture, "without history");
+            assert_eq!(capture.notifications().len(), 1);
+        }
+
+        let restored: bool = editor
+            .exec_lua("return eel_captured_messages == nil", ())
+            .expect("Failed to exec lua");
+        assert!(restored);
+    }
+
+    #[cfg(feature = "cursor")]
+    struct SuiteFixture {
+        buffer: <NvimEditor as Editor>::BufferHandle,
+        runs: AtomicUsize,
+    }
+
+    #[cfg(feature = "cursor")]
+    fn suite_reset(editor: &NvimEditor, fixture: &SuiteFixture) {
+        reset_editor_state(editor);
+
+        set_buffer_state(&fixture.buffer, "|Shared buffer");
+    }
+
+    #[cfg(feature = "cursor")]
+    fn suite_edit(editor: &NvimEditor, fixture: &SuiteFixture) {
+        editor
+            .set_current_buffer(&mut fixture.buffer.write())
+            .expect("Failed to set current buffer");
+
+        simulate_keys(editor, "dw");
+        assert_buffer_state!(fixture.buffer, "|buffer");
+
+        fixture.runs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "cursor")]
+    fn suite_runs(_editor: &NvimEditor, fixture: &SuiteFixture) {
+        // Edited by the previous test, but reset
+        assert_buffer_state!(fixture.buffer, "|Shared buffer");
+        assert_eq!(fixture.runs.load(Ordering::Relaxed), 2);
+    }
+
+    #[cfg(feature = "cursor")]
+    #[nvim_oxi::test]
+    fn test_suite() {
+        NvimTestSuite::new(|editor| SuiteFixture {
+            buffer: new_buffer_with_state(editor, "|Shared buffer"),
+            runs: AtomicUsize::new(0),
+        })
+        .reset(suite_reset)
+        .test("edit", suite_edit)
+        .test("edit_again", suite_edit)
+        .test("runs", suite_runs)
+        .run();
+    }
+}
@@ -1,50 +1,175 @@
-use std::sync::Arc;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
 
+use parking_lot::Mutex;
 use tracing::{Level, level_filters::LevelFilter};
-use tracing_subscriber::{Layer, filter::Targets, fmt::MakeWriter};
+use tracing_subscriber::{Layer, Registry, filter::Targets, fmt::MakeWriter, reload};
 
 use eel::tracing::{ResultExt, TracingLayer};
 
-use nvim_oxi::api as nvim_api;
+use nvim_oxi::api::{self as nvim_api, opts::CreateCommandOpts, types::CommandNArgs};
 
-use crate::{editor::NvimEditor, error::IntoNvimResult};
+use crate::{editor::NvimEditor, error::IntoNvimResult, ui::ScratchBuffer};
+
+pub(crate) const DEFAULT_MAX_PER_SECOND: usize = 3;
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const HISTORY_LEN: usize = 1000;
+
+type Message = (String, &'static str);
+
+static MSG_TARGETS: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+/// Shared by the writers and the `:EelMessages` command.
+#[derive(Default)]
+struct MessageState {
+    history: VecDeque<String>,
+    pending: Vec<Message>,
+    flushing: bool,
+    window_start: Option<Instant>,
+    shown: usize,
+    suppressed: usize,
+}
 
 struct NvimIoWriter {
     editor: Arc<NvimEditor>,
+    state: Arc<Mutex<MessageState>>,
+    max_per_second: usize,
 }
 
 impl NvimIoWriter {
-    fn new(editor: Arc<NvimEditor>) -> Self {
-        NvimIoWriter { editor }
+    fn push(&self, message: Message) {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        if state.history.len() == HISTORY_LEN {
+            state.history.pop_front();
+        }
+        state.history.push_back(message.0.clone());
+
+        let window_start = *state.window_start.get_or_insert(now);
+
+        // With suppressed messages the summary starts the next window
+        if now.duration_since(window_start) >= RATE_WINDOW && state.suppressed == 0 {
+            state.window_start = Some(now);
+            state.shown = 0;
+        }
+
+        if state.shown < self.max_per_second {
+            state.shown += 1;
+            queue(&self.editor, &self.state, &mut state, message);
+
+            return;
+        }
+
+        state.suppressed += 1;
+
+        if state.suppressed == 1 {
+            spawn_summary(
+                self.editor.clone(),
+                self.state.clone(),
+                window_start + RATE_WINDOW,
+            );
+        }
+    }
+}
+
+fn queue(
+    editor: &Arc<NvimEditor>,
+    shared: &Arc<Mutex<MessageState>>,
+    state: &mut MessageState,
+    message: Message,
+) {
+    state.pending.push(message);
+
+    if !std::mem::replace(&mut state.flushing, true) {
+        spawn_flush(editor.clone(), shared.clone());
     }
 }
 
+/// Echoes pending messages in one go, messages queued meanwhile are picked up by the same
+/// thread.
+fn spawn_flush(editor: Arc<NvimEditor>, state: Arc<Mutex<MessageState>>) {
+    std::thread::spawn(move || {
+        loop {
+            let messages = {
+                let mut state = state.lock();
+
+                if state.pending.is_empty() {
+                    state.flushing = false;
+                    return;
+                }
+
+                std::mem::take(&mut state.pending)
+            };
+
+            echo(&editor, messages).ok();
+        }
+    });
+}
+
+/// Summarizes the messages suppressed in the current window once it ends.
+fn spawn_summary(editor: Arc<NvimEditor>, shared: Arc<Mutex<MessageState>>, window_end: Instant) {
+    std::thread::spawn(move || {
+        std::thread::sleep(window_end.saturating_duration_since(Instant::now()));
+
+        let mut state = shared.lock();
+        let suppressed = std::mem::take(&mut state.suppressed);
+
+        state.window_start = None;
+        state.shown = 0;
+
+        queue(
+            &editor,
+            &shared,
+            &mut state,
+            (
+                format!("…and {suppressed} more (see :EelMessages)"),
+                "DiagnosticWarn",
+            ),
+        );
+    });
+}
+
+fn echo(editor: &NvimEditor, messages: Vec<Message>) -> eel::Result<()> {
+    editor
+        .dispatch(move || {
+            let mut chunks = Vec::with_capacity(messages.len() * 2);
+
+            for (i, (message, highlight)) in messages.into_iter().enumerate() {
+                if i > 0 {
+                    chunks.push(("\n".to_string(), None));
+                }
+
+                chunks.push((message, Some(highlight)));
+            }
+
+            nvim_api::echo(chunks, false, &Default::default())?;
+            nvim_api::command("redraw")
+        })
+        .log_err_msg("Failed to dispatch log echo")?
+        .into_nvim()
+        .log_err_msg("Log echo failed")?;
+
+    Ok(())
+}
+
 impl std::io::Write for NvimIoWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = buf.len();
         let buf = buf.trim_ascii();
 
         let message = String::from_utf8(buf.to_vec()).map_err(std::io::Error::other)?;
-        let len = buf.len();
 
         let highlight = match message {
-            ref s if s.starts_with("ERROR") => Some("DiagnosticError"),
-            ref s if s.starts_with("WARN") => Some("DiagnosticWarn"),
+            ref s if s.starts_with("ERROR") => "DiagnosticError",
+            ref s if s.starts_with("WARN") => "DiagnosticWarn",
             _ => return Ok(len),
         };
 
-        let editor = self.editor.clone();
-        std::thread::spawn(move || {
-            editor
-                .dispatch(move || {
-                    nvim_api::echo([(message, highlight)], false, &Default::default())?;
-                    nvim_api::command("redraw")
-                })
-                .log_err_msg("Failed to dispatch log echo")?
-                .into_nvim()
-                .log_err_msg("Log echo failed")?;
-
-            Ok::<_, eel::Error>(())
-        });
+        self.push((message, highlight));
 
         Ok(len)
     }
@@ -56,38 +181,222 @@ impl std::io::Write for NvimIoWriter {
 
 struct NvimMakeWriter {
     editor: Arc<NvimEditor>,
+    state: Arc<Mutex<MessageState>>,
+    max_per_second: usize,
 }
 
 impl<'a> MakeWriter<'a> for NvimMakeWriter {
     type Writer = tracing_appender::non_blocking::NonBlocking;
 
     fn make_writer(&self) -> Self::Writer {
-        let (writer, guard) = tracing_appender::non_blocking(std::io::LineWriter::new(
-            NvimIoWriter::new(self.editor.clone()),
-        ));
+        let (writer, guard) =
+            tracing_appender::non_blocking(std::io::LineWriter::new(NvimIoWriter {
+                editor: self.editor.clone(),
+                state: self.state.clone(),
+                max_per_second: self.max_per_second,
+            }));
         Box::leak(Box::new(guard));
 
         writer
     }
 }
 
-impl NvimMakeWriter {
-    fn new(editor: Arc<NvimEditor>) -> Self {
-        NvimMakeWriter { editor }
+/// Opens the message history in a scratch buffer.
+fn create_messages_command(
+    editor: Arc<NvimEditor>,
+    state: Arc<Mutex<MessageState>>,
+) -> eel::Result<()> {
+    let command_editor = editor.clone();
+
+    editor.dispatch(move || {
+        let opts = CreateCommandOpts::builder().force(true).build();
+
+        nvim_api::create_user_command(
+            "EelMessages",
+            move |_| {
+                let history = state.lock().history.iter().cloned().collect();
+
+                ScratchBuffer::new(String::clone)
+                    .name("eel://messages")
+                    .open(&command_editor, history)
+                    .map(|_| ())
+            },
+            &opts,
+        )
+        .into_nvim()
+    })??;
+
+    Ok(())
+}
+
+impl NvimEditor {
+    /// Overrides the level of `target` (of all targets if `None`) in the global filter and in
+    /// the message layer, if installed.
+    pub fn set_log_level(&self, target: Option<&str>, level: LevelFilter) -> eel::Result<()> {
+        eel::tracing::set_log_level(target, level)?;
+
+        if let Some(handle) = MSG_TARGETS.get() {
+            handle
+                .modify(|targets| {
+                    let current = std::mem::take(targets);
+
+                    *targets = match target {
+                        Some(target) => current.with_target(target, level),
+                        None => current.with_default(level),
+                    };
+                })
+                .map_err(|e| {
+                    eel::buffer::Error::Custom(format!("Failed to set log level: {e}").into())
+                })?;
+        }
+
+        Ok(())
     }
 }
 
+/// Registers `:EelLogLevel [target] {level}`, calling [`NvimEditor::set_log_level`].
+pub fn create_log_level_command(editor: Arc<NvimEditor>) -> eel::Result<()> {
+    let command_editor = editor.clone();
+
+    editor.dispatch(move || {
+        let opts = CreateCommandOpts::builder()
+            .nargs(CommandNArgs::OneOrMore)
+            .force(true)
+            .build();
+
+        nvim_api::create_user_command(
+            "EelLogLevel",
+            move |args: nvim_api::types::CommandArgs| {
+                let (target, level) = match &args.fargs[..] {
+                    [level] => (None, level),
+                    [target, level] => (Some(target.as_str()), level),
+                    _ => Err(eel::buffer::Error::Custom(
+                        "Usage: EelLogLevel [target] {level}".into(),
+                    ))?,
+                };
+
+                let level: LevelFilter = level.parse().map_err(|e| {
+                    eel::buffer::Error::Custom(format!("Invalid log level {level}: {e}").into())
+                })?;
+
+                command_editor.set_log_level(target, level)
+            },
+            &opts,
+        )
+        .into_nvim()
+    })??;
+
+    Ok(())
+}
+
+/// Echoes WARN and ERROR events, 3 per second at most, and registers the `:EelMessages`
+/// command listing them all and `:EelLogLevel`.
 pub fn nvim_msg_layer(editor: Arc<NvimEditor>) -> TracingLayer {
+    nvim_msg_layer_with_rate(editor, DEFAULT_MAX_PER_SECOND)
+}
+
+/// Messages over `max_per_second` are summarized after the second ends.
+pub fn nvim_msg_layer_with_rate(editor: Arc<NvimEditor>, max_per_second: usize) -> TracingLayer {
+    let state = Arc::new(Mutex::new(MessageState::default()));
+
+    create_messages_command(editor.clone(), state.clone())
+        .log_err_msg("Failed to create :EelMessages")
+        .ok();
+
+    create_log_level_command(editor.clone())
+        .log_err_msg("Failed to create :EelLogLevel")
+        .ok();
+
     let targets = Targets::new()
         .with_default(Level::WARN)
         .with_target("eel_nvim::tracing", LevelFilter::OFF)
         .with_target("eel_nvim::dispatcher", LevelFilter::OFF);
 
+    let (targets, handle) = reload::Layer::new(targets);
+    MSG_TARGETS.set(handle).ok();
+
     let layer = tracing_subscriber::fmt::layer()
-        .with_writer(NvimMakeWriter::new(editor))
+        .with_writer(NvimMakeWriter {
+            editor,
+            state,
+            max_per_second,
+        })
         .without_time()
         .with_ansi(false)
         .with_filter(targets);
 
     Box::new(layer)
 }
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::io::Write as _;
+
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::test_utils::{nvim_editor_factory, run_nvim_test_with_timeout};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn log_level(editor: NvimEditor) {
+        let editor = Arc::new(editor);
+
+        editor
+            .set_log_level(Some("eel_log_level_test"), LevelFilter::TRACE)
+            .expect("Failed to set log level");
+        assert!(tracing::enabled!(target: "eel_log_level_test", Level::TRACE));
+
+        create_log_level_command(editor.clone()).expect("Failed to create command");
+
+        editor
+            .exec("EelLogLevel eel_log_level_test debug")
+            .expect("Failed to run command");
+        assert!(tracing::enabled!(target: "eel_log_level_test", Level::DEBUG));
+        assert!(!tracing::enabled!(target: "eel_log_level_test", Level::TRACE));
+
+        assert!(editor.exec("EelLogLevel eel_log_level_test loud").is_err());
+        assert!(editor.exec("EelLogLevel a b c").is_err());
+    }
+
+    #[nvim_oxi::test]
+    fn rate_limit() {
+        run_nvim_test_with_timeout(
+            |editor: NvimEditor| {
+                let state = Arc::new(Mutex::new(MessageState::default()));
+                let mut writer = NvimIoWriter {
+                    editor: Arc::new(editor),
+                    state: state.clone(),
+                    max_per_second: 2,
+                };
+
+                // One write per line, like through the `LineWriter`
+                for i in 0..5 {
+                    writer
+                        .write_all(format!("WARN message {i}\n").as_bytes())
+                        .expect("Failed to write");
+                }
+                writer
+                    .write_all(b"INFO not shown\n")
+                    .expect("Failed to write");
+
+                {
+                    let state = state.lock();
+
+                    assert_eq!(state.history.len(), 5);
+                    assert_eq!(state.shown, 2);
+                    assert_eq!(state.suppressed, 3);
+                }
+
+                std::thread::sleep(RATE_WINDOW + Duration::from_millis(200));
+
+                let state = state.lock();
+
+                assert_eq!(state.suppressed, 0);
+                assert!(state.window_start.is_none());
+            },
+            nvim_editor_factory,
+            Duration::from_secs(5),
+        )
+    }
+}
@@ -0,0 +1,700 @@
+use std::sync::Arc;
+
+use nvim_oxi::api::{opts::OptionOpts, types::Mode};
+use parking_lot::Mutex;
+
+use eel::{Editor, Result, dispatch::MainThreadDispatcher};
+
+use crate::{
+    buffer::NvimBufferHandle, dispatcher::Dispatcher, editor::NvimEditor,
+    error::Error as NvimError, window::NvimWindow,
+};
+
+type Render<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+type ItemCallback<T> = Box<dyn FnMut(&T) -> Result<()> + Send>;
+
+/// Builder of a read-only buffer listing items of a data model (one line per item), opened in
+/// a split, e.g. for pickers or previews.
+pub struct ScratchBuffer<T> {
+    name: Option<String>,
+    filetype: Option<String>,
+    render: Render<T>,
+    keymaps: Vec<(Mode, String, ItemCallback<T>)>,
+}
+
+impl<T: Clone + Send + 'static> ScratchBuffer<T> {
+    pub fn new(render: impl Fn(&T) -> String + Send + Sync + 'static) -> Self {
+        Self {
+            name: None,
+            filetype: None,
+            render: Arc::new(render),
+            keymaps: Vec::new(),
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn filetype(mut self, filetype: impl Into<String>) -> Self {
+        self.filetype = Some(filetype.into());
+        self
+    }
+
+    /// Maps `lhs` to `callback`, which gets the item under the cursor. Nothing is called on
+    /// lines without an item.
+    pub fn keymap(
+        mut self,
+        mode: Mode,
+        lhs: impl Into<String>,
+        callback: impl FnMut(&T) -> Result<()> + Send + 'static,
+    ) -> Self {
+        self.keymaps.push((mode, lhs.into(), Box::new(callback)));
+        self
+    }
+
+    /// Maps `<CR>` to `callback`.
+    pub fn on_select(self, callback: impl FnMut(&T) -> Result<()> + Send + 'static) -> Self {
+        self.keymap(Mode::Normal, "<CR>", callback)
+    }
+
+    /// Opens the buffer in a new split below the current window, which becomes current.
+    pub fn open(self, editor: &NvimEditor, items: Vec<T>) -> Result<Scratch<T>> {
+        let buffer = editor.new_buffer()?;
+        let mut buf = buffer.inner_buf();
+
+        let name = self.name;
+        let filetype = self.filetype;
+
+        let window = editor.dispatch(move || {
+            let opts = OptionOpts::builder().buffer(buf.clone()).build();
+
+            nvim_oxi::api::set_option_value("bufhidden", "wipe", &opts)?;
+            nvim_oxi::api::set_option_value("modifiable", false, &opts)?;
+
+            if let Some(filetype) = filetype {
+                nvim_oxi::api::set_option_value("filetype", filetype, &opts)?;
+            }
+
+            if let Some(name) = name {
+                buf.set_name(name)?;
+            }
+
+            nvim_oxi::api::command("botright split")?;
+
+            let mut window = nvim_oxi::api::get_current_win();
+            window.set_buf(&buf)?;
+
+            Ok::<_, NvimError>(window)
+        })??;
+
+        let scratch = Scratch {
+            window: NvimWindow::wrap(window, editor.dispatcher.clone()),
+            buffer,
+            dispatcher: editor.dispatcher.clone(),
+            items: Arc::new(Mutex::new(Vec::new())),
+            render: self.render,
+        };
+
+        scratch.set_items(items)?;
+
+        for (mode, lhs, mut callback) in self.keymaps {
+            let items = scratch.items.clone();
+
+            scratch.buffer.set_keymap(mode, &lhs, move || {
+                let (row, _) = nvim_oxi::api::get_current_win()
+                    .get_cursor()
+                    .map_err(NvimError::from)?;
+
+                let item = items.lock().get(row - 1).cloned();
+
+                // Not holding the lock, so the callback can update the items
+                match item {
+                    Some(item) => callback(&item),
+                    None => Ok(()),
+                }
+            })?;
+        }
+
+        Ok(scratch)
+    }
+}
+
+/// Buffer and window opened by [`ScratchBuffer::open`], the buffer is wiped once it's hidden.
+pub struct Scratch<T> {
+    buffer: NvimBufferHandle,
+    window: NvimWindow,
+    items: Arc<Mutex<Vec<T>>>,
+    render: Render<T>,
+    dispatcher: Arc<Dispatcher>,
+}
+
+impl<T: Clone + Send + 'static> Scratch<T> {
+    pub fn buffer(&self) -> &NvimBufferHandle {
+        &self.buffer
+    }
+
+    pub fn window(&mut self) -> &mut NvimWindow {
+        &mut self.window
+    }
+
+    /// Replaces the items and re-renders the buffer.
+    pub fn set_items(&self, items: Vec<T>) -> Result<()> {
+        let lines: Vec<String> = items.iter().map(|item| (self.render)(item)).collect();
+        let mut buf = self.buffer.inner_buf();
+
+        *self.items.lock() = items;
+
+        self.dispatcher.dispatch(move || {
+            let opts = OptionOpts::builder().buffer(buf.clone()).build();
+
+            nvim_oxi::api::set_option_value("modifiable", true, &opts)?;
+            buf.set_lines(.., true, lines)?;
+            nvim_oxi::api::set_option_value("modifiable", false, &opts)?;
+            nvim_oxi::api::set_option_value("modified", false, &opts)?;
+
+            Ok::<_, NvimError>(())
+        })??;
+
+        Ok(())
+    }
+
+    /// Item on the window's cursor line.
+    pub fn selected(&self) -> Result<Option<T>> {
+        let row = self.window.get_cursor()?.row;
+
+        Ok(self.items.lock().get(row).cloned())
+    }
+
+    pub fn close(self) -> Result<()> {
+        self.window.close(true)
+    }
+}
+
+/// Options of [`HoverWindow::show_at`].
+#[cfg(feature = "mark")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverOpts {
+    /// `border` of the float, see `nvim_open_win`.
+    pub border: String,
+    pub filetype: Option<String>,
+    /// Longer lines are wrapped.
+    pub max_width: Option<usize>,
+    /// Closes the window once the cursor leaves where it was when it was shown.
+    pub close_on_cursor_move: bool,
+}
+
+#[cfg(feature = "mark")]
+impl Default for HoverOpts {
+    fn default() -> Self {
+        Self {
+            border: "rounded".into(),
+            filetype: None,
+            max_width: None,
+            close_on_cursor_move: true,
+        }
+    }
+}
+
+/// Float shown below a mark, following it as the text around it is edited. Scrolling is
+/// followed by nvim itself.
+///
+/// The window is closed when the handle is dropped.
+#[cfg(feature = "mark")]
+pub struct HoverWindow {
+    window: i32,
+    dispatcher: Arc<Dispatcher>,
+    closed: bool,
+    // Keeps the watched mark alive
+    _mark: eel::mark::Mark<NvimBufferHandle>,
+}
+
+#[cfg(feature = "mark")]
+impl HoverWindow {
+    /// Shown in the current window if it has the mark's buffer, otherwise in the first one
+    /// that has it. Fails if the buffer isn't shown anywhere.
+    pub fn show_at(
+        editor: &NvimEditor,
+        mark: &eel::mark::Mark<NvimBufferHandle>,
+        content: &str,
+        opts: HoverOpts,
+    ) -> Result<Self> {
+        use crate::lua::mlua;
+
+        let position = mark.lock_read().get_position()?;
+        let buffer = mark.buffer().inner_buf().handle();
+        let (namespace, extmark) = (mark.id().namespace(), mark.id().extmark());
+        let lines = content.split('\n').map(String::from).collect::<Vec<_>>();
+
+        let window = editor.dispatch(move || {
+            let lua = mlua::lua();
+
+            let config = lua.create_table()?;
+            config.set("border", opts.border)?;
+            config.set("filetype", opts.filetype)?;
+            config.set("max_width", opts.max_width)?;
+            config.set("close_on_cursor_move", opts.close_on_cursor_move)?;
+
+            lua.load(
+                r#"
+                local buf, namespace, extmark, row, col, lines, config = ...
+
+                local anchor = vim.api.nvim_get_current_win()
+                if vim.api.nvim_win_get_buf(anchor) ~= buf then
+                    anchor = vim.fn.bufwinid(buf)
+                end
+                if anchor == -1 then
+                    error("Buffer " .. buf .. " isn't shown in any window")
+                end
+
+                local width = 1
+                for _, line in ipairs(lines) do
+                    width = math.max(width, vim.fn.strdisplaywidth(line))
+                end
+                width = math.min(width, config.max_width or width)
+
+                -- Lines longer than the width wrap
+                local height = 0
+                for _, line in ipairs(lines) do
+                    height = height + math.max(1, math.ceil(vim.fn.strdisplaywidth(line) / width))
+                end
+
+                local float_buf = vim.api.nvim_create_buf(false, true)
+                vim.api.nvim_buf_set_lines(float_buf, 0, -1, true, lines)
+                vim.bo[float_buf].bufhidden = "wipe"
+                vim.bo[float_buf].modifiable = false
+                if config.filetype then
+                    vim.bo[float_buf].filetype = config.filetype
+                end
+
+                local win = vim.api.nvim_open_win(float_buf, false, {
+                    relative = "win",
+                    win = anchor,
+                    bufpos = { row, col },
+                    width = width,
+                    height = height,
+                    style = "minimal",
+                    border = config.border,
+                    focusable = false,
+                })
+                vim.wo[win].wrap = true
+
+                local group = vim.api.nvim_create_augroup("eel_hover_" .. win, { clear = true })
+                local closed = false
+                local close = function()
+                    closed = true
+                    pcall(vim.api.nvim_win_close, win, true)
+                    pcall(vim.api.nvim_del_augroup_by_id, group)
+                end
+
+                -- Extmarks move with the text, the float is moved after them once it's safe
+                vim.api.nvim_buf_attach(buf, false, {
+                    on_lines = function()
+                        if closed then
+                            return true
+                        end
+
+                        vim.schedule(function()
+                            if closed or not vim.api.nvim_win_is_valid(win)
+                                or not vim.api.nvim_win_is_valid(anchor) then
+                                return close()
+                            end
+
+                            local pos = vim.api.nvim_buf_get_extmark_by_id(buf, namespace, extmark, {})
+                            if pos[1] then
+                                vim.api.nvim_win_set_config(win, {
+                                    relative = "win",
+                                    win = anchor,
+                                    bufpos = pos,
+                                })
+                            end
+                        end)
+                    end,
+                })
+
+                vim.api.nvim_create_autocmd("WinClosed", {
+                    group = group,
+                    pattern = { tostring(win), tostring(anchor) },
+                    callback = close,
+                })
+
+                if config.close_on_cursor_move then
+                    local shown_at = vim.api.nvim_win_get_cursor(anchor)
+                    vim.api.nvim_create_autocmd({ "CursorMoved", "CursorMovedI", "WinLeave" }, {
+                        group = group,
+                        callback = function(event)
+                            local cursor = vim.api.nvim_win_get_cursor(anchor)
+                            if event.event == "WinLeave"
+                                or cursor[1] ~= shown_at[1] or cursor[2] ~= shown_at[2] then
+                                close()
+                            end
+                        end,
+                    })
+                end
+
+                return win
+                "#,
+            )
+            .call::<i32>((
+                buffer,
+                namespace,
+                extmark,
+                position.row,
+                position.col,
+                lines,
+                config,
+            ))
+            .map_err(NvimError::from)
+        })??;
+
+        Ok(Self {
+            window,
+            dispatcher: editor.dispatcher.clone(),
+            closed: false,
+            _mark: mark.clone(),
+        })
+    }
+
+    pub fn is_open(&self) -> Result<bool> {
+        let window: nvim_oxi::api::Window = self.window.into();
+
+        self.dispatcher.dispatch(move || window.is_valid())
+    }
+
+    /// Closing an already closed window does nothing.
+    pub fn close(mut self) -> Result<()> {
+        self.closed = true;
+        let window = self.window;
+
+        self.dispatcher
+            .dispatch(move || Self::close_window(window))?
+    }
+
+    fn close_window(window: i32) -> Result<()> {
+        let window: nvim_oxi::api::Window = window.into();
+
+        if window.is_valid() {
+            window.close(true).map_err(NvimError::from)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mark")]
+impl Drop for HoverWindow {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        // Not waiting, the handle may be dropped on the nvim thread in a fast context
+        let window = self.window;
+        let closing = self.dispatcher.dispatch_async(move || {
+            if let Err(e) = Self::close_window(window) {
+                tracing::warn!("Failed to close hover window: {e}");
+            }
+        });
+
+        if let Err(e) = closing {
+            tracing::warn!("Failed to close hover window: {e}");
+        }
+    }
+}
+
+/// Asks through `vim.ui.input` and `vim.ui.select`, confirmations being a select between
+/// "Yes" and "No". Waits for the callback, so plugins replacing `vim.ui` work too.
+#[cfg(feature = "ui")]
+pub(crate) fn prompt(
+    dispatcher: &Dispatcher,
+    spec: &eel::ui::PromptSpec,
+) -> Result<eel::ui::UserResponse> {
+    use std::sync::mpsc;
+
+    use eel::ui::{PromptSpec, UserResponse};
+
+    use crate::lua::mlua;
+
+    // The callback can't run while we block the thread it's called on
+    if dispatcher.on_main_thread() {
+        Err(eel::buffer::Error::Custom(
+            "Prompts can't be awaited on the nvim thread".into(),
+        ))?;
+    }
+
+    let checked = spec;
+    let spec = spec.clone();
+    let (tx, rx) = mpsc::sync_channel(1);
+
+    let started = dispatcher.dispatch_async(move || {
+        let lua = mlua::lua();
+
+        let respond = move |response| {
+            // Only the first answer counts, in case a `vim.ui` override calls back twice
+            let _ = tx.try_send(response);
+        };
+
+        let (kind, done, arg) = match &spec {
+            PromptSpec::Input { default, .. } => (
+                "input",
+                lua.create_function(move |_, text: Option<String>| {
+                    respond(text.map_or(UserResponse::Cancelled, UserResponse::Text));
+                    Ok(())
+                })?,
+                mlua::IntoLua::into_lua(default.clone(), &lua)?,
+            ),
+            PromptSpec::Confirm { .. } => (
+                "select",
+                lua.create_function(move |_, (_, index): (mlua::Value, Option<usize>)| {
+                    respond(
+                        index.map_or(UserResponse::Cancelled, |i| UserResponse::Confirmed(i == 1)),
+                    );
+                    Ok(())
+                })?,
+                mlua::IntoLua::into_lua(vec!["Yes", "No"], &lua)?,
+            ),
+            PromptSpec::Pick { items, .. } => (
+                "select",
+                lua.create_function(move |_, (_, index): (mlua::Value, Option<usize>)| {
+                    respond(match index {
+                        Some(i) if i > 0 => UserResponse::Picked(i - 1),
+                        // Out of range, left for `PromptSpec::check` to reject
+                        Some(_) => UserResponse::Picked(usize::MAX),
+                        None => UserResponse::Cancelled,
+                    });
+                    Ok(())
+                })?,
+                mlua::IntoLua::into_lua(items.clone(), &lua)?,
+            ),
+        };
+
+        lua.load(
+            r#"
+            local kind, prompt, arg, done = ...
+            if kind == "input" then
+                vim.ui.input({ prompt = prompt, default = arg }, done)
+            else
+                vim.ui.select(arg, { prompt = prompt }, done)
+            end
+            "#,
+        )
+        .call::<()>((kind, spec.prompt(), arg, done))
+        .map_err(NvimError::from)
+    })?;
+
+    started
+        .recv()
+        .map_err(|_| eel::buffer::Error::Custom("Prompt wasn't started".into()))??;
+
+    let response = rx
+        .recv()
+        .map_err(|_| eel::buffer::Error::Custom("Prompt was dropped unanswered".into()))?;
+
+    // `vim.ui` overrides can call back with anything
+    checked.check(&response)?;
+
+    Ok(response)
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{
+        assert_buffer_content,
+        buffer::{BufferHandle, WriteBuffer},
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use super::*;
+
+    use crate::test_utils::nvim_editor_factory;
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn scratch_buffer(editor: NvimEditor) {
+        let selected = Arc::new(Mutex::new(Vec::new()));
+        let on_select = selected.clone();
+
+        let scratch = ScratchBuffer::new(|item: &String| format!("- {item}"))
+            .name("eel-scratch-test")
+            .filetype("eel")
+            .on_select(move |item| {
+                on_select.lock().push(item.clone());
+                Ok(())
+            })
+            .open(&editor, vec!["a".into(), "b".into(), "c".into()])
+            .expect("Failed to open scratch buffer");
+
+        assert_buffer_content!(scratch.buffer(), "- a\n- b\n- c");
+        assert!(scratch.buffer().write().set_content("x").is_err());
+        assert!(
+            editor
+                .current_buffer()
+                .expect("Failed to get current buffer")
+                == *scratch.buffer()
+        );
+
+        editor.exec("normal! 2G").expect("Failed to move cursor");
+        assert_eq!(
+            scratch.selected().expect("Failed to get selected"),
+            Some("b".into())
+        );
+
+        editor
+            .exec_lua::<_, ()>(r#"vim.cmd.normal(vim.keycode("<CR>"))"#, ())
+            .expect("Failed to select");
+        assert_eq!(*selected.lock(), ["b"]);
+
+        scratch
+            .set_items(vec!["x".into()])
+            .expect("Failed to set items");
+        assert_buffer_content!(scratch.buffer(), "- x");
+
+        let buf = scratch.buffer().inner_buf();
+        scratch.close().expect("Failed to close");
+
+        assert!(
+            !editor
+                .dispatch(move || buf.is_valid())
+                .expect("Failed to dispatch")
+        );
+    }
+
+    #[cfg(feature = "mark")]
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn hover_window(editor: NvimEditor) {
+        use std::time::{Duration, Instant};
+
+        use eel::{Position, mark::Mark, test_utils::new_buffer_with_content};
+
+        use crate::test_utils::simulate_keys;
+
+        let buffer = new_buffer_with_content(&editor, "First line\nSecond line");
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+
+        let mark = Mark::lock_new(&buffer, &Position::new(1, 7)).expect("Failed to create mark");
+        let hover = HoverWindow::show_at(&editor, &mark, "Docs\nMore docs", HoverOpts::default())
+            .expect("Failed to show hover");
+
+        let hover_state = || -> (Vec<usize>, Vec<String>) {
+            editor
+                .exec_lua(
+                    r#"
+                    local win = ...
+                    local buf = vim.api.nvim_win_get_buf(win)
+                    return vim.api.nvim_win_get_config(win).bufpos,
+                        vim.api.nvim_buf_get_lines(buf, 0, -1, true)
+                    "#,
+                    hover.window,
+                )
+                .expect("Failed to get hover state")
+        };
+
+        assert_eq!(
+            hover_state(),
+            (vec![1, 7], vec!["Docs".into(), "More docs".into()])
+        );
+
+        buffer
+            .write()
+            .set_text((Position::origin(), Position::origin()), "New line\n")
+            .expect("Failed to set text");
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while hover_state().0 != [2, 7] {
+            assert!(Instant::now() < deadline, "Hover didn't follow the mark");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(hover.is_open().expect("Failed to check window"));
+        simulate_keys(&editor, "gg");
+        assert!(!hover.is_open().expect("Failed to check window"));
+
+        let pinned = HoverWindow::show_at(
+            &editor,
+            &mark,
+            "Pinned",
+            HoverOpts {
+                close_on_cursor_move: false,
+                ..Default::default()
+            },
+        )
+        .expect("Failed to show hover");
+
+        simulate_keys(&editor, "j");
+        assert!(pinned.is_open().expect("Failed to check window"));
+        pinned.close().expect("Failed to close hover");
+
+        let wrapped = HoverWindow::show_at(
+            &editor,
+            &mark,
+            "0123456789\nshort",
+            HoverOpts {
+                max_width: Some(4),
+                close_on_cursor_move: false,
+                ..Default::default()
+            },
+        )
+        .expect("Failed to show hover");
+
+        let height: i64 = editor
+            .exec_lua("return vim.api.nvim_win_get_height(...)", wrapped.window)
+            .expect("Failed to get height");
+        assert_eq!(height, 4);
+
+        // Closing a window closed by nvim is fine
+        let window = wrapped.window;
+        editor
+            .exec_lua::<_, ()>("vim.api.nvim_win_close(..., true)", window)
+            .expect("Failed to close window");
+        wrapped.close().expect("Failed to close hover");
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn prompt(editor: NvimEditor) {
+        use eel::ui::{PromptSpec, UserResponse};
+
+        // Headless nvim has no user to answer, so `vim.ui` is stubbed
+        editor
+            .exec_lua::<_, ()>(
+                r#"
+                vim.ui.input = function(opts, on_confirm)
+                    on_confirm(opts.prompt == "Cancel" and nil or opts.default .. "!")
+                end
+                vim.ui.select = function(items, opts, on_choice)
+                    local index = ({ Zero = 0, Past = #items + 1 })[opts.prompt] or 2
+                    on_choice(items[index], index)
+                end
+                "#,
+                (),
+            )
+            .expect("Failed to stub vim.ui");
+
+        let prompt = |spec| editor.prompt(&spec).expect("Failed to prompt");
+
+        assert_eq!(
+            prompt(PromptSpec::input("Name").with_default("foo")),
+            UserResponse::Text("foo!".into())
+        );
+        assert_eq!(prompt(PromptSpec::input("Cancel")), UserResponse::Cancelled);
+        assert_eq!(
+            prompt(PromptSpec::confirm("Sure?")),
+            UserResponse::Confirmed(false)
+        );
+        assert_eq!(
+            prompt(PromptSpec::pick("Pick", ["a", "b", "c"])),
+            UserResponse::Picked(1)
+        );
+        assert!(
+            editor
+                .prompt(&PromptSpec::pick("Zero", ["a", "b"]))
+                .is_err()
+        );
+        assert!(
+            editor
+                .prompt(&PromptSpec::pick("Past", ["a", "b"]))
+                .is_err()
+        );
+    }
+}
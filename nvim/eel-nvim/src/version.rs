@@ -0,0 +1,87 @@
+use std::sync::OnceLock;
+
+use crate::{error::Error as NvimError, lua::mlua};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NvimVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl NvimVersion {
+    /// Oldest version the nvim-oxi API level eel-nvim builds against runs on.
+    pub const MIN: Self = Self::new(0, 10, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl std::fmt::Display for NvimVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Version of the running nvim, detected on the first call. Has to be called on the main
+/// thread, see [`NvimEditor::version`](crate::editor::NvimEditor::version).
+pub(crate) fn current() -> Result<NvimVersion, NvimError> {
+    static VERSION: OnceLock<NvimVersion> = OnceLock::new();
+
+    if let Some(version) = VERSION.get() {
+        return Ok(*version);
+    }
+
+    let (major, minor, patch) = mlua::lua()
+        .load(
+            r#"
+            local version = vim.fn.api_info().version
+            return version.major, version.minor, version.patch
+            "#,
+        )
+        .call::<(u32, u32, u32)>(())?;
+
+    Ok(*VERSION.get_or_init(|| NvimVersion::new(major, minor, patch)))
+}
+
+/// Fails on versions older than [`NvimVersion::MIN`], which eel-nvim has no fallbacks for.
+pub(crate) fn check() -> Result<NvimVersion, NvimError> {
+    let version = current()?;
+
+    if version < NvimVersion::MIN {
+        Err(NvimError::UnsupportedVersion(version))?;
+    }
+
+    Ok(version)
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::Editor;
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn version(editor: NvimEditor) {
+        let version = editor.version().expect("Failed to get version");
+
+        let expected = editor
+            .exec_lua::<_, (u32, u32, u32)>(
+                "local v = vim.version(); return v.major, v.minor, v.patch",
+                (),
+            )
+            .expect("Failed to get version");
+        assert_eq!((version.major, version.minor, version.patch), expected);
+
+        assert_eq!(
+            editor.capabilities().decorations,
+            cfg!(feature = "decoration")
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Read-only buffers showing what a content provider returns for their name, e.g.
+//! `git://HEAD:src/lib.rs` or `docs://api/buffer`.
+
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+use nvim_oxi::api::{
+    Buffer,
+    opts::{CreateAutocmdOpts, OptionOpts},
+    types::AutocmdCallbackArgs,
+};
+use parking_lot::RwLock;
+use tracing::{debug, warn};
+
+use eel::{Result, dispatch::MainThreadDispatcher};
+
+use crate::{
+    buffer::NvimBufferHandle,
+    dispatcher::Dispatcher,
+    editor::NvimEditor,
+    error::{Error as NvimError, IntoNvimResult},
+};
+
+type Provider = Arc<dyn Fn(&str) -> Result<Vec<String>> + Send + Sync>;
+
+/// Providers by scheme, each editor has its own.
+#[derive(Default)]
+pub(crate) struct Providers(RwLock<BTreeMap<String, Provider>>);
+
+impl std::fmt::Debug for Providers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.read().keys()).finish()
+    }
+}
+
+impl Providers {
+    fn get(&self, name: &str) -> Result<Provider> {
+        name.split_once("://")
+            .and_then(|(scheme, _)| self.0.read().get(scheme).cloned())
+            .ok_or_else(|| {
+                eel::buffer::Error::Custom(format!("No content provider for {name}").into()).into()
+            })
+    }
+}
+
+fn make_virtual(buf: &Buffer) -> std::result::Result<(), NvimError> {
+    let opts = OptionOpts::builder().buffer(buf.clone()).build();
+
+    nvim_oxi::api::set_option_value("buftype", "nofile", &opts)?;
+    nvim_oxi::api::set_option_value("swapfile", false, &opts)?;
+    nvim_oxi::api::set_option_value("readonly", true, &opts)?;
+    nvim_oxi::api::set_option_value("modifiable", false, &opts)?;
+
+    Ok(())
+}
+
+fn set_lines(dispatcher: &Dispatcher, mut buf: Buffer, lines: Vec<String>) -> Result<()> {
+    dispatcher.dispatch(move || {
+        let opts = OptionOpts::builder().buffer(buf.clone()).build();
+
+        nvim_oxi::api::set_option_value("modifiable", true, &opts)?;
+        buf.set_lines(.., true, lines)?;
+        nvim_oxi::api::set_option_value("modifiable", false, &opts)?;
+        nvim_oxi::api::set_option_value("modified", false, &opts)?;
+
+        Ok::<_, NvimError>(())
+    })??;
+
+    Ok(())
+}
+
+/// Buffer opened by [`NvimEditor::open_virtual`].
+#[derive(Clone)]
+pub struct VirtualBuffer {
+    name: String,
+    buffer: NvimBufferHandle,
+    provider: Provider,
+    dispatcher: Arc<Dispatcher>,
+}
+
+impl std::fmt::Debug for VirtualBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualBuffer")
+            .field("name", &self.name)
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+impl VirtualBuffer {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn buffer(&self) -> &NvimBufferHandle {
+        &self.buffer
+    }
+
+    /// Runs the provider again on the calling thread and replaces the content.
+    pub fn refresh(&self) -> Result<()> {
+        let lines = (self.provider)(&self.name)?;
+
+        set_lines(&self.dispatcher, self.buffer.inner_buf(), lines)
+    }
+}
+
+impl NvimEditor {
+    /// Makes buffers named `<scheme>://...` read-only views of what `provider` returns for
+    /// their name. Editing one (`:edit` again refreshes it) runs `provider` as the
+    /// `virtual:<name>` task of [`Editor::tasks`](eel::Editor::tasks), it's empty until the
+    /// task finishes.
+    ///
+    /// Providers belong to this editor, registering a scheme again replaces its provider.
+    pub fn register_content_provider(
+        &self,
+        scheme: &str,
+        provider: impl Fn(&str) -> Result<Vec<String>> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let replaced = self
+            .providers
+            .0
+            .write()
+            .insert(scheme.to_string(), Arc::new(provider))
+            .is_some();
+
+        if replaced {
+            return Ok(());
+        }
+
+        let (dispatcher, pattern) = (self.dispatcher.clone(), format!("{scheme}://*"));
+        let (providers, tasks) = (self.providers.clone(), self.tasks.clone());
+
+        self.dispatch(move || {
+            let opts = CreateAutocmdOpts::builder()
+                .patterns([pattern.as_str()])
+                .callback(move |args: AutocmdCallbackArgs| {
+                    make_virtual(&args.buffer)?;
+
+                    let (buf, name) = (args.buffer, args.r#match);
+                    let (dispatcher, providers) = (dispatcher.clone(), providers.clone());
+                    debug!("Loading virtual buffer {name}");
+
+                    let spawned = tasks.spawn(&format!("virtual:{name}"), move |_| {
+                        let lines = providers.get(&name)?(&name)?;
+                        set_lines(&dispatcher, buf.clone(), lines)
+                    });
+
+                    if let Err(e) = spawned {
+                        warn!("Failed to load virtual buffer: {e}");
+                    }
+
+                    Ok::<_, NvimError>(false)
+                })
+                .build();
+
+            nvim_oxi::api::create_autocmd(["BufReadCmd"], &opts).into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    /// Creates the buffer `name` (or reuses it) without showing it, its content is loaded
+    /// before returning.
+    pub fn open_virtual(&self, name: &str) -> Result<VirtualBuffer> {
+        let provider = self.providers.get(name)?;
+        let lines = provider(name)?;
+
+        let buf_name = name.to_string();
+        let buf = self.dispatch(move || {
+            let existing = nvim_oxi::api::list_bufs()
+                .find(|buf| buf.get_name().is_ok_and(|n| n == Path::new(&buf_name)));
+
+            let buf = match existing {
+                Some(buf) => buf,
+                None => {
+                    let mut buf = nvim_oxi::api::create_buf(true, false)?;
+                    buf.set_name(&buf_name)?;
+                    buf
+                }
+            };
+
+            make_virtual(&buf)?;
+
+            Ok::<_, NvimError>(buf)
+        })??;
+
+        set_lines(&self.dispatcher, buf.clone(), lines)?;
+
+        Ok(VirtualBuffer {
+            name: name.to_string(),
+            buffer: self.buffer_handle(buf)?,
+            provider,
+            dispatcher: self.dispatcher.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    use eel::{
+        Editor,
+        buffer::{BufferHandle, ReadBuffer, WriteBuffer},
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn virtual_buffer(editor: NvimEditor) {
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let counter = loads.clone();
+        editor
+            .register_content_provider("eeltest", move |name| {
+                let load = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                Ok(vec![name.to_string(), format!("load {load}")])
+            })
+            .expect("Failed to register provider");
+
+        let virtual_buffer = editor
+            .open_virtual("eeltest://docs/page")
+            .expect("Failed to open virtual buffer");
+        let buffer = virtual_buffer.buffer();
+
+        let content = || buffer.read().get_content().expect("Failed to get content");
+        assert_eq!(content(), "eeltest://docs/page\nload 1");
+        assert!(buffer.write().set_content("edited").is_err());
+
+        virtual_buffer.refresh().expect("Failed to refresh");
+        assert_eq!(content(), "eeltest://docs/page\nload 2");
+
+        assert!(editor.open_virtual("missing://page").is_err());
+
+        // Editing the name loads it in the background
+        editor
+            .exec("edit eeltest://other")
+            .expect("Failed to edit buffer");
+        let current = editor.current_buffer().expect("Failed to get buffer");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let content = current.read().get_content().expect("Failed to get content");
+            if content == "eeltest://other\nload 3" {
+                break;
+            }
+
+            assert!(Instant::now() < deadline, "Buffer wasn't loaded: {content}");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // Loaded by this editor's own tasks
+        assert!(editor.tasks().status("virtual:eeltest://other").is_some());
+        assert!(
+            eel::tasks::Tasks::global()
+                .status("virtual:eeltest://other")
+                .is_none()
+        );
+    }
+}
@@ -1,8 +1,16 @@
 use std::sync::Arc;
 
-use eel::{Position, Result};
+use nvim_oxi::api::opts::OptionOpts;
 
-use crate::{buffer::NativePosition, dispatcher::Dispatcher, error::IntoNvimResult};
+use eel::{OptionValue, Position, Result, dispatch::MainThreadDispatcher};
+
+use crate::{
+    buffer::NativePosition,
+    dispatcher::Dispatcher,
+    error::{Error as NvimError, IntoNvimResult},
+    lua::mlua::{self, FromLuaMulti},
+    option,
+};
 
 pub struct NvimWindow {
     inner: nvim_oxi::api::Window,
@@ -43,4 +51,221 @@ impl NvimWindow {
 
         Ok(())
     }
+
+    /// Adds the cursor position to the window's jump list.
+    pub fn push_jump(&self) -> Result<()> {
+        let window = self.inner.clone();
+
+        self.dispatcher.dispatch(move || {
+            window
+                .call::<_, _, ()>(|()| nvim_oxi::api::command("normal! m'"))
+                .into_nvim()
+        })??;
+
+        Ok(())
+    }
+
+    pub fn close(self, force: bool) -> Result<()> {
+        let window = self.inner;
+
+        self.dispatcher
+            .dispatch(move || window.close(force).into_nvim())??;
+
+        Ok(())
+    }
+
+    fn exec_lua<R>(&self, chunk: &'static str, args: (usize, usize)) -> Result<R>
+    where
+        R: FromLuaMulti + Send + 'static,
+    {
+        let window = self.inner.handle();
+
+        let value = self.dispatcher.dispatch(move || {
+            mlua::lua()
+                .load(chunk)
+                .call::<R>((window, args.0, args.1))
+                .map_err(NvimError::from)
+        })??;
+
+        Ok(value)
+    }
+
+    /// Where `position` is shown in the window, relative to the top left of its text area (after
+    /// the sign and number columns), taking wrapping, folds and concealed text into account.
+    /// `None` if it's scrolled out of view.
+    pub fn display_position(&self, position: &Position) -> Result<Option<Position>> {
+        let (row, col) = self.exec_lua::<(Option<usize>, Option<usize>)>(
+            r#"
+            local win, row, col = ...
+            local info = vim.fn.getwininfo(win)[1]
+            local buf = vim.api.nvim_win_get_buf(win)
+            local line = vim.api.nvim_buf_get_lines(buf, row, row + 1, true)[1]
+
+            local pos
+            if col > 0 and col >= #line then
+                -- After the last character, on the next screen cell
+                pos = vim.fn.screenpos(win, row + 1, #line)
+                pos.col = pos.endcol + 1
+            else
+                pos = vim.fn.screenpos(win, row + 1, col + 1)
+            end
+
+            if pos.row == 0 then
+                return nil, nil
+            end
+
+            return pos.row - info.winrow, pos.col - info.wincol - info.textoff
+            "#,
+            (position.row, position.col),
+        )?;
+
+        Ok(row.zip(col).map(|(row, col)| Position::new(row, col)))
+    }
+
+    /// Inverse of [`NvimWindow::display_position`], the last character starting at or before
+    /// `display_position` on its screen row. A closed fold maps to its first line. `None` if
+    /// no buffer line is shown on that row.
+    pub fn buffer_position(&self, display_position: &Position) -> Result<Option<Position>> {
+        let (row, col) = self.exec_lua::<(Option<usize>, Option<usize>)>(
+            r#"
+            local win, display_row, display_col = ...
+            local info = vim.fn.getwininfo(win)[1]
+            local buf = vim.api.nvim_win_get_buf(win)
+
+            local screen_row = info.winrow + display_row
+            local screen_col = info.wincol + info.textoff + display_col
+
+            for lnum = info.topline, info.botline do
+                local fold = vim.api.nvim_win_call(win, function()
+                    return vim.fn.foldclosed(lnum)
+                end)
+
+                if fold == -1 or fold == lnum then
+                    local line = vim.api.nvim_buf_get_lines(buf, lnum - 1, lnum, true)[1]
+                    local found
+
+                    local i = 0
+                    repeat
+                        local pos = vim.fn.screenpos(win, lnum, i + 1)
+                        if pos.row > screen_row then
+                            break
+                        elseif pos.row == screen_row and pos.col <= screen_col then
+                            found = i
+                            if fold == lnum then
+                                break
+                            end
+                        end
+
+                        if #line > 0 then
+                            i = i + vim.str_utf_end(line, i + 1) + 1
+                        end
+                    until i >= #line
+
+                    if found then
+                        return lnum - 1, found
+                    end
+                end
+            end
+
+            return nil, nil
+            "#,
+            (display_position.row, display_position.col),
+        )?;
+
+        Ok(row.zip(col).map(|(row, col)| Position::new(row, col)))
+    }
+
+    /// Window-local option, e.g. `wrap` or `number`.
+    pub fn get_option(&self, name: &str) -> Result<OptionValue> {
+        let window = self.inner.clone();
+        let name = name.to_string();
+
+        let value = self
+            .dispatcher
+            .dispatch(move || option::get(&name, &OptionOpts::builder().win(window).build()))??;
+
+        Ok(value)
+    }
+
+    pub fn set_option(&mut self, name: &str, value: OptionValue) -> Result<()> {
+        let window = self.inner.clone();
+        let name = name.to_string();
+
+        self.dispatcher.dispatch(move || {
+            option::set(&name, value, &OptionOpts::builder().win(window).build())
+        })??;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "nvim-tests")]
+mod tests {
+    use eel::{
+        Editor, OptionValue, Position, buffer::BufferHandle, test_utils::new_buffer_with_content,
+    };
+    use eel_nvim_macros::nvim_test;
+
+    use crate::{editor::NvimEditor, test_utils::nvim_editor_factory};
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn window_options(editor: NvimEditor) {
+        let mut window = editor.current_window().expect("Failed to get window");
+
+        window
+            .set_option("wrap", false.into())
+            .expect("Failed to set option");
+        window
+            .set_option("conceallevel", 2.into())
+            .expect("Failed to set option");
+
+        assert_eq!(
+            window.get_option("wrap").expect("Failed to get option"),
+            OptionValue::Bool(false)
+        );
+        assert_eq!(
+            window
+                .get_option("conceallevel")
+                .expect("Failed to get option"),
+            OptionValue::Int(2)
+        );
+    }
+
+    #[nvim_test(editor_factory = nvim_editor_factory)]
+    fn display_positions(editor: NvimEditor) {
+        let buffer = new_buffer_with_content(
+            &editor,
+            &format!("{}\nfolded\nlines\nżółw", "a".repeat(100)),
+        );
+        editor
+            .set_current_buffer(&mut buffer.write())
+            .expect("Failed to set current buffer");
+        editor
+            .exec("only | set columns=80 wrap nonumber signcolumn=no | 2,3fold")
+            .expect("Failed to set up window");
+
+        let window = editor.current_window().expect("Failed to get window");
+        let display = |row, col| {
+            window
+                .display_position(&Position::new(row, col))
+                .expect("Failed to get display position")
+        };
+        let buffer_pos = |row, col| {
+            window
+                .buffer_position(&Position::new(row, col))
+                .expect("Failed to get buffer position")
+        };
+
+        // The first line wraps into two rows, the fold takes one
+        assert_eq!(display(0, 10), Some(Position::new(0, 10)));
+        assert_eq!(display(0, 90), Some(Position::new(1, 10)));
+        assert_eq!(display(0, 100), Some(Position::new(1, 20)));
+        assert_eq!(display(3, 0), Some(Position::new(3, 0)));
+        assert_eq!(display(3, "żó".len()), Some(Position::new(3, 2)));
+
+        assert_eq!(buffer_pos(1, 10), Some(Position::new(0, 90)));
+        assert_eq!(buffer_pos(2, 5), Some(Position::new(1, 0)));
+        assert_eq!(buffer_pos(3, 2), Some(Position::new(3, "żó".len())));
+        assert_eq!(buffer_pos(10, 0), None);
+    }
 }
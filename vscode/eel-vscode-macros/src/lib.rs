@@ -0,0 +1,51 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Expr, Ident, ItemFn, parse_macro_input, spanned::Spanned};
+
+#[derive(deluxe::ParseMetaItem)]
+#[deluxe(attributes(vscode_test))]
+struct VscodeTestArgs {
+    editor_factory: Expr,
+}
+
+#[proc_macro_attribute]
+pub fn vscode_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut function = parse_macro_input!(item as ItemFn);
+
+    let args: VscodeTestArgs = match deluxe::parse(attr) {
+        Ok(args) => args,
+        Err(e) => return e.into_compile_error().into(),
+    };
+
+    let editor_factory = args.editor_factory;
+
+    // Identifier of the generated test function
+    let test_ident = Ident::new(&function.sig.ident.to_string(), Span::call_site());
+
+    // Modifying identifier of the original function to avoid duplicate
+    let new_ident = Ident::new(&format!("_{}", function.sig.ident), function.sig.span());
+    function.sig.ident = new_ident.clone();
+
+    // Kept outside of the #[test] function, so the factory is compiled (and its imports used)
+    // in non-test builds too
+    let run_ident = Ident::new(&format!("_{}_run", test_ident), function.sig.span());
+
+    let return_type = function.sig.output.clone();
+
+    quote! {
+        #function
+
+        fn #run_ident() #return_type {
+            let editor_factory = #editor_factory;
+
+            crate::test_utils::run_vscode_test(#new_ident, editor_factory)
+        }
+
+        #[test]
+        fn #test_ident() #return_type {
+            #run_ident()
+        }
+    }
+    .into()
+}
@@ -0,0 +1,98 @@
+use eel::{
+    Position, Result,
+    buffer::ReadBuffer,
+    cursor::{CursorReadBuffer, CursorWriteBuffer},
+};
+
+use crate::protocol::{NativePosition, SelectionParams};
+
+use super::VscodeBuffer;
+
+/// The cursor is the active end of the primary selection.
+impl CursorReadBuffer for VscodeBuffer {
+    fn get_cursor(&self) -> Result<Position> {
+        let position: NativePosition = self.bridge.request("eel/getSelection", self.params())?;
+
+        Ok(position.to_position(&self.get_line(position.line)?))
+    }
+}
+
+impl CursorWriteBuffer for VscodeBuffer {
+    fn set_cursor(&mut self, position: &Position) -> Result<()> {
+        self.validate_pos(position)?;
+
+        let params = SelectionParams {
+            uri: self.uri.to_string(),
+            position: NativePosition::from_position(position, &self.get_line(position.row)?),
+        };
+
+        self.bridge.request("eel/setSelection", params)
+    }
+
+    fn push_jump(&mut self) -> Result<()> {
+        self.jumps.push((self.uri.clone(), self.get_cursor()?));
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "vscode-tests")]
+mod tests {
+    use eel::{
+        Position,
+        buffer::BufferHandle,
+        cursor::{CursorReadBuffer, CursorWriteBuffer},
+        eel_cursor_tests,
+        test_utils::new_buffer_with_content,
+    };
+    use eel_vscode_macros::vscode_test;
+
+    use crate::{
+        VscodeEditor,
+        protocol::{DocumentParams, NativePosition},
+        test_utils::fake_host_editor_factory,
+    };
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn utf16_columns(editor: VscodeEditor) {
+        let buffer = new_buffer_with_content(&editor, "ä😀x\nb");
+        let uri = buffer.uri().to_string();
+
+        // ä is one UTF-16 unit, 😀 a surrogate pair
+        buffer
+            .write()
+            .set_cursor(&Position::new(0, 6))
+            .expect("Failed to set cursor");
+
+        let native: NativePosition = editor
+            .bridge()
+            .request("eel/getSelection", DocumentParams::new(&uri))
+            .expect("Failed to get selection");
+        assert_eq!(
+            native,
+            NativePosition {
+                line: 0,
+                character: 3
+            }
+        );
+
+        assert_eq!(
+            buffer.read().get_cursor().expect("Failed to get cursor"),
+            Position::new(0, 6)
+        );
+
+        // Inside the surrogate pair, e.g. set by another extension
+        assert_eq!(
+            NativePosition {
+                line: 0,
+                character: 2
+            }
+            .to_position("ä😀x"),
+            Position::new(0, 6)
+        );
+
+        assert!(buffer.write().set_cursor(&Position::new(0, 3)).is_err());
+    }
+
+    eel_cursor_tests!(::eel_vscode_macros::vscode_test, fake_host_editor_factory);
+}
@@ -0,0 +1,351 @@
+use std::{
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Weak},
+};
+
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, RwLock};
+use tracing::trace;
+
+use eel::{
+    EditorConfig, PosRange, Result,
+    buffer::{
+        BufferData, BufferHandle, ChangeHooks, CloseHooks, ReadBuffer, WeakBufferHandle,
+        WriteBuffer,
+    },
+};
+
+use crate::{
+    protocol::{DocumentParams, EditParams, EditResult, LinesParams, NativePosition, NativeRange},
+    rpc::Bridge,
+};
+
+#[cfg(feature = "cursor")]
+pub mod cursor;
+
+/// Entry of the editor's jump list, VSCode's navigation history isn't exposed to extensions.
+#[cfg(feature = "cursor")]
+pub(crate) type Jump = (Arc<str>, eel::Position);
+
+pub struct VscodeBuffer {
+    uri: Arc<str>,
+    bridge: Arc<Bridge>,
+    #[cfg(feature = "cursor")]
+    jumps: Arc<eel::cursor::JumpList<Jump>>,
+}
+
+impl std::fmt::Debug for VscodeBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VscodeBuffer")
+            .field("uri", &self.uri)
+            .finish()
+    }
+}
+
+impl VscodeBuffer {
+    pub(crate) fn new(
+        uri: Arc<str>,
+        bridge: Arc<Bridge>,
+        #[cfg(feature = "cursor")] jumps: Arc<eel::cursor::JumpList<Jump>>,
+    ) -> Self {
+        Self {
+            uri,
+            bridge,
+            #[cfg(feature = "cursor")]
+            jumps,
+        }
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn params(&self) -> DocumentParams {
+        DocumentParams::new(&self.uri)
+    }
+
+    /// Sends the edit, applied by the bridge only if the document is still at `expected_tick`
+    /// (if any).
+    fn edit(&self, expected_tick: Option<u64>, range: &PosRange, text: &str) -> Result<()> {
+        let (start, end) = (range.start(), range.end());
+
+        self.validate_range(start, end)?;
+
+        let start_line = self.get_line(start.row)?;
+        let end_line = if end.row == start.row {
+            start_line.clone()
+        } else {
+            self.get_line(end.row)?
+        };
+
+        let params = EditParams {
+            uri: self.uri.to_string(),
+            range: NativeRange {
+                start: NativePosition::from_position(start, &start_line),
+                end: NativePosition::from_position(end, &end_line),
+            },
+            text: text.to_string(),
+            version: expected_tick,
+        };
+
+        let result: EditResult = self.bridge.request("eel/edit", params)?;
+
+        if !result.applied {
+            Err(eel::buffer::Error::Conflict {
+                expected: expected_tick.unwrap_or_default(),
+                actual: result.version,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ReadBuffer for VscodeBuffer {
+    fn line_count(&self) -> Result<usize> {
+        self.bridge.request("eel/lineCount", self.params())
+    }
+
+    fn get_lines<R: RangeBounds<usize> + Send + 'static>(
+        &self,
+        range: R,
+    ) -> Result<impl Iterator<Item = String> + Send> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => Some(end + 1),
+            Bound::Excluded(&end) => Some(end),
+            Bound::Unbounded => None,
+        };
+
+        let params = LinesParams {
+            uri: self.uri.to_string(),
+            start,
+            end,
+        };
+
+        let lines: Vec<String> = self.bridge.request("eel/getLines", params)?;
+
+        Ok(lines.into_iter())
+    }
+
+    /// The document version, VSCode increases it on every change.
+    fn changedtick(&self) -> Result<u64> {
+        self.bridge.request("eel/version", self.params())
+    }
+}
+
+impl WriteBuffer for VscodeBuffer {
+    fn set_text_in(&mut self, range: &PosRange, text: &str) -> Result<()> {
+        self.edit(None, range, text)
+    }
+
+    fn set_text_if_unchanged(
+        &mut self,
+        expected_tick: u64,
+        range: &PosRange,
+        text: &str,
+    ) -> Result<()> {
+        self.edit(Some(expected_tick), range, text)
+    }
+}
+
+#[derive(Clone, derivative::Derivative)]
+#[derivative(Debug, Eq, PartialEq)]
+pub struct VscodeBufferHandle {
+    uri: Arc<str>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    buffer_lock: Arc<RwLock<VscodeBuffer>>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    data: Arc<BufferData>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    close_hooks: Arc<CloseHooks>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    change_hooks: Arc<ChangeHooks>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    bridge: Arc<Bridge>,
+}
+
+impl VscodeBufferHandle {
+    pub(crate) fn new(buffer: VscodeBuffer) -> Self {
+        Self {
+            uri: buffer.uri.clone(),
+            bridge: buffer.bridge.clone(),
+            buffer_lock: Arc::new(RwLock::new(buffer)),
+            data: Arc::default(),
+            close_hooks: Arc::default(),
+            change_hooks: Arc::default(),
+        }
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Called on `eel/didClose`, clears user data and runs the `on_close` callbacks.
+    pub(crate) fn close(&self) {
+        self.data.clear();
+        self.change_hooks.close();
+        self.close_hooks.close();
+    }
+
+    /// Called on `eel/didChange`, runs the `on_change` callbacks.
+    pub(crate) fn changed(&self) {
+        self.change_hooks.changed();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VscodeWeakBufferHandle {
+    uri: Arc<str>,
+    buffer_lock: Weak<RwLock<VscodeBuffer>>,
+    data: Weak<BufferData>,
+    close_hooks: Weak<CloseHooks>,
+    change_hooks: Weak<ChangeHooks>,
+    bridge: Weak<Bridge>,
+}
+
+impl WeakBufferHandle for VscodeWeakBufferHandle {
+    type Handle = VscodeBufferHandle;
+
+    fn upgrade(&self) -> Option<VscodeBufferHandle> {
+        Some(VscodeBufferHandle {
+            uri: self.uri.clone(),
+            buffer_lock: self.buffer_lock.upgrade()?,
+            data: self.data.upgrade()?,
+            close_hooks: self.close_hooks.upgrade()?,
+            change_hooks: self.change_hooks.upgrade()?,
+            bridge: self.bridge.upgrade()?,
+        })
+    }
+}
+
+impl BufferHandle for VscodeBufferHandle {
+    type ReadBuffer = VscodeBuffer;
+    type WriteBuffer = VscodeBuffer;
+    type ReadBufferLock = ArcRwLockReadGuard<parking_lot::RawRwLock, Self::ReadBuffer>;
+    type WriteBufferLock = ArcRwLockWriteGuard<parking_lot::RawRwLock, Self::WriteBuffer>;
+    type WeakHandle = VscodeWeakBufferHandle;
+
+    fn read(&self) -> Self::ReadBufferLock {
+        trace!(uri = &*self.uri, "Read-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        self.buffer_lock.read_arc()
+    }
+
+    fn write(&self) -> Self::WriteBufferLock {
+        trace!(uri = &*self.uri, "Write-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        self.buffer_lock.write_arc()
+    }
+
+    fn read_timeout(&self) -> Result<Self::ReadBufferLock> {
+        let Some(timeout) = EditorConfig::current().lock_timeout else {
+            return Ok(self.read());
+        };
+
+        trace!(uri = &*self.uri, ?timeout, "Read-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        Ok(self
+            .buffer_lock
+            .try_read_arc_for(timeout)
+            .ok_or(eel::buffer::Error::LockTimeout(timeout))?)
+    }
+
+    fn write_timeout(&self) -> Result<Self::WriteBufferLock> {
+        let Some(timeout) = EditorConfig::current().lock_timeout else {
+            return Ok(self.write());
+        };
+
+        trace!(uri = &*self.uri, ?timeout, "Write-locking buffer");
+
+        #[cfg(feature = "metrics")]
+        let _wait = eel::metrics::Metrics::global().lock_wait.start_timer();
+
+        Ok(self
+            .buffer_lock
+            .try_write_arc_for(timeout)
+            .ok_or(eel::buffer::Error::LockTimeout(timeout))?)
+    }
+
+    fn downgrade_lock(&self, lock: Self::WriteBufferLock) -> Self::ReadBufferLock {
+        ArcRwLockWriteGuard::downgrade(lock)
+    }
+
+    fn data(&self) -> &BufferData {
+        &self.data
+    }
+
+    fn downgrade(&self) -> VscodeWeakBufferHandle {
+        VscodeWeakBufferHandle {
+            uri: self.uri.clone(),
+            buffer_lock: Arc::downgrade(&self.buffer_lock),
+            data: Arc::downgrade(&self.data),
+            close_hooks: Arc::downgrade(&self.close_hooks),
+            change_hooks: Arc::downgrade(&self.change_hooks),
+            bridge: Arc::downgrade(&self.bridge),
+        }
+    }
+
+    fn on_close(&self, callback: impl FnOnce() + Send + 'static) {
+        self.close_hooks.on_close(callback)
+    }
+
+    fn on_change(&self, callback: impl FnMut() -> bool + Send + 'static) {
+        self.change_hooks.on_change(callback)
+    }
+
+    fn changedtick(&self) -> Result<u64> {
+        self.bridge
+            .request("eel/version", DocumentParams::new(&self.uri))
+    }
+}
+
+#[cfg(feature = "vscode-tests")]
+mod tests {
+    use eel::{
+        eel_buffer_tests, eel_comment_tests, eel_debounce_tests, eel_journal_tests,
+        eel_search_tests, eel_textobject_tests, eel_workspace_tests,
+    };
+
+    eel_buffer_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_journal_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_debounce_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_search_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_textobject_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_comment_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+    eel_workspace_tests!(
+        ::eel_vscode_macros::vscode_test,
+        crate::test_utils::fake_host_editor_factory
+    );
+}
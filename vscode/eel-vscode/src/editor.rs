@@ -0,0 +1,507 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use parking_lot::RwLock;
+use serde_json::{Value, json};
+use tracing::{trace, warn};
+
+use eel::{
+    Capabilities, Editor, OptionValue, Result,
+    buffer::{BufferHandle, WeakBufferHandle},
+    debounce::IdleTimer,
+};
+
+use crate::{
+    buffer::{VscodeBuffer, VscodeBufferHandle, VscodeWeakBufferHandle},
+    protocol::{ConfigurationParams, DocumentInfo, DocumentParams},
+    rpc::Bridge,
+};
+
+/// Holds weak handles, so a document's handle (with its data and hooks) lives as long as
+/// something else holds it. There's at most one live handle per document.
+#[derive(Debug, Default)]
+struct BufferStore {
+    buffers: RwLock<HashMap<Arc<str>, VscodeWeakBufferHandle>>,
+    #[cfg(feature = "cursor")]
+    jumps: Arc<eel::cursor::JumpList<crate::buffer::Jump>>,
+}
+
+impl BufferStore {
+    fn get_buffer_handle(&self, uri: &str, bridge: &Arc<Bridge>) -> VscodeBufferHandle {
+        if let Some(h) = self.get(uri) {
+            return h;
+        }
+
+        let mut buffers = self.buffers.write();
+
+        // Checked again under the write lock, another thread may have created it meanwhile
+        if let Some(h) = buffers.get(uri).and_then(|h| h.upgrade()) {
+            return h;
+        }
+
+        trace!(uri, "Creating new buffer handle");
+
+        let uri: Arc<str> = uri.into();
+        let handle = VscodeBufferHandle::new(VscodeBuffer::new(
+            uri.clone(),
+            bridge.clone(),
+            #[cfg(feature = "cursor")]
+            self.jumps.clone(),
+        ));
+
+        buffers.insert(uri, handle.downgrade());
+
+        handle
+    }
+
+    fn get(&self, uri: &str) -> Option<VscodeBufferHandle> {
+        self.buffers.read().get(uri)?.upgrade()
+    }
+
+    fn remove(&self, uri: &str) -> Option<VscodeBufferHandle> {
+        self.buffers.write().remove(uri)?.upgrade()
+    }
+}
+
+/// Drives the `on_change` and `on_close` callbacks of the live handles, changes also count as
+/// activity for the idle timer.
+fn handle_notification(
+    store: Weak<BufferStore>,
+    idle: Weak<IdleTimer>,
+) -> impl Fn(&str, Value) + Send + 'static {
+    move |method, params| {
+        if !matches!(method, "eel/didChange" | "eel/didClose") {
+            trace!(method, "Ignoring bridge notification");
+            return;
+        }
+
+        if method == "eel/didChange"
+            && let Some(idle) = idle.upgrade()
+        {
+            idle.activity();
+        }
+
+        let Some(store) = store.upgrade() else {
+            return;
+        };
+
+        let document: DocumentParams = match serde_json::from_value(params) {
+            Ok(document) => document,
+            Err(e) => {
+                warn!("Invalid {method} notification: {e}");
+                return;
+            }
+        };
+
+        if method == "eel/didChange" {
+            if let Some(handle) = store.get(&document.uri) {
+                handle.changed();
+            }
+        } else {
+            trace!(uri = document.uri, "Document closed");
+
+            if let Some(handle) = store.remove(&document.uri) {
+                handle.close();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VscodeEditor {
+    bridge: Arc<Bridge>,
+    buffer_store: Arc<BufferStore>,
+    idle: Arc<IdleTimer>,
+}
+
+impl VscodeEditor {
+    /// Spawns the bridge process, which is expected to be connected to a running VSCode.
+    pub fn spawn(command: &mut Command) -> Result<Self> {
+        let buffer_store = Arc::new(BufferStore::default());
+        let idle = Arc::new(IdleTimer::new());
+        let bridge = Bridge::spawn(
+            command,
+            handle_notification(Arc::downgrade(&buffer_store), Arc::downgrade(&idle)),
+        )?;
+
+        Ok(Self {
+            bridge: Arc::new(bridge),
+            buffer_store,
+            idle,
+        })
+    }
+
+    /// Talks to a bridge over an established stream, e.g. a socket.
+    pub fn connect(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Result<Self> {
+        let buffer_store = Arc::new(BufferStore::default());
+        let idle = Arc::new(IdleTimer::new());
+        let bridge = Bridge::new(
+            reader,
+            writer,
+            handle_notification(Arc::downgrade(&buffer_store), Arc::downgrade(&idle)),
+        )?;
+
+        Ok(Self {
+            bridge: Arc::new(bridge),
+            buffer_store,
+            idle,
+        })
+    }
+
+    /// For requests not covered by the eel traits.
+    pub fn bridge(&self) -> &Bridge {
+        &self.bridge
+    }
+
+    fn handle(&self, uri: &str) -> VscodeBufferHandle {
+        self.buffer_store.get_buffer_handle(uri, &self.bridge)
+    }
+
+    fn documents(&self) -> Result<Vec<DocumentInfo>> {
+        self.bridge.request("eel/documents", json!({}))
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_to(&self, jump: Option<crate::buffer::Jump>) -> Result<bool> {
+        use eel::cursor::CursorWriteBuffer;
+
+        let Some((uri, position)) = jump else {
+            return Ok(false);
+        };
+
+        // The document may have been closed since
+        if !self.documents()?.iter().any(|d| *d.uri == *uri) {
+            return Ok(false);
+        }
+
+        let buffer = self.handle(&uri);
+        let mut lock = buffer.write();
+
+        self.set_current_buffer(&mut lock)?;
+        lock.set_cursor(&position)?;
+
+        Ok(true)
+    }
+}
+
+impl Editor for VscodeEditor {
+    type BufferHandle = VscodeBufferHandle;
+
+    fn current_buffer(&self) -> Result<VscodeBufferHandle> {
+        let uri: Option<String> = self.bridge.request("eel/activeDocument", json!({}))?;
+
+        let uri = uri.ok_or_else(|| eel::buffer::Error::Custom("No active text editor".into()))?;
+
+        Ok(self.handle(&uri))
+    }
+
+    fn set_current_buffer(
+        &self,
+        buffer: &mut <Self::BufferHandle as BufferHandle>::WriteBuffer,
+    ) -> Result<()> {
+        self.bridge
+            .request("eel/showDocument", DocumentParams::new(buffer.uri()))
+    }
+
+    fn new_buffer(&self) -> Result<VscodeBufferHandle> {
+        let document: DocumentInfo = self.bridge.request("eel/newDocument", json!({}))?;
+
+        Ok(self.handle(&document.uri))
+    }
+
+    fn buffers(&self) -> Result<Vec<VscodeBufferHandle>> {
+        Ok(self
+            .documents()?
+            .iter()
+            .map(|d| self.handle(&d.uri))
+            .collect())
+    }
+
+    fn buffer_name(&self, buffer: &VscodeBufferHandle) -> Result<Option<String>> {
+        Ok(self
+            .documents()?
+            .into_iter()
+            .find(|d| d.uri == buffer.uri())
+            .and_then(|d| d.file_name))
+    }
+
+    fn buffer_by_name(&self, name: &str) -> Result<Option<VscodeBufferHandle>> {
+        let name = normalize_path(Path::new(name));
+
+        Ok(self
+            .documents()?
+            .into_iter()
+            .find(|d| {
+                d.file_name
+                    .as_ref()
+                    .is_some_and(|f| normalize_path(Path::new(f)) == name)
+            })
+            .map(|d| self.handle(&d.uri)))
+    }
+
+    /// Options are VSCode settings, e.g. `editor.tabSize`.
+    fn get_option(&self, name: &str) -> Result<Option<OptionValue>> {
+        let params = ConfigurationParams {
+            key: name.to_string(),
+            value: None,
+        };
+
+        let value: Option<Value> = self.bridge.request("eel/getConfiguration", params)?;
+
+        value.map(|v| from_value(name, v)).transpose()
+    }
+
+    fn set_option(&self, name: &str, value: OptionValue) -> Result<()> {
+        let value = match value {
+            OptionValue::Bool(b) => b.into(),
+            OptionValue::Int(i) => i.into(),
+            OptionValue::String(s) => s.into(),
+        };
+
+        let params = ConfigurationParams {
+            key: name.to_string(),
+            value: Some(value),
+        };
+
+        self.bridge.request("eel/setConfiguration", params)
+    }
+
+    /// Idle once no document changed for `delay`, VSCode doesn't report other input.
+    fn on_idle(
+        &self,
+        delay: Duration,
+        callback: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<()> {
+        self.idle.on_idle(delay, callback);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_back(&self) -> Result<bool> {
+        use eel::cursor::CursorReadBuffer;
+
+        let current = self.current_buffer()?;
+        let position = current.read().get_cursor()?;
+
+        self.jump_to(
+            self.buffer_store
+                .jumps
+                .back((current.uri().into(), position)),
+        )
+    }
+
+    #[cfg(feature = "cursor")]
+    fn jump_forward(&self) -> Result<bool> {
+        self.jump_to(self.buffer_store.jumps.forward())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            cursor: cfg!(feature = "cursor"),
+            ..Default::default()
+        }
+    }
+}
+
+fn from_value(name: &str, value: Value) -> Result<OptionValue> {
+    Ok(match value {
+        Value::Bool(b) => OptionValue::Bool(b),
+        Value::Number(n) if n.is_i64() => OptionValue::Int(n.as_i64().expect("Number is an i64")),
+        Value::String(s) => OptionValue::String(s),
+        value => Err(eel::buffer::Error::Custom(
+            format!("Unsupported value of setting {name}: {value}").into(),
+        ))?,
+    })
+}
+
+/// Makes the path absolute and resolves symlinks if the file exists, so different spellings of
+/// the same path match.
+fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(feature = "vscode-tests")]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use eel::{
+        Capabilities, Editor, OptionValue, assert_buffer_content,
+        buffer::{BufferHandle, WeakBufferHandle, WriteBuffer},
+        eel_editor_tests,
+        test_utils::{EditorFactory as _, Fixture, new_buffer_with_content},
+    };
+    use eel_vscode_macros::vscode_test;
+    use serde_json::json;
+
+    use super::VscodeEditor;
+    use crate::{
+        error::Error as VscodeError, protocol::DocumentParams, test_utils::fake_host_editor_factory,
+    };
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn capabilities(editor: impl Editor) {
+        assert_eq!(
+            editor.capabilities(),
+            Capabilities {
+                cursor: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[vscode_test(editor_factory = || fake_host_editor_factory.create_editor_with(Fixture::unicode()))]
+    fn fixture_editor(editor: VscodeEditor) {
+        let buffer = editor.current_buffer().expect("Failed to get buffer");
+
+        assert_buffer_content!(buffer, Fixture::unicode().content());
+    }
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn document_close(editor: VscodeEditor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+        buffer.data().insert(42usize);
+
+        let closed = Arc::new(AtomicBool::new(false));
+        buffer.on_close({
+            let closed = closed.clone();
+            move || closed.store(true, Ordering::SeqCst)
+        });
+
+        // Closed by the user
+        editor
+            .bridge()
+            .request::<_, ()>("fake/closeDocument", DocumentParams::new(buffer.uri()))
+            .expect("Failed to close document");
+
+        assert!(closed.load(Ordering::SeqCst));
+        assert!(buffer.data().is_empty());
+        assert!(
+            !editor
+                .buffers()
+                .expect("Failed to list buffers")
+                .contains(&buffer)
+        );
+
+        let weak = buffer.downgrade();
+        drop(buffer);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn settings(editor: VscodeEditor) {
+        editor
+            .set_option("editor.tabSize", OptionValue::Int(3))
+            .expect("Failed to set option");
+        editor
+            .set_option("editor.wordWrap", "on".into())
+            .expect("Failed to set option");
+
+        assert_eq!(
+            editor
+                .get_option("editor.tabSize")
+                .expect("Failed to get option"),
+            Some(OptionValue::Int(3))
+        );
+        assert_eq!(
+            editor
+                .get_option("editor.wordWrap")
+                .expect("Failed to get option"),
+            Some(OptionValue::String("on".to_string()))
+        );
+    }
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn buffer_by_name(editor: VscodeEditor) {
+        let path = std::env::current_dir()
+            .expect("Failed to get cwd")
+            .join("eel_lookup_test.txt");
+
+        editor
+            .bridge()
+            .request::<_, serde_json::Value>(
+                "fake/openDocument",
+                json!({ "fileName": path, "content": "Hello" }),
+            )
+            .expect("Failed to open document");
+
+        let found = editor
+            .buffer_by_name("eel_lookup_test.txt")
+            .expect("Failed to look up buffer")
+            .expect("Buffer not found");
+        assert_eq!(
+            editor.buffer_name(&found).expect("Failed to get name"),
+            Some(path.to_string_lossy().into_owned())
+        );
+        assert!(
+            editor
+                .buffer_by_path(&path)
+                .expect("Failed to look up buffer")
+                == Some(found)
+        );
+
+        let untitled = editor.new_buffer().expect("Failed to create buffer");
+        assert_eq!(
+            editor.buffer_name(&untitled).expect("Failed to get name"),
+            None
+        );
+    }
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn remote_errors(editor: VscodeEditor) {
+        let result = editor
+            .bridge()
+            .request::<_, ()>("eel/notAMethod", json!({}));
+
+        assert!(matches!(
+            result,
+            Err(eel::Error::Platform(e))
+                if matches!(
+                    (&*e as &dyn std::error::Error).downcast_ref::<VscodeError>(),
+                    Some(VscodeError::Remote { code: -32601, .. })
+                )
+        ));
+    }
+
+    #[vscode_test(editor_factory = fake_host_editor_factory)]
+    fn idle_after_changes(editor: VscodeEditor) {
+        let buffer = new_buffer_with_content(&editor, "First line");
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        editor
+            .on_idle(Duration::from_millis(50), move || tx.send(()).is_ok())
+            .expect("Failed to register idle callback");
+
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("Idle callback didn't run");
+
+        buffer
+            .write()
+            .set_content("Second line")
+            .expect("Failed to set content");
+
+        // Once more after the change
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("Idle callback didn't run after the change");
+        assert!(rx.recv_timeout(Duration::from_millis(150)).is_err());
+    }
+
+    eel_editor_tests!(::eel_vscode_macros::vscode_test, fake_host_editor_factory);
+}
@@ -0,0 +1,16 @@
+use eel::error::EelPlatformError;
+
+#[derive(thiserror::Error, Debug, EelPlatformError)]
+pub enum Error {
+    #[error("Bridge IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Bridge JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("VSCode error {code}: {message}")]
+    Remote { code: i64, message: String },
+
+    #[error("Bridge disconnected")]
+    Disconnected,
+}
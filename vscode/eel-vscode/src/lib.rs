@@ -0,0 +1,12 @@
+pub mod error;
+
+pub mod buffer;
+pub mod editor;
+
+pub mod protocol;
+pub mod rpc;
+
+pub use editor::VscodeEditor;
+
+#[cfg(feature = "vscode-tests")]
+pub mod test_utils;
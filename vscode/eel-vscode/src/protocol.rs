@@ -0,0 +1,128 @@
+//! Messages exchanged with the bridge process, which runs inside the VSCode extension host and
+//! forwards them to the `vscode` API.
+//!
+//! Requests (params → result):
+//!
+//! * `eel/newDocument` (none) → [`DocumentInfo`], an empty untitled document
+//! * `eel/documents` (none) → `[DocumentInfo]`, `workspace.textDocuments`
+//! * `eel/activeDocument` (none) → `uri | null`, the document of the active text editor
+//! * `eel/showDocument` [`DocumentParams`] → `null`
+//! * `eel/lineCount` [`DocumentParams`] → `number`
+//! * `eel/getLines` [`LinesParams`] → `[string]`, clamped to the document
+//! * `eel/version` [`DocumentParams`] → `number`
+//! * `eel/edit` [`EditParams`] → [`EditResult`]
+//! * `eel/getSelection` [`DocumentParams`] → [`NativePosition`]
+//! * `eel/setSelection` [`SelectionParams`] → `null`
+//! * `eel/getConfiguration` [`ConfigurationParams`] → `value | null`
+//! * `eel/setConfiguration` [`ConfigurationParams`] → `null`
+//!
+//! Notifications from the bridge, [`DocumentParams`] each:
+//!
+//! * `eel/didChange`, sent before the result of the `eel/edit` causing it
+//! * `eel/didClose`
+//!
+//! The bridge keeps a selection per document, so documents not shown in any editor have one
+//! too.
+
+use serde::{Deserialize, Serialize};
+
+use eel::Position;
+
+/// Position as VSCode counts it, `character` being in UTF-16 code units.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativePosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl NativePosition {
+    /// `line` is the text of the position's row, `position` must be on a char boundary.
+    pub fn from_position(position: &Position, line: &str) -> Self {
+        Self {
+            line: position.row,
+            character: line[..position.col].encode_utf16().count(),
+        }
+    }
+
+    /// Positions past the line or inside a surrogate pair snap to the next char boundary.
+    pub fn to_position(self, line: &str) -> Position {
+        let mut units = 0;
+
+        let col = line
+            .char_indices()
+            .find(|(_, c)| {
+                let reached = units >= self.character;
+                units += c.len_utf16();
+                reached
+            })
+            .map_or(line.len(), |(i, _)| i);
+
+        Position::new(self.line, col)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativeRange {
+    pub start: NativePosition,
+    pub end: NativePosition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentInfo {
+    pub uri: String,
+    /// `None` for untitled documents.
+    pub file_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DocumentParams {
+    pub uri: String,
+}
+
+impl DocumentParams {
+    pub fn new(uri: &str) -> Self {
+        Self {
+            uri: uri.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LinesParams {
+    pub uri: String,
+    pub start: usize,
+    /// Exclusive, `None` for the rest of the document.
+    pub end: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EditParams {
+    pub uri: String,
+    pub range: NativeRange,
+    pub text: String,
+    /// The edit is only applied if the document is still at this version.
+    pub version: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditResult {
+    pub applied: bool,
+    /// Version after the edit, or the conflicting one if it wasn't applied.
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SelectionParams {
+    pub uri: String,
+    pub position: NativePosition,
+}
+
+/// Settings are addressed by their VSCode keys, e.g. `editor.tabSize`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigurationParams {
+    pub key: String,
+    /// Only for `eel/setConfiguration`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use parking_lot::Mutex;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tracing::{trace, warn};
+
+use eel::{
+    EditorConfig, Result,
+    server::{read_message, write_message},
+};
+
+use crate::error::Error as VscodeError;
+
+type Writer = Mutex<Option<Box<dyn Write + Send>>>;
+type Pending = Mutex<HashMap<u64, mpsc::Sender<std::result::Result<Value, VscodeError>>>>;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC 2.0 connection to the bridge process, see [`crate::protocol`].
+///
+/// Responses and notifications are read on a separate thread, requests block until their
+/// response arrives.
+pub struct Bridge {
+    writer: Arc<Writer>,
+    pending: Arc<Pending>,
+    next_id: AtomicU64,
+    child: Option<Child>,
+}
+
+impl Bridge {
+    /// `on_notification` runs on the reader thread before any later message is handled, so a
+    /// change notification is handled before the edit causing it returns. It must not wait
+    /// for bridge requests.
+    pub fn new(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+        on_notification: impl Fn(&str, Value) + Send + 'static,
+    ) -> Result<Self> {
+        let writer: Arc<Writer> = Arc::new(Mutex::new(Some(Box::new(writer))));
+        let pending: Arc<Pending> = Arc::default();
+
+        std::thread::Builder::new()
+            .name("eel-vscode-bridge".into())
+            .spawn({
+                let writer = writer.clone();
+                let pending = pending.clone();
+
+                move || read_loop(BufReader::new(reader), &writer, &pending, on_notification)
+            })
+            .map_err(VscodeError::from)?;
+
+        Ok(Self {
+            writer,
+            pending,
+            next_id: AtomicU64::new(1),
+            child: None,
+        })
+    }
+
+    /// Spawns the bridge process, talking to it over its stdin and stdout.
+    pub fn spawn(
+        command: &mut Command,
+        on_notification: impl Fn(&str, Value) + Send + 'static,
+    ) -> Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(VscodeError::from)?;
+
+        let stdin = child.stdin.take().expect("Stdin is piped");
+        let stdout = child.stdout.take().expect("Stdout is piped");
+
+        let mut bridge = Self::new(stdout, stdin, on_notification)?;
+        bridge.child = Some(child);
+
+        Ok(bridge)
+    }
+
+    pub fn request<P, R>(&self, method: &str, params: P) -> Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let params = serde_json::to_value(params).map_err(VscodeError::from)?;
+
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().insert(id, tx);
+
+        trace!(id, method, "Sending bridge request");
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        if let Err(e) = send(&self.writer, &message) {
+            self.pending.lock().remove(&id);
+            Err(e)?;
+        }
+
+        let result = match EditorConfig::current().op_timeout {
+            Some(timeout) => match rx.recv_timeout(timeout) {
+                Ok(result) => result,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    self.pending.lock().remove(&id);
+                    Err(eel::dispatch::Error::Timeout(timeout))?
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => Err(VscodeError::Disconnected)?,
+            },
+            None => rx.recv().map_err(|_| VscodeError::Disconnected)?,
+        }?;
+
+        Ok(serde_json::from_value(result).map_err(VscodeError::from)?)
+    }
+}
+
+impl std::fmt::Debug for Bridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bridge")
+            .field("pending", &self.pending.lock().len())
+            .finish()
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        // Closing the stream ends the bridge, and with it the reader thread
+        self.writer.lock().take();
+
+        if let Some(mut child) = self.child.take() {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+fn send(writer: &Writer, message: &Value) -> std::result::Result<(), VscodeError> {
+    let mut writer = writer.lock();
+    let writer = writer.as_mut().ok_or(VscodeError::Disconnected)?;
+
+    Ok(write_message(writer, message)?)
+}
+
+fn read_loop(
+    mut reader: impl BufRead,
+    writer: &Writer,
+    pending: &Pending,
+    on_notification: impl Fn(&str, Value),
+) {
+    loop {
+        let mut message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read bridge message: {e}");
+                break;
+            }
+        };
+
+        let id = message.get("id").and_then(Value::as_u64);
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        match (id, method.as_deref()) {
+            (Some(id), None) => respond(pending, id, &mut message),
+            (None, Some(method)) => {
+                trace!(method, "Bridge notification");
+
+                let params = message.get("params").cloned().unwrap_or_default();
+                on_notification(method, params);
+            }
+            // The bridge has nothing to ask for yet
+            (Some(id), Some(method)) => {
+                let reply = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("Method not found: {method}") },
+                });
+
+                if let Err(e) = send(writer, &reply) {
+                    warn!("Failed to reply to bridge request: {e}");
+                }
+            }
+            (None, None) => warn!("Ignoring invalid bridge message: {message}"),
+        }
+    }
+
+    // Dropping the senders fails the waiting requests with Error::Disconnected
+    pending.lock().clear();
+}
+
+fn respond(pending: &Pending, id: u64, message: &mut Value) {
+    let Some(sender) = pending.lock().remove(&id) else {
+        warn!(id, "Response to unknown bridge request");
+        return;
+    };
+
+    let result = match message.get("error") {
+        Some(error) => Err(VscodeError::Remote {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        None => Ok(message
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or_default()),
+    };
+
+    // The requester can't go away while waiting
+    let _ = sender.send(result);
+}
+
+#[cfg(feature = "vscode-tests")]
+mod tests {
+    #[test]
+    fn message_framing() {
+        use std::io::Cursor;
+
+        use serde_json::json;
+
+        use eel::server::{read_message, write_message};
+
+        let messages = [
+            json!({ "id": 1, "result": null }),
+            json!({ "text": "ä\r\n" }),
+        ];
+
+        let mut stream = Vec::new();
+        for message in &messages {
+            write_message(&mut stream, message).expect("Failed to write message");
+        }
+
+        let mut reader = Cursor::new(stream);
+        for message in &messages {
+            let read = read_message(&mut reader).expect("Failed to read message");
+            assert_eq!(read.as_ref(), Some(message));
+        }
+
+        assert!(
+            read_message(&mut reader)
+                .expect("Failed to read message")
+                .is_none()
+        );
+
+        let mut reader = Cursor::new(b"Content-Type: json\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut reader).is_err());
+    }
+}
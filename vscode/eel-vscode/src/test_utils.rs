@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    io::{BufReader, Read, Write},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use eel::{
+    Editor, Position,
+    server::{read_message, write_message},
+    test_utils::{EditorFactory, EditorTest},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::{
+    editor::VscodeEditor,
+    protocol::{
+        ConfigurationParams, DocumentInfo, DocumentParams, EditParams, EditResult, LinesParams,
+        NativePosition, SelectionParams,
+    },
+};
+
+const DEFAULT_TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs the test on its own thread, so a deadlocked test fails instead of hanging.
+pub fn run_vscode_test<E, EF, T, R>(test: T, editor_factory: EF) -> R
+where
+    E: Editor,
+    EF: EditorFactory<Editor = E>,
+    T: EditorTest<E, R>,
+    R: Send + 'static,
+{
+    let editor = editor_factory.create_editor();
+
+    let (send, recv) = mpsc::channel();
+
+    let test_handle = std::thread::spawn(move || {
+        send.send(test.run(editor)).expect("Test result send error");
+    });
+
+    match recv.recv_timeout(DEFAULT_TEST_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => panic!("Test timed out"),
+        Err(RecvTimeoutError::Disconnected) => match test_handle.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("Test finished without a result"),
+        },
+    }
+}
+
+/// Editor connected to a [`FakeHost`] running on another thread.
+pub fn fake_host_editor_factory() -> VscodeEditor {
+    let (host_reader, editor_writer) = std::io::pipe().expect("Failed to create pipe");
+    let (editor_reader, host_writer) = std::io::pipe().expect("Failed to create pipe");
+
+    std::thread::spawn(move || FakeHost::default().serve(host_reader, host_writer));
+
+    VscodeEditor::connect(editor_reader, editor_writer).expect("Failed to connect to fake host")
+}
+
+#[derive(Debug)]
+struct FakeDocument {
+    info: DocumentInfo,
+    lines: Vec<String>,
+    version: u64,
+    selection: Position,
+}
+
+impl FakeDocument {
+    fn new(info: DocumentInfo, content: &str) -> Self {
+        Self {
+            info,
+            lines: content.split('\n').map(String::from).collect(),
+            version: 1,
+            selection: Position::origin(),
+        }
+    }
+
+    fn position(&self, position: NativePosition) -> Result<Position, String> {
+        let line = self
+            .lines
+            .get(position.line)
+            .ok_or_else(|| format!("Line out of range: {}", position.line))?;
+
+        Ok(position.to_position(line))
+    }
+
+    /// Positions after the range move along with the text, ones inside go to its new end.
+    fn shift(
+        position: &Position,
+        start: &Position,
+        end: &Position,
+        new_end: &Position,
+    ) -> Position {
+        if position <= start {
+            position.clone()
+        } else if position < end {
+            new_end.clone()
+        } else if position.row == end.row {
+            Position::new(new_end.row, new_end.col + position.col - end.col)
+        } else {
+            Position::new(position.row + new_end.row - end.row, position.col)
+        }
+    }
+
+    fn edit(&mut self, start: &Position, end: &Position, text: &str) {
+        let prefix = &self.lines[start.row][..start.col];
+        let suffix = &self.lines[end.row][end.col..];
+
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        lines[0] = format!("{prefix}{}", lines[0]);
+        lines
+            .last_mut()
+            .expect("Split yields a line")
+            .push_str(suffix);
+
+        self.lines.splice(start.row..=end.row, lines);
+        self.version += 1;
+
+        let new_end = start.offset(&Position::max_text_pos(text));
+        self.selection = Self::shift(&self.selection, start, end, &new_end);
+    }
+}
+
+/// In-process stand-in for the bridge, keeping documents in memory and answering like the
+/// `vscode` API would: UTF-16 columns, versions, change notifications before edit results.
+///
+/// Besides the [`crate::protocol`] requests it handles `fake/openDocument` (`fileName` and
+/// `content`) and `fake/closeDocument` ([`DocumentParams`]), to simulate the user.
+#[derive(Debug, Default)]
+pub struct FakeHost {
+    documents: Vec<FakeDocument>,
+    active: Option<String>,
+    settings: HashMap<String, Value>,
+    untitled: usize,
+}
+
+type Notifications = Vec<(&'static str, Value)>;
+
+fn parse<P: DeserializeOwned>(params: Value) -> Result<P, String> {
+    serde_json::from_value(params).map_err(|e| format!("Invalid params: {e}"))
+}
+
+fn to_value(value: impl serde::Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+impl FakeHost {
+    pub fn serve(mut self, reader: impl Read, mut writer: impl Write) {
+        let mut reader = BufReader::new(reader);
+
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            let id = message["id"].clone();
+            let method = message["method"].as_str().unwrap_or_default().to_string();
+            let params = message.get("params").cloned().unwrap_or_default();
+
+            let mut notifications = Vec::new();
+            let result = self.handle(&method, params, &mut notifications);
+
+            for (method, params) in notifications {
+                let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+
+                if write_message(&mut writer, &notification).is_err() {
+                    return;
+                }
+            }
+
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err((code, message)) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": code, "message": message },
+                }),
+            };
+
+            if write_message(&mut writer, &response).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn document(&mut self, uri: &str) -> Result<&mut FakeDocument, String> {
+        self.documents
+            .iter_mut()
+            .find(|d| d.info.uri == uri)
+            .ok_or_else(|| format!("Unknown document: {uri}"))
+    }
+
+    fn handle(
+        &mut self,
+        method: &str,
+        params: Value,
+        notifications: &mut Notifications,
+    ) -> Result<Value, (i64, String)> {
+        let invalid = |message: String| (-32602, message);
+
+        match method {
+            "eel/newDocument" => {
+                self.untitled += 1;
+
+                let info = DocumentInfo {
+                    uri: format!("untitled:Untitled-{}", self.untitled),
+                    file_name: None,
+                };
+                self.documents.push(FakeDocument::new(info.clone(), ""));
+
+                to_value(info).map_err(invalid)
+            }
+            "fake/openDocument" => {
+                let file_name = params["fileName"].as_str().unwrap_or_default();
+                let content = params["content"].as_str().unwrap_or_default();
+
+                let info = DocumentInfo {
+                    uri: format!("file://{file_name}"),
+                    file_name: Some(file_name.to_string()),
+                };
+                self.documents
+                    .push(FakeDocument::new(info.clone(), content));
+
+                to_value(info).map_err(invalid)
+            }
+            "fake/closeDocument" => {
+                let params: DocumentParams = parse(params).map_err(invalid)?;
+
+                self.documents.retain(|d| d.info.uri != params.uri);
+                if self.active.as_ref() == Some(&params.uri) {
+                    self.active = None;
+                }
+
+                notifications.push(("eel/didClose", json!(params)));
+
+                Ok(Value::Null)
+            }
+            "eel/documents" => {
+                let infos: Vec<&DocumentInfo> = self.documents.iter().map(|d| &d.info).collect();
+
+                to_value(infos).map_err(invalid)
+            }
+            "eel/activeDocument" => Ok(json!(self.active)),
+            "eel/showDocument" => {
+                let params: DocumentParams = parse(params).map_err(invalid)?;
+
+                self.document(&params.uri).map_err(invalid)?;
+                self.active = Some(params.uri);
+
+                Ok(Value::Null)
+            }
+            "eel/lineCount" => {
+                let params: DocumentParams = parse(params).map_err(invalid)?;
+
+                Ok(json!(
+                    self.document(&params.uri).map_err(invalid)?.lines.len()
+                ))
+            }
+            "eel/getLines" => {
+                let params: LinesParams = parse(params).map_err(invalid)?;
+                let lines = &self.document(&params.uri).map_err(invalid)?.lines;
+
+                let end = params.end.unwrap_or(lines.len()).min(lines.len());
+                let start = params.start.min(end);
+
+                Ok(json!(lines[start..end]))
+            }
+            "eel/version" => {
+                let params: DocumentParams = parse(params).map_err(invalid)?;
+
+                Ok(json!(self.document(&params.uri).map_err(invalid)?.version))
+            }
+            "eel/edit" => {
+                let params: EditParams = parse(params).map_err(invalid)?;
+                let document = self.document(&params.uri).map_err(invalid)?;
+
+                if params.version.is_some_and(|v| v != document.version) {
+                    return to_value(EditResult {
+                        applied: false,
+                        version: document.version,
+                    })
+                    .map_err(invalid);
+                }
+
+                let start = document.position(params.range.start).map_err(invalid)?;
+                let end = document.position(params.range.end).map_err(invalid)?;
+                document.edit(&start, &end, &params.text);
+
+                let version = document.version;
+                notifications.push(("eel/didChange", json!({ "uri": params.uri })));
+
+                to_value(EditResult {
+                    applied: true,
+                    version,
+                })
+                .map_err(invalid)
+            }
+            "eel/getSelection" => {
+                let params: DocumentParams = parse(params).map_err(invalid)?;
+                let document = self.document(&params.uri).map_err(invalid)?;
+
+                let position = NativePosition::from_position(
+                    &document.selection,
+                    &document.lines[document.selection.row],
+                );
+
+                to_value(position).map_err(invalid)
+            }
+            "eel/setSelection" => {
+                let params: SelectionParams = parse(params).map_err(invalid)?;
+                let document = self.document(&params.uri).map_err(invalid)?;
+
+                document.selection = document.position(params.position).map_err(invalid)?;
+
+                Ok(Value::Null)
+            }
+            "eel/getConfiguration" => {
+                let params: ConfigurationParams = parse(params).map_err(invalid)?;
+
+                Ok(self.settings.get(&params.key).cloned().unwrap_or_default())
+            }
+            "eel/setConfiguration" => {
+                let params: ConfigurationParams = parse(params).map_err(invalid)?;
+
+                self.settings
+                    .insert(params.key, params.value.unwrap_or_default());
+
+                Ok(Value::Null)
+            }
+            _ => Err((-32601, format!("Method not found: {method}"))),
+        }
+    }
+}